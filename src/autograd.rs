@@ -2,12 +2,13 @@
 
 use core::{
     cell::{Ref, RefMut},
-    fmt::Debug,
+    fmt::{Debug, Write},
     marker::PhantomData,
 };
 
 use crate::{
-    borrowing_cache::BorrowingCache, prelude::One, Alloc, Buffer, Device, Ident, Shape, WriteBuf,
+    borrowing_cache::BorrowingCache, graph::dot_escape, json, prelude::One, Alloc, Buffer, Device,
+    Ident, MayTrace, Shape, WriteBuf, EXPORT_VERSION,
 };
 
 /// A cache for gradients.
@@ -168,7 +169,18 @@ impl<D> Gradients<D> {
     }
 }
 
-type GradFn<D> = Box<dyn Fn(&mut Gradients<D>, &D)>;
+type GradFn<D> = Box<dyn Fn(&mut Gradients<D>, &D) + Send>;
+
+/// Describes the operation that added a gradient function to the [`Tape`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TapeEntry {
+    /// The name of the operation, e.g. `"unary_ew"`. `None` for gradient functions added with [`Tape::add_grad_fn`].
+    pub op: Option<&'static str>,
+    /// The [`Ident`]s of the input [`Buffer`]s, whose gradients are computed by the gradient function.
+    pub inputs: Vec<Ident>,
+    /// The [`Ident`]s of the output [`Buffer`]s, whose gradients are read by the gradient function.
+    pub outputs: Vec<Ident>,
+}
 
 /// Stores the grad functions and gradient cache.
 #[derive(Default)]
@@ -176,6 +188,7 @@ pub struct Tape<D: Device> {
     /// Caches gradients for each [`Buffer`]'s id ([`Ident`]).
     pub grads: Gradients<D>,
     grad_fns: Vec<GradFn<D>>,
+    entries: Vec<TapeEntry>,
 }
 
 /// This trait is implemented for all devices that provide a [`Tape`].
@@ -188,19 +201,135 @@ pub trait TapeReturn: Device {
 
 impl<D: Device> Debug for Tape<D> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("Tape").field("grads", &self.grads).finish()
+        f.debug_struct("Tape")
+            .field("grads", &self.grads)
+            .field("entries", &self.entries)
+            .finish()
     }
 }
 
 impl<D: Device> Tape<D> {
     /// Adds a gradient function to the tape.
+    /// The function must be `Send`, as the tapes of all threads are dropped by the thread that drops the device.
+    #[inline]
+    pub fn add_grad_fn<F: Fn(&mut Gradients<D>, &D) + Send + 'static>(&mut self, grad_fn: F) {
+        self.add_entry(TapeEntry::default(), grad_fn)
+    }
+
+    /// Adds a gradient function to the tape, which is described by `entry` in [`entries`](Tape::entries) and the exports.
+    #[inline]
+    pub fn add_entry<F: Fn(&mut Gradients<D>, &D) + Send + 'static>(
+        &mut self,
+        entry: TapeEntry,
+        grad_fn: F,
+    ) {
+        self.grad_fns.push(Box::new(grad_fn));
+        self.entries.push(entry);
+    }
+
+    /// Returns the entries of the gradient functions that were not executed yet, in the order they were added.
+    #[inline]
+    pub fn entries(&self) -> &[TapeEntry] {
+        &self.entries
+    }
+
+    /// Returns the pending gradient functions in the Graphviz DOT format.
+    ///
+    /// Every entry is a box labeled with its index and operation. The [`Buffer`]s are ellipses labeled with their [`Ident`].
+    /// Edges point from the inputs to the entry and from the entry to its outputs, i.e. in the direction of the forward pass.
+    /// # Example
+    #[cfg_attr(all(feature = "cpu", feature = "macro"), doc = "```")]
+    #[cfg_attr(not(all(feature = "cpu", feature = "macro")), doc = "```ignore")]
+    /// use custos::{Buffer, Combiner, TapeReturn, UnaryElementWiseMayGrad, CPU};
+    ///
+    /// let device = CPU::new();
+    /// let buf = Buffer::from((&device, [1., 2., 3.]));
+    /// let _out = device.unary_ew(&buf, |x| x.exp(), |x| x.exp());
+    ///
+    /// let dot = device.tape().to_dot();
+    /// assert!(dot.starts_with("digraph tape {"));
+    /// assert!(dot.contains(r##"g0 [label="#0 unary_ew"];"##));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut buffers = Vec::new();
+        let mut edges = String::new();
+
+        let mut dot = String::from("digraph tape {\n    node [shape=box];\n");
+        for (idx, entry) in self.entries.iter().enumerate() {
+            writeln!(
+                dot,
+                r##"    g{idx} [label="#{idx} {}"];"##,
+                dot_escape(entry.op.unwrap_or("grad_fn"))
+            )
+            .unwrap();
+
+            for input in &entry.inputs {
+                writeln!(edges, "    b{}_{} -> g{idx};", input.idx, input.len).unwrap();
+            }
+            for output in &entry.outputs {
+                writeln!(edges, "    g{idx} -> b{}_{};", output.idx, output.len).unwrap();
+            }
+            buffers.extend(entry.inputs.iter().chain(&entry.outputs).copied());
+        }
+
+        buffers.sort();
+        buffers.dedup();
+        for ident in buffers {
+            writeln!(
+                dot,
+                r#"    b{}_{} [label="{} ({})", shape=ellipse];"#,
+                ident.idx, ident.len, ident.idx, ident.len
+            )
+            .unwrap();
+        }
+
+        dot.push_str(&edges);
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Returns the pending gradient functions as JSON.
+    ///
+    /// The schema is `{"version": 1, "entries": [..]}`, see [`EXPORT_VERSION`].
+    /// Every entry is an object with the keys
+    /// - `idx`: the position on the tape
+    /// - `op`: the name of the operation or `null`
+    /// - `inputs`, `outputs`: arrays of [`Ident`]s (`{"idx": .., "len": ..}`)
+    pub fn to_json(&self) -> String {
+        let entries = self.entries.iter().enumerate().map(|(idx, entry)| {
+            format!(
+                r#"{{"idx":{idx},"op":{},"inputs":{},"outputs":{}}}"#,
+                json::opt_string(entry.op),
+                json::array(entry.inputs.iter().copied().map(json::ident)),
+                json::array(entry.outputs.iter().copied().map(json::ident))
+            )
+        });
+
+        format!(
+            r#"{{"version":{EXPORT_VERSION},"entries":{}}}"#,
+            json::array(entries)
+        )
+    }
+
+    /// Returns the number of gradient functions on the tape.
     #[inline]
-    pub fn add_grad_fn<F: Fn(&mut Gradients<D>, &D) + 'static>(&mut self, grad_fn: F) {
-        self.grad_fns.push(Box::new(grad_fn))
+    pub fn len(&self) -> usize {
+        self.grad_fns.len()
+    }
+
+    /// Returns `true` if there are no gradient functions on the tape.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.grad_fns.is_empty()
     }
 
     /// Calls all gradient functions in reverse order.
-    pub fn backward(&mut self, device: &D) {
+    pub fn backward(&mut self, device: &D)
+    where
+        D: MayTrace,
+    {
+        let _span = crate::trace::span(device, "backward", 0, None);
+        self.entries.clear();
         for grad_fn in self.grad_fns.drain(..).rev() {
             grad_fn(&mut self.grads, device);
         }
@@ -210,7 +339,7 @@ impl<D: Device> Tape<D> {
     pub fn backward_seeded<T, S: Shape>(&mut self, buf: &Buffer<T, D, S>)
     where
         T: Clone + One + 'static,
-        D: for<'a> Alloc<'a, T, S> + WriteBuf<T, S, D> + 'static,
+        D: for<'a> Alloc<'a, T, S> + WriteBuf<T, S, D> + MayTrace + 'static,
     {
         // TODO // TODO
         //let mut out = self.grads.get_like::<T, S>(buf);
@@ -224,7 +353,7 @@ impl<D: Device> Tape<D> {
 impl<'a, T, D, S> Buffer<'a, T, D, S>
 where
     T: Clone + One + 'static,
-    D: TapeReturn + WriteBuf<T, S, D> + for<'b> Alloc<'b, T, S> + 'static,
+    D: TapeReturn + WriteBuf<T, S, D> + for<'b> Alloc<'b, T, S> + MayTrace + 'static,
     S: Shape,
 {
     /// Calls `.backward_seeded` on the [`Tape`].
@@ -0,0 +1,86 @@
+use crate::{Buffer, Device, Eval, MayToCLSource, Resolve, Shape};
+
+/// Applies a function to the elements of two buffers and returns a new buffer.
+pub trait BinaryElementWise<T, S: Shape = (), D: Device = Self>: Device {
+    /// Applies a function to the elements of `lhs` and `rhs` and returns a new buffer.
+    /// # Example
+    #[cfg_attr(all(feature = "cpu", feature = "macro"), doc = "```")]
+    #[cfg_attr(not(all(feature = "cpu", feature = "macro")), doc = "```ignore")]
+    /// use custos::{CPU, Buffer, BinaryElementWise, Combiner};
+    ///
+    /// let device = CPU::new();
+    /// let lhs = Buffer::from((&device, [1., 2., 3., 3., 2., 1.,]));
+    /// let rhs = Buffer::from((&device, [2., 2., 2., 1., 1., 1.,]));
+    ///
+    /// let out = device.binary_ew(&lhs, &rhs, |lhs, rhs| lhs.mul(rhs));
+    /// assert_eq!(&*out, &[2., 4., 6., 3., 2., 1.,]);
+    /// ```
+    #[inline]
+    #[track_caller]
+    fn binary_ew<F>(
+        &self,
+        lhs: &Buffer<T, D, S>,
+        rhs: &Buffer<T, D, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> Buffer<'_, T, Self, S>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        self.try_binary_ew(lhs, rhs, f).unwrap()
+    }
+
+    /// Like [`binary_ew`](BinaryElementWise::binary_ew), but returns an error if the lengths of `lhs` and `rhs` differ,
+    /// the output could not be allocated or the kernel failed.
+    #[track_caller]
+    fn try_binary_ew<F>(
+        &self,
+        lhs: &Buffer<T, D, S>,
+        rhs: &Buffer<T, D, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<'_, T, Self, S>>
+    where
+        F: Eval<T> + MayToCLSource;
+}
+
+/// Applies a function to the elements of two buffers and writes the result to an existing buffer.
+/// [`Lazy`](crate::Lazy) uses it to write the result of a recorded operation to the buffer that was returned on record.
+pub trait BinaryElementWiseTo<T, S: Shape = (), D: Device = Self>: Device {
+    /// Applies a function to the elements of `lhs` and `rhs` and writes the result to `out`.
+    /// # Example
+    #[cfg_attr(all(feature = "cpu", feature = "macro"), doc = "```")]
+    #[cfg_attr(not(all(feature = "cpu", feature = "macro")), doc = "```ignore")]
+    /// use custos::{CPU, Buffer, BinaryElementWiseTo, Combiner};
+    ///
+    /// let device = CPU::new();
+    /// let lhs = Buffer::from((&device, [1, 2, 3]));
+    /// let rhs = Buffer::from((&device, [4, 5, 6]));
+    /// let mut out = Buffer::new(&device, 3);
+    ///
+    /// device.binary_ew_to(&lhs, &rhs, &mut out, |lhs, rhs| lhs.add(rhs));
+    /// assert_eq!(&*out, &[5, 7, 9]);
+    /// ```
+    #[inline]
+    #[track_caller]
+    fn binary_ew_to<F>(
+        &self,
+        lhs: &Buffer<T, D, S>,
+        rhs: &Buffer<T, D, S>,
+        out: &mut Buffer<T, Self, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) where
+        F: Eval<T> + MayToCLSource,
+    {
+        self.try_binary_ew_to(lhs, rhs, out, f).unwrap()
+    }
+
+    /// Like [`binary_ew_to`](BinaryElementWiseTo::binary_ew_to), but returns an error if the lengths of the buffers differ or the kernel failed.
+    fn try_binary_ew_to<F>(
+        &self,
+        lhs: &Buffer<T, D, S>,
+        rhs: &Buffer<T, D, S>,
+        out: &mut Buffer<T, Self, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<()>
+    where
+        F: Eval<T> + MayToCLSource;
+}
@@ -8,7 +8,7 @@ use crate::CPU;
 
 use crate::{
     flag::AllocFlag, shape::Shape, Alloc, ClearBuf, CloneBuf, CommonPtrs, Device, DevicelessAble,
-    Ident, IsShapeIndep, MainMemory, PtrType, Read, ShallowCopy, WriteBuf,
+    Ident, IsShapeIndep, MainMemory, PtrType, Read, ShallowCopy, Transfer, WriteBuf,
 };
 
 pub use self::num::Num;
@@ -17,6 +17,10 @@ pub use impl_from_const::*;
 mod impl_from;
 mod impl_from_const;
 mod num;
+#[cfg(not(feature = "no-std"))]
+mod owned;
+#[cfg(not(feature = "no-std"))]
+pub use owned::*;
 
 /// The underlying non-growable array structure of `custos`. A `Buffer` may be encapsulated in other data structures.
 /// By default, the `Buffer` is a f32 CPU Buffer with no statically known shape.
@@ -44,10 +48,6 @@ pub struct Buffer<'a, T = f32, D: Device = CPU, S: Shape = ()> {
     pub ident: Option<Ident>,
 }
 
-unsafe impl<'a, T, D: Device, S: Shape> Send for Buffer<'a, T, D, S> {}
-
-unsafe impl<'a, T, D: Device, S: Shape> Sync for Buffer<'a, T, D, S> {}
-
 impl<'a, T, D: Device, S: Shape> Buffer<'a, T, D, S> {
     /// Creates a zeroed (or values set to default) `Buffer` with the given length on the specified device.
     /// This `Buffer` can't outlive the device specified as a parameter.
@@ -67,23 +67,45 @@ impl<'a, T, D: Device, S: Shape> Buffer<'a, T, D, S> {
     ///
     /// ```
     #[inline]
+    #[track_caller]
     pub fn new(device: &'a D, len: usize) -> Buffer<'a, T, D, S>
     where
         D: Alloc<'a, T, S>, /*+ GraphReturn*/
     {
-        let ptr = device.alloc(len, AllocFlag::None);
+        Buffer::try_new(device, len).unwrap()
+    }
+
+    /// Like [`Buffer::new`], but returns an error if the memory could not be allocated.
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::{CPU, Buffer};
+    ///
+    /// let device = CPU::new();
+    ///
+    /// let buffer = Buffer::<i32>::try_new(&device, 6).unwrap();
+    /// assert_eq!(buffer.as_slice(), &[0; 6]);
+    ///
+    /// assert!(Buffer::<i32>::try_new(&device, 0).is_err());
+    /// ```
+    #[inline]
+    #[track_caller]
+    pub fn try_new(device: &'a D, len: usize) -> crate::Result<Buffer<'a, T, D, S>>
+    where
+        D: Alloc<'a, T, S>,
+    {
+        let ptr = device.try_alloc(len, AllocFlag::None)?;
 
         #[cfg(not(feature = "no-std"))]
         let ident = device.add_to_cache(&ptr);
 
-        Buffer {
+        Ok(Buffer {
             ptr,
             device: Some(device),
             // TODO: enable, if leafs get more important
             //node: device.graph().add_leaf(len),
             #[cfg(not(feature = "no-std"))]
             ident,
-        }
+        })
     }
 
     /// Buffers created with this method can outlive the device used to create this `Buffer`.<br>
@@ -187,6 +209,42 @@ impl<'a, T, D: Device, S: Shape> Buffer<'a, T, D, S> {
         self.device().write_buf(self, src)
     }
 
+    /// Copies the `Buffer` to another device. The [`Shape`] is preserved.
+    /// # Example
+    #[cfg_attr(all(feature = "cpu", feature = "stack"), doc = "```")]
+    #[cfg_attr(not(all(feature = "cpu", feature = "stack")), doc = "```ignore")]
+    /// use custos::{Buffer, Dim1, Stack, CPU};
+    ///
+    /// let device = CPU::new();
+    /// let buf = Buffer::<_, _, Dim1<3>>::from((&Stack, [1, 2, 3]));
+    ///
+    /// let on_cpu = buf.transfer_to(&device);
+    /// assert_eq!(on_cpu.as_slice(), [1, 2, 3]);
+    /// ```
+    /// # Panics
+    /// If the data could not be read or the memory on `dst` could not be allocated, see [`Buffer::try_transfer_to`].
+    #[inline]
+    #[track_caller]
+    pub fn transfer_to<'b, Dst: Device>(&self, dst: &'b Dst) -> Buffer<'b, T, Dst, S>
+    where
+        D: Transfer<T, Dst, S>,
+    {
+        self.device().transfer(self, dst)
+    }
+
+    /// Like [`Buffer::transfer_to`], but returns an error if the data could not be read or the memory on `dst` could not be allocated.
+    #[inline]
+    #[track_caller]
+    pub fn try_transfer_to<'b, Dst: Device>(
+        &self,
+        dst: &'b Dst,
+    ) -> crate::Result<Buffer<'b, T, Dst, S>>
+    where
+        D: Transfer<T, Dst, S>,
+    {
+        self.device().try_transfer(self, dst)
+    }
+
     /// Returns the number of elements contained in `Buffer`.
     /// # Example
     #[cfg_attr(feature = "cpu", doc = "```")]
@@ -369,43 +427,86 @@ impl<'a, T, D: Device, S: Shape> Buffer<'a, T, D, S> {
     /// Creates a new `Buffer` from a slice (&[T]).
     /// The pointer of the allocation may be added to the cache of the device.
     /// Usually, this pointer / `Buffer` is then returned by a `device.get_existing_buf(..)` (accesses the cache) call.
+    /// # Panics
+    /// If the memory could not be allocated, see [`Buffer::try_from_slice`].
     #[inline]
+    #[track_caller]
     pub fn from_slice(device: &'a D, slice: &[T]) -> Self
     where
         T: Clone,
         D: Alloc<'a, T, S>,
     {
-        let ptr = device.with_slice(slice);
+        Buffer::try_from_slice(device, slice).unwrap()
+    }
+
+    /// Like [`Buffer::from_slice`], but returns an error if the memory could not be allocated, e.g. because the memory limit of the device is exceeded.
+    /// # Example
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::{Buffer, Error, MemoryReturn, CPU};
+    ///
+    /// let device = CPU::new();
+    /// device.set_memory_limit(Some(8));
+    ///
+    /// let buf = Buffer::<u32>::try_from_slice(&device, &[1, 2]).unwrap();
+    /// assert_eq!(buf.read(), [1, 2]);
+    ///
+    /// let err = Buffer::<u32>::try_from_slice(&device, &[1, 2, 3]).unwrap_err();
+    /// assert!(matches!(err, Error::Alloc { bytes: 12, limit: Some(_) }));
+    /// ```
+    #[inline]
+    #[track_caller]
+    pub fn try_from_slice(device: &'a D, slice: &[T]) -> crate::Result<Self>
+    where
+        T: Clone,
+        D: Alloc<'a, T, S>,
+    {
+        let ptr = device.try_with_slice(slice)?;
 
         #[cfg(not(feature = "no-std"))]
         let ident = device.add_to_cache(&ptr);
 
-        Buffer {
+        Ok(Buffer {
             ptr,
             #[cfg(not(feature = "no-std"))]
             ident,
             device: Some(device),
-        }
+        })
     }
 
     /// Creates a new `Buffer` from a `Vec`.
     /// The pointer of the allocation may be added to the cache of the device.
     /// Usually, this pointer / `Buffer` is then returned by a `device.get_existing_buf(..)` call.
+    /// # Panics
+    /// If the memory could not be allocated, see [`Buffer::try_from_vec`].
     #[cfg(not(feature = "no-std"))]
     #[inline]
+    #[track_caller]
     pub fn from_vec(device: &'a D, data: Vec<T>) -> Self
     where
         T: Clone,
         D: Alloc<'a, T, S>,
     {
-        let ptr = device.alloc_with_vec(data);
+        Buffer::try_from_vec(device, data).unwrap()
+    }
+
+    /// Like [`Buffer::from_vec`], but returns an error if the memory could not be allocated, e.g. because the memory limit of the device is exceeded.
+    #[cfg(not(feature = "no-std"))]
+    #[inline]
+    #[track_caller]
+    pub fn try_from_vec(device: &'a D, data: Vec<T>) -> crate::Result<Self>
+    where
+        T: Clone,
+        D: Alloc<'a, T, S>,
+    {
+        let ptr = device.try_alloc_with_vec(data)?;
         let ident = device.add_to_cache(&ptr);
 
-        Buffer {
+        Ok(Buffer {
             ptr,
             ident,
             device: Some(device),
-        }
+        })
     }
 
     /// Creates a new `Buffer` from an nd-array.
@@ -493,6 +594,7 @@ impl<'a, T, S: Shape> Buffer<'a, T, crate::OpenCL, S> {
             !self.ptr.ptr.is_null(),
             "called cl_ptr() on an invalid OpenCL buffer"
         );
+        crate::sanitize(&self.ptr);
         self.ptrs().1
     }
 }
@@ -507,6 +609,7 @@ impl<'a, T> Buffer<'a, T, crate::CUDA> {
             self.ptrs().2 != 0,
             "called cu_ptr() on an invalid CUDA buffer"
         );
+        crate::sanitize(&self.ptr);
         self.ptr.ptr
     }
 }
@@ -536,6 +639,7 @@ where
             !self.ptrs().0.is_null(),
             "called host_ptr() on an invalid CPU buffer (this would dereference a null pointer)"
         );
+        crate::sanitize(&self.ptr);
         self.ptrs().0
     }
 
@@ -546,6 +650,7 @@ where
             !self.ptrs().0.is_null(),
             "called host_ptr_mut() on an invalid CPU buffer (this would dereference a null pointer)"
         );
+        crate::sanitize(&self.ptr);
         self.ptrs_mut().0
     }
 }
@@ -623,6 +728,7 @@ impl<T, D: MainMemory, S: Shape> core::ops::Deref for Buffer<'_, T, D, S> {
 
     #[inline]
     fn deref(&self) -> &Self::Target {
+        crate::sanitize(&self.ptr);
         unsafe { core::slice::from_raw_parts(D::as_ptr(&self.ptr), self.len()) }
     }
 }
@@ -652,6 +758,7 @@ impl<T, D: MainMemory, S: Shape> core::ops::Deref for Buffer<'_, T, D, S> {
 impl<T, D: MainMemory, S: Shape> core::ops::DerefMut for Buffer<'_, T, D, S> {
     #[inline]
     fn deref_mut(&mut self) -> &mut Self::Target {
+        crate::sanitize(&self.ptr);
         unsafe { core::slice::from_raw_parts_mut(D::as_ptr_mut(&mut self.ptr), self.len()) }
     }
 }
@@ -12,6 +12,7 @@ where
     D: Alloc<'a, T>,
 {
     #[inline]
+    #[track_caller]
     fn from((device, array): (&'a D, [T; N])) -> Self {
         Buffer::from_slice(device, &array)
     }
@@ -22,6 +23,7 @@ where
     D: Alloc<'a, T>,
 {
     #[inline]
+    #[track_caller]
     fn from((device, len): (&'a D, usize)) -> Self {
         Buffer::new(device, len)
     }
@@ -45,6 +47,7 @@ where
     D: Alloc<'a, T>,
 {
     #[inline]
+    #[track_caller]
     fn from((device, range): (&'a D, Range<usize>)) -> Self {
         Buffer::from_vec(device, range.map(|x| T::from_usize(x)).collect())
     }
@@ -87,6 +90,7 @@ where
     D: Alloc<'a, T>,
 {
     #[inline]
+    #[track_caller]
     fn from((device, array): (&'a D, &[T; N])) -> Self {
         Buffer::from_slice(device, array)
     }
@@ -115,6 +119,7 @@ where
     D: Alloc<'a, T, S>,
 {
     #[inline]
+    #[track_caller]
     fn from((device, slice): (&'a D, &[T])) -> Self {
         Buffer::from_slice(device, slice)
     }
@@ -143,6 +148,7 @@ where
     D: Alloc<'a, T, S>,
 {
     #[inline]
+    #[track_caller]
     fn from((device, vec): (&'a D, Vec<T>)) -> Self {
         Buffer::from_vec(device, vec)
     }
@@ -156,6 +162,7 @@ where
     D: Alloc<'a, T, S>,
 {
     #[inline]
+    #[track_caller]
     fn from((device, vec): (&'a D, &Vec<T>)) -> Self {
         Buffer::from_slice(device, vec)
     }
@@ -167,6 +174,7 @@ where
     S: Shape,
     D: WriteBuf<T, S> + for<'c> Alloc<'c, T, S>,
 {
+    #[track_caller]
     fn from((device, buf): (&'a D, Buffer<'b, T, CPU, S>)) -> Self {
         let mut out = device.retrieve(buf.len(), &buf);
         device.write(&mut out, &buf);
@@ -0,0 +1,219 @@
+use core::{
+    fmt::Debug,
+    ops::{Deref, DerefMut},
+};
+use std::{rc::Rc, sync::Arc};
+
+use crate::{shape::Shape, Alloc, Buffer, CloneBuf, Device, MainMemory, Read, CPU};
+
+/// A reference counted handle that keeps a device alive, e.g. `Arc<D>` or `Rc<D>`.
+///
+/// # Safety
+/// All clones of a handle must dereference to the same device, which must not move while one of the handles is alive.
+pub unsafe trait DeviceHandle<D: Device>: Clone + Deref<Target = D> {}
+
+unsafe impl<D: Device> DeviceHandle<D> for Arc<D> {}
+unsafe impl<D: Device> DeviceHandle<D> for Rc<D> {}
+
+/// A [`Buffer`] that keeps its device alive through a [`DeviceHandle`] instead of borrowing it.
+/// Hence, an `OwnedBuffer` has no lifetime parameter and can be stored in ordinary structs or returned from the function that created the device.
+///
+/// Existing operations are called with the borrowed `Buffer` returned by [`as_buf`](OwnedBuffer::as_buf).
+/// Their results are converted back with [`from_buf`](OwnedBuffer::from_buf).
+/// # Example
+#[cfg_attr(all(feature = "cpu", feature = "macro"), doc = "```")]
+#[cfg_attr(not(all(feature = "cpu", feature = "macro")), doc = "```ignore")]
+/// use std::sync::Arc;
+/// use custos::{ApplyFunction, Combiner, OwnedBuffer, CPU};
+///
+/// struct Linear {
+///     weights: OwnedBuffer<f32>,
+/// }
+///
+/// fn create() -> Linear {
+///     let device = Arc::new(CPU::new());
+///     Linear {
+///         weights: OwnedBuffer::from_slice(&device, &[1., 2., 3.]),
+///     }
+/// }
+///
+/// let linear = create();
+///
+/// let device = linear.weights.handle();
+/// let doubled = device.apply_fn(linear.weights.as_buf(), |x| x.mul(2.));
+/// let doubled = OwnedBuffer::from_buf(device, doubled);
+///
+/// assert_eq!(doubled.read(), [2., 4., 6.]);
+/// ```
+pub struct OwnedBuffer<
+    T = f32,
+    D: Device + 'static = CPU,
+    S: Shape = (),
+    H: DeviceHandle<D> = Arc<D>,
+> {
+    // declared before `device`: the buffer is dropped while the device is still alive
+    buf: Buffer<'static, T, D, S>,
+    device: H,
+}
+
+impl<T, D: Device + 'static, S: Shape, H: DeviceHandle<D>> OwnedBuffer<T, D, S, H> {
+    /// Creates a zeroed (or values set to default) `OwnedBuffer` with the given length on the device of the handle.
+    #[inline]
+    pub fn new(device: &H, len: usize) -> Self
+    where
+        D: for<'a> Alloc<'a, T, S>,
+    {
+        OwnedBuffer::from_buf(device, Buffer::new(device, len))
+    }
+
+    /// Creates an `OwnedBuffer` from a slice.
+    #[inline]
+    pub fn from_slice(device: &H, slice: &[T]) -> Self
+    where
+        T: Clone,
+        D: for<'a> Alloc<'a, T, S>,
+    {
+        OwnedBuffer::from_buf(device, Buffer::from_slice(device, slice))
+    }
+
+    /// Creates an `OwnedBuffer` from a `Vec`.
+    #[inline]
+    pub fn from_vec(device: &H, data: Vec<T>) -> Self
+    where
+        T: Clone,
+        D: for<'a> Alloc<'a, T, S>,
+    {
+        OwnedBuffer::from_buf(device, Buffer::from_vec(device, data))
+    }
+
+    /// Takes ownership of a `Buffer` that borrows the device of the handle.
+    /// The `Buffer` is moved, not copied. A `Buffer` returned by a cached operation stays a wrapper around the cache entry.
+    /// # Panics
+    /// If the `Buffer` belongs to another device.
+    pub fn from_buf(device: &H, buf: Buffer<'_, T, D, S>) -> Self {
+        if let Some(buf_device) = buf.device {
+            assert!(
+                core::ptr::eq(buf_device, &**device),
+                "The buffer belongs to another device than the handle."
+            );
+        }
+
+        // SAFETY: the device is kept alive and in place by the cloned handle, which is dropped after the buffer.
+        let buf =
+            unsafe { core::mem::transmute::<Buffer<'_, T, D, S>, Buffer<'static, T, D, S>>(buf) };
+
+        OwnedBuffer {
+            buf,
+            device: device.clone(),
+        }
+    }
+
+    /// Converts the `OwnedBuffer` into a `Buffer` that borrows `device`.
+    /// # Panics
+    /// If `device` is not the device of the `OwnedBuffer`.
+    pub fn into_buf(self, device: &D) -> Buffer<'_, T, D, S> {
+        assert!(
+            core::ptr::eq(device, &*self.device),
+            "The buffer belongs to another device."
+        );
+        // the device outlives the returned buffer, as it is borrowed
+        self.buf
+    }
+
+    /// Returns the borrowed `Buffer`, which can be passed to all operations.
+    #[inline]
+    pub fn as_buf(&self) -> &Buffer<'_, T, D, S> {
+        &self.buf
+    }
+
+    /// Calls `f` with the mutable borrowed `Buffer`.
+    /// A `&mut Buffer` is not returned directly, as another `Buffer` with a shorter lifetime could be assigned to it.
+    #[inline]
+    pub fn with_buf_mut<R>(&mut self, f: impl for<'b> FnOnce(&mut Buffer<'b, T, D, S>) -> R) -> R {
+        f(&mut self.buf)
+    }
+
+    /// Returns the handle of the device.
+    #[inline]
+    pub fn handle(&self) -> &H {
+        &self.device
+    }
+
+    /// Returns the device.
+    #[inline]
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    /// Returns the length of the `OwnedBuffer`.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns `true` if the `OwnedBuffer` is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads the contents of the `OwnedBuffer`.
+    #[inline]
+    pub fn read(&self) -> D::Read<'_>
+    where
+        T: Clone + Default,
+        D: Read<T, S>,
+    {
+        self.device.read(self.as_buf())
+    }
+
+    /// Reads the contents of the `OwnedBuffer` and writes them into a vector.
+    #[inline]
+    pub fn read_to_vec(&self) -> Vec<T>
+    where
+        T: Clone + Default,
+        D: Read<T, S>,
+    {
+        self.device.read_to_vec(self.as_buf())
+    }
+}
+
+impl<T, D, S, H> Clone for OwnedBuffer<T, D, S, H>
+where
+    D: Device + for<'a> CloneBuf<'a, T, S> + 'static,
+    S: Shape,
+    H: DeviceHandle<D>,
+{
+    fn clone(&self) -> Self {
+        OwnedBuffer::from_buf(&self.device, self.device.clone_buf(self.as_buf()))
+    }
+}
+
+impl<T, D: MainMemory + 'static, S: Shape, H: DeviceHandle<D>> Deref for OwnedBuffer<T, D, S, H> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.buf
+    }
+}
+
+impl<T, D: MainMemory + 'static, S: Shape, H: DeviceHandle<D>> DerefMut
+    for OwnedBuffer<T, D, S, H>
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buf
+    }
+}
+
+impl<T, D, H> Debug for OwnedBuffer<T, D, (), H>
+where
+    D: Device + 'static,
+    H: DeviceHandle<D>,
+    for<'a> Buffer<'a, T, D>: Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("OwnedBuffer").field(self.as_buf()).finish()
+    }
+}
@@ -62,6 +62,10 @@ impl AsRangeArg for (usize, usize) {
 /// which is only freed when the device is dropped. <br>
 /// To disable this caching behaviour, enable the `realloc` feature.
 ///
+/// The iterations run in a [`transparent`](crate::CountScope::transparent) [`CountScope`](crate::CountScope),
+/// i.e. they use the [`Ident`](crate::Ident)s of the enclosing scope, starting at the count before the loop.
+/// The cache count is restored after the loop, even if it is left early.
+///
 /// # Example
 #[cfg_attr(not(feature = "no-std"), doc = "```")]
 #[cfg_attr(feature = "no-std", doc = "```ignore")]
@@ -87,22 +91,29 @@ pub struct Count(pub(super) usize, pub(super) usize);
 #[derive(Debug)]
 pub struct CountIntoIter {
     epoch: usize,
-    #[cfg(not(feature = "no-std"))]
-    idx: usize,
     end: usize,
+    #[cfg(not(feature = "no-std"))]
+    scope: Option<crate::CountScope>,
 }
 
 impl Iterator for CountIntoIter {
     type Item = usize;
 
     fn next(&mut self) -> Option<Self::Item> {
-        #[cfg(not(feature = "no-std"))]
-        unsafe {
-            crate::set_count(self.idx)
-        };
         if self.epoch >= self.end {
+            // restores the count of the enclosing scope
+            #[cfg(not(feature = "no-std"))]
+            {
+                self.scope = None;
+            }
             return None;
         }
+
+        #[cfg(not(feature = "no-std"))]
+        if let Some(scope) = &self.scope {
+            unsafe { scope.reset() };
+        }
+
         let epoch = Some(self.epoch);
         self.epoch += 1;
         epoch
@@ -118,9 +129,9 @@ impl IntoIterator for Count {
     fn into_iter(self) -> Self::IntoIter {
         CountIntoIter {
             epoch: self.0,
-            #[cfg(not(feature = "no-std"))]
-            idx: crate::get_count(),
             end: self.1,
+            #[cfg(not(feature = "no-std"))]
+            scope: Some(crate::CountScope::transparent()),
         }
     }
 }
@@ -133,13 +144,16 @@ mod tests {
         iter.next();
         assert_eq!(iter.epoch, 1);
         #[cfg(not(feature = "no-std"))]
-        assert_eq!(iter.idx, 0);
+        assert_eq!(crate::get_count(), 0);
         assert_eq!(iter.end, 10);
 
+        #[cfg(not(feature = "no-std"))]
+        crate::bump_count();
+
         iter.next();
         assert_eq!(iter.epoch, 2);
         #[cfg(not(feature = "no-std"))]
-        assert_eq!(iter.idx, 0);
+        assert_eq!(crate::get_count(), 0);
         assert_eq!(iter.end, 10);
     }
 
@@ -147,9 +161,9 @@ mod tests {
     fn test_count_into_iter() {
         let mut iter = CountIntoIter {
             epoch: 0,
-            #[cfg(not(feature = "no-std"))]
-            idx: 0,
             end: 10,
+            #[cfg(not(feature = "no-std"))]
+            scope: Some(crate::CountScope::transparent()),
         };
 
         count_iter(&mut iter);
@@ -170,4 +184,43 @@ mod tests {
             assert_eq!(idx, other)
         }
     }
+
+    #[cfg(not(feature = "no-std"))]
+    #[test]
+    fn test_range_restores_count_on_break() {
+        use crate::{bump_count, get_count, ident_idx};
+
+        bump_count();
+        let idx_before = ident_idx();
+
+        for epoch in range(10) {
+            bump_count();
+            bump_count();
+            if epoch == 3 {
+                break;
+            }
+        }
+
+        assert_eq!(get_count(), 1);
+        assert_eq!(ident_idx(), idx_before);
+    }
+
+    #[cfg(not(feature = "no-std"))]
+    #[test]
+    fn test_nested_range() {
+        use crate::{bump_count, get_count, ident_idx};
+
+        let mut inner_idents = vec![];
+        for _ in range(3) {
+            bump_count();
+            for _ in range(2) {
+                inner_idents.push(ident_idx());
+                bump_count();
+            }
+            assert_eq!(get_count(), 1);
+        }
+
+        assert!(inner_idents.iter().all(|idx| *idx == inner_idents[0]));
+        assert_eq!(get_count(), 0);
+    }
 }
@@ -1,6 +1,9 @@
 use core::{cell::RefCell, fmt::Debug};
 
-use crate::{Cache, CacheReturn, Device, GlobalCount, Graph, GraphReturn, NodeIdx, PtrConv};
+use crate::{
+    Cache, CacheReturn, CacheStrategy, Device, GlobalCount, Graph, GraphReturn, NodeIdx, PerThread,
+    PtrConv,
+};
 
 use super::caller_cache::{CallerCacheReturn, TrackCallerCache};
 
@@ -8,6 +11,9 @@ use super::caller_cache::{CallerCacheReturn, TrackCallerCache};
 /// - `graph`: An optimizeable graph.
 /// - `cache`: A cache for allocations.
 /// - `tape`: A (gradient) tape.
+/// - `caller_cache`: A cache for allocations that is keyed by the caller location.
+/// - `cache_strategy`: Selects the cache that is used by [`Device::retrieve`]. It is chosen when the device is constructed.
+/// - `tracer`: Receives the traced operations of the device.
 pub struct Addons<D: Device, IdxFrom: NodeIdx = GlobalCount> {
     /// An optimizeable graph.
     pub graph: RefCell<Graph<IdxFrom>>,
@@ -16,7 +22,13 @@ pub struct Addons<D: Device, IdxFrom: NodeIdx = GlobalCount> {
     /// A (gradient) tape.
     #[cfg(feature = "autograd")]
     pub tape: RefCell<crate::Tape<D>>,
+    /// A cache for allocations that is keyed by the caller location.
     pub caller_cache: RefCell<TrackCallerCache<D>>,
+    /// Selects the cache that is used by [`Device::retrieve`].
+    pub cache_strategy: CacheStrategy,
+    /// Receives the traced operations of the device.
+    #[cfg(feature = "trace")]
+    pub tracer: RefCell<Option<std::sync::Arc<dyn crate::Tracer>>>,
 }
 
 impl<D: Device + Debug> Debug for Addons<D>
@@ -24,20 +36,20 @@ where
     D::Ptr<u8, ()>: Debug,
 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut debug = f.debug_struct("Addons");
+        debug
+            .field("graph", &self.graph)
+            .field("cache", &self.cache);
+
         #[cfg(feature = "autograd")]
-        {
-            f.debug_struct("Addons")
-                .field("graph", &self.graph)
-                .field("cache", &self.cache)
-                .field("tape", &self.tape)
-                .finish()
-        }
+        debug.field("tape", &self.tape);
 
-        #[cfg(not(feature = "autograd"))]
-        f.debug_struct("Addons")
-            .field("graph", &self.graph)
-            .field("cache", &self.cache)
-            .finish()
+        debug.field("cache_strategy", &self.cache_strategy);
+
+        #[cfg(feature = "trace")]
+        debug.field("tracer", &self.tracer.borrow().is_some());
+
+        debug.finish()
     }
 }
 
@@ -52,8 +64,34 @@ where
             #[cfg(feature = "autograd")]
             tape: Default::default(),
             caller_cache: Default::default(),
+            cache_strategy: Default::default(),
+            #[cfg(feature = "trace")]
+            tracer: Default::default(),
+        }
+    }
+}
+
+impl<D: Device + Default> Addons<D>
+where
+    D::Ptr<u8, ()>: Default,
+{
+    /// Returns [`Addons`] whose [`Device::retrieve`] uses the given [`CacheStrategy`].
+    #[inline]
+    pub fn with_cache_strategy(strategy: CacheStrategy) -> Self {
+        Self {
+            cache_strategy: strategy,
+            ..Default::default()
         }
     }
+
+    /// Returns [`Addons`] for every thread that use the given [`CacheStrategy`].
+    #[inline]
+    pub(crate) fn per_thread(strategy: CacheStrategy) -> PerThread<Self>
+    where
+        D: 'static,
+    {
+        PerThread::with_init(move || Addons::with_cache_strategy(strategy))
+    }
 }
 
 /// `AddonsReturn` is probably implemented for all devices that have an [`Addons`] field.
@@ -90,11 +128,16 @@ impl<D: AddonsReturn> CacheReturn for D {
     {
         self.addons().cache.borrow_mut()
     }
+
+    #[inline]
+    fn cache_strategy(&self) -> CacheStrategy {
+        self.addons().cache_strategy
+    }
 }
 
 impl<D: AddonsReturn> CallerCacheReturn for D {
     #[inline]
-    fn cache(&self) -> core::cell::Ref<TrackCallerCache<Self>>
+    fn caller_cache(&self) -> core::cell::Ref<TrackCallerCache<Self>>
     where
         Self: PtrConv,
     {
@@ -102,7 +145,7 @@ impl<D: AddonsReturn> CallerCacheReturn for D {
     }
 
     #[inline]
-    fn cache_mut(&self) -> core::cell::RefMut<TrackCallerCache<Self>>
+    fn caller_cache_mut(&self) -> core::cell::RefMut<TrackCallerCache<Self>>
     where
         Self: PtrConv,
     {
@@ -122,3 +165,19 @@ impl<D: AddonsReturn> crate::TapeReturn for D {
         self.addons().tape.borrow_mut()
     }
 }
+
+#[cfg(feature = "trace")]
+impl<D: AddonsReturn> crate::TraceReturn for D {
+    #[inline]
+    fn tracer(&self) -> Option<std::sync::Arc<dyn crate::Tracer>> {
+        self.addons().tracer.borrow().clone()
+    }
+
+    #[inline]
+    fn set_tracer(
+        &self,
+        tracer: Option<std::sync::Arc<dyn crate::Tracer>>,
+    ) -> Option<std::sync::Arc<dyn crate::Tracer>> {
+        self.addons().tracer.replace(tracer)
+    }
+}
@@ -0,0 +1,390 @@
+//! The `AnyDevice` module provides a device whose backend is selected at runtime.
+
+mod ops;
+
+use core::{ffi::c_void, fmt::Debug, mem::ManuallyDrop};
+
+use crate::{
+    cpu::CPUPtr, flag::AllocFlag, shape::Shape, AddGraph, Alloc, Buffer, CommonPtrs, Device,
+    DeviceError, Ident, MemoryReturn, MemoryTracker, PtrConv, PtrType, CPU,
+};
+
+#[cfg(feature = "opencl")]
+use crate::{opencl::CLPtr, OpenCL};
+
+#[cfg(feature = "cuda")]
+use crate::{cuda::CUDAPtr, CUDA};
+
+/// A device that forwards every operation to one of the compiled-in backends, which is selected at runtime.
+/// Application code is written once for `AnyDevice` and [`AnyBuffer`]s instead of being generic over the device.
+///
+/// Only [`Buffer`]s without a static [`Shape`] are supported.
+/// # Example
+#[cfg_attr(feature = "macro", doc = "```")]
+#[cfg_attr(not(feature = "macro"), doc = "```ignore")]
+/// use custos::{AnyBuffer, AnyDevice, ApplyFunction, Buffer, Combiner};
+///
+/// fn main() -> custos::Result<()> {
+///     // e.g. parsed from a command line flag: "cpu", "opencl", "cuda:1", ...
+///     let device = AnyDevice::from_name("cpu")?;
+///
+///     let buf: AnyBuffer = Buffer::from((&device, [1., 2., 3.]));
+///     let out = device.apply_fn(&buf, |x| x.mul(2.));
+///
+///     assert_eq!(out.read(), [2., 4., 6.]);
+///     Ok(())
+/// }
+/// ```
+#[allow(clippy::large_enum_variant)]
+pub enum AnyDevice {
+    /// A [`CPU`] device.
+    CPU(CPU),
+    /// An [`OpenCL`] device.
+    #[cfg(feature = "opencl")]
+    OpenCL(OpenCL),
+    /// A [`CUDA`] device.
+    #[cfg(feature = "cuda")]
+    CUDA(CUDA),
+}
+
+/// A [`Buffer`] of an [`AnyDevice`].
+pub type AnyBuffer<'a, T = f32> = Buffer<'a, T, AnyDevice>;
+
+/// The pointer of an [`AnyBuffer`]. The variant matches the backend of the device.
+#[derive(Debug, PartialEq, Eq)]
+pub enum AnyPtr<T> {
+    /// The pointer of a [`CPU`] buffer.
+    CPU(CPUPtr<T>),
+    /// The pointer of an [`OpenCL`] buffer.
+    #[cfg(feature = "opencl")]
+    OpenCL(CLPtr<T>),
+    /// The pointer of a [`CUDA`] buffer.
+    #[cfg(feature = "cuda")]
+    CUDA(CUDAPtr<T>),
+}
+
+/// Matches on the backend of an [`AnyDevice`] and evaluates `$body` with the inner device bound to `$dev`.
+macro_rules! forward {
+    ($device:expr, $dev:ident => $body:expr) => {
+        match $device {
+            AnyDevice::CPU($dev) => $body,
+            #[cfg(feature = "opencl")]
+            AnyDevice::OpenCL($dev) => $body,
+            #[cfg(feature = "cuda")]
+            AnyDevice::CUDA($dev) => $body,
+        }
+    };
+}
+
+pub(crate) use forward;
+
+impl AnyDevice {
+    /// Creates the device described by `name`, e.g. `"cpu"`, `"opencl"`, `"cuda"` or `"opencl:1"` for the device with index 1.
+    /// # Errors
+    /// - The backend is unknown, its feature is disabled or the CPU is selected with an index other than 0 ([`DeviceError::UnknownDevice`]).
+    /// - The backend failed to create the device.
+    pub fn from_name(name: &str) -> crate::Result<AnyDevice> {
+        let (backend, idx) = match name.split_once(':') {
+            Some((backend, idx)) => (
+                backend,
+                idx.trim()
+                    .parse::<usize>()
+                    .map_err(|_| DeviceError::UnknownDevice)?,
+            ),
+            None => (name, 0),
+        };
+
+        match backend.trim().to_lowercase().as_str() {
+            // there is only one CPU
+            "cpu" if idx == 0 => Ok(AnyDevice::CPU(CPU::new())),
+            #[cfg(feature = "opencl")]
+            "opencl" | "cl" => Ok(AnyDevice::OpenCL(OpenCL::new(idx)?)),
+            #[cfg(feature = "cuda")]
+            "cuda" | "cu" => Ok(AnyDevice::CUDA(CUDA::new(idx)?)),
+            _ => Err(DeviceError::UnknownDevice.into()),
+        }
+    }
+
+    /// Returns the name of the selected backend: `"cpu"`, `"opencl"` or `"cuda"`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnyDevice::CPU(_) => "cpu",
+            #[cfg(feature = "opencl")]
+            AnyDevice::OpenCL(_) => "opencl",
+            #[cfg(feature = "cuda")]
+            AnyDevice::CUDA(_) => "cuda",
+        }
+    }
+}
+
+impl Debug for AnyDevice {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        forward!(self, device => f.debug_tuple("AnyDevice").field(device).finish())
+    }
+}
+
+impl From<CPU> for AnyDevice {
+    #[inline]
+    fn from(device: CPU) -> Self {
+        AnyDevice::CPU(device)
+    }
+}
+
+#[cfg(feature = "opencl")]
+impl From<OpenCL> for AnyDevice {
+    #[inline]
+    fn from(device: OpenCL) -> Self {
+        AnyDevice::OpenCL(device)
+    }
+}
+
+#[cfg(feature = "cuda")]
+impl From<CUDA> for AnyDevice {
+    #[inline]
+    fn from(device: CUDA) -> Self {
+        AnyDevice::CUDA(device)
+    }
+}
+
+/// A backend of an [`AnyDevice`].
+pub(crate) trait Backend: PtrConv {
+    /// Wraps the pointer of the backend into an [`AnyPtr`].
+    fn into_any_ptr<T, S: Shape>(ptr: Self::Ptr<T, S>) -> AnyPtr<T>;
+
+    /// Returns the pointer of the backend, if `ptr` belongs to this backend.
+    fn backend_ptr<T, S: Shape>(ptr: &AnyPtr<T>) -> Option<&Self::Ptr<T, S>>;
+}
+
+impl Backend for CPU {
+    #[inline]
+    fn into_any_ptr<T, S: Shape>(ptr: CPUPtr<T>) -> AnyPtr<T> {
+        AnyPtr::CPU(ptr)
+    }
+
+    #[inline]
+    fn backend_ptr<T, S: Shape>(ptr: &AnyPtr<T>) -> Option<&CPUPtr<T>> {
+        match ptr {
+            AnyPtr::CPU(ptr) => Some(ptr),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "opencl")]
+impl Backend for OpenCL {
+    #[inline]
+    fn into_any_ptr<T, S: Shape>(ptr: CLPtr<T>) -> AnyPtr<T> {
+        AnyPtr::OpenCL(ptr)
+    }
+
+    #[inline]
+    fn backend_ptr<T, S: Shape>(ptr: &AnyPtr<T>) -> Option<&CLPtr<T>> {
+        match ptr {
+            AnyPtr::OpenCL(ptr) => Some(ptr),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "cuda")]
+impl Backend for CUDA {
+    #[inline]
+    fn into_any_ptr<T, S: Shape>(ptr: CUDAPtr<T>) -> AnyPtr<T> {
+        AnyPtr::CUDA(ptr)
+    }
+
+    #[inline]
+    fn backend_ptr<T, S: Shape>(ptr: &AnyPtr<T>) -> Option<&CUDAPtr<T>> {
+        match ptr {
+            AnyPtr::CUDA(ptr) => Some(ptr),
+            _ => None,
+        }
+    }
+}
+
+/// Borrows an [`AnyBuffer`] as a `Buffer` of the backend `D`. The returned `Buffer` does not own its memory.
+/// # Panics
+/// If the buffer was allocated by another backend.
+pub(crate) fn backend_buf<'a, T, D: Backend, S: Shape>(
+    device: &'a D,
+    buf: &Buffer<T, AnyDevice, S>,
+) -> Buffer<'a, T, D, S> {
+    let ptr = D::backend_ptr::<T, S>(&buf.ptr)
+        .expect("The buffer was allocated by another backend than the one of the device.");
+
+    Buffer {
+        ptr: unsafe { D::convert(ptr, AllocFlag::Wrapper) },
+        device: Some(device),
+        ident: buf.ident,
+    }
+}
+
+/// Moves the pointer of a `Buffer` of the backend `D` into an [`AnyBuffer`].
+pub(crate) fn into_any_buf<'a, T, D: Backend, S: Shape>(
+    device: &'a AnyDevice,
+    buf: Buffer<'_, T, D, S>,
+) -> Buffer<'a, T, AnyDevice, S> {
+    // the pointer is moved, hence `buf` must not be dropped
+    let buf = ManuallyDrop::new(buf);
+
+    Buffer {
+        ptr: D::into_any_ptr(unsafe { core::ptr::read(&buf.ptr) }),
+        device: Some(device),
+        ident: buf.ident,
+    }
+}
+
+impl Device for AnyDevice {
+    type Ptr<U, S: Shape> = AnyPtr<U>;
+    type Cache = ();
+
+    fn new() -> crate::Result<Self> {
+        Ok(AnyDevice::CPU(CPU::new()))
+    }
+
+    #[inline]
+    fn synchronize(&self) -> crate::Result<()> {
+        forward!(self, device => device.synchronize())
+    }
+
+    #[inline]
+    #[track_caller]
+    fn retrieve<T, S: Shape>(&self, len: usize, add_node: impl AddGraph) -> Buffer<'_, T, Self, S>
+    where
+        for<'a> Self: Alloc<'a, T, S>,
+    {
+        forward!(self, device => into_any_buf(self, device.retrieve::<T, S>(len, add_node)))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn try_retrieve<T, S: Shape>(
+        &self,
+        len: usize,
+        add_node: impl AddGraph,
+    ) -> crate::Result<Buffer<'_, T, Self, S>>
+    where
+        for<'a> Self: Alloc<'a, T, S>,
+    {
+        forward!(self, device => Ok(into_any_buf(self, device.try_retrieve::<T, S>(len, add_node)?)))
+    }
+
+    #[cfg(feature = "autograd")]
+    #[inline]
+    unsafe fn get_existing_buf<T, S: Shape>(&self, ident: Ident) -> Buffer<'_, T, Self, S> {
+        forward!(self, device => into_any_buf(self, device.get_existing_buf::<T, S>(ident)))
+    }
+
+    #[inline]
+    fn remove(&self, ident: Ident) {
+        forward!(self, device => device.remove(ident))
+    }
+
+    #[inline]
+    fn add_to_cache<T, S: Shape>(&self, ptr: &AnyPtr<T>) -> Option<Ident> {
+        fn add_to_cache<T, S: Shape, D: Backend>(device: &D, ptr: &AnyPtr<T>) -> Option<Ident> {
+            device.add_to_cache::<T, S>(D::backend_ptr::<T, S>(ptr)?)
+        }
+        forward!(self, device => add_to_cache::<T, S, _>(device, ptr))
+    }
+}
+
+impl MemoryReturn for AnyDevice {
+    #[inline]
+    fn memory(&self) -> &MemoryTracker {
+        forward!(self, device => device.memory())
+    }
+}
+
+impl<T, S: Shape> Alloc<'_, T, S> for AnyDevice {
+    #[inline]
+    #[track_caller]
+    fn try_alloc(&self, len: usize, flag: AllocFlag) -> crate::Result<AnyPtr<T>> {
+        #[track_caller]
+        fn try_alloc<T, S: Shape, D>(
+            device: &D,
+            len: usize,
+            flag: AllocFlag,
+        ) -> crate::Result<AnyPtr<T>>
+        where
+            D: Backend + for<'a> Alloc<'a, T, S>,
+        {
+            Ok(D::into_any_ptr::<T, S>(device.try_alloc(len, flag)?))
+        }
+        forward!(self, device => try_alloc::<T, S, _>(device, len, flag))
+    }
+
+    #[inline]
+    fn try_with_slice(&self, data: &[T]) -> crate::Result<AnyPtr<T>>
+    where
+        T: Clone,
+    {
+        fn try_with_slice<T: Clone, S: Shape, D>(device: &D, data: &[T]) -> crate::Result<AnyPtr<T>>
+        where
+            D: Backend + for<'a> Alloc<'a, T, S>,
+        {
+            Ok(D::into_any_ptr::<T, S>(device.try_with_slice(data)?))
+        }
+        forward!(self, device => try_with_slice::<T, S, _>(device, data))
+    }
+}
+
+impl<T> PtrType for AnyPtr<T> {
+    #[inline]
+    fn size(&self) -> usize {
+        match self {
+            AnyPtr::CPU(ptr) => ptr.size(),
+            #[cfg(feature = "opencl")]
+            AnyPtr::OpenCL(ptr) => ptr.size(),
+            #[cfg(feature = "cuda")]
+            AnyPtr::CUDA(ptr) => ptr.size(),
+        }
+    }
+
+    #[inline]
+    fn flag(&self) -> AllocFlag {
+        match self {
+            AnyPtr::CPU(ptr) => ptr.flag(),
+            #[cfg(feature = "opencl")]
+            AnyPtr::OpenCL(ptr) => ptr.flag(),
+            #[cfg(feature = "cuda")]
+            AnyPtr::CUDA(ptr) => ptr.flag(),
+        }
+    }
+
+    #[inline]
+    fn allocation(&self) -> Option<&crate::Allocation> {
+        match self {
+            AnyPtr::CPU(ptr) => ptr.allocation(),
+            #[cfg(feature = "opencl")]
+            AnyPtr::OpenCL(ptr) => ptr.allocation(),
+            #[cfg(feature = "cuda")]
+            AnyPtr::CUDA(ptr) => ptr.allocation(),
+        }
+    }
+}
+
+impl<T> CommonPtrs<T> for AnyPtr<T> {
+    #[inline]
+    fn ptrs(&self) -> (*const T, *mut c_void, u64) {
+        match self {
+            AnyPtr::CPU(ptr) => ptr.ptrs(),
+            #[cfg(feature = "opencl")]
+            AnyPtr::OpenCL(ptr) => ptr.ptrs(),
+            #[cfg(feature = "cuda")]
+            AnyPtr::CUDA(ptr) => ptr.ptrs(),
+        }
+    }
+
+    #[inline]
+    fn ptrs_mut(&mut self) -> (*mut T, *mut c_void, u64) {
+        match self {
+            AnyPtr::CPU(ptr) => ptr.ptrs_mut(),
+            #[cfg(feature = "opencl")]
+            AnyPtr::OpenCL(ptr) => ptr.ptrs_mut(),
+            #[cfg(feature = "cuda")]
+            AnyPtr::CUDA(ptr) => ptr.ptrs_mut(),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use core::ops::{Range, RangeBounds};
+
+use crate::{Alloc, Buffer, CDatatype, ClearBuf, CloneBuf, CopySlice, Read, Transfer, WriteBuf};
+
+use super::{backend_buf, forward, into_any_buf, AnyDevice};
+
+impl<T: Clone + Default> Read<T> for AnyDevice {
+    type Read<'a>
+        = Vec<T>
+    where
+        T: 'a;
+
+    #[inline]
+    fn try_read<'a>(&self, buf: &'a Buffer<T, AnyDevice>) -> crate::Result<Self::Read<'a>> {
+        Ok(self.read_to_vec(buf))
+    }
+
+    #[inline]
+    fn read_to_vec(&self, buf: &Buffer<T, AnyDevice>) -> Vec<T> {
+        forward!(self, device => device.read_to_vec(&backend_buf(device, buf)))
+    }
+}
+
+impl<T: Clone + Default, Dst> Transfer<T, Dst> for AnyDevice
+where
+    Dst: for<'b> Alloc<'b, T>,
+{
+    #[inline]
+    fn try_transfer<'b>(
+        &self,
+        buf: &Buffer<T, AnyDevice>,
+        dst: &'b Dst,
+    ) -> crate::Result<Buffer<'b, T, Dst>> {
+        Buffer::try_from_vec(dst, self.try_read(buf)?)
+    }
+}
+
+impl<T: Copy> WriteBuf<T> for AnyDevice {
+    #[inline]
+    fn try_write(&self, buf: &mut Buffer<T, AnyDevice>, data: &[T]) -> crate::Result<()> {
+        forward!(self, device => device.try_write(&mut backend_buf(device, buf), data))
+    }
+
+    #[inline]
+    fn write_buf(&self, dst: &mut Buffer<T, AnyDevice>, src: &Buffer<T, AnyDevice>) {
+        forward!(self, device => device.write_buf(&mut backend_buf(device, dst), &backend_buf(device, src)))
+    }
+}
+
+impl<T: CDatatype + Default> ClearBuf<T> for AnyDevice {
+    #[inline]
+    fn clear(&self, buf: &mut Buffer<T, AnyDevice>) {
+        forward!(self, device => device.clear(&mut backend_buf(device, buf)))
+    }
+}
+
+impl<T: Copy> CopySlice<T> for AnyDevice {
+    #[inline]
+    fn try_copy_slice_to<SR: RangeBounds<usize>, DR: RangeBounds<usize>>(
+        &self,
+        source: &Buffer<T, AnyDevice>,
+        source_range: SR,
+        dest: &mut Buffer<T, AnyDevice>,
+        dest_range: DR,
+    ) -> crate::Result<()> {
+        forward!(self, device => device.try_copy_slice_to(
+            &backend_buf(device, source),
+            source_range,
+            &mut backend_buf(device, dest),
+            dest_range,
+        ))
+    }
+
+    #[inline]
+    fn copy_slice_all<I: IntoIterator<Item = (Range<usize>, Range<usize>)>>(
+        &self,
+        source: &Buffer<T, AnyDevice>,
+        dest: &mut Buffer<T, AnyDevice>,
+        ranges: I,
+    ) {
+        forward!(self, device => device.copy_slice_all(
+            &backend_buf(device, source),
+            &mut backend_buf(device, dest),
+            ranges,
+        ))
+    }
+}
+
+impl<'a, T: Clone> CloneBuf<'a, T> for AnyDevice {
+    #[inline]
+    fn clone_buf(&'a self, buf: &Buffer<'a, T, AnyDevice>) -> Buffer<'a, T, AnyDevice> {
+        forward!(self, device => into_any_buf(self, device.clone_buf(&backend_buf(device, buf))))
+    }
+}
+
+#[cfg(feature = "macro")]
+impl<T> crate::ApplyFunction<T> for AnyDevice
+where
+    T: CDatatype + crate::number::Number + crate::ToVal,
+{
+    #[track_caller]
+    fn try_apply_fn<F>(
+        &self,
+        buf: &Buffer<T, AnyDevice>,
+        f: impl Fn(crate::Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<'_, T, AnyDevice>>
+    where
+        F: crate::Eval<T> + crate::MayToCLSource,
+    {
+        Ok(match self {
+            AnyDevice::CPU(device) => {
+                into_any_buf(self, device.try_apply_fn(&backend_buf(device, buf), f)?)
+            }
+            #[cfg(feature = "opencl")]
+            AnyDevice::OpenCL(device) => {
+                into_any_buf(self, device.try_apply_fn(&backend_buf(device, buf), f)?)
+            }
+            // CUDA has no native `ApplyFunction`, it copies `buf` to the host and back (see `FallbackOps`)
+            #[cfg(feature = "cuda")]
+            AnyDevice::CUDA(device) => {
+                into_any_buf(self, device.try_apply_fn(&backend_buf(device, buf), f)?)
+            }
+        })
+    }
+}
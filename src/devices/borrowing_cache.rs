@@ -8,6 +8,11 @@ pub(crate) struct BorrowingCache {
     pub(crate) cache: HashMap<Ident, Box<dyn Any>, BuildHasherDefault<IdentHasher>>,
 }
 
+// SAFETY: Only `Buffer`s with the `BorrowedCache` flag are stored.
+// Dropping such a `Buffer` deallocates its memory without accessing the device or the elements.
+// The `Buffer`s are only accessed through the `Tape` of the thread that owns the `BorrowingCache`.
+unsafe impl Send for BorrowingCache {}
+
 // TODO: make BorrowedCache unuseable without device (=> Static get methods with D: CacheReturn)
 impl BorrowingCache {
     pub(crate) fn add_or_get<'a, T, D, S>(
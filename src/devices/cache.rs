@@ -1,15 +1,41 @@
 //! Contains the [`Cache`]ing logic.
 
-use core::{cell::RefMut, fmt::Debug, hash::BuildHasherDefault, ops::BitXor};
-use std::collections::HashMap;
+use core::{any::TypeId, cell::RefMut, fmt::Debug, hash::BuildHasherDefault, ops::BitXor};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::{
-    flag::AllocFlag, shape::Shape, Alloc, Buffer, CacheAble, Device, GlobalCount, GraphReturn,
-    Ident, PtrConv, PtrType,
+    flag::AllocFlag, shape::Shape, Alloc, Allocation, Buffer, CacheAble, CallerCacheReturn, Device,
+    GlobalCount, GraphReturn, Ident, MayTrace, PtrConv, PtrType,
 };
 
+/// Selects how [`Device::retrieve`] reuses allocations.
+/// It is chosen when a device is constructed, e.g. `CPU::new().with_cache_strategy(CacheStrategy::Caller)`, and can't be changed afterwards.
+/// With the `realloc` feature enabled, every call allocates a new [`Buffer`] regardless of the strategy.
+///
+/// [`Stack`](crate::Stack) allocates its arrays by value and has no cache, hence it does not accept a strategy:
+#[cfg_attr(feature = "stack", doc = "```compile_fail")]
+#[cfg_attr(not(feature = "stack"), doc = "```ignore")]
+/// use custos::{CacheStrategy, Stack};
+///
+/// let device = Stack.with_cache_strategy(CacheStrategy::Caller);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheStrategy {
+    /// Entries are identified by the thread-local cache count, see [`Cache`].
+    /// Loops need a [`range`](crate::range) or [`set_count`](crate::set_count) to reuse the entries.
+    #[default]
+    Count,
+    /// Entries are identified by the source location of the caller, see [`TrackCallerCache`](crate::TrackCallerCache).
+    /// No count bookkeeping is required, but every operation between the user code and the `retrieve` call must be `#[track_caller]`.
+    /// Otherwise, all calls of an operation share the same entry.
+    /// The entries are not recorded in the [`Graph`](crate::Graph) and can't be used for gradients.
+    Caller,
+    /// Every call allocates a new [`Buffer`].
+    None,
+}
+
 /// This trait makes a device's [`Cache`] accessible and is implemented for all compute devices.
 pub trait CacheReturn: GraphReturn<GlobalCount> {
     /// Returns a reference to a device's [`Cache`].
@@ -21,6 +47,10 @@ pub trait CacheReturn: GraphReturn<GlobalCount> {
     fn cache_mut(&self) -> RefMut<Cache<Self>>
     where
         Self: PtrConv;
+
+    /// Returns the [`CacheStrategy`] that is used by [`Device::retrieve`].
+    /// It is chosen when the device is constructed, e.g. with [`CPU::with_cache_strategy`](crate::CPU::with_cache_strategy).
+    fn cache_strategy(&self) -> CacheStrategy;
 }
 
 const K: usize = 0x517cc1b727220a95;
@@ -50,42 +80,69 @@ impl std::hash::Hasher for IdentHasher {
 
 impl<D> CacheAble<D> for Cache<D>
 where
-    D: PtrConv + CacheReturn,
+    D: PtrConv + CacheReturn + CallerCacheReturn + MayTrace,
 {
     #[cfg(not(feature = "realloc"))]
     #[inline]
-    fn retrieve<T, S: Shape>(
+    #[track_caller]
+    fn try_retrieve<T, S: Shape>(
         device: &D,
         len: usize,
         add_node: impl crate::AddGraph,
-    ) -> Buffer<T, D, S>
+    ) -> crate::Result<Buffer<T, D, S>>
     where
         for<'b> D: Alloc<'b, T, S>,
     {
-        device
-            .cache_mut()
-            .get(device, Ident::new(len), add_node, crate::bump_count)
+        let mut span =
+            crate::trace::span(device, "retrieve", len * core::mem::size_of::<T>(), None);
+
+        let buf = match device.cache_strategy() {
+            CacheStrategy::Count => device.cache_mut().try_get_entry(
+                device,
+                Ident::new(len),
+                add_node,
+                crate::bump_count,
+            ),
+            CacheStrategy::Caller => {
+                device
+                    .caller_cache_mut()
+                    .try_get(device, Ident::new(len), crate::bump_count)
+            }
+            CacheStrategy::None => Buffer::try_new(device, len),
+        };
+
+        if let Ok(buf) = &buf {
+            span.set_ident(buf.ident);
+        }
+        buf
     }
 
     #[cfg(feature = "realloc")]
     #[inline]
-    fn retrieve<T, S: Shape>(
+    fn try_retrieve<T, S: Shape>(
         device: &D,
         len: usize,
         _add_node: impl crate::AddGraph,
-    ) -> Buffer<T, D, S>
+    ) -> crate::Result<Buffer<T, D, S>>
     where
         for<'b> D: Alloc<'b, T, S>,
     {
-        Buffer::new(device, len)
+        let _span = crate::trace::span(device, "retrieve", len * core::mem::size_of::<T>(), None);
+        Buffer::try_new(device, len)
     }
 
     #[inline]
     unsafe fn get_existing_buf<T, S: Shape>(device: &D, ident: Ident) -> Option<Buffer<T, D, S>> {
-        let ptr = D::convert(device.cache().nodes.get(&ident)?, AllocFlag::Wrapper);
+        let cache = device.cache();
 
-        Some(Buffer {
-            ptr,
+        #[cfg(debug_assertions)]
+        return cache
+            .try_get_existing_buf(device, ident)
+            .unwrap_or_else(|mismatch| panic!("{mismatch}"));
+
+        #[cfg(not(debug_assertions))]
+        cache.nodes.get(&ident).map(|ptr| Buffer {
+            ptr: D::convert(ptr, AllocFlag::Wrapper),
             device: Some(device),
             ident: Some(ident),
         })
@@ -93,22 +150,239 @@ where
 
     #[inline]
     fn remove(device: &D, ident: Ident) {
-        device.cache_mut().nodes.remove(&ident);
+        let mut cache = device.cache_mut();
+        // Only entries added by `add_to_cache` are removed.
+        // If the `Buffer` is dropped on another thread, the cache of that thread may contain an unrelated entry with the same `Ident`.
+        if cache.external.remove(&ident) {
+            cache.remove(&ident);
+        }
     }
 
     fn add_to_cache<T, S: Shape>(device: &D, ptr: &<D as Device>::Ptr<T, S>) -> Option<Ident> {
-        device.graph_mut().add_leaf(ptr.size());
+        {
+            let mut graph = device.graph_mut();
+            let node = graph.add_leaf(ptr.size());
+            graph.set_elem_size(node.idx, core::mem::size_of::<T>());
+        }
         let ident = Ident::new_bumped(ptr.size());
-        let raw_ptr = unsafe { std::rc::Rc::new(D::convert(ptr, AllocFlag::Wrapper)) };
-        device.cache_mut().nodes.insert(ident, raw_ptr);
+        let raw_ptr = unsafe { Arc::new(D::convert(ptr, AllocFlag::Wrapper)) };
+
+        let mut cache = device.cache_mut();
+        cache.external.insert(ident);
+        cache.track_use::<T, S>(ident);
+        cache.insert(ident, raw_ptr);
         Some(ident)
     }
 }
 
+/// Bookkeeping information of a [`Cache`] entry.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EntryInfo {
+    /// The size of the cached allocation in bytes.
+    pub bytes: usize,
+    /// The size of one element in bytes.
+    pub elem_size: usize,
+    /// How often the entry was reused after its allocation.
+    pub hits: usize,
+    /// The value of the cache's use counter at the last time this entry was used.
+    pub last_use: usize,
+    /// The element type and shape the entry was allocated with.
+    pub ty: Option<EntryType>,
+}
+
+/// The element type and [`Shape`] of a cache entry.
+/// The element type is identified by its name, as `T` is not required to be `'static`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryType {
+    /// The name of the element type.
+    pub type_name: &'static str,
+    /// The [`TypeId`] of the [`Shape`].
+    pub shape: TypeId,
+    /// The name of the [`Shape`].
+    pub shape_name: &'static str,
+}
+
+impl EntryType {
+    /// Returns the [`EntryType`] of a `Buffer<T, D, S>`.
+    #[inline]
+    pub fn of<T, S: Shape>() -> EntryType {
+        EntryType {
+            type_name: core::any::type_name::<T>(),
+            shape: TypeId::of::<S>(),
+            shape_name: core::any::type_name::<S>(),
+        }
+    }
+}
+
+/// A cache entry was retrieved with another element type or [`Shape`] than it was allocated with.
+/// This usually means that the cache count drifted, e.g. because a `range` or `set_count` is missing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeMismatch {
+    /// The [`Ident`] of the entry.
+    pub ident: Ident,
+    /// The type the entry was allocated with.
+    pub allocated: EntryType,
+    /// The type the entry was retrieved with.
+    pub requested: EntryType,
+}
+
+impl core::fmt::Display for TypeMismatch {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "The cache entry with idx {} (len {}) was allocated as Buffer<{}, _, {}>, but retrieved as Buffer<{}, _, {}>. The cache count may have drifted.",
+            self.ident.idx,
+            self.ident.len,
+            self.allocated.type_name,
+            self.allocated.shape_name,
+            self.requested.type_name,
+            self.requested.shape_name,
+        )
+    }
+}
+
+impl std::error::Error for TypeMismatch {}
+
+impl EntryInfo {
+    /// Returns an error if the entry was allocated with another element type or [`Shape`] than `T` and `S`.
+    /// Entries without a recorded type are accepted.
+    pub fn check_type<T, S: Shape>(&self, ident: Ident) -> Result<(), TypeMismatch> {
+        let Some(allocated) = self.ty else {
+            return Ok(());
+        };
+        let requested = EntryType::of::<T, S>();
+
+        if allocated == requested {
+            return Ok(());
+        }
+
+        Err(TypeMismatch {
+            ident,
+            allocated,
+            requested,
+        })
+    }
+}
+
+/// Statistics about the usage of a cache.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CacheStats {
+    /// The amount of retrievals that reused an existing entry.
+    pub hits: usize,
+    /// The amount of retrievals that allocated a new entry.
+    pub misses: usize,
+    /// The amount of entries in the cache.
+    pub live_entries: usize,
+    /// The amount of bytes that are currently allocated by the cache.
+    pub total_bytes: usize,
+    /// The allocated bytes grouped by the size of one element.
+    pub bytes_by_elem_size: BTreeMap<usize, usize>,
+    /// The highest amount of bytes that were allocated by the cache at the same time.
+    pub high_water_mark: usize,
+}
+
+impl CacheStats {
+    /// Collects the live entries and allocated bytes. Shared allocations are only counted once.
+    pub(crate) fn collect<'a, P: PtrType + 'a>(
+        &mut self,
+        entries: impl Iterator<Item = (&'a Arc<P>, Option<&'a EntryInfo>)>,
+    ) {
+        let mut allocations = HashMap::<*const P, EntryInfo>::new();
+
+        self.live_entries = 0;
+        for (ptr, info) in entries {
+            self.live_entries += 1;
+            if ptr.flag() == AllocFlag::Wrapper {
+                continue;
+            }
+            let info = info.copied().unwrap_or_default();
+            let allocation = allocations.entry(Arc::as_ptr(ptr)).or_default();
+            if info.bytes >= allocation.bytes {
+                *allocation = info;
+            }
+        }
+
+        self.bytes_by_elem_size.clear();
+        for info in allocations.values() {
+            *self.bytes_by_elem_size.entry(info.elem_size).or_default() += info.bytes;
+        }
+
+        self.total_bytes = allocations.values().map(|info| info.bytes).sum();
+        self.high_water_mark = self.high_water_mark.max(self.total_bytes);
+    }
+}
+
+/// Cache entries that were retrieved while a [`Plan`](crate::Plan) was captured or replayed, in the order of retrieval.
+type CapturedEntries<P> = Vec<(Ident, Arc<P>)>;
+
+/// The retrieved entries and the recorded operations of a capture.
+type Captured<P> = (CapturedEntries<P>, Option<Vec<CapturedOp>>);
+
+/// An operation that was recorded while a [`Plan`](crate::Plan) was captured.
+pub(crate) struct CapturedOp {
+    pub name: &'static str,
+    /// The cache entry the operation writes to.
+    pub output: Option<Ident>,
+    /// The allocations the operation accesses. It is only replayed while none of them was freed.
+    pub allocations: Vec<Allocation>,
+    pub exec: Box<dyn Fn() + Send>,
+}
+
+impl CapturedOp {
+    /// Returns `None` if the memory of an accessed pointer is not tracked, hence it can't be checked before a replay.
+    pub(crate) fn new(
+        name: &'static str,
+        output: Option<Ident>,
+        allocations: &[Option<&Allocation>],
+        exec: impl Fn() + Send + 'static,
+    ) -> Option<CapturedOp> {
+        Some(CapturedOp {
+            name,
+            output,
+            allocations: allocations
+                .iter()
+                .map(|allocation| allocation.cloned())
+                .collect::<Option<_>>()?,
+            exec: Box::new(exec),
+        })
+    }
+}
+
+/// Whether the retrieved entries of a cache are recorded or replayed.
+#[derive(Default)]
+enum Capture<P> {
+    #[default]
+    Idle,
+    Recording {
+        entries: CapturedEntries<P>,
+        /// `None` if an operation could not be recorded.
+        ops: Option<Vec<CapturedOp>>,
+    },
+    Replaying {
+        entries: CapturedEntries<P>,
+        pos: usize,
+    },
+}
+
 /// A cache for 'no-generic' raw pointers.
 pub struct Cache<D: Device> {
     /// A map of all cached buffers using a custom hash function.
-    pub nodes: HashMap<Ident, Rc<D::Ptr<u8, ()>>, BuildHasherDefault<IdentHasher>>,
+    /// Entries should be added and removed with [`insert`](Cache::insert) and [`remove`](Cache::remove), which keep the allocated bytes up to date.
+    pub nodes: HashMap<Ident, Arc<D::Ptr<u8, ()>>, BuildHasherDefault<IdentHasher>>,
+    /// The size and last use of the entries in `nodes`.
+    pub entries: HashMap<Ident, EntryInfo, BuildHasherDefault<IdentHasher>>,
+    /// Entries that are never evicted, e.g. allocations that are shared with shorter entries after an optimization.
+    pub pinned: HashSet<Ident, BuildHasherDefault<IdentHasher>>,
+    /// Entries that wrap around the memory of a user owned [`Buffer`], see [`CacheAble::add_to_cache`].
+    /// These entries are never returned by [`get`](Cache::get).
+    pub external: HashSet<Ident, BuildHasherDefault<IdentHasher>>,
+    budget: Option<usize>,
+    use_counter: usize,
+    // the number of entries and the bytes of every allocation in `nodes`, keyed by the address of the shared pointer
+    allocations: HashMap<usize, (usize, usize)>,
+    allocated_bytes: usize,
+    stats: CacheStats,
+    capture: Capture<D::Ptr<u8, ()>>,
 }
 
 impl<D: Device> Debug for Cache<D>
@@ -116,8 +390,9 @@ where
     D::Ptr<u8, ()>: Debug,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Cache2")
+        f.debug_struct("Cache")
             .field("cache", &self.nodes)
+            .field("budget", &self.budget)
             .finish()
     }
 }
@@ -130,6 +405,317 @@ where
     fn default() -> Self {
         Self {
             nodes: Default::default(),
+            entries: Default::default(),
+            pinned: Default::default(),
+            external: Default::default(),
+            budget: None,
+            use_counter: 0,
+            allocations: Default::default(),
+            allocated_bytes: 0,
+            stats: CacheStats::default(),
+            capture: Capture::Idle,
+        }
+    }
+}
+
+impl<D: Device> Cache<D> {
+    /// Returns the maximum amount of bytes the cache may hold before unused entries are evicted.
+    #[inline]
+    pub fn budget(&self) -> Option<usize> {
+        self.budget
+    }
+
+    /// Sets the maximum amount of bytes the cache may hold. `None` disables the limit.
+    /// If a new entry exceeds the budget, the least recently used entries that are no longer referenced are evicted.
+    /// An entry is referenced as long as a [`Buffer`] that was retrieved from it is alive, see [`Allocation::handles`](crate::Allocation::handles).
+    /// # Example
+    #[cfg_attr(all(feature = "cpu", not(feature = "realloc")), doc = "```")]
+    #[cfg_attr(not(all(feature = "cpu", not(feature = "realloc"))), doc = "```ignore")]
+    /// use custos::{CacheReturn, Device, Ident, CPU};
+    ///
+    /// let device = CPU::new();
+    /// device.cache_mut().set_budget(Some(40));
+    ///
+    /// let first_ident = {
+    ///     let buf = device.retrieve::<f32, ()>(10, ());
+    ///     buf.id()
+    /// };
+    /// let _second = device.retrieve::<f32, ()>(10, ());
+    ///
+    /// // the first entry was evicted to stay within the budget
+    /// assert!(device.cache().nodes.get(&first_ident).is_none());
+    /// assert_eq!(device.cache().allocated_bytes(), 40);
+    /// ```
+    #[inline]
+    pub fn set_budget(&mut self, budget: Option<usize>) {
+        self.budget = budget;
+        self.trim();
+    }
+
+    /// Records a new entry with the given [`Ident`] that stores a `Buffer<T, D, S>`.
+    #[inline]
+    pub fn track_use<T, S: Shape>(&mut self, ident: Ident) {
+        self.use_counter += 1;
+        self.entries.insert(
+            ident,
+            EntryInfo {
+                bytes: ident.len * core::mem::size_of::<T>(),
+                elem_size: core::mem::size_of::<T>(),
+                hits: 0,
+                last_use: self.use_counter,
+                ty: Some(EntryType::of::<T, S>()),
+            },
+        );
+    }
+
+    /// Returns an error if the entry with the given [`Ident`] was allocated with another element type or [`Shape`] than `T` and `S`.
+    /// Missing entries are accepted.
+    #[inline]
+    pub fn check_type<T, S: Shape>(&self, ident: Ident) -> Result<(), TypeMismatch> {
+        match self.entries.get(&ident) {
+            Some(info) => info.check_type::<T, S>(ident),
+            None => Ok(()),
+        }
+    }
+
+    /// Inserts `ptr` as the entry of `ident`, whose size must have been recorded with [`track_use`](Cache::track_use).
+    /// Unless `ptr` is a wrapper, its allocation is counted by [`allocated_bytes`](Cache::allocated_bytes).
+    /// Returns the previous pointer of the entry.
+    pub fn insert(
+        &mut self,
+        ident: Ident,
+        ptr: Arc<D::Ptr<u8, ()>>,
+    ) -> Option<Arc<D::Ptr<u8, ()>>> {
+        if ptr.flag() != AllocFlag::Wrapper {
+            let bytes = self.entries.get(&ident).map_or(0, |info| info.bytes);
+            let (entries, allocation_bytes) = self
+                .allocations
+                .entry(Arc::as_ptr(&ptr) as usize)
+                .or_insert((0, bytes));
+            if *entries == 0 {
+                self.allocated_bytes += *allocation_bytes;
+            }
+            *entries += 1;
+        }
+
+        let old_ptr = self.nodes.insert(ident, ptr)?;
+        self.untrack(&old_ptr);
+        Some(old_ptr)
+    }
+
+    /// Returns the [`CacheStats`] of this cache.
+    /// # Example
+    #[cfg_attr(all(feature = "cpu", not(feature = "realloc")), doc = "```")]
+    #[cfg_attr(not(all(feature = "cpu", not(feature = "realloc"))), doc = "```ignore")]
+    /// use custos::{range, CacheReturn, Device, CPU};
+    ///
+    /// let device = CPU::new();
+    ///
+    /// for _ in range(9) {
+    ///     device.retrieve::<f32, ()>(10, ());
+    ///     device.retrieve::<u8, ()>(10, ());
+    /// }
+    ///
+    /// let stats = device.cache().stats();
+    /// assert_eq!(stats.hits, 16);
+    /// assert_eq!(stats.misses, 2);
+    /// assert_eq!(stats.live_entries, 2);
+    /// assert_eq!(stats.total_bytes, 50);
+    /// assert_eq!(stats.bytes_by_elem_size[&4], 40);
+    /// ```
+    pub fn stats(&self) -> CacheStats {
+        let mut stats = self.stats.clone();
+        stats.collect(
+            self.nodes
+                .iter()
+                .map(|(ident, ptr)| (ptr, self.entries.get(ident))),
+        );
+        stats
+    }
+
+    /// Returns every entry with its [`EntryInfo`], sorted by the allocated bytes (descending) and the [`Ident`].
+    /// Entries with many bytes or without any hits typically point to a misused cache count (e.g. a missing [`range`](crate::range)).
+    pub fn dump(&self) -> Vec<(Ident, EntryInfo)> {
+        let mut dump = self
+            .entries
+            .iter()
+            .filter(|(ident, _)| self.nodes.contains_key(ident))
+            .map(|(ident, info)| (*ident, *info))
+            .collect::<Vec<_>>();
+
+        dump.sort_by(|(lhs_ident, lhs), (rhs_ident, rhs)| {
+            rhs.bytes
+                .cmp(&lhs.bytes)
+                .then(lhs_ident.idx.cmp(&rhs_ident.idx))
+                .then(lhs_ident.len.cmp(&rhs_ident.len))
+        });
+        dump
+    }
+
+    /// Removes the entry of `ident`. Returns its pointer.
+    pub fn remove(&mut self, ident: &Ident) -> Option<Arc<D::Ptr<u8, ()>>> {
+        self.entries.remove(ident);
+        let ptr = self.nodes.remove(ident)?;
+        self.untrack(&ptr);
+        Some(ptr)
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.entries.clear();
+        self.external.clear();
+        self.allocations.clear();
+        self.allocated_bytes = 0;
+    }
+
+    fn untrack(&mut self, ptr: &Arc<D::Ptr<u8, ()>>) {
+        let key = Arc::as_ptr(ptr) as usize;
+        let Some((entries, bytes)) = self.allocations.get_mut(&key) else {
+            return;
+        };
+        *entries -= 1;
+        if *entries == 0 {
+            self.allocated_bytes -= *bytes;
+            self.allocations.remove(&key);
+        }
+    }
+
+    /// Returns the amount of bytes that are allocated by the cache.
+    /// Entries that wrap around memory owned by something else are not counted and shared allocations are only counted once.
+    /// Only entries that were added with [`insert`](Cache::insert) are counted.
+    #[inline]
+    pub fn allocated_bytes(&self) -> usize {
+        self.allocated_bytes
+    }
+
+    /// `true` if the entry owns its allocation alone and is neither pinned nor referenced by another entry or a retrieved [`Buffer`].
+    /// Entries whose allocation is not tracked are never evicted, as their retrieved `Buffer`s cannot be counted.
+    fn is_evictable(&self, ident: &Ident, ptr: &Arc<D::Ptr<u8, ()>>) -> bool {
+        ptr.flag() != AllocFlag::Wrapper
+            && Arc::strong_count(ptr) == 1
+            && ptr
+                .allocation()
+                .map_or(false, |allocation| allocation.handles() == 1)
+            && !self.pinned.contains(ident)
+    }
+
+    /// Evicts unreferenced entries, except `keep`, in least recently used order.
+    /// Stops as soon as the allocated bytes do not exceed `target_bytes`. Returns the amount of freed bytes.
+    fn evict(&mut self, keep: Option<Ident>, target_bytes: usize) -> usize {
+        let mut candidates = self
+            .nodes
+            .iter()
+            .filter(|(ident, ptr)| Some(**ident) != keep && self.is_evictable(ident, ptr))
+            .map(|(ident, _)| {
+                let last_use = self.entries.get(ident).map_or(0, |info| info.last_use);
+                (last_use, *ident)
+            })
+            .collect::<Vec<_>>();
+
+        candidates.sort_unstable();
+
+        let allocated_bytes = self.allocated_bytes;
+        for (_, ident) in candidates {
+            if self.allocated_bytes <= target_bytes {
+                break;
+            }
+            self.remove(&ident);
+        }
+        allocated_bytes - self.allocated_bytes
+    }
+
+    /// Evicts the least recently used entries that are no longer referenced until the cache fits into its budget.
+    /// Does nothing if no budget is set. Returns the amount of freed bytes.
+    #[inline]
+    pub fn trim(&mut self) -> usize {
+        self.trim_except(None)
+    }
+
+    fn trim_except(&mut self, keep: Option<Ident>) -> usize {
+        let Some(budget) = self.budget else {
+            return 0;
+        };
+        self.evict(keep, budget)
+    }
+
+    /// Evicts all entries that are no longer referenced, regardless of the budget.
+    /// Returns the amount of freed bytes.
+    /// # Example
+    #[cfg_attr(all(feature = "cpu", not(feature = "realloc")), doc = "```")]
+    #[cfg_attr(not(all(feature = "cpu", not(feature = "realloc"))), doc = "```ignore")]
+    /// use custos::{Buffer, CacheReturn, Device, CPU};
+    ///
+    /// let device = CPU::new();
+    ///
+    /// let _owned = Buffer::from((&device, [1f32, 2., 3.]));
+    /// // the retrieved buffer is dropped immediately, hence its entry is no longer referenced
+    /// device.retrieve::<f32, ()>(10, ());
+    ///
+    /// assert_eq!(device.cache_mut().clear_unused(), 40);
+    ///
+    /// // the entry of the owned buffer does not allocate any memory on its own
+    /// assert_eq!(device.cache().nodes.len(), 1);
+    /// ```
+    #[inline]
+    pub fn clear_unused(&mut self) -> usize {
+        self.evict(None, 0)
+    }
+
+    /// Starts recording the entries that are retrieved with the cache count and the executed operations, see [`Plan`](crate::Plan).
+    /// A capture or replay that is already in progress is discarded.
+    #[inline]
+    pub(crate) fn start_capture(&mut self) {
+        self.capture = Capture::Recording {
+            entries: Vec::new(),
+            ops: Some(Vec::new()),
+        };
+    }
+
+    /// Returns `true` if a capture is in progress.
+    #[inline]
+    pub(crate) fn is_capturing(&self) -> bool {
+        matches!(self.capture, Capture::Recording { .. })
+    }
+
+    /// Records an operation of the capture in progress. `None` marks an operation that could not be recorded.
+    #[inline]
+    pub(crate) fn record_op(&mut self, op: Option<CapturedOp>) {
+        if let Capture::Recording { ops, .. } = &mut self.capture {
+            match (ops.as_mut(), op) {
+                (Some(ops), Some(op)) => ops.push(op),
+                _ => *ops = None,
+            }
+        }
+    }
+
+    /// Stops recording and returns the retrieved entries and the recorded operations.
+    #[inline]
+    pub(crate) fn finish_capture(&mut self) -> Captured<D::Ptr<u8, ()>> {
+        match core::mem::take(&mut self.capture) {
+            Capture::Recording { entries, ops } => (entries, ops),
+            _ => (Vec::new(), None),
+        }
+    }
+
+    /// Hands out the recorded `entries` in order, as long as the retrieved [`Ident`]s match.
+    #[inline]
+    pub(crate) fn start_replay(&mut self, entries: CapturedEntries<D::Ptr<u8, ()>>) {
+        self.capture = Capture::Replaying { entries, pos: 0 };
+    }
+
+    /// Discards a capture or replay that is in progress, e.g. because the captured step panicked.
+    #[inline]
+    pub(crate) fn reset_capture(&mut self) {
+        self.capture = Capture::Idle;
+    }
+
+    /// Stops replaying and returns the recorded entries.
+    #[inline]
+    pub(crate) fn finish_replay(&mut self) -> CapturedEntries<D::Ptr<u8, ()>> {
+        match core::mem::take(&mut self.capture) {
+            Capture::Replaying { entries, .. } => entries,
+            _ => Vec::new(),
         }
     }
 }
@@ -157,20 +743,42 @@ impl<D: PtrConv + GraphReturn> Cache<D> {
     ///
     /// assert_eq!(cache.host_ptr(), ptr.ptr as *mut f32);
     /// ```
+    #[track_caller]
     pub fn add_node<'a, T, S: Shape>(
         &mut self,
         device: &'a D,
         ident: Ident,
-        _add_node: impl crate::AddGraph,
+        add_node: impl crate::AddGraph,
         callback: fn(),
     ) -> Buffer<'a, T, D, S>
     where
         D: Alloc<'a, T, S>,
     {
-        let ptr = device.alloc(ident.len, AllocFlag::Wrapper);
+        self.try_add_node(device, ident, add_node, callback)
+            .unwrap()
+    }
+
+    /// Like [`add_node`](Cache::add_node), but returns an error if the memory could not be allocated.
+    /// The cache is left unchanged in this case.
+    pub fn try_add_node<'a, T, S: Shape>(
+        &mut self,
+        device: &'a D,
+        ident: Ident,
+        _add_node: impl crate::AddGraph,
+        callback: fn(),
+    ) -> crate::Result<Buffer<'a, T, D, S>>
+    where
+        D: Alloc<'a, T, S>,
+    {
+        let ptr = device.try_alloc(ident.len, AllocFlag::Wrapper)?;
 
         #[cfg(feature = "opt-cache")]
-        let graph_node = device.graph_mut().add(ident.len, _add_node);
+        let graph_node = {
+            let mut graph = device.graph_mut();
+            let node = graph.add(ident.len, _add_node);
+            graph.set_elem_size(node.idx, core::mem::size_of::<T>());
+            node
+        };
 
         #[cfg(not(feature = "opt-cache"))]
         let graph_node = crate::Node {
@@ -180,18 +788,26 @@ impl<D: PtrConv + GraphReturn> Cache<D> {
         };
 
         let untyped_ptr = unsafe { D::convert(&ptr, AllocFlag::None) };
-        self.nodes.insert(ident, Rc::new(untyped_ptr));
+        self.external.remove(&ident);
+        self.track_use::<T, S>(ident);
+        self.insert(ident, Arc::new(untyped_ptr));
+
+        self.stats.misses += 1;
+        self.stats.high_water_mark = self.stats.high_water_mark.max(self.allocated_bytes);
+
+        // the returned buffer is not evicted immediately
+        self.trim_except(Some(ident));
 
         callback();
 
-        Buffer {
+        Ok(Buffer {
             ptr,
             device: Some(device),
             ident: Some(Ident {
                 idx: graph_node.idx,
                 len: ident.len,
             }),
-        }
+        })
     }
 
     /// Retrieves cached pointers and constructs a [`Buffer`] with the pointers and the given `len`gth.
@@ -215,6 +831,9 @@ impl<D: PtrConv + GraphReturn> Cache<D> {
     /// let first_entry: Buffer = device.cache_mut().get(&device, Ident::new(10), (), bump_count);
     /// assert_eq!(cache_entry.ptrs(), first_entry.ptrs());
     /// ```
+    /// # Panics (debug)
+    /// If the cached entry was allocated with another element type or [`Shape`].
+    #[track_caller]
     pub fn get<'a, T, S: Shape>(
         &mut self,
         device: &'a D,
@@ -222,23 +841,160 @@ impl<D: PtrConv + GraphReturn> Cache<D> {
         add_node: impl crate::AddGraph,
         callback: fn(),
     ) -> Buffer<'a, T, D, S>
+    where
+        D: Alloc<'a, T, S>,
+    {
+        self.try_get_entry(device, ident, add_node, callback)
+            .unwrap()
+    }
+
+    /// Same as [`get`](Cache::get), but a failed allocation is returned as an error.
+    #[track_caller]
+    pub(crate) fn try_get_entry<'a, T, S: Shape>(
+        &mut self,
+        device: &'a D,
+        ident: Ident,
+        add_node: impl crate::AddGraph,
+        callback: fn(),
+    ) -> crate::Result<Buffer<'a, T, D, S>>
+    where
+        D: Alloc<'a, T, S>,
+    {
+        #[cfg(debug_assertions)]
+        if let Err(mismatch) = self.check_type::<T, S>(ident) {
+            panic!("{mismatch}");
+        }
+
+        self.get_entry(device, ident, add_node, callback)
+    }
+
+    /// Like [`get`](Cache::get), but returns an error if the memory could not be allocated
+    /// or if the cached entry was allocated with another element type or [`Shape`] ([`Error::TypeMismatch`](crate::Error::TypeMismatch)).
+    /// # Example
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::prelude::*;
+    /// use custos::{bump_count, Error};
+    ///
+    /// let device = CPU::new();
+    ///
+    /// let _f32_entry: Buffer<f32> = device.cache_mut().get(&device, Ident { idx: 0, len: 10 }, (), bump_count);
+    ///
+    /// let err = device
+    ///     .cache_mut()
+    ///     .try_get::<f64, ()>(&device, Ident { idx: 0, len: 10 }, (), bump_count)
+    ///     .unwrap_err();
+    ///
+    /// let Error::TypeMismatch(mismatch) = err else {
+    ///     panic!("expected a type mismatch, got {err}");
+    /// };
+    /// assert_eq!(mismatch.ident.idx, 0);
+    /// assert_eq!(mismatch.allocated.type_name, "f32");
+    /// assert_eq!(mismatch.requested.type_name, "f64");
+    /// ```
+    pub fn try_get<'a, T, S: Shape>(
+        &mut self,
+        device: &'a D,
+        ident: Ident,
+        add_node: impl crate::AddGraph,
+        callback: fn(),
+    ) -> crate::Result<Buffer<'a, T, D, S>>
+    where
+        D: Alloc<'a, T, S>,
+    {
+        self.check_type::<T, S>(ident)?;
+        self.get_entry(device, ident, add_node, callback)
+    }
+
+    /// Returns the `Buffer` of an existing cache entry or `None` if it does not exist.
+    /// An error is returned if the entry was allocated with another element type or [`Shape`].
+    /// # Safety
+    /// Same as [`CacheAble::get_existing_buf`].
+    pub unsafe fn try_get_existing_buf<'a, T, S: Shape>(
+        &self,
+        device: &'a D,
+        ident: Ident,
+    ) -> Result<Option<Buffer<'a, T, D, S>>, TypeMismatch> {
+        self.check_type::<T, S>(ident)?;
+
+        let Some(ptr) = self.nodes.get(&ident) else {
+            return Ok(None);
+        };
+
+        Ok(Some(Buffer {
+            ptr: D::convert(ptr, AllocFlag::Wrapper),
+            device: Some(device),
+            ident: Some(ident),
+        }))
+    }
+
+    fn get_entry<'a, T, S: Shape>(
+        &mut self,
+        device: &'a D,
+        ident: Ident,
+        add_node: impl crate::AddGraph,
+        callback: fn(),
+    ) -> crate::Result<Buffer<'a, T, D, S>>
+    where
+        D: Alloc<'a, T, S>,
+    {
+        // a replayed entry skips the lookup and bookkeeping of the cache
+        if let Capture::Replaying { entries, pos } = &mut self.capture {
+            if let Some((recorded, ptr)) =
+                entries.get(*pos).filter(|(recorded, _)| *recorded == ident)
+            {
+                *pos += 1;
+                callback();
+                return Ok(Buffer {
+                    ptr: unsafe { D::convert(ptr, AllocFlag::Wrapper) },
+                    device: Some(device),
+                    ident: Some(*recorded),
+                });
+            }
+        }
+
+        let buf = self.get_or_add_entry(device, ident, add_node, callback)?;
+
+        if let Capture::Recording { entries, .. } = &mut self.capture {
+            if let Some(ptr) = self.nodes.get(&ident) {
+                entries.push((ident, ptr.clone()));
+            }
+        }
+        Ok(buf)
+    }
+
+    fn get_or_add_entry<'a, T, S: Shape>(
+        &mut self,
+        device: &'a D,
+        ident: Ident,
+        add_node: impl crate::AddGraph,
+        callback: fn(),
+    ) -> crate::Result<Buffer<'a, T, D, S>>
     where
         D: Alloc<'a, T, S>,
     {
         let may_allocated = self.nodes.get(&ident);
 
         match may_allocated {
-            Some(ptr) => {
+            // Handing out the memory of a user owned buffer would alias it.
+            Some(ptr) if !self.external.contains(&ident) => {
                 callback();
                 let typed_ptr = unsafe { D::convert(ptr, AllocFlag::Wrapper) };
 
-                Buffer {
+                self.stats.hits += 1;
+                if let Some(info) = self.entries.get_mut(&ident) {
+                    self.use_counter += 1;
+                    info.last_use = self.use_counter;
+                    info.hits += 1;
+                }
+
+                Ok(Buffer {
                     ptr: typed_ptr,
                     device: Some(device),
                     ident: Some(ident),
-                }
+                })
             }
-            None => self.add_node(device, ident, add_node, callback),
+            _ => self.try_add_node(device, ident, add_node, callback),
         }
     }
 }
@@ -1,18 +1,21 @@
 use core::{cell::RefMut, panic::Location};
 use std::collections::HashMap;
 
-use std::rc::Rc;
+use std::sync::Arc;
 
-use crate::{bump_count, flag::AllocFlag, Alloc, Buffer, Device, Ident, PtrConv, Shape, CPU};
+use crate::{
+    bump_count, flag::AllocFlag, Alloc, Buffer, CacheStats, Device, EntryInfo, EntryType, Ident,
+    PtrConv, Shape, CPU,
+};
 
 pub trait CallerCacheReturn {
-    /// Returns a reference to a device's [`Cache`].
-    fn cache(&self) -> core::cell::Ref<TrackCallerCache<Self>>
+    /// Returns a reference to a device's [`TrackCallerCache`].
+    fn caller_cache(&self) -> core::cell::Ref<TrackCallerCache<Self>>
     where
         Self: PtrConv;
 
-    /// Returns a mutable reference to a device's [`Cache`].
-    fn cache_mut(&self) -> RefMut<TrackCallerCache<Self>>
+    /// Returns a mutable reference to a device's [`TrackCallerCache`].
+    fn caller_cache_mut(&self) -> RefMut<TrackCallerCache<Self>>
     where
         Self: PtrConv;
 }
@@ -33,9 +36,14 @@ impl Cache for CPU {
     type CallerCache = TrackCallerCache<CPU>;
 }
 
+/// A call site and the requested length. A call site that retrieves several lengths gets an entry per length.
+type CallerKey = (&'static Location<'static>, usize);
+
 #[derive(Debug, Default)]
 pub struct TrackCallerCache<D: Device> {
-    nodes: HashMap<&'static std::panic::Location<'static>, Rc<D::Ptr<u8, ()>>>,
+    nodes: HashMap<CallerKey, Arc<D::Ptr<u8, ()>>>,
+    entries: HashMap<CallerKey, EntryInfo>,
+    stats: CacheStats,
 }
 
 pub trait TrackCallerCacheAble<D: Device> {
@@ -64,11 +72,42 @@ where
     where
         D: for<'a> Alloc<'a, T, S>,
     {
-        device.cache_mut().get(device, Ident::new(len), bump_count)
+        device
+            .caller_cache_mut()
+            .get(device, Ident::new(len), bump_count)
     }
 }
 
 impl<D: PtrConv> TrackCallerCache<D> {
+    /// Returns the [`CacheStats`] of this cache.
+    pub fn stats(&self) -> CacheStats {
+        let mut stats = self.stats.clone();
+        stats.collect(
+            self.nodes
+                .iter()
+                .map(|(key, ptr)| (ptr, self.entries.get(key))),
+        );
+        stats
+    }
+
+    /// Returns every call site with its [`EntryInfo`], sorted by the allocated bytes (descending).
+    pub fn dump(&self) -> Vec<(&'static Location<'static>, EntryInfo)> {
+        let mut dump = self
+            .entries
+            .iter()
+            .map(|((location, _), info)| (*location, *info))
+            .collect::<Vec<_>>();
+
+        dump.sort_by(|(lhs_location, lhs), (rhs_location, rhs)| {
+            rhs.bytes
+                .cmp(&lhs.bytes)
+                .then(lhs_location.file().cmp(rhs_location.file()))
+                .then(lhs_location.line().cmp(&rhs_location.line()))
+                .then(lhs_location.column().cmp(&rhs_location.column()))
+        });
+        dump
+    }
+
     #[track_caller]
     pub fn get<'a, T, S>(
         &mut self,
@@ -80,44 +119,92 @@ impl<D: PtrConv> TrackCallerCache<D> {
         D: Alloc<'a, T, S>,
         S: Shape,
     {
-        let maybe_allocated = self.nodes.get(Location::caller());
+        self.try_get(device, ident, callback).unwrap()
+    }
+
+    /// Like [`get`](TrackCallerCache::get), but returns an error if the memory could not be allocated.
+    #[track_caller]
+    pub fn try_get<'a, T, S>(
+        &mut self,
+        device: &'a D,
+        ident: Ident,
+        callback: fn(),
+    ) -> crate::Result<Buffer<'a, T, D, S>>
+    where
+        D: Alloc<'a, T, S>,
+        S: Shape,
+    {
+        let key = (Location::caller(), ident.len);
+        let maybe_allocated = self.nodes.get(&key);
 
         match maybe_allocated {
             Some(ptr) => {
+                #[cfg(debug_assertions)]
+                if let Some(Err(mismatch)) = self
+                    .entries
+                    .get(&key)
+                    .map(|info| info.check_type::<T, S>(ident))
+                {
+                    panic!("{mismatch} (called at {})", Location::caller());
+                }
+
                 callback();
                 let typed_ptr = unsafe { D::convert(ptr, AllocFlag::Wrapper) };
 
-                Buffer {
+                self.stats.hits += 1;
+                if let Some(info) = self.entries.get_mut(&key) {
+                    info.hits += 1;
+                }
+
+                Ok(Buffer {
                     ptr: typed_ptr,
                     device: Some(device),
                     ident: Some(ident),
-                }
+                })
             }
             None => self.add_node(device, ident, callback),
         }
     }
 
     #[track_caller]
-    fn add_node<'a, T, S>(&mut self, device: &'a D, ident: Ident, callback: fn()) -> Buffer<'a, T, D, S>
+    fn add_node<'a, T, S>(
+        &mut self,
+        device: &'a D,
+        ident: Ident,
+        callback: fn(),
+    ) -> crate::Result<Buffer<'a, T, D, S>>
     where
         D: Alloc<'a, T, S>,
         S: Shape,
     {
-        let ptr = device.alloc(ident.len, AllocFlag::Wrapper);
-        
+        let ptr = device.try_alloc(ident.len, AllocFlag::Wrapper)?;
+
+        let key = (Location::caller(), ident.len);
         let untyped_ptr = unsafe { D::convert(&ptr, AllocFlag::None) };
-        self.nodes.insert(Location::caller(), Rc::new(untyped_ptr));
-        
+        self.nodes.insert(key, Arc::new(untyped_ptr));
+        self.entries.insert(
+            key,
+            EntryInfo {
+                bytes: ident.len * core::mem::size_of::<T>(),
+                elem_size: core::mem::size_of::<T>(),
+                ty: Some(EntryType::of::<T, S>()),
+                ..Default::default()
+            },
+        );
+
+        self.stats.misses += 1;
+        self.stats.high_water_mark = self.stats().high_water_mark;
+
         callback();
-        
-        Buffer {
+
+        Ok(Buffer {
             ptr,
             device: Some(device),
             ident: Some(Ident {
                 idx: ident.idx,
                 len: ident.len,
             }),
-        }
+        })
     }
 }
 
@@ -125,7 +212,7 @@ impl<D: PtrConv> TrackCallerCache<D> {
 mod tests {
     use core::ops::Add;
 
-    use crate::{Buffer, Device, CPU, devices::caller_cache::CallerCacheReturn};
+    use crate::{devices::caller_cache::CallerCacheReturn, Buffer, Device, CPU};
 
     use super::Cache;
 
@@ -156,6 +243,65 @@ mod tests {
             add(&device, &lhs, &rhs);
         }
 
-        assert_eq!(device.cache().nodes.len(), 1);
+        assert_eq!(device.caller_cache().nodes.len(), 1);
+    }
+
+    #[test]
+    fn test_caller_cache_stats() {
+        let device = CPU::new();
+
+        let lhs = device.buffer([1, 2, 3, 4]);
+        let rhs = device.buffer([1, 2, 3, 4]);
+
+        for _i in 0..100 {
+            add(&device, &lhs, &rhs);
+        }
+        add(&device, &lhs, &rhs);
+
+        let stats = device.caller_cache().stats();
+        assert_eq!(stats.hits, 99);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.live_entries, 2);
+        assert_eq!(stats.total_bytes, 32);
+        assert_eq!(stats.high_water_mark, 32);
+
+        let dump = device.caller_cache().dump();
+        assert_eq!(dump.len(), 2);
+        assert_eq!(dump.iter().map(|(_, info)| info.hits).sum::<usize>(), 99);
+    }
+
+    #[test]
+    fn test_caller_cache_different_lengths() {
+        #[track_caller]
+        fn alloc(device: &CPU, len: usize) -> Buffer<i32> {
+            device.call::<i32, ()>(len)
+        }
+
+        let device = CPU::new();
+
+        for len in [2, 1000, 2, 1000] {
+            let mut buf = alloc(&device, len);
+            assert_eq!(buf.len(), len);
+            buf[len - 1] = 1;
+        }
+
+        let stats = device.caller_cache().stats();
+        assert_eq!(stats.live_entries, 2);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.hits, 2);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "retrieved as Buffer<f64")]
+    fn test_caller_cache_type_mismatch() {
+        // without #[track_caller], every instantiation shares the same location
+        fn alloc<T>(device: &CPU) -> Buffer<T> {
+            device.call::<T, ()>(10)
+        }
+
+        let device = CPU::new();
+        alloc::<f32>(&device);
+        alloc::<f64>(&device);
     }
 }
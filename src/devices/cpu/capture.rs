@@ -0,0 +1,341 @@
+use core::{fmt::Debug, mem::size_of};
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{
+    check_len, get_count, set_count, Allocation, Buffer, CacheReturn, CapturedOp, GraphReturn,
+    Ident, MainMemory, Node, Shape, CPU,
+};
+
+use super::CPUPtr;
+
+/// A step that was recorded with [`CPU::capture`] and can be executed again with [`replay`](Plan::replay).
+///
+/// While the step is captured, the operations of the `CPU` record their kernels and the memory they access.
+/// A replay executes these kernels in the recorded order, without calling the step,
+/// looking up [`Cache`](crate::Cache) entries or allocating.
+/// The value returned by the step is kept by the plan. If it contains `Buffer`s, they hold the results of the last replay.
+///
+/// If the step can't be replayed from its operations, the step is executed again instead.
+/// Then, the recorded cache entries are handed out in order, as long as the retrieved [`Ident`]s match.
+/// This is the case if the step
+/// - passes a closure to an operation, e.g. [`apply_fn`](crate::ApplyFunction::apply_fn), as the closure is not kept beyond the call,
+/// - reads data with [`Read`](crate::Read) or transfers it to another device, as the host may use the data,
+/// - retrieves a `Buffer` that is not written by a recorded operation (e.g. through `DerefMut`),
+/// - adds gradient functions that are not executed in the step,
+/// - or if memory that a recorded operation accesses is freed, e.g. a temporary `Buffer` that was not retrieved from the cache.
+///
+/// Operations of other devices are not recorded.
+/// Captures can not be nested, and a plan must be replayed on the thread it was captured on.
+/// # Example
+#[cfg_attr(all(feature = "macro", not(feature = "realloc")), doc = "```")]
+#[cfg_attr(
+    not(all(feature = "macro", not(feature = "realloc"))),
+    doc = "```ignore"
+)]
+/// use custos::{ApplyFunction, Buffer, Combiner, CPU};
+///
+/// fn step<'a>(device: &'a CPU, x: &Buffer<f32>) -> Buffer<'a, f32> {
+///     let doubled = device.apply_fn(x, |x| x.mul(2.));
+///     device.apply_fn(&doubled, |x| x.add(1.))
+/// }
+///
+/// let device = CPU::new();
+/// let x = Buffer::from((&device, [1., 2., 3.]));
+///
+/// let mut plan = device.capture(|| step(&device, &x));
+/// assert_eq!(plan.replay().read(), [3., 5., 7.]);
+///
+/// // the data of `next` is copied into `x` before every replay
+/// let next = Buffer::from((&device, [4., 5., 6.]));
+/// plan.bind(&x, &next).unwrap();
+/// assert_eq!(plan.replay().read(), [9., 11., 13.]);
+/// ```
+pub struct Plan<'a, R, F: FnMut() -> R> {
+    device: &'a CPU,
+    step: F,
+    output: R,
+    count: usize,
+    entries: Vec<(Ident, Arc<CPUPtr<u8>>)>,
+    /// `None` if the step is executed again on every replay.
+    ops: Option<Vec<CapturedOp>>,
+    nodes: Vec<Node>,
+    bindings: Vec<Binding>,
+}
+
+/// Copies `bytes` bytes from `source` to `input` before a replay.
+struct Binding {
+    input: *mut u8,
+    source: *const u8,
+    bytes: usize,
+}
+
+/// Discards the capture or replay in progress when it is dropped.
+/// Hence, the cache is usable again if the step panics.
+struct ResetCapture<'a>(&'a CPU);
+
+impl Drop for ResetCapture<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.cache_mut().reset_capture();
+    }
+}
+
+impl CPU {
+    /// Executes `step` once and records the operations it executes and the cache entries it retrieves into a [`Plan`].
+    /// The graph [`Node`]s of these entries are collected from the [`Graph`](crate::Graph), which records them with the `opt-cache` feature.
+    pub fn capture<R, F: FnMut() -> R>(&self, mut step: F) -> Plan<'_, R, F> {
+        let count = get_count();
+        #[cfg(feature = "autograd")]
+        let grad_fns = crate::TapeReturn::tape(self).len();
+
+        let reset = ResetCapture(self);
+        self.cache_mut().start_capture();
+        let output = step();
+        let (entries, mut ops) = self.cache_mut().finish_capture();
+        drop(reset);
+
+        #[cfg(feature = "autograd")]
+        if crate::TapeReturn::tape(self).len() > grad_fns {
+            ops = None;
+        }
+
+        let idents = entries
+            .iter()
+            .map(|(ident, _)| *ident)
+            .collect::<HashSet<_>>();
+
+        // the data of every retrieved entry must be produced by a recorded operation
+        if let Some(recorded) = &ops {
+            let written = recorded
+                .iter()
+                .filter_map(|op| op.output)
+                .collect::<HashSet<_>>();
+            if !idents.is_subset(&written) || accesses_freed(recorded) {
+                ops = None;
+            }
+        }
+
+        let graph = self.graph();
+        let nodes = graph
+            .nodes
+            .iter()
+            .filter(|node| {
+                graph.idx_trans.get(&node.idx).map_or(false, |idx| {
+                    idents.contains(&Ident {
+                        idx: *idx,
+                        len: node.len,
+                    })
+                })
+            })
+            .copied()
+            .collect();
+
+        Plan {
+            device: self,
+            step,
+            output,
+            count,
+            entries,
+            ops,
+            nodes,
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Records an operation if a capture is in progress. `record` returns `None` if the operation can't be replayed.
+    #[inline]
+    pub(crate) fn capture_op(&self, record: impl FnOnce() -> Option<CapturedOp>) {
+        if self.cache().is_capturing() {
+            let op = record();
+            self.cache_mut().record_op(op);
+        }
+    }
+
+    /// Records copying `bytes` bytes from `src` to `dst`, e.g. by [`WriteBuf::write_buf`](crate::WriteBuf::write_buf).
+    #[inline]
+    pub(crate) fn capture_copy(&self, name: &'static str, dst: Region, src: Region, bytes: usize) {
+        self.capture_op(|| {
+            let (dst_addr, src_addr) = (dst.addr, src.addr);
+            CapturedOp::new(
+                name,
+                dst.ident,
+                &[dst.allocation, src.allocation],
+                move || unsafe {
+                    core::ptr::copy(src_addr as *const u8, dst_addr as *mut u8, bytes)
+                },
+            )
+        })
+    }
+
+    /// Records writing `data` to `dst`, see [`WriteBuf::write`](crate::WriteBuf::write).
+    pub(crate) fn capture_write(&self, dst: Region, data: &[u8]) {
+        self.capture_op(|| {
+            let (dst_addr, data) = (dst.addr, data.to_vec());
+            CapturedOp::new("write", dst.ident, &[dst.allocation], move || unsafe {
+                core::ptr::copy_nonoverlapping(data.as_ptr(), dst_addr as *mut u8, data.len())
+            })
+        })
+    }
+
+    /// Records setting `len` values of `T` in `dst` to `T::default()`, see [`ClearBuf`](crate::ClearBuf).
+    pub(crate) fn capture_clear<T: Default>(&self, dst: Region, len: usize) {
+        self.capture_op(|| {
+            let kernel: unsafe fn(*mut u8, usize) = clear_kernel::<T>;
+            let dst_addr = dst.addr;
+            CapturedOp::new("clear", dst.ident, &[dst.allocation], move || unsafe {
+                kernel(dst_addr as *mut u8, len)
+            })
+        })
+    }
+}
+
+#[cfg(feature = "macro")]
+impl crate::MayCapture for CPU {
+    #[inline]
+    fn capture_closure_op(&self) {
+        self.capture_op(|| None)
+    }
+}
+
+/// Sets `len` values of `T` to `T::default()`.
+/// # Safety
+/// `dst` must point to `len` values of `T`.
+unsafe fn clear_kernel<T: Default>(dst: *mut u8, len: usize) {
+    let dst = dst.cast::<T>();
+    for idx in 0..len {
+        *dst.add(idx) = T::default();
+    }
+}
+
+/// Returns `true` if an operation accesses memory that was freed.
+fn accesses_freed(ops: &[CapturedOp]) -> bool {
+    ops.iter()
+        .flat_map(|op| &op.allocations)
+        .any(Allocation::is_freed)
+}
+
+/// The host memory of a `Buffer` that is accessed by a recorded operation.
+/// The address is kept as `usize`, hence the recorded operations are `Send`.
+#[derive(Clone, Copy)]
+pub(crate) struct Region<'b> {
+    addr: usize,
+    allocation: Option<&'b Allocation>,
+    ident: Option<Ident>,
+}
+
+impl<'b> Region<'b> {
+    /// The memory of `buf`, starting at the element `offset`.
+    #[inline]
+    pub(crate) fn of<T, D: MainMemory, S: Shape>(
+        buf: &'b Buffer<T, D, S>,
+        offset: usize,
+    ) -> Region<'b> {
+        Region {
+            addr: D::as_ptr(&buf.ptr).wrapping_add(offset) as usize,
+            allocation: crate::PtrType::allocation(&buf.ptr),
+            ident: buf.ident,
+        }
+    }
+}
+
+impl<'a, R, F: FnMut() -> R> Plan<'a, R, F> {
+    /// Executes the captured step again and returns its result.
+    /// The data of every bound source is copied into its input beforehand, see [`bind`](Plan::bind).
+    pub fn replay(&mut self) -> &R {
+        for binding in &self.bindings {
+            // SAFETY: both buffers are borrowed for the lifetime of the plan and contain `bytes` bytes
+            unsafe { core::ptr::copy(binding.source, binding.input, binding.bytes) };
+        }
+
+        if self.ops.as_deref().map_or(false, accesses_freed) {
+            self.ops = None;
+        }
+
+        match &self.ops {
+            Some(ops) => ops.iter().for_each(|op| (op.exec)()),
+            None => self.replay_step(),
+        }
+        &self.output
+    }
+
+    /// Executes the step with the recorded cache entries.
+    fn replay_step(&mut self) {
+        let count = get_count();
+        unsafe { set_count(self.count) };
+
+        let reset = ResetCapture(self.device);
+        self.device
+            .cache_mut()
+            .start_replay(core::mem::take(&mut self.entries));
+        self.output = (self.step)();
+        self.entries = self.device.cache_mut().finish_replay();
+        drop(reset);
+
+        // `Buffer`s retrieved after the capture keep their entries
+        unsafe { set_count(count.max(get_count())) };
+    }
+
+    /// Re-binds an input of the captured step: the data of `source` is copied into `input` before every replay.
+    /// Binding the same `input` again replaces its previous source.
+    /// Returns an error if both `Buffer`s differ in length.
+    pub fn bind<T: Copy, S: Shape>(
+        &mut self,
+        input: &'a Buffer<T, CPU, S>,
+        source: &'a Buffer<T, CPU, S>,
+    ) -> crate::Result<()> {
+        check_len(input.len(), source.len())?;
+
+        let input = input.ptr.ptr.cast::<u8>();
+        self.bindings.retain(|binding| binding.input != input);
+        self.bindings.push(Binding {
+            input,
+            source: source.ptr.ptr.cast(),
+            bytes: source.len() * size_of::<T>(),
+        });
+        Ok(())
+    }
+
+    /// Removes all bindings added with [`bind`](Plan::bind).
+    #[inline]
+    pub fn unbind_all(&mut self) {
+        self.bindings.clear()
+    }
+
+    /// Returns the names of the recorded operations in the order they are replayed.
+    /// Empty if the step is executed again on every replay.
+    #[inline]
+    pub fn ops(&self) -> Vec<&'static str> {
+        self.ops.iter().flatten().map(|op| op.name).collect()
+    }
+
+    /// Returns `true` if a replay executes the recorded operations instead of the step.
+    #[inline]
+    pub fn is_recorded(&self) -> bool {
+        self.ops.is_some()
+    }
+
+    /// Returns the [`Ident`]s of the recorded cache entries in the order they were retrieved.
+    #[inline]
+    pub fn idents(&self) -> Vec<Ident> {
+        self.entries.iter().map(|(ident, _)| *ident).collect()
+    }
+
+    /// Returns the graph [`Node`]s of the recorded cache entries.
+    /// Empty without the `opt-cache` feature, as the [`Graph`](crate::Graph) does not record retrieved entries then.
+    #[inline]
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+}
+
+impl<'a, R, F: FnMut() -> R> Debug for Plan<'a, R, F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Plan")
+            .field("count", &self.count)
+            .field("ops", &self.ops())
+            .field("idents", &self.idents())
+            .field("nodes", &self.nodes)
+            .field("bindings", &self.bindings.len())
+            .finish()
+    }
+}
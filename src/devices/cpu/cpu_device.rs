@@ -1,14 +1,15 @@
 use crate::{
     devices::cache::Cache, flag::AllocFlag, shape::Shape, Addons, AddonsReturn, Alloc, Buffer,
-    CloneBuf, Device, DevicelessAble, MainMemory, PtrConv,
+    CacheStrategy, CloneBuf, Device, DeviceEvent, DevicelessAble, MainMemory, MemoryReturn,
+    MemoryTracker, PerThread, PtrConv, RecordEvent,
 };
 
 use core::{
     fmt::Debug,
-    mem::{align_of, size_of},
+    mem::{align_of, size_of, size_of_val},
 };
 
-use super::CPUPtr;
+use super::{CPUEvent, CPUPtr, WorkQueue};
 
 #[derive(Debug, Default)]
 /// A CPU is used to perform calculations on the host CPU.
@@ -25,9 +26,35 @@ use super::CPUPtr;
 ///
 /// assert_eq!(out, vec![1, 2, 3]);
 /// ```
+///
+/// A `CPU` is `Send + Sync` and can be shared between threads, e.g. with an `Arc`.
+/// Every thread uses its own [`Addons`], hence the caches of different threads do not share any entries.
+/// ```
+/// use std::sync::Arc;
+/// use custos::{Buffer, CPU};
+///
+/// let device = Arc::new(CPU::new());
+///
+/// let handles = (0..4).map(|i| {
+///     let device = device.clone();
+///     std::thread::spawn(move || {
+///         let buf = Buffer::from((&*device, [i; 4]));
+///         buf.read().iter().sum::<i32>()
+///     })
+/// }).collect::<Vec<_>>();
+///
+/// let sums = handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>();
+/// assert_eq!(sums, [0, 4, 8, 12]);
+/// ```
+///
+/// Operations are executed on the calling thread.
+/// Additionally, a `CPU` owns a [`WorkQueue`] that executes jobs on a background thread ([`CPU::enqueue`]).
+/// [`ReadAsync`](crate::ReadAsync), [`RecordEvent`] and [`Device::synchronize`] are ordered with these jobs.
 pub struct CPU {
     /// Provides additional functionality for the CPU. e.g. a cache, a gradient [`Tape`](crate::Tape), an optimizeable [`Graph`](crate::Graph) and a [`Cache`](crate::Cache).
-    pub addons: Addons<CPU>,
+    pub addons: PerThread<Addons<CPU>>,
+    queue: WorkQueue,
+    memory: MemoryTracker,
 }
 
 impl CPU {
@@ -35,9 +62,58 @@ impl CPU {
     #[must_use]
     pub fn new() -> CPU {
         CPU {
-            addons: Addons::default(),
+            addons: PerThread::default(),
+            queue: WorkQueue::default(),
+            memory: MemoryTracker::default(),
         }
     }
+
+    /// Sets the [`CacheStrategy`] that is used by [`Device::retrieve`] on every thread.
+    /// The strategy is part of the construction of the `CPU`, previously cached entries are discarded.
+    /// # Example
+    #[cfg_attr(not(feature = "realloc"), doc = "```")]
+    #[cfg_attr(feature = "realloc", doc = "```ignore")]
+    /// use custos::{CacheReturn, CacheStrategy, Device, CPU};
+    ///
+    /// let device = CPU::new().with_cache_strategy(CacheStrategy::Caller);
+    /// assert_eq!(device.cache_strategy(), CacheStrategy::Caller);
+    ///
+    /// let mut ptrs = vec![];
+    /// for _ in 0..10 {
+    ///     // no range required
+    ///     let buf = device.retrieve::<f32, ()>(10, ());
+    ///     ptrs.push(buf.ptr.ptr);
+    /// }
+    ///
+    /// assert!(ptrs.iter().all(|ptr| *ptr == ptrs[0]));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_cache_strategy(mut self, strategy: CacheStrategy) -> CPU {
+        self.addons = Addons::per_thread(strategy);
+        self
+    }
+
+    /// Executes `job` on the background thread of the [`WorkQueue`] of the `CPU`.
+    /// The returned event completes after `job` has finished.
+    /// # Example
+    /// ```
+    /// use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+    /// use custos::{Device, CPU};
+    ///
+    /// let device = CPU::new();
+    /// let done = Arc::new(AtomicBool::new(false));
+    ///
+    /// let job_done = done.clone();
+    /// device.enqueue(move || job_done.store(true, Ordering::SeqCst));
+    ///
+    /// device.synchronize().unwrap();
+    /// assert!(done.load(Ordering::SeqCst));
+    /// ```
+    #[inline]
+    pub fn enqueue(&self, job: impl FnOnce() + Send + 'static) -> CPUEvent {
+        self.queue.enqueue(job)
+    }
 }
 
 impl Device for CPU {
@@ -47,49 +123,100 @@ impl Device for CPU {
     fn new() -> crate::Result<Self> {
         Ok(Self::new())
     }
+
+    /// Waits until every job of the [`WorkQueue`] has finished.
+    #[inline]
+    fn synchronize(&self) -> crate::Result<()> {
+        self.queue.record().wait()
+    }
+}
+
+impl RecordEvent for CPU {
+    type Event = CPUEvent;
+
+    #[inline]
+    fn record_event(&self) -> crate::Result<CPUEvent> {
+        Ok(self.queue.record())
+    }
+
+    fn wait_event(&self, event: &CPUEvent) -> crate::Result<()> {
+        let event = event.clone();
+        self.queue.enqueue(move || {
+            event.wait().ok();
+        });
+        Ok(())
+    }
+}
+
+impl MemoryReturn for CPU {
+    #[inline]
+    fn memory(&self) -> &MemoryTracker {
+        &self.memory
+    }
 }
 
 impl AddonsReturn for CPU {
     #[inline]
     fn addons(&self) -> &Addons<Self> {
-        &self.addons
+        self.addons.local()
     }
 }
 
 impl<'a, T> DevicelessAble<'a, T> for CPU {}
 
 impl<T, S: Shape> Alloc<'_, T, S> for CPU {
-    fn alloc(&self, mut len: usize, flag: AllocFlag) -> CPUPtr<T> {
-        assert!(len > 0, "invalid buffer len: 0");
-
+    #[track_caller]
+    fn try_alloc(&self, mut len: usize, flag: AllocFlag) -> crate::Result<CPUPtr<T>> {
         if S::LEN > len {
             len = S::LEN
         }
 
-        CPUPtr::new_initialized(len, flag)
+        let bytes = len.saturating_mul(size_of::<T>());
+        let (cpu_ptr, allocation) = self
+            .memory
+            .track(bytes, || CPUPtr::try_new_initialized(len, flag))?;
+
+        Ok(cpu_ptr.tracked(allocation))
     }
 
-    fn with_slice(&self, data: &[T]) -> CPUPtr<T>
+    #[track_caller]
+    fn try_with_slice(&self, data: &[T]) -> crate::Result<CPUPtr<T>>
     where
         T: Clone,
     {
-        assert!(!data.is_empty(), "invalid buffer len: 0");
-        assert!(S::LEN <= data.len(), "invalid buffer len: {}", data.len());
+        if S::LEN > data.len() {
+            return Err(crate::Error::ShapeMismatch {
+                expected: S::LEN,
+                found: data.len(),
+            });
+        }
 
-        let cpu_ptr = unsafe { CPUPtr::new(data.len(), AllocFlag::None) };
+        let (cpu_ptr, allocation) = self.memory.track(size_of_val(data), || unsafe {
+            CPUPtr::try_new(data.len(), AllocFlag::None)
+        })?;
         let slice = unsafe { std::slice::from_raw_parts_mut(cpu_ptr.ptr, data.len()) };
         slice.clone_from_slice(data);
 
-        cpu_ptr
+        Ok(cpu_ptr.tracked(allocation))
     }
-    fn alloc_with_vec(&self, mut vec: Vec<T>) -> CPUPtr<T> {
-        assert!(!vec.is_empty(), "invalid buffer len: 0");
+
+    #[track_caller]
+    fn try_alloc_with_vec(&self, mut vec: Vec<T>) -> crate::Result<CPUPtr<T>> {
+        if vec.is_empty() {
+            return Err(crate::Error::Alloc {
+                bytes: 0,
+                limit: None,
+            });
+        }
+
+        // the vector is dropped if the limit is exceeded
+        let allocation = self.memory.try_reserve(size_of_val(&vec[..]))?;
 
         let ptr = vec.as_mut_ptr();
         let len = vec.len();
         core::mem::forget(vec);
 
-        unsafe { CPUPtr::from_ptr(ptr, len, AllocFlag::None) }
+        Ok(unsafe { CPUPtr::from_ptr(ptr, len, AllocFlag::None) }.tracked(allocation))
     }
 }
 
@@ -105,6 +232,24 @@ impl PtrConv for CPU {
             flag,
             align: Some(align_of::<T>()),
             size: Some(size_of::<T>()),
+            allocation: ptr.allocation.clone(),
+        }
+    }
+
+    #[inline]
+    unsafe fn convert_with_len<T, IS: Shape, Conv, OS: Shape>(
+        ptr: &Self::Ptr<T, IS>,
+        len: usize,
+        flag: AllocFlag,
+    ) -> Self::Ptr<Conv, OS> {
+        debug_assert!(len <= ptr.len);
+        CPUPtr {
+            ptr: ptr.ptr as *mut Conv,
+            len,
+            flag,
+            align: Some(align_of::<T>()),
+            size: Some(size_of::<T>()),
+            allocation: ptr.allocation.clone(),
         }
     }
 }
@@ -126,6 +271,9 @@ impl<'a, T: Clone, S: Shape> CloneBuf<'a, T, S> for CPU {
     fn clone_buf(&'a self, buf: &Buffer<'a, T, CPU, S>) -> Buffer<'a, T, CPU, S> {
         let mut cloned = Buffer::new(self, buf.len());
         cloned.clone_from_slice(buf);
+
+        // `T` may not be copied byte by byte, hence a captured step must be executed again
+        self.capture_op(|| None);
         cloned
     }
 }
@@ -1,8 +1,9 @@
 //! The CPU module provides the CPU backend for custos.
 
-use crate::{CommonPtrs, PtrType, ShallowCopy};
+use crate::{Allocation, CommonPtrs, PtrType, ShallowCopy};
 #[cfg(feature = "blas")]
 pub use blas::*;
+pub use capture::*;
 use core::{
     alloc::Layout,
     mem::{align_of, size_of},
@@ -15,8 +16,12 @@ use crate::flag::AllocFlag;
 
 #[cfg(feature = "blas")]
 mod blas;
+mod capture;
 mod cpu_device;
 mod ops;
+mod work_queue;
+
+pub use work_queue::*;
 
 /// The pointer used for `CPU` [`Buffer`](crate::Buffer)s
 #[derive(PartialEq, Eq, Debug)]
@@ -31,8 +36,14 @@ pub struct CPUPtr<T> {
     pub align: Option<usize>,
     /// The size of type `T`
     pub size: Option<usize>,
+    /// The bytes reserved for the memory, released when the memory is freed.
+    pub(crate) allocation: Option<Allocation>,
 }
 
+// SAFETY: A `CPUPtr` behaves like a `Box<[T]>` (or a reference to one if it is not the owner).
+unsafe impl<T: Send> Send for CPUPtr<T> {}
+unsafe impl<T: Sync> Sync for CPUPtr<T> {}
+
 impl<T> CPUPtr<T> {
     /// Create a new `CPUPtr` with the given length and allocation flag
     ///
@@ -61,6 +72,29 @@ impl<T> CPUPtr<T> {
         CPUPtr::from_ptr(ptr.cast(), len, flag)
     }
 
+    /// Like [`CPUPtr::new`], but returns an error instead of aborting if the memory could not be allocated.
+    /// # Safety
+    /// The allocated memory is uninitialized.
+    pub unsafe fn try_new(len: usize, flag: AllocFlag) -> crate::Result<CPUPtr<T>> {
+        let bytes = len.saturating_mul(size_of::<T>());
+        if len == 0 {
+            return Err(crate::Error::Alloc {
+                bytes: 0,
+                limit: None,
+            });
+        }
+
+        let layout =
+            Layout::array::<T>(len).map_err(|_| crate::Error::Alloc { bytes, limit: None })?;
+        let ptr = unsafe { std::alloc::alloc(layout) };
+
+        if ptr.is_null() {
+            return Err(crate::Error::Alloc { bytes, limit: None });
+        }
+
+        Ok(CPUPtr::from_ptr(ptr.cast(), len, flag))
+    }
+
     /// Create a new `CPUPtr` with the given length and allocation flag. Initializes memory as well.
     /// # Example
     /// ```
@@ -84,6 +118,37 @@ impl<T> CPUPtr<T> {
         cpu_ptr
     }
 
+    /// Like [`CPUPtr::new_initialized`], but returns an error instead of aborting if the memory could not be allocated.
+    /// # Example
+    /// ```
+    /// use custos::{cpu::CPUPtr, flag::AllocFlag};
+    ///
+    /// let ptr = CPUPtr::<f32>::try_new_initialized(10, AllocFlag::None).unwrap();
+    /// assert_eq!(ptr.len, 10);
+    ///
+    /// assert!(CPUPtr::<f32>::try_new_initialized(0, AllocFlag::None).is_err());
+    /// assert!(CPUPtr::<f32>::try_new_initialized(usize::MAX, AllocFlag::None).is_err());
+    /// ```
+    pub fn try_new_initialized(len: usize, flag: AllocFlag) -> crate::Result<CPUPtr<T>> {
+        let bytes = len.saturating_mul(size_of::<T>());
+        if len == 0 {
+            return Err(crate::Error::Alloc {
+                bytes: 0,
+                limit: None,
+            });
+        }
+
+        let layout =
+            Layout::array::<T>(len).map_err(|_| crate::Error::Alloc { bytes, limit: None })?;
+        let ptr = unsafe { std::alloc::alloc_zeroed(layout) };
+
+        if ptr.is_null() {
+            return Err(crate::Error::Alloc { bytes, limit: None });
+        }
+
+        Ok(unsafe { CPUPtr::from_ptr(ptr.cast(), len, flag) })
+    }
+
     /// Wrap a raw pointer with the given length and allocation flag into a `CPUPtr`
     /// Depending on the flag: [`AllocFlag`], the pointer will be freed or left untouched when the `CPUPtr` is dropped.
     ///
@@ -107,8 +172,16 @@ impl<T> CPUPtr<T> {
             flag,
             align: None,
             size: None,
+            allocation: None,
         }
     }
+
+    /// Attaches `allocation`, which is released when the memory is freed.
+    #[inline]
+    pub(crate) fn tracked(mut self, allocation: Allocation) -> CPUPtr<T> {
+        self.allocation = Some(allocation);
+        self
+    }
 }
 
 impl<T> Default for CPUPtr<T> {
@@ -119,6 +192,7 @@ impl<T> Default for CPUPtr<T> {
             len: 0,
             align: None,
             size: None,
+            allocation: None,
         }
     }
 }
@@ -144,6 +218,10 @@ impl<T> Drop for CPUPtr<T> {
         unsafe {
             std::alloc::dealloc(self.ptr as *mut u8, layout);
         }
+
+        if let Some(allocation) = &self.allocation {
+            allocation.release();
+        }
     }
 }
 
@@ -157,6 +235,11 @@ impl<T> PtrType for CPUPtr<T> {
     fn flag(&self) -> AllocFlag {
         self.flag
     }
+
+    #[inline]
+    fn allocation(&self) -> Option<&Allocation> {
+        self.allocation.as_ref()
+    }
 }
 
 impl<T> CommonPtrs<T> for CPUPtr<T> {
@@ -180,6 +263,7 @@ impl<T> ShallowCopy for CPUPtr<T> {
             flag: AllocFlag::Wrapper,
             align: self.align,
             size: self.size,
+            allocation: self.allocation.clone(),
         }
     }
 }
@@ -1,13 +1,34 @@
-use core::ops::{Index, Range, RangeBounds};
+use core::{
+    mem::{size_of, size_of_val},
+    ops::{Index, Range, RangeBounds},
+};
 
-use crate::{bounds_to_range, Buffer, ClearBuf, CopySlice, MainMemory, Read, Shape, WriteBuf, CPU};
+use crate::{
+    check_len, checked_range, Alloc, Buffer, ClearBuf, CopySlice, MainMemory, PendingRead, Read,
+    ReadAsync, Shape, Transfer, WriteBuf, CPU,
+};
+
+use super::Region;
 
 impl<T, D: MainMemory, S: Shape> Read<T, S, D> for CPU {
-    type Read<'a> = &'a [T] where T: 'a, D: 'a, S: 'a;
+    type Read<'a>
+        = &'a [T]
+    where
+        T: 'a,
+        D: 'a,
+        S: 'a;
 
     #[inline]
-    fn read<'a>(&self, buf: &'a Buffer<T, D, S>) -> Self::Read<'a> {
-        buf.as_slice()
+    fn try_read<'a>(&self, buf: &'a Buffer<T, D, S>) -> crate::Result<Self::Read<'a>> {
+        let _span = crate::trace::span(
+            self,
+            "read",
+            buf.len() * core::mem::size_of::<T>(),
+            buf.ident,
+        );
+        // the host may use the data, hence a captured step must be executed again
+        self.capture_op(|| None);
+        Ok(buf.as_slice())
     }
 
     #[inline]
@@ -15,28 +36,90 @@ impl<T, D: MainMemory, S: Shape> Read<T, S, D> for CPU {
     where
         T: Default + Clone,
     {
+        let _span = crate::trace::span(
+            self,
+            "read",
+            buf.len() * core::mem::size_of::<T>(),
+            buf.ident,
+        );
+        self.capture_op(|| None);
         buf.to_vec()
     }
 }
 
+/// A pointer that is moved to the worker thread of the CPU.
+struct SendPtr<T>(*mut T);
+
+// SAFETY: The memory is kept alive by the `PendingRead` until the job has finished, which the caller of `read_async` guarantees.
+unsafe impl<T: Send> Send for SendPtr<T> {}
+
+impl<T> SendPtr<T> {
+    #[inline]
+    fn get(&self) -> *mut T {
+        self.0
+    }
+}
+
+impl<T, S> ReadAsync<T, S> for CPU
+where
+    T: Copy + Default + Send + 'static,
+    S: Shape,
+{
+    /// The data is copied on the background thread, after every job enqueued before has finished.
+    unsafe fn read_async<'a>(
+        &'a self,
+        buf: &'a Buffer<T, CPU, S>,
+    ) -> crate::Result<PendingRead<'a, T>> {
+        self.capture_op(|| None);
+        let mut data = vec![T::default(); buf.len()];
+
+        let len = buf.len();
+        let src = SendPtr(buf.ptr.ptr);
+        let dst = SendPtr(data.as_mut_ptr());
+
+        let event = self.enqueue(move || core::ptr::copy_nonoverlapping(src.get(), dst.get(), len));
+
+        Ok(PendingRead::new(data, event))
+    }
+}
+
 impl<T: Copy, D: MainMemory, S: Shape> WriteBuf<T, S, D> for CPU {
     #[inline]
-    fn write(&self, buf: &mut Buffer<T, D, S>, data: &[T]) {
-        buf.copy_from_slice(data)
+    fn try_write(&self, buf: &mut Buffer<T, D, S>, data: &[T]) -> crate::Result<()> {
+        let _span = crate::trace::span(self, "write", core::mem::size_of_val(data), buf.ident);
+        check_len(buf.len(), data.len())?;
+        buf.copy_from_slice(data);
+
+        // SAFETY: `T` is `Copy`, hence the data may be copied byte by byte
+        let bytes =
+            unsafe { core::slice::from_raw_parts(data.as_ptr().cast::<u8>(), size_of_val(data)) };
+        self.capture_write(Region::of(buf, 0), bytes);
+        Ok(())
     }
 
     #[inline]
     fn write_buf(&self, dst: &mut Buffer<T, D, S>, src: &Buffer<T, D, S>) {
-        self.write(dst, src)
+        let _span = crate::trace::span(self, "write", src.len() * size_of::<T>(), dst.ident);
+        check_len(dst.len(), src.len()).unwrap();
+        dst.copy_from_slice(src);
+
+        self.capture_copy(
+            "write_buf",
+            Region::of(dst, 0),
+            Region::of(src, 0),
+            src.len() * size_of::<T>(),
+        );
     }
 }
 
 // #[impl_stack]
 impl<T: Default, D: MainMemory, S: Shape> ClearBuf<T, S, D> for CPU {
     fn clear(&self, buf: &mut Buffer<T, D, S>) {
-        for value in buf {
+        for value in &mut *buf {
             *value = T::default();
         }
+
+        self.capture_clear::<T>(Region::of(buf, 0), buf.len());
     }
 }
 
@@ -44,22 +127,26 @@ impl<T: Copy, D: MainMemory> CopySlice<T, D> for CPU
 where
     [T]: Index<Range<usize>, Output = [T]>,
 {
-    fn copy_slice_to<SR: RangeBounds<usize>, DR: RangeBounds<usize>>(
+    fn try_copy_slice_to<SR: RangeBounds<usize>, DR: RangeBounds<usize>>(
         &self,
         source: &Buffer<T, D>,
         source_range: SR,
         dest: &mut Buffer<T, Self>,
         dest_range: DR,
-    ) {
-        let source_range = bounds_to_range(source_range, source.len());
-        let dest_range = bounds_to_range(dest_range, dest.len());
+    ) -> crate::Result<()> {
+        let source_range = checked_range(source_range, source.len())?;
+        let dest_range = checked_range(dest_range, dest.len())?;
 
-        assert_eq!(
-            source_range.end - source_range.start,
-            dest_range.end - dest_range.start,
-        );
+        check_len(source_range.len(), dest_range.len())?;
 
+        self.capture_copy(
+            "copy_slice",
+            Region::of(dest, dest_range.start),
+            Region::of(source, source_range.start),
+            source_range.len() * size_of::<T>(),
+        );
         dest[dest_range].copy_from_slice(&source[source_range]);
+        Ok(())
     }
 
     fn copy_slice_all<I: IntoIterator<Item = (Range<usize>, Range<usize>)>>(
@@ -73,3 +160,18 @@ where
         }
     }
 }
+
+impl<T: Clone, S: Shape, Dst> Transfer<T, Dst, S> for CPU
+where
+    Dst: for<'b> Alloc<'b, T, S>,
+{
+    #[inline]
+    fn try_transfer<'b>(
+        &self,
+        buf: &Buffer<T, CPU, S>,
+        dst: &'b Dst,
+    ) -> crate::Result<Buffer<'b, T, Dst, S>> {
+        self.capture_op(|| None);
+        Buffer::try_from_slice(dst, buf.as_slice())
+    }
+}
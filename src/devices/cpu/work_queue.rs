@@ -0,0 +1,154 @@
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        mpsc::{channel, Sender},
+        Arc, Condvar, Mutex,
+    },
+    task::Waker,
+    thread::JoinHandle,
+};
+
+use crate::DeviceEvent;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// The number of finished jobs, shared with the worker thread.
+#[derive(Debug, Default)]
+struct Progress {
+    finished: Mutex<u64>,
+    changed: Condvar,
+    /// Wakers of pending events and the ticket they wait for.
+    wakers: Mutex<Vec<(u64, Waker)>>,
+}
+
+impl Progress {
+    /// Marks the next job as finished and wakes the waiting events.
+    fn finish(&self) {
+        let finished = {
+            let mut finished = self.finished.lock().unwrap();
+            *finished += 1;
+            *finished
+        };
+        self.changed.notify_all();
+
+        let mut wakers = self.wakers.lock().unwrap();
+        let (complete, pending) = core::mem::take(&mut *wakers)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(ticket, _)| *ticket <= finished);
+        *wakers = pending;
+        drop(wakers);
+
+        for (_, waker) in complete {
+            waker.wake();
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Worker {
+    sender: Option<Sender<Job>>,
+    handle: Option<JoinHandle<()>>,
+    submitted: u64,
+}
+
+/// A queue that executes jobs in order on a background thread.
+/// The thread is started with the first enqueued job.
+#[derive(Debug, Default)]
+pub struct WorkQueue {
+    worker: Mutex<Worker>,
+    progress: Arc<Progress>,
+}
+
+impl WorkQueue {
+    /// Enqueues `job` and returns an event that completes after `job` has finished.
+    pub fn enqueue(&self, job: impl FnOnce() + Send + 'static) -> CPUEvent {
+        let mut worker = self.worker.lock().unwrap();
+
+        if worker.sender.is_none() {
+            let (sender, receiver) = channel::<Job>();
+            let progress = self.progress.clone();
+
+            worker.handle = Some(std::thread::spawn(move || {
+                for job in receiver {
+                    // a panicking job must not stop the queue, otherwise waiting events would never complete
+                    catch_unwind(AssertUnwindSafe(job)).ok();
+                    progress.finish();
+                }
+            }));
+            worker.sender = Some(sender);
+        }
+
+        worker.submitted += 1;
+        worker
+            .sender
+            .as_ref()
+            .unwrap()
+            .send(Box::new(job))
+            .expect("The worker thread of the CPU panicked.");
+
+        CPUEvent {
+            ticket: worker.submitted,
+            progress: self.progress.clone(),
+        }
+    }
+
+    /// Returns an event that completes after every job enqueued so far has finished.
+    pub fn record(&self) -> CPUEvent {
+        CPUEvent {
+            ticket: self.worker.lock().unwrap().submitted,
+            progress: self.progress.clone(),
+        }
+    }
+}
+
+impl Drop for WorkQueue {
+    fn drop(&mut self) {
+        let worker = self.worker.get_mut().unwrap_or_else(|err| err.into_inner());
+
+        // closing the channel stops the worker after the remaining jobs
+        worker.sender.take();
+        if let Some(handle) = worker.handle.take() {
+            handle.join().ok();
+        }
+    }
+}
+
+/// An event of the [`WorkQueue`] of a [`CPU`](crate::CPU).
+#[derive(Debug, Clone)]
+pub struct CPUEvent {
+    ticket: u64,
+    progress: Arc<Progress>,
+}
+
+impl DeviceEvent for CPUEvent {
+    fn wait(&self) -> crate::Result<()> {
+        let mut finished = self.progress.finished.lock().unwrap();
+        while *finished < self.ticket {
+            finished = self.progress.changed.wait(finished).unwrap();
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn is_complete(&self) -> bool {
+        *self.progress.finished.lock().unwrap() >= self.ticket
+    }
+
+    /// `waker` is woken by the worker thread after the job of the event has finished.
+    fn wake_on_complete(&self, waker: &Waker) {
+        // holding the lock prevents the worker from missing the waker between the check and the push
+        let mut wakers = self.progress.wakers.lock().unwrap();
+        if self.is_complete() {
+            drop(wakers);
+            waker.wake_by_ref();
+            return;
+        }
+
+        let registered = wakers
+            .iter()
+            .any(|(ticket, registered)| *ticket == self.ticket && registered.will_wake(waker));
+        if !registered {
+            wakers.push((self.ticket, waker.clone()));
+        }
+    }
+}
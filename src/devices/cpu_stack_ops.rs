@@ -6,7 +6,10 @@ use custos_macro::impl_stack;
 
 use crate::MayToCLSource;
 #[cfg(any(feature = "cpu", feature = "stack"))]
-use crate::{ApplyFunction, Buffer, Device, Eval, MainMemory, Resolve, Shape, ToVal, UnaryGrad};
+use crate::{
+    ApplyFunction, ApplyFunctionTo, BinaryElementWise, BinaryElementWiseTo, Buffer, Device, Eval,
+    Inplace, MainMemory, Named, Resolve, Shape, ToVal, UnaryGrad,
+};
 
 #[cfg(feature = "cpu")]
 use crate::CPU;
@@ -14,6 +17,17 @@ use crate::CPU;
 #[cfg(feature = "stack")]
 use crate::Stack;
 
+/// Records the operations of the `CPU` into a captured [`Plan`](crate::Plan). The `Stack` does not capture.
+#[cfg(any(feature = "cpu", feature = "stack"))]
+pub(crate) trait MayCapture: Device {
+    /// Marks an operation that received a closure. The closure is not kept beyond the call, hence the operation can't be recorded.
+    #[inline]
+    fn capture_closure_op(&self) {}
+}
+
+#[cfg(feature = "stack")]
+impl MayCapture for Stack {}
+
 #[impl_stack]
 impl<T, D, S> ApplyFunction<T, S, D> for CPU
 where
@@ -21,17 +35,177 @@ where
     D: crate::MainMemory,
     S: Shape,
 {
-    fn apply_fn<F>(&self, buf: &Buffer<T, D, S>, f: impl Fn(Resolve<T>) -> F) -> Buffer<T, Self, S>
+    #[track_caller]
+    fn try_apply_fn<F>(
+        &self,
+        buf: &Buffer<T, D, S>,
+        f: impl Fn(Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<T, Self, S>>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        let mut span = crate::trace::span(
+            self,
+            "apply_fn",
+            buf.len() * core::mem::size_of::<T>(),
+            None,
+        );
+        let mut out = self.try_retrieve::<T, S>(buf.len(), Inplace(Named("apply_fn", buf)))?;
+        span.set_ident(out.ident);
+
+        // `out` may share its memory with `buf`, hence both are accessed element by element
+        for idx in 0..buf.len() {
+            out[idx] = f(buf[idx].to_val()).eval()
+        }
+        self.capture_closure_op();
+
+        Ok(out)
+    }
+}
+
+#[impl_stack]
+impl<T, D, S> ApplyFunctionTo<T, S, D> for CPU
+where
+    T: Copy + ToVal,
+    D: crate::MainMemory,
+    S: Shape,
+{
+    fn try_apply_fn_to<F>(
+        &self,
+        buf: &Buffer<T, D, S>,
+        out: &mut Buffer<T, Self, S>,
+        f: impl Fn(Resolve<T>) -> F,
+    ) -> crate::Result<()>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        crate::check_len(out.len(), buf.len())?;
+        let _span = crate::trace::span(
+            self,
+            "apply_fn",
+            buf.len() * core::mem::size_of::<T>(),
+            out.ident,
+        );
+
+        for (out, value) in out.iter_mut().zip(buf.iter()) {
+            *out = f((*value).to_val()).eval()
+        }
+        self.capture_closure_op();
+        Ok(())
+    }
+}
+
+#[impl_stack]
+impl<T, D, S> BinaryElementWise<T, S, D> for CPU
+where
+    T: Copy + Default + ToVal,
+    D: crate::MainMemory,
+    S: Shape,
+{
+    #[track_caller]
+    fn try_binary_ew<F>(
+        &self,
+        lhs: &Buffer<T, D, S>,
+        rhs: &Buffer<T, D, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<T, Self, S>>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        crate::check_len(lhs.len(), rhs.len())?;
+        let mut out = self.try_retrieve::<T, S>(lhs.len(), Named("binary_ew", (lhs, rhs)))?;
+        self.try_binary_ew_to(lhs, rhs, &mut out, f)?;
+        Ok(out)
+    }
+}
+
+#[impl_stack]
+impl<T, D, S> BinaryElementWiseTo<T, S, D> for CPU
+where
+    T: Copy + ToVal,
+    D: crate::MainMemory,
+    S: Shape,
+{
+    fn try_binary_ew_to<F>(
+        &self,
+        lhs: &Buffer<T, D, S>,
+        rhs: &Buffer<T, D, S>,
+        out: &mut Buffer<T, Self, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<()>
     where
         F: Eval<T> + MayToCLSource,
     {
-        let mut out = self.retrieve::<T, S>(buf.len(), buf);
+        crate::check_len(out.len(), lhs.len())?;
+        crate::check_len(out.len(), rhs.len())?;
+        let _span = crate::trace::span(
+            self,
+            "binary_ew",
+            out.len() * core::mem::size_of::<T>(),
+            out.ident,
+        );
 
-        for (value, x) in out.iter_mut().zip(buf.iter()) {
-            *value = f((*x).to_val()).eval()
+        for ((out, lhs), rhs) in out.iter_mut().zip(lhs.iter()).zip(rhs.iter()) {
+            *out = f((*lhs).to_val(), (*rhs).to_val()).eval()
         }
+        self.capture_closure_op();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "cpu")]
+impl<T, D, S> crate::Reduce<T, S, D> for CPU
+where
+    T: Copy + ToVal,
+    D: MainMemory,
+    S: Shape,
+{
+    #[track_caller]
+    fn try_reduce<F>(
+        &self,
+        buf: &Buffer<T, D, S>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<T, Self>>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        let mut out = self.try_retrieve::<T, ()>(1, Named("reduce", buf))?;
+        crate::ReduceTo::try_reduce_to(self, buf, &mut out, init, f)?;
+        Ok(out)
+    }
+}
+
+#[cfg(feature = "cpu")]
+impl<T, D, S> crate::ReduceTo<T, S, D> for CPU
+where
+    T: Copy + ToVal,
+    D: MainMemory,
+    S: Shape,
+{
+    fn try_reduce_to<F>(
+        &self,
+        buf: &Buffer<T, D, S>,
+        out: &mut Buffer<T, Self>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<()>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        crate::check_len(1, out.len())?;
+        let _span = crate::trace::span(
+            self,
+            "reduce",
+            buf.len() * core::mem::size_of::<T>(),
+            out.ident,
+        );
 
-        out
+        out[0] = buf
+            .iter()
+            .fold(init, |acc, value| f(acc.to_val(), (*value).to_val()).eval());
+        self.capture_closure_op();
+        Ok(())
     }
 }
 
@@ -54,5 +228,6 @@ where
         for ((lhs, lhs_grad), out) in lhs.iter().zip(lhs_grad.iter_mut()).zip(out.iter()) {
             *lhs_grad += *out * lhs_grad_fn((*lhs).to_val()).eval();
         }
+        self.capture_closure_op();
     }
 }
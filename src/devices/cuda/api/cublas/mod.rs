@@ -3,10 +3,9 @@
 mod error;
 mod ffi;
 
+pub use error::*;
 pub use ffi::*;
 
-use self::error::CublasResult;
-
 /// Raw CUBLAS handle
 #[derive(Debug)]
 pub struct CublasHandle(pub *mut cublasContext);
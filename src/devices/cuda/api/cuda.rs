@@ -3,8 +3,12 @@ use super::{
     cuMemFree_v2, cuMemcpyDtoH_v2, cuMemcpyHtoD_v2, cuModuleGetFunction, cuModuleLoad,
     cuModuleLoadData, cuModuleUnload, cuStreamCreate, cuStreamSynchronize,
     error::{CudaErrorKind, CudaResult},
-    ffi::cuMemAlloc_v2,
-    CUcontext, CUdevice, CUfunction, CUmodule, CUstream,
+    ffi::{
+        cuCtxPopCurrent_v2, cuCtxPushCurrent_v2, cuCtxSetCurrent, cuEventCreate, cuEventDestroy_v2,
+        cuEventQuery, cuEventRecord, cuEventSynchronize, cuLaunchHostFunc, cuMemAlloc_v2,
+        cuMemcpyDtoHAsync_v2, cuStreamWaitEvent, CUresult,
+    },
+    CUcontext, CUdevice, CUevent, CUfunction, CUmodule, CUstream,
 };
 
 use std::{
@@ -49,6 +53,13 @@ impl Drop for Context {
     }
 }
 
+impl Context {
+    /// Binds the context to the calling thread.
+    pub fn set_current(&self) -> CudaResult<()> {
+        unsafe { cuCtxSetCurrent(self.0) }.to_result()
+    }
+}
+
 pub fn create_context(device: &CudaIntDevice) -> CudaResult<Context> {
     let mut context = Context(null_mut());
     unsafe {
@@ -77,6 +88,17 @@ pub unsafe fn cufree(ptr: CUdeviceptr) -> CudaResult<()> {
     cuMemFree_v2(ptr).into()
 }
 
+/// Free CUDA GPU memory of `ctx`, regardless of the context that is bound to the calling thread.
+/// # Safety
+/// FFI, `ptr` must be a valid pointer allocated in `ctx`.
+pub unsafe fn cufree_in(ctx: CUcontext, ptr: CUdeviceptr) -> CudaResult<()> {
+    cuCtxPushCurrent_v2(ctx).to_result()?;
+    let freed = cufree(ptr);
+    let mut popped = null_mut();
+    cuCtxPopCurrent_v2(&mut popped).to_result()?;
+    freed
+}
+
 pub fn cu_write<T>(dst: CUdeviceptr, src_host: &[T]) -> CudaResult<()> {
     let bytes_to_copy = src_host.len() * std::mem::size_of::<T>();
     unsafe { cuMemcpyHtoD_v2(dst, src_host.as_ptr() as *const c_void, bytes_to_copy) }.into()
@@ -87,9 +109,30 @@ pub fn cu_read<T>(dst_host: &mut [T], src: CUdeviceptr) -> CudaResult<()> {
     unsafe { cuMemcpyDtoH_v2(dst_host.as_mut_ptr() as *mut c_void, src, bytes_to_copy) }.into()
 }
 
+/// Enqueues a device to host copy on `stream`.
+/// # Safety
+/// `dst_host` must stay valid until every operation of `stream` enqueued so far has finished.
+pub unsafe fn cu_read_async<T>(
+    dst_host: &mut [T],
+    src: CUdeviceptr,
+    stream: &Stream,
+) -> CudaResult<()> {
+    let bytes_to_copy = std::mem::size_of_val(dst_host);
+    cuMemcpyDtoHAsync_v2(
+        dst_host.as_mut_ptr() as *mut c_void,
+        src,
+        bytes_to_copy,
+        stream.0,
+    )
+    .into()
+}
+
 #[derive(Debug)]
 pub struct Module(pub CUmodule);
 
+// SAFETY: Modules can be used from every thread that has the context of the module bound.
+unsafe impl Send for Module {}
+
 impl Module {
     pub fn function(&self, fn_name: &str) -> CudaResult<FnHandle> {
         module_get_fn(self, fn_name)
@@ -121,6 +164,9 @@ pub fn load_module_data(src: CString) -> CudaResult<Module> {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct FnHandle(pub CUfunction);
 
+// SAFETY: Functions can be used from every thread that has the context of the function bound.
+unsafe impl Send for FnHandle {}
+
 pub fn module_get_fn(module: &Module, fn_name: &str) -> CudaResult<FnHandle> {
     let fn_name = CString::new(fn_name).unwrap();
 
@@ -144,6 +190,62 @@ pub fn create_stream() -> CudaResult<Stream> {
     Ok(ph_stream)
 }
 
+#[derive(Debug)]
+pub struct Event(pub CUevent);
+
+impl Event {
+    /// Records the event into `stream`.
+    pub fn record(&self, stream: &Stream) -> CudaResult<()> {
+        unsafe { cuEventRecord(self.0, stream.0) }.to_result()
+    }
+
+    pub fn sync(&self) -> CudaResult<()> {
+        unsafe { cuEventSynchronize(self.0) }.to_result()
+    }
+
+    /// Returns `Ok(false)` if the recorded work has not finished yet.
+    pub fn query(&self) -> CudaResult<bool> {
+        match unsafe { cuEventQuery(self.0) } {
+            CUresult::CUDA_ERROR_NOT_READY => Ok(false),
+            result => result.to_result().map(|_| true),
+        }
+    }
+}
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        unsafe { cuEventDestroy_v2(self.0) };
+    }
+}
+
+pub fn create_event() -> CudaResult<Event> {
+    let mut ph_event = Event(null_mut());
+    unsafe { cuEventCreate(&mut ph_event.0, 0) }.to_result()?;
+    Ok(ph_event)
+}
+
+/// Enqueues `callback` on `stream`. It is called by a thread of the driver after the work enqueued before has finished.
+/// `callback` must not call into the CUDA API.
+pub fn launch_host_fn(stream: &Stream, callback: Box<dyn FnOnce() + Send>) -> CudaResult<()> {
+    unsafe extern "C" fn call(user_data: *mut c_void) {
+        let callback = Box::from_raw(user_data as *mut Box<dyn FnOnce() + Send>);
+        callback()
+    }
+
+    let user_data = Box::into_raw(Box::new(callback));
+    let result = unsafe { cuLaunchHostFunc(stream.0, call, user_data as *mut c_void) }.to_result();
+    if result.is_err() {
+        // the callback is never called
+        drop(unsafe { Box::from_raw(user_data) });
+    }
+    result
+}
+
+/// Makes every operation enqueued on `stream` after this call wait for `event`.
+pub fn stream_wait_event(stream: &Stream, event: &Event) -> CudaResult<()> {
+    unsafe { cuStreamWaitEvent(stream.0, event.0, 0) }.to_result()
+}
+
 pub fn culaunch_kernel(
     f: &FnHandle,
     grid: [u32; 3],
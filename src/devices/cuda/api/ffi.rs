@@ -21,6 +21,11 @@ pub type CUfunction = *mut CUfunc_st;
 pub enum CUstream_st {}
 pub type CUstream = *mut CUstream_st;
 
+pub enum CUevent_st {}
+pub type CUevent = *mut CUevent_st;
+
+pub type CUhostFn = unsafe extern "C" fn(user_data: *mut c_void);
+
 #[repr(u32)]
 pub enum CUdevice_attribute {
     CU_DEVICE_ATTRIBUTE_MAX_THREADS_PER_BLOCK = 1,
@@ -145,6 +150,9 @@ extern "C" {
     ) -> CUresult;
     pub fn cuCtxCreate_v2(context: *mut CUcontext, flags: u32, device: CUdevice) -> CUresult;
     pub fn cuCtxDestroy(context: CUcontext);
+    pub fn cuCtxSetCurrent(context: CUcontext) -> CUresult;
+    pub fn cuCtxPushCurrent_v2(context: CUcontext) -> CUresult;
+    pub fn cuCtxPopCurrent_v2(context: *mut CUcontext) -> CUresult;
     pub fn cuCtxSynchronize() -> CUresult;
     pub fn cuMemAlloc_v2(ptr: *mut CUdeviceptr, size: usize) -> CUresult;
     pub fn cuMemFree_v2(ptr: CUdeviceptr) -> CUresult;
@@ -185,6 +193,19 @@ extern "C" {
     pub fn cuStreamCreate(ph_stream: *mut CUstream, flags: u32) -> CUresult;
     pub fn cuStreamDestroy(hstream: CUstream) -> CUresult;
     pub fn cuStreamSynchronize(stream: CUstream) -> CUresult;
+    pub fn cuStreamWaitEvent(stream: CUstream, event: CUevent, flags: u32) -> CUresult;
+    pub fn cuLaunchHostFunc(stream: CUstream, func: CUhostFn, user_data: *mut c_void) -> CUresult;
+    pub fn cuEventCreate(ph_event: *mut CUevent, flags: u32) -> CUresult;
+    pub fn cuEventRecord(event: CUevent, stream: CUstream) -> CUresult;
+    pub fn cuEventQuery(event: CUevent) -> CUresult;
+    pub fn cuEventSynchronize(event: CUevent) -> CUresult;
+    pub fn cuEventDestroy_v2(event: CUevent) -> CUresult;
+    pub fn cuMemcpyDtoHAsync_v2(
+        dst_host: *mut c_void,
+        src_device: CUdeviceptr,
+        bytes_to_copy: usize,
+        stream: CUstream,
+    ) -> CUresult;
     pub fn cuOccupancyMaxPotentialBlockSize(
         min_grid_size: *mut i32,
         block_size: *mut i32,
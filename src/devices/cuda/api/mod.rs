@@ -11,6 +11,7 @@ pub mod cublas;
 pub mod nvrtc;
 
 pub use cuda::*;
+pub use error::*;
 pub use ffi::*;
 
 #[cfg(test)]
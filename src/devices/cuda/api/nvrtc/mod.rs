@@ -9,7 +9,7 @@ use std::{
     ptr::{null, null_mut},
 };
 
-use self::error::NvrtcResult;
+pub use error::*;
 pub use ffi::*;
 
 /// A compileable nvrtc program
@@ -25,6 +25,11 @@ impl NvrtcProgram {
     pub fn ptx(&self) -> NvrtcResult<CString> {
         get_ptx(self)
     }
+
+    /// Returns the compilation log
+    pub fn log(&self) -> NvrtcResult<String> {
+        get_log(self)
+    }
 }
 
 /// creates a new compileable nvrtc program
@@ -80,6 +85,19 @@ pub fn get_ptx(prog: &NvrtcProgram) -> NvrtcResult<CString> {
     }
 }
 
+/// Returns the log of the last compilation
+pub fn get_log(prog: &NvrtcProgram) -> NvrtcResult<String> {
+    unsafe {
+        let mut log_size = 0;
+        nvrtcGetProgramLogSize(prog.0, &mut log_size).to_result()?;
+        let mut log: Vec<u8> = vec![0; log_size as usize];
+        nvrtcGetProgramLog(prog.0, log.as_mut_ptr() as *mut c_char).to_result()?;
+        // the log is null terminated
+        log.pop();
+        Ok(String::from_utf8_lossy(&log).into_owned())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
@@ -1,5 +1,9 @@
-use std::{cell::RefCell, collections::HashMap};
 use std::marker::PhantomData;
+use std::mem::{size_of, size_of_val};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+};
 
 use super::api::FnHandle;
 use super::{
@@ -12,26 +16,35 @@ use super::{
 };
 
 use crate::{
-    cache::Cache, flag::AllocFlag, Addons, AddonsReturn, Alloc, Buffer, CacheReturn, CloneBuf,
-    Device, PtrConv, Shape,
+    cache::Cache, flag::AllocFlag, Addons, AddonsReturn, Alloc, Buffer, CacheReturn, CacheStrategy,
+    CloneBuf, Device, MemoryReturn, MemoryTracker, PerThread, PtrConv, Shape,
 };
 
 /// Used to perform calculations with a CUDA capable device.
 /// To make new calculations invocable, a trait providing new operations should be implemented for [`CUDA`].
 #[derive(Debug)]
 pub struct CUDA {
-    /// Stores compiled CUDA kernels.
-    pub kernel_cache: RefCell<KernelCacheCU>,
-    /// Stores CUDA modules from the compiled kernels.
-    pub modules: RefCell<HashMap<FnHandle, Module>>,
+    /// Stores compiled CUDA kernels. Each thread has its own kernel cache.
+    pub kernel_cache: PerThread<RefCell<KernelCacheCU>>,
+    /// Stores CUDA modules from the compiled kernels. Each thread has its own modules.
+    pub modules: PerThread<RefCell<HashMap<FnHandle, Module>>>,
     device: CudaIntDevice,
-    ctx: Context,
     stream: Stream,
     handle: CublasHandle,
     /// Provides additional functionality for the CUDA device. e.g. a cache, a gradient [`Tape`](crate::Tape), an optimizeable [`Graph`](crate::Graph) and a [`Cache`](crate::Cache).
-    pub addons: Addons<CUDA>,
+    pub addons: PerThread<Addons<CUDA>>,
+    memory: MemoryTracker,
+    /// Whether the context is bound to the thread, see [`CUDA::bind`].
+    bound: PerThread<Cell<bool>>,
+    // dropped last, after the memory of all threads was freed
+    ctx: Context,
 }
 
+// SAFETY: Kernels, modules and addons are stored per thread.
+// Every operation binds the context to the calling thread before it uses the remaining handles, see [`CUDA::bind`].
+unsafe impl Send for CUDA {}
+unsafe impl Sync for CUDA {}
+
 /// Short form for `CUDA`
 pub type CU = CUDA;
 
@@ -53,12 +66,43 @@ impl CUDA {
             modules: Default::default(),
             addons: Default::default(),
             device,
-            ctx,
             stream,
             handle,
+            memory: MemoryTracker::default(),
+            bound: PerThread::new(Cell::new(true)),
+            ctx,
         })
     }
 
+    /// Sets the [`CacheStrategy`] that is used by [`Device::retrieve`] on every thread.
+    /// The strategy is part of the construction of the device, previously cached entries are discarded.
+    #[inline]
+    #[must_use]
+    pub fn with_cache_strategy(mut self, strategy: CacheStrategy) -> CUDA {
+        self.addons = Addons::per_thread(strategy);
+        self
+    }
+
+    /// Binds the context of the device to the calling thread.
+    /// The device binds its context itself when a thread uses it for the first time.
+    /// Hence, this is only required if another context was bound to the thread in the meantime.
+    #[inline]
+    pub fn make_current(&self) -> crate::Result<()> {
+        self.ctx.set_current()?;
+        self.bound.set(true);
+        Ok(())
+    }
+
+    /// Binds the context to the calling thread, unless it was already bound by the device.
+    /// Every operation calls this before using the device on the calling thread.
+    #[inline]
+    pub(crate) fn bind(&self) -> crate::Result<()> {
+        if self.bound.get() {
+            return Ok(());
+        }
+        self.make_current()
+    }
+
     /// Returns the internal CUDA device.
     #[inline]
     pub fn device(&self) -> &CudaIntDevice {
@@ -103,6 +147,20 @@ impl Device for CUDA {
     fn new() -> crate::Result<Self> {
         CUDA::new(chosen_cu_idx())
     }
+
+    /// Waits until every operation of the stream has finished.
+    #[inline]
+    fn synchronize(&self) -> crate::Result<()> {
+        self.bind()?;
+        Ok(self.stream().sync()?)
+    }
+}
+
+impl MemoryReturn for CUDA {
+    #[inline]
+    fn memory(&self) -> &MemoryTracker {
+        &self.memory
+    }
 }
 
 impl AddonsReturn for CUDA {
@@ -111,7 +169,7 @@ impl AddonsReturn for CUDA {
     where
         Self: Device,
     {
-        &self.addons
+        self.addons.local()
     }
 }
 
@@ -125,6 +183,25 @@ impl PtrConv for CUDA {
             ptr: ptr.ptr,
             len: ptr.len,
             flag,
+            allocation: ptr.allocation.clone(),
+            ctx: ptr.ctx,
+            p: PhantomData,
+        }
+    }
+
+    #[inline]
+    unsafe fn convert_with_len<T, IS: Shape, Conv, OS: Shape>(
+        ptr: &Self::Ptr<T, IS>,
+        len: usize,
+        flag: AllocFlag,
+    ) -> Self::Ptr<Conv, OS> {
+        debug_assert!(len <= ptr.len);
+        CUDAPtr {
+            ptr: ptr.ptr,
+            len,
+            flag,
+            allocation: ptr.allocation.clone(),
+            ctx: ptr.ctx,
             p: PhantomData,
         }
     }
@@ -140,7 +217,7 @@ impl Default for CUDA {
 impl Drop for CUDA {
     fn drop(&mut self) {
         // deallocates all cached buffers before destroying the context etc
-        self.cache_mut().nodes.clear();
+        self.cache_mut().clear();
 
         unsafe {
             cublasDestroy_v2(self.handle.0);
@@ -149,27 +226,55 @@ impl Drop for CUDA {
     }
 }
 
-impl<T> Alloc<'_, T> for CUDA {
-    fn alloc(&self, len: usize, flag: AllocFlag) -> CUDAPtr<T> {
-        let ptr = cumalloc::<T>(len).unwrap();
+impl<T, S: Shape> Alloc<'_, T, S> for CUDA {
+    #[track_caller]
+    fn try_alloc(&self, len: usize, flag: AllocFlag) -> crate::Result<CUDAPtr<T>> {
+        if len == 0 {
+            return Err(crate::Error::Alloc {
+                bytes: 0,
+                limit: None,
+            });
+        }
+
+        self.bind()?;
+        let bytes = len.saturating_mul(size_of::<T>());
+        let (ptr, allocation) = self.memory.track(bytes, || Ok(cumalloc::<T>(len)?))?;
         // TODO: use unified mem if available -> i can't test this
-        CUDAPtr {
+        Ok(CUDAPtr {
             ptr,
             len,
             flag,
+            allocation: Some(allocation),
+            ctx: self.ctx.0,
             p: PhantomData,
-        }
+        })
     }
 
-    fn with_slice(&self, data: &[T]) -> CUDAPtr<T> {
-        let ptr = cumalloc::<T>(data.len()).unwrap();
-        cu_write(ptr, data).unwrap();
-        CUDAPtr {
+    #[track_caller]
+    fn try_with_slice(&self, data: &[T]) -> crate::Result<CUDAPtr<T>> {
+        if data.is_empty() {
+            return Err(crate::Error::Alloc {
+                bytes: 0,
+                limit: None,
+            });
+        }
+
+        self.bind()?;
+        let (ptr, allocation) = self
+            .memory
+            .track(size_of_val(data), || Ok(cumalloc::<T>(data.len())?))?;
+
+        // the memory is freed if the write fails
+        let ptr = CUDAPtr {
             ptr,
             len: data.len(),
             flag: AllocFlag::None,
+            allocation: Some(allocation),
+            ctx: self.ctx.0,
             p: PhantomData,
-        }
+        };
+        cu_write(ptr.ptr, data)?;
+        Ok(ptr)
     }
 }
 
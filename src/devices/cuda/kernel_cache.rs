@@ -44,10 +44,20 @@ impl KernelCacheCU {
             return Ok(*kernel);
         }
 
+        let _span = crate::trace::span(device, "compile_kernel", src.len(), None);
+
         // TODO: not optimal, if multiple functions are used in the same source code, they are compiled multiple times
         let mut x = create_program(src, "")?;
 
-        x.compile(Some(vec![CString::new("--use_fast_math").unwrap()]))?;
+        if let Err(err) = x.compile(Some(vec![CString::new("--use_fast_math").unwrap()])) {
+            let log = x.log().unwrap_or_default();
+            unsafe { nvrtcDestroyProgram(&mut x.0).to_result()? };
+
+            if log.is_empty() {
+                return Err(err.into());
+            }
+            return Err(Error::KernelCompile { log });
+        }
 
         let module = load_module_data(x.ptx()?)?;
         let function = module.function(fn_name)?;
@@ -63,6 +73,7 @@ impl KernelCacheCU {
 
 /// Exactly like [`KernelCacheCU`], but with a immutable source of the cache using interior mutability.
 pub fn fn_cache(device: &CUDA, src: &str, fn_name: &str) -> crate::Result<FnHandle> {
+    device.bind()?;
     device
         .kernel_cache
         .borrow_mut()
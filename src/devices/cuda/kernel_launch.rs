@@ -45,6 +45,7 @@ pub trait AsCudaCvoidPtr {
 impl<'a, T> AsCudaCvoidPtr for &Buffer<'a, T, CUDA> {
     #[inline]
     fn as_cvoid_ptr(&self) -> *mut c_void {
+        crate::sanitize(&self.ptr);
         &self.ptr.ptr as *const u64 as *mut c_void
     }
 }
@@ -52,6 +53,7 @@ impl<'a, T> AsCudaCvoidPtr for &Buffer<'a, T, CUDA> {
 impl<'a, T> AsCudaCvoidPtr for Buffer<'a, T, CUDA> {
     #[inline]
     fn as_cvoid_ptr(&self) -> *mut c_void {
+        crate::sanitize(&self.ptr);
         &self.ptr.ptr as *const u64 as *mut c_void
     }
 }
@@ -59,6 +61,7 @@ impl<'a, T> AsCudaCvoidPtr for Buffer<'a, T, CUDA> {
 impl<'a, T> AsCudaCvoidPtr for CUDAPtr<T> {
     #[inline]
     fn as_cvoid_ptr(&self) -> *mut c_void {
+        crate::sanitize(self);
         &self.ptr as *const u64 as *mut c_void
     }
 }
@@ -80,9 +83,8 @@ pub fn launch_kernel(
     fn_name: &str,
     params: &[&dyn AsCudaCvoidPtr],
 ) -> crate::Result<()> {
-
     let func = fn_cache(device, src, fn_name)?;
-    launch_kernel_with_fn(device, &func, grid, blocks, shared_mem_bytes, params)    
+    launch_kernel_with_fn(device, &func, grid, blocks, shared_mem_bytes, params)
 }
 
 /// Launch a CUDA kernel with the given CUDA function grid and block sizes.
@@ -15,10 +15,11 @@ use std::{marker::PhantomData, ptr::null_mut};
 pub use cuda_device::*;
 pub use kernel_cache::*;
 pub use kernel_launch::*;
+pub use ops::*;
 
-use crate::{flag::AllocFlag, Buffer, CDatatype, CommonPtrs, PtrType, ShallowCopy};
+use crate::{flag::AllocFlag, Allocation, Buffer, CDatatype, CommonPtrs, PtrType, ShallowCopy};
 
-use self::api::cufree;
+use self::api::{cufree_in, CUcontext};
 
 /// Another shorter type for Buffer<'a, T, CUDA, S>
 pub type CUBuffer<'a, T> = Buffer<'a, T, CUDA>;
@@ -42,9 +43,18 @@ pub struct CUDAPtr<T> {
     pub len: usize,
     /// Allocation flag for the pointer.
     pub flag: AllocFlag,
+    /// The bytes reserved for the memory object, released when it is freed.
+    pub(crate) allocation: Option<Allocation>,
+    /// The context the memory was allocated in, which is bound while the memory is freed.
+    pub(crate) ctx: CUcontext,
     pub p: PhantomData<T>,
 }
 
+// SAFETY: A `CUDAPtr` only stores the address of device memory and the handle of its context.
+// The memory is used by the `CUDA` device, which binds its context on every thread, and freed with the context bound.
+unsafe impl<T: Send> Send for CUDAPtr<T> {}
+unsafe impl<T: Sync> Sync for CUDAPtr<T> {}
+
 impl<T> Default for CUDAPtr<T> {
     #[inline]
     fn default() -> Self {
@@ -52,6 +62,8 @@ impl<T> Default for CUDAPtr<T> {
             ptr: 0,
             len: 0,
             flag: AllocFlag::default(),
+            allocation: None,
+            ctx: null_mut(),
             p: PhantomData,
         }
     }
@@ -67,7 +79,11 @@ impl<T> Drop for CUDAPtr<T> {
             return;
         }
         unsafe {
-            cufree(self.ptr).unwrap();
+            cufree_in(self.ctx, self.ptr).unwrap();
+        }
+
+        if let Some(allocation) = &self.allocation {
+            allocation.release();
         }
     }
 }
@@ -79,6 +95,8 @@ impl<T> ShallowCopy for CUDAPtr<T> {
             ptr: self.ptr,
             len: self.len,
             flag: AllocFlag::Wrapper,
+            allocation: self.allocation.clone(),
+            ctx: self.ctx,
             p: PhantomData,
         }
     }
@@ -94,6 +112,11 @@ impl<T> PtrType for CUDAPtr<T> {
     fn flag(&self) -> AllocFlag {
         self.flag
     }
+
+    #[inline]
+    fn allocation(&self) -> Option<&Allocation> {
+        self.allocation.as_ref()
+    }
 }
 
 impl<T> CommonPtrs<T> for CUDAPtr<T> {
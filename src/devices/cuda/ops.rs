@@ -1,40 +1,158 @@
-use core::ops::{Range, RangeBounds};
+use core::{
+    ops::{Range, RangeBounds},
+    task::Waker,
+};
+use std::sync::{Arc, Mutex};
 
 use crate::{
-    bounds_to_range, cuda::api::cu_read, Buffer, CDatatype, ClearBuf, CopySlice, Read, WriteBuf,
-    CUDA,
+    check_len, checked_range, cuda::api::cu_read, Alloc, Buffer, CDatatype, ClearBuf, CopySlice,
+    Device, DeviceEvent, PendingRead, Read, ReadAsync, RecordEvent, Transfer, WriteBuf, CUDA,
 };
 
 use super::{
-    api::{cuMemcpy, cu_write},
+    api::{
+        create_event, cuMemcpy, cu_read_async, cu_write, launch_host_fn, stream_wait_event, Event,
+    },
     cu_clear,
 };
 
 impl<T: Default + Clone> Read<T> for CUDA {
-    type Read<'a> = Vec<T>
+    type Read<'a>
+        = Vec<T>
     where
         T: 'a,
         CUDA: 'a;
 
-    #[inline]
-    fn read(&self, buf: &Buffer<T, CUDA>) -> Vec<T> {
-        self.read_to_vec(buf)
+    fn try_read(&self, buf: &Buffer<T, CUDA>) -> crate::Result<Vec<T>> {
+        let _span = crate::trace::span(
+            self,
+            "read",
+            buf.len() * core::mem::size_of::<T>(),
+            buf.ident,
+        );
+        assert!(
+            buf.ptrs().2 != 0,
+            "called Read::read(..) on a non CUDA buffer"
+        );
+        crate::sanitize(&buf.ptr);
+        self.synchronize()?;
+
+        let mut read = vec![T::default(); buf.len()];
+        cu_read(&mut read, buf.ptr.ptr)?;
+        Ok(read)
     }
 
+    #[inline]
     fn read_to_vec(&self, buf: &Buffer<T, CUDA>) -> Vec<T>
     where
         T: Default + Clone,
     {
-        assert!(
-            buf.ptrs().2 != 0,
-            "called Read::read(..) on a non CUDA buffer"
-        );
-        // TODO: sync here or somewhere else?
-        self.stream().sync().unwrap();
+        self.read(buf)
+    }
+}
 
-        let mut read = vec![T::default(); buf.len()];
-        cu_read(&mut read, buf.ptr.ptr).unwrap();
-        read
+/// A CUDA event, recorded into the stream of a [`CUDA`] device.
+#[derive(Debug)]
+pub struct CUDAEvent {
+    /// The underlying CUDA event
+    pub event: Event,
+    wakers: Arc<Mutex<EventWakers>>,
+}
+
+/// The wakers of a [`CUDAEvent`]. They are woken by a host function that is enqueued after the event.
+#[derive(Debug, Default)]
+struct EventWakers {
+    complete: bool,
+    wakers: Vec<Waker>,
+}
+
+impl DeviceEvent for CUDAEvent {
+    #[inline]
+    fn wait(&self) -> crate::Result<()> {
+        Ok(self.event.sync()?)
+    }
+
+    #[inline]
+    fn is_complete(&self) -> bool {
+        // a failed query completes the event as well, the error is returned by `wait`
+        self.event.query().unwrap_or(true)
+    }
+
+    fn wake_on_complete(&self, waker: &Waker) {
+        // holding the lock prevents the host function from missing the waker between the check and the push
+        let mut wakers = self.wakers.lock().unwrap();
+        if wakers.complete {
+            drop(wakers);
+            waker.wake_by_ref();
+            return;
+        }
+
+        if !wakers
+            .wakers
+            .iter()
+            .any(|registered| registered.will_wake(waker))
+        {
+            wakers.wakers.push(waker.clone());
+        }
+    }
+}
+
+impl RecordEvent for CUDA {
+    type Event = CUDAEvent;
+
+    fn record_event(&self) -> crate::Result<CUDAEvent> {
+        self.bind()?;
+        let event = create_event()?;
+        event.record(self.stream())?;
+
+        let wakers = Arc::new(Mutex::new(EventWakers::default()));
+        let complete = wakers.clone();
+        launch_host_fn(
+            self.stream(),
+            Box::new(move || {
+                let mut complete = complete.lock().unwrap();
+                complete.complete = true;
+                core::mem::take(&mut complete.wakers)
+                    .into_iter()
+                    .for_each(Waker::wake);
+            }),
+        )?;
+
+        Ok(CUDAEvent { event, wakers })
+    }
+
+    #[inline]
+    fn wait_event(&self, event: &CUDAEvent) -> crate::Result<()> {
+        self.bind()?;
+        Ok(stream_wait_event(self.stream(), &event.event)?)
+    }
+}
+
+impl<T: Default + Clone> ReadAsync<T> for CUDA {
+    /// Enqueues a `cuMemcpyDtoHAsync` on the stream of the device.
+    unsafe fn read_async<'a>(
+        &'a self,
+        buf: &'a Buffer<T, CUDA>,
+    ) -> crate::Result<PendingRead<'a, T>> {
+        self.bind()?;
+        let mut data = vec![T::default(); buf.len()];
+        cu_read_async(&mut data, buf.ptr.ptr, self.stream())?;
+        let event = self.record_event()?;
+        Ok(PendingRead::new(data, event))
+    }
+}
+
+impl<T: Default + Clone, Dst> Transfer<T, Dst> for CUDA
+where
+    Dst: for<'b> Alloc<'b, T>,
+{
+    #[inline]
+    fn try_transfer<'b>(
+        &self,
+        buf: &Buffer<T, CUDA>,
+        dst: &'b Dst,
+    ) -> crate::Result<Buffer<'b, T, Dst>> {
+        Buffer::try_from_vec(dst, self.try_read(buf)?)
     }
 }
 
@@ -46,27 +164,30 @@ impl<T: CDatatype> ClearBuf<T> for CUDA {
 }
 
 impl<T> CopySlice<T> for CUDA {
-    fn copy_slice_to<SR: RangeBounds<usize>, DR: RangeBounds<usize>>(
+    fn try_copy_slice_to<SR: RangeBounds<usize>, DR: RangeBounds<usize>>(
         &self,
         source: &Buffer<T, Self>,
         source_range: SR,
         dest: &mut Buffer<T, Self>,
         dest_range: DR,
-    ) {
-        let source_range = bounds_to_range(source_range, source.len());
-        let dest_range = bounds_to_range(dest_range, dest.len());
+    ) -> crate::Result<()> {
+        let source_range = checked_range(source_range, source.len())?;
+        let dest_range = checked_range(dest_range, dest.len())?;
 
-        let len = source_range.end - source_range.start;
-        assert_eq!(len, dest_range.end - dest_range.start);
+        let len = source_range.len();
+        check_len(len, dest_range.len())?;
         let size = std::mem::size_of::<T>();
 
+        self.bind()?;
         unsafe {
             cuMemcpy(
                 dest.ptr.ptr + (dest_range.start * size) as u64,
                 source.ptr.ptr + (source_range.start * size) as u64,
                 len * size,
-            );
+            )
         }
+        .to_result()?;
+        Ok(())
     }
 
     fn copy_slice_all<I: IntoIterator<Item = (Range<usize>, Range<usize>)>>(
@@ -83,12 +204,25 @@ impl<T> CopySlice<T> for CUDA {
 
 impl<T> WriteBuf<T> for CUDA {
     #[inline]
-    fn write(&self, buf: &mut Buffer<T, CUDA>, data: &[T]) {
-        cu_write(buf.cu_ptr(), data).unwrap();
+    fn try_write(&self, buf: &mut Buffer<T, CUDA>, data: &[T]) -> crate::Result<()> {
+        let _span = crate::trace::span(self, "write", core::mem::size_of_val(data), buf.ident);
+        check_len(buf.len(), data.len())?;
+        self.bind()?;
+        cu_write(buf.cu_ptr(), data)?;
+        Ok(())
     }
 
     #[inline]
     fn write_buf(&self, dst: &mut Buffer<T, Self, ()>, src: &Buffer<T, Self, ()>) {
+        let _span = crate::trace::span(
+            self,
+            "write_buf",
+            src.len() * core::mem::size_of::<T>(),
+            dst.ident,
+        );
+        crate::sanitize(&dst.ptr);
+        crate::sanitize(&src.ptr);
+        self.bind().unwrap();
         unsafe {
             cuMemcpy(
                 dst.ptr.ptr,
@@ -98,3 +232,9 @@ impl<T> WriteBuf<T> for CUDA {
         }
     }
 }
+
+#[cfg(feature = "cpu")]
+impl crate::exec_on_cpu::CPUFallback for CUDA {}
+
+#[cfg(feature = "cpu")]
+impl crate::exec_on_cpu::FallbackOps for CUDA {}
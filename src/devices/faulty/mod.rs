@@ -0,0 +1,368 @@
+//! The faulty module provides [`Faulty`], a wrapper device that injects failures into the device it wraps.
+
+mod ops;
+
+use core::{
+    fmt::{Debug, Display},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::{
+    devices::cache::Cache, flag::AllocFlag, shape::Shape, Addons, AddonsReturn, Alloc, Buffer,
+    Device, InnerBuf, MainMemory, MemoryReturn, MemoryTracker, PerThread, PtrConv, Read, Wrapper,
+    WriteBuf,
+};
+
+/// An operation of a [`Faulty`] device that failed, because a fault was injected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// An allocation, see [`Faulty::fail_alloc`].
+    Alloc,
+    /// A read, see [`Faulty::fail_reads`].
+    Read,
+    /// A write, see [`Faulty::fail_writes`].
+    Write,
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Fault::Alloc => write!(f, "allocation"),
+            Fault::Read => write!(f, "read"),
+            Fault::Write => write!(f, "write"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Faults {
+    allocs: AtomicUsize,
+    // the index of the allocation that fails, `usize::MAX` if no allocation fails
+    fail_alloc: AtomicUsize,
+    fail_reads: AtomicBool,
+    fail_writes: AtomicBool,
+}
+
+impl Default for Faults {
+    fn default() -> Self {
+        Faults {
+            allocs: AtomicUsize::new(0),
+            fail_alloc: AtomicUsize::new(usize::MAX),
+            fail_reads: AtomicBool::new(false),
+            fail_writes: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Faults {
+    fn alloc(&self) -> crate::Result<()> {
+        let idx = self.allocs.fetch_add(1, Ordering::Relaxed);
+
+        if self
+            .fail_alloc
+            .compare_exchange(idx, usize::MAX, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+        {
+            return Err(crate::Error::InjectedFault(Fault::Alloc));
+        }
+        Ok(())
+    }
+
+    fn check(&self, fault: Fault) -> crate::Result<()> {
+        let fail = match fault {
+            Fault::Alloc => return self.alloc(),
+            Fault::Read => &self.fail_reads,
+            Fault::Write => &self.fail_writes,
+        };
+
+        if fail.load(Ordering::Relaxed) {
+            return Err(crate::Error::InjectedFault(fault));
+        }
+        Ok(())
+    }
+}
+
+/// A device that wraps the device `D` and fails operations on request. Enabled by the `faulty` feature.
+///
+/// This is used to test how code (and custos itself, e.g. the cache or the graph) behaves if allocations, reads or writes fail.
+/// Injected failures are returned as [`Error::InjectedFault`](crate::Error::InjectedFault) by the fallible `try_*` methods.
+/// The infallible counterparts, e.g. [`Buffer::new`] or [`Read::read`], panic instead.
+///
+/// Memory is allocated by `D`, but `Faulty` has its own [`Addons`], hence its own cache and graph.
+/// `Read`, `WriteBuf`, `ClearBuf`, `CopySlice`, `MainMemory` and `MemoryReturn` are forwarded to `D`.
+/// The closure based ops, e.g. `ApplyFunction` and `UnaryGrad`, are executed via [`FallbackOps`](crate::exec_on_cpu::FallbackOps) (with the `cpu` and `macro` features),
+/// so that their allocations, reads and writes can fail as well.
+/// # Example
+#[cfg_attr(feature = "cpu", doc = "```")]
+#[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+/// use custos::{Buffer, Error, Fault, Faulty, Read, CPU};
+///
+/// let device = Faulty::new(CPU::new());
+/// let buf = Buffer::from((&device, [1, 2, 3]));
+///
+/// // the next allocation fails
+/// device.fail_alloc(0);
+/// assert!(matches!(
+///     Buffer::<i32, _>::try_new(&device, 3),
+///     Err(Error::InjectedFault(Fault::Alloc))
+/// ));
+/// assert!(Buffer::<i32, _>::try_new(&device, 3).is_ok());
+///
+/// device.fail_reads(true);
+/// assert!(device.try_read(&buf).is_err());
+///
+/// // `MainMemory` is forwarded as well
+/// assert_eq!(buf.as_slice(), &[1, 2, 3]);
+/// ```
+pub struct Faulty<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Provides additional functionality for the `Faulty` device. e.g. a cache, a gradient [`Tape`](crate::Tape) or an optimizeable [`Graph`](crate::Graph).
+    pub addons: PerThread<Addons<Faulty<D>>>,
+    inner: D,
+    faults: Faults,
+}
+
+impl<D> Faulty<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Wraps `inner`. No faults are injected until they are requested.
+    #[must_use]
+    pub fn new(inner: D) -> Faulty<D> {
+        Faulty {
+            addons: PerThread::default(),
+            inner,
+            faults: Faults::default(),
+        }
+    }
+
+    /// Returns the number of allocations that were requested since the creation of the device, including failed ones.
+    #[inline]
+    pub fn allocs(&self) -> usize {
+        self.faults.allocs.load(Ordering::Relaxed)
+    }
+
+    /// The allocation `n` allocations from now fails, e.g. `0` fails the next allocation.
+    /// Only this allocation fails, the following allocations succeed again.
+    /// # Example
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::{Buffer, Faulty, CPU};
+    ///
+    /// let device = Faulty::new(CPU::new());
+    /// device.fail_alloc(1);
+    ///
+    /// assert!(Buffer::<f32, _>::try_new(&device, 10).is_ok());
+    /// assert!(Buffer::<f32, _>::try_new(&device, 10).is_err());
+    /// assert!(Buffer::<f32, _>::try_new(&device, 10).is_ok());
+    /// ```
+    #[inline]
+    pub fn fail_alloc(&self, n: usize) {
+        let idx = self.allocs().saturating_add(n);
+        self.faults.fail_alloc.store(idx, Ordering::Relaxed);
+    }
+
+    /// Sets whether every read of a `Buffer` fails.
+    #[inline]
+    pub fn fail_reads(&self, fail: bool) {
+        self.faults.fail_reads.store(fail, Ordering::Relaxed);
+    }
+
+    /// Sets whether every write to a `Buffer` fails.
+    /// This includes copies between buffers ([`WriteBuf::write_buf`], [`CopySlice`](crate::CopySlice)) and [`ClearBuf::clear`](crate::ClearBuf::clear).
+    #[inline]
+    pub fn fail_writes(&self, fail: bool) {
+        self.faults.fail_writes.store(fail, Ordering::Relaxed);
+    }
+
+    /// Removes all requested faults.
+    pub fn clear_faults(&self) {
+        self.faults.fail_alloc.store(usize::MAX, Ordering::Relaxed);
+        self.fail_reads(false);
+        self.fail_writes(false);
+    }
+
+    /// Corrupts the data of `buf` by applying `corrupt` to every element.
+    /// The data is changed directly in the memory of `D`, hence injected read and write failures do not apply.
+    /// # Example
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::{Buffer, Faulty, CPU};
+    ///
+    /// let device = Faulty::new(CPU::new());
+    /// let mut buf = Buffer::from((&device, [1., 2., 3.]));
+    ///
+    /// device.corrupt(&mut buf, |x| *x = f32::NAN).unwrap();
+    /// assert!(buf.iter().all(|x| x.is_nan()));
+    /// ```
+    pub fn corrupt<T, S>(
+        &self,
+        buf: &mut Buffer<T, Faulty<D>, S>,
+        corrupt: impl FnMut(&mut T),
+    ) -> crate::Result<()>
+    where
+        T: Clone + Default,
+        S: Shape,
+        D: Read<T, S> + WriteBuf<T, S>,
+    {
+        let mut inner = self.inner_buf(buf);
+        let mut data = self.inner.read_to_vec(&inner);
+        data.iter_mut().for_each(corrupt);
+        self.inner.try_write(&mut inner, &data)
+    }
+}
+
+impl<D> Wrapper for Faulty<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    type Inner = D;
+
+    #[inline]
+    fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    #[inline]
+    fn addons_mut(&mut self) -> &mut PerThread<Addons<Faulty<D>>> {
+        &mut self.addons
+    }
+}
+
+impl<D> Default for Faulty<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn default() -> Self {
+        Faulty::new(D::default())
+    }
+}
+
+impl<D> Debug for Faulty<D>
+where
+    D: PtrConv + Default + Debug,
+    D::Ptr<u8, ()>: Default,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Faulty")
+            .field("inner", &self.inner)
+            .field("faults", &self.faults)
+            .finish()
+    }
+}
+
+impl<D> Device for Faulty<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    type Ptr<U, S: Shape> = D::Ptr<U, S>;
+    type Cache = Cache<Faulty<D>>;
+
+    fn new() -> crate::Result<Self> {
+        Ok(Faulty::new(D::new()?))
+    }
+
+    #[inline]
+    fn synchronize(&self) -> crate::Result<()> {
+        self.inner.synchronize()
+    }
+}
+
+impl<D> AddonsReturn for Faulty<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn addons(&self) -> &Addons<Self> {
+        self.addons.local()
+    }
+}
+
+impl<D> MemoryReturn for Faulty<D>
+where
+    D: PtrConv + Default + MemoryReturn,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn memory(&self) -> &MemoryTracker {
+        self.inner.memory()
+    }
+}
+
+impl<'a, T, S: Shape, D> Alloc<'a, T, S> for Faulty<D>
+where
+    D: PtrConv + Default + Alloc<'a, T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    #[track_caller]
+    fn try_alloc(&'a self, len: usize, flag: AllocFlag) -> crate::Result<D::Ptr<T, S>> {
+        self.faults.check(Fault::Alloc)?;
+        self.inner.try_alloc(len, flag)
+    }
+
+    #[track_caller]
+    fn try_with_slice(&'a self, data: &[T]) -> crate::Result<D::Ptr<T, S>>
+    where
+        T: Clone,
+    {
+        self.faults.check(Fault::Alloc)?;
+        self.inner.try_with_slice(data)
+    }
+
+    #[track_caller]
+    fn try_alloc_with_vec(&'a self, vec: Vec<T>) -> crate::Result<D::Ptr<T, S>>
+    where
+        T: Clone,
+    {
+        self.faults.check(Fault::Alloc)?;
+        self.inner.try_alloc_with_vec(vec)
+    }
+}
+
+impl<D> PtrConv for Faulty<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    unsafe fn convert<T, IS: Shape, Conv, OS: Shape>(
+        ptr: &D::Ptr<T, IS>,
+        flag: AllocFlag,
+    ) -> D::Ptr<Conv, OS> {
+        D::convert::<T, IS, Conv, OS>(ptr, flag)
+    }
+
+    #[inline]
+    unsafe fn convert_with_len<T, IS: Shape, Conv, OS: Shape>(
+        ptr: &D::Ptr<T, IS>,
+        len: usize,
+        flag: AllocFlag,
+    ) -> D::Ptr<Conv, OS> {
+        D::convert_with_len::<T, IS, Conv, OS>(ptr, len, flag)
+    }
+}
+
+impl<D> MainMemory for Faulty<D>
+where
+    D: PtrConv + Default + MainMemory,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn as_ptr<T, S: Shape>(ptr: &D::Ptr<T, S>) -> *const T {
+        D::as_ptr(ptr)
+    }
+
+    #[inline]
+    fn as_ptr_mut<T, S: Shape>(ptr: &mut D::Ptr<T, S>) -> *mut T {
+        D::as_ptr_mut(ptr)
+    }
+}
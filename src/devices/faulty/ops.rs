@@ -0,0 +1,150 @@
+use core::ops::{Range, RangeBounds};
+
+use crate::{
+    Alloc, Buffer, ClearBuf, CloneBuf, CopySlice, InnerBuf, PtrConv, Read, Shape, Transfer,
+    WriteBuf,
+};
+
+use super::{Fault, Faulty};
+
+impl<T, S, D> Read<T, S> for Faulty<D>
+where
+    T: Clone + Default,
+    S: Shape,
+    D: PtrConv + Default + Read<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    type Read<'a>
+        = Vec<T>
+    where
+        T: 'a,
+        S: 'a;
+
+    fn try_read(&self, buf: &Buffer<T, Faulty<D>, S>) -> crate::Result<Vec<T>> {
+        self.faults.check(Fault::Read)?;
+        Ok(self.inner.read_to_vec(&self.inner_buf(buf)))
+    }
+
+    #[track_caller]
+    fn read_to_vec(&self, buf: &Buffer<T, Faulty<D>, S>) -> Vec<T>
+    where
+        T: Default + Clone,
+    {
+        self.try_read(buf).unwrap()
+    }
+}
+
+impl<T, S, D> WriteBuf<T, S> for Faulty<D>
+where
+    S: Shape,
+    D: PtrConv + Default + WriteBuf<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    fn try_write(&self, buf: &mut Buffer<T, Faulty<D>, S>, data: &[T]) -> crate::Result<()> {
+        self.faults.check(Fault::Write)?;
+        self.inner.try_write(&mut self.inner_buf(buf), data)
+    }
+
+    #[track_caller]
+    fn write_buf(&self, dst: &mut Buffer<T, Faulty<D>, S>, src: &Buffer<T, Faulty<D>, S>) {
+        self.faults.check(Fault::Write).unwrap();
+        self.inner
+            .write_buf(&mut self.inner_buf(dst), &self.inner_buf(src))
+    }
+}
+
+impl<T, S, D, Dst> Transfer<T, Dst, S> for Faulty<D>
+where
+    T: Clone + Default,
+    S: Shape,
+    D: PtrConv + Default + Read<T, S>,
+    D::Ptr<u8, ()>: Default,
+    Dst: for<'b> Alloc<'b, T, S>,
+{
+    #[inline]
+    fn try_transfer<'b>(
+        &self,
+        buf: &Buffer<T, Faulty<D>, S>,
+        dst: &'b Dst,
+    ) -> crate::Result<Buffer<'b, T, Dst, S>> {
+        Buffer::try_from_vec(dst, self.try_read(buf)?)
+    }
+}
+
+impl<T, S, D> ClearBuf<T, S> for Faulty<D>
+where
+    S: Shape,
+    D: PtrConv + Default + ClearBuf<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    #[track_caller]
+    fn clear(&self, buf: &mut Buffer<T, Faulty<D>, S>) {
+        self.faults.check(Fault::Write).unwrap();
+        self.inner.clear(&mut self.inner_buf(buf))
+    }
+}
+
+impl<T, D> CopySlice<T> for Faulty<D>
+where
+    D: PtrConv + Default + CopySlice<T>,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn try_copy_slice_to<SR: RangeBounds<usize>, DR: RangeBounds<usize>>(
+        &self,
+        source: &Buffer<T, Faulty<D>>,
+        source_range: SR,
+        dest: &mut Buffer<T, Faulty<D>>,
+        dest_range: DR,
+    ) -> crate::Result<()> {
+        self.faults.check(Fault::Write)?;
+        self.inner.try_copy_slice_to(
+            &self.inner_buf(source),
+            source_range,
+            &mut self.inner_buf(dest),
+            dest_range,
+        )
+    }
+
+    #[track_caller]
+    fn copy_slice_all<I: IntoIterator<Item = (Range<usize>, Range<usize>)>>(
+        &self,
+        source: &Buffer<T, Faulty<D>>,
+        dest: &mut Buffer<T, Faulty<D>>,
+        ranges: I,
+    ) {
+        self.faults.check(Fault::Write).unwrap();
+        self.inner
+            .copy_slice_all(&self.inner_buf(source), &mut self.inner_buf(dest), ranges)
+    }
+}
+
+impl<'a, T, S, D> CloneBuf<'a, T, S> for Faulty<D>
+where
+    S: Shape,
+    D: PtrConv + Default + Alloc<'a, T, S> + WriteBuf<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    #[track_caller]
+    fn clone_buf(&'a self, buf: &Buffer<'a, T, Faulty<D>, S>) -> Buffer<'a, T, Faulty<D>, S> {
+        let mut cloned = Buffer::new(self, buf.len());
+        self.write_buf(&mut cloned, buf);
+        cloned
+    }
+}
+
+#[cfg(feature = "cpu")]
+impl<D> crate::exec_on_cpu::CPUFallback for Faulty<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+}
+
+#[cfg(feature = "cpu")]
+impl<D> crate::exec_on_cpu::FallbackOps for Faulty<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+}
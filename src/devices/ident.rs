@@ -1,12 +1,16 @@
-use core::cell::Cell;
+use core::{cell::Cell, hash::Hasher, marker::PhantomData};
 use std::thread_local;
 
+use crate::IdentHasher;
+
 thread_local! {
-    pub(crate) static COUNT: Cell<usize> = Cell::new(0);
+    pub(crate) static COUNT: Cell<usize> = const { Cell::new(0) };
+    static NAMESPACE: Cell<usize> = const { Cell::new(0) };
 }
 
 /// Sets current cache identifier / index.
 /// This function is usually called after an iteration in a loop -> [Count](crate::Count) or [range](crate::range)
+/// Inside of a [`CountScope`], the count of the scope is set.
 /// # Safety
 /// Manually setting the count may yield multiple `Buffer` pointing two the same data.
 #[inline]
@@ -15,6 +19,7 @@ pub unsafe fn set_count(count: usize) {
 }
 
 /// Returns current cache identifier / index
+/// Inside of a [`CountScope`], the count of the scope is returned.
 #[inline]
 pub fn get_count() -> usize {
     COUNT.with(|c| c.get())
@@ -29,6 +34,173 @@ pub fn bump_count() {
     })
 }
 
+/// Returns the index the next [`Ident`] receives.
+/// Outside of any [`CountScope`], this is the same as [`get_count`].
+#[inline]
+pub fn ident_idx() -> usize {
+    let namespace = NAMESPACE.with(|namespace| namespace.get());
+    if namespace == 0 {
+        return get_count();
+    }
+    mix(namespace, get_count())
+}
+
+#[inline]
+fn mix(lhs: usize, rhs: usize) -> usize {
+    let mut hasher = IdentHasher::default();
+    hasher.write_usize(lhs);
+    hasher.write_usize(rhs);
+    // 0 is reserved for the root namespace
+    (hasher.finish() as usize).max(1)
+}
+
+/// A RAII guard that opens a nested cache count namespace.
+/// The count starts at 0 inside of the scope and the [`Ident`]s of the scope do not collide with the [`Ident`]s of other namespaces.
+/// Dropping the guard restores the namespace and count of the enclosing scope.
+/// Hence, a function that retrieves a variable amount of `Buffer`s does not shift the [`Ident`]s of its caller.
+///
+/// Scopes can be nested, but must be dropped in reverse order of their creation.
+///
+/// # Example
+#[cfg_attr(all(feature = "cpu", not(feature = "realloc")), doc = "```")]
+#[cfg_attr(not(all(feature = "cpu", not(feature = "realloc"))), doc = "```ignore")]
+/// use custos::{get_count, range, CountScope, Device, CPU};
+///
+/// fn helper(device: &CPU, times: usize) {
+///     let _scope = CountScope::new();
+///     for _ in 0..times {
+///         device.retrieve::<f32, ()>(10, ());
+///     }
+/// }
+///
+/// let device = CPU::new();
+///
+/// let mut ptrs = vec![];
+/// for epoch in range(3) {
+///     // the amount of retrieved buffers differs in every epoch
+///     helper(&device, epoch);
+///     assert_eq!(get_count(), 1);
+///
+///     ptrs.push(device.retrieve::<f32, ()>(10, ()).ptr.ptr);
+/// }
+/// assert!(ptrs.iter().all(|ptr| *ptr == ptrs[0]));
+/// ```
+#[derive(Debug)]
+#[must_use = "the scope is closed immediately if the guard is not bound to a variable"]
+pub struct CountScope {
+    namespace: usize,
+    // the count that `reset` returns to
+    start: usize,
+    prev_namespace: usize,
+    prev_count: usize,
+    // the thread local count must be restored on the same thread
+    _not_send: PhantomData<*const ()>,
+}
+
+impl CountScope {
+    /// Opens a new scope. The namespace is derived from the enclosing namespace and count.
+    /// The scope takes up one count of the enclosing scope, i.e. consecutive scopes receive different namespaces.
+    #[inline]
+    pub fn new() -> CountScope {
+        let mut scope = CountScope::open(get_count());
+        scope.prev_count += 1;
+        scope
+    }
+
+    /// Opens a new scope with a namespace derived from the enclosing namespace and the given `key`.
+    /// The count of the enclosing scope is not changed.
+    /// Therefore, the [`Ident`]s of this scope only depend on the `key` and the enclosing namespace.
+    #[inline]
+    pub fn with_key(key: usize) -> CountScope {
+        let namespace = NAMESPACE.with(|namespace| namespace.get());
+        CountScope::open(mix(mix(namespace, usize::MAX), key))
+    }
+
+    /// Opens a scope that keeps the namespace and count of the enclosing scope.
+    /// The [`Ident`]s of this scope are the same as without the scope, but the count is restored when the guard is dropped.
+    /// [`reset`](CountScope::reset) returns to the count at the creation of the scope.
+    /// [`range`](crate::range) runs its iterations in such a scope.
+    /// # Example
+    /// ```
+    /// use custos::{bump_count, get_count, ident_idx, CountScope};
+    ///
+    /// bump_count();
+    /// let idx = ident_idx();
+    ///
+    /// {
+    ///     let _scope = CountScope::transparent();
+    ///     assert_eq!(ident_idx(), idx);
+    ///     bump_count();
+    /// }
+    /// assert_eq!(get_count(), 1);
+    /// ```
+    #[inline]
+    pub fn transparent() -> CountScope {
+        let namespace = NAMESPACE.with(|namespace| namespace.get());
+        let count = get_count();
+
+        CountScope {
+            namespace,
+            start: count,
+            prev_namespace: namespace,
+            prev_count: count,
+            _not_send: PhantomData,
+        }
+    }
+
+    fn open(key: usize) -> CountScope {
+        let prev_namespace = NAMESPACE.with(|namespace| namespace.get());
+        let prev_count = get_count();
+        let namespace = mix(prev_namespace, key);
+
+        NAMESPACE.with(|current| current.set(namespace));
+        unsafe { set_count(0) };
+
+        CountScope {
+            namespace,
+            start: 0,
+            prev_namespace,
+            prev_count,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Returns the namespace of this scope.
+    #[inline]
+    pub fn namespace(&self) -> usize {
+        self.namespace
+    }
+
+    /// Resets the count of this scope to its start, i.e. 0 or the count at the creation of a [`transparent`](CountScope::transparent) scope.
+    /// Usually called at the start of every iteration of a loop.
+    /// # Safety
+    /// The [`Ident`]s of the scope are handed out again, see [`set_count`].
+    #[inline]
+    pub unsafe fn reset(&self) {
+        debug_assert_eq!(
+            NAMESPACE.with(|namespace| namespace.get()),
+            self.namespace,
+            "A nested CountScope is still open."
+        );
+        set_count(self.start);
+    }
+}
+
+impl Default for CountScope {
+    #[inline]
+    fn default() -> Self {
+        CountScope::new()
+    }
+}
+
+impl Drop for CountScope {
+    #[inline]
+    fn drop(&mut self) {
+        NAMESPACE.with(|namespace| namespace.set(self.prev_namespace));
+        unsafe { set_count(self.prev_count) };
+    }
+}
+
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 /// An `Ident` is used to identify a cached pointer.
 pub struct Ident {
@@ -43,7 +215,7 @@ impl Ident {
     #[inline]
     pub fn new(len: usize) -> Ident {
         Ident {
-            idx: get_count(),
+            idx: ident_idx(),
             len,
         }
     }
@@ -52,7 +224,7 @@ impl Ident {
     #[inline]
     pub fn new_bumped(len: usize) -> Ident {
         let id = Ident {
-            idx: get_count(),
+            idx: ident_idx(),
             len,
         };
         bump_count();
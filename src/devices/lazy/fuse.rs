@@ -0,0 +1,178 @@
+use core::any::Any;
+use std::rc::Rc;
+
+use crate::{
+    flag::AllocFlag, ApplyFunctionTo, Buffer, Eval, MayToCLSource, PtrConv, PtrType, Resolve,
+    Shape, ToCLSource, ToVal,
+};
+
+use super::{Lazy, Recorded};
+
+/// Executes a [`MapOp`] with a single stage on the wrapped device.
+type Kernel<T, S, D> =
+    Box<dyn for<'a> Fn(&'a D, &Buffer<'a, T, D, S>, &mut Buffer<'a, T, D, S>) -> crate::Result<()>>;
+
+/// A recorded `out[i] = f(buf[i])`. Consecutive maps are executed by a single kernel on [`realize`](Lazy::realize).
+pub(super) trait Map<D> {
+    fn as_any(&self) -> &dyn Any;
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+
+    /// Returns `true` if `next`, which reads the output of `self`, can be fused into `self`.
+    fn fuses_with(&self, next: &dyn Map<D>) -> bool;
+
+    /// Returns `true` if the output can be read by something else than the `pending` accesses of the recorded operations.
+    fn is_observable(&self, pending: usize) -> bool;
+
+    /// Appends `next` to `self`, the output of `self` is not written anymore.
+    /// `fuses_with` must have returned `true` for `next`.
+    fn fuse(&mut self, next: Box<dyn Map<D>>);
+
+    fn exec(self: Box<Self>, device: &D) -> crate::Result<()>;
+}
+
+/// A [`Map`] that applies the closures of its stages one after another.
+pub(super) struct MapOp<T, S: Shape, D: PtrConv> {
+    buf: Recorded<T, S, D>,
+    out: Recorded<T, S, D>,
+    // the output was retrieved from the cache, i.e. the cache keeps it alive
+    cached: bool,
+    // executes the only stage without indirection
+    kernel: Kernel<T, S, D>,
+    stages: Vec<Stage<T>>,
+}
+
+impl<T, S, D> MapOp<T, S, D>
+where
+    T: Copy + 'static,
+    S: Shape,
+    D: PtrConv + Default + ApplyFunctionTo<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Records `out[i] = f(buf[i])`.
+    /// `cached` must only be set if `out` was just retrieved from the cache.
+    pub(super) fn new<C, F>(
+        buf: &Buffer<T, Lazy<D>, S>,
+        out: &Buffer<T, Lazy<D>, S>,
+        cached: bool,
+        f: C,
+    ) -> Self
+    where
+        C: Fn(Resolve<T>) -> F + 'static,
+        F: Eval<T> + MayToCLSource + 'static,
+    {
+        let f = Rc::new(f);
+        let (kernel, eval, source) = (f.clone(), f.clone(), f);
+        MapOp {
+            buf: Recorded::new(buf),
+            out: Recorded::new(out),
+            cached: cached && out.ptr.flag() == AllocFlag::Wrapper,
+            kernel: Box::new(move |device, buf, out| device.try_apply_fn_to(buf, out, &*kernel)),
+            stages: vec![Stage {
+                eval: Box::new(move |x| eval(x.to_val()).eval()),
+                source: Box::new(move |x| source(x).to_cl_source()),
+            }],
+        }
+    }
+}
+
+impl<T, S, D> Map<D> for MapOp<T, S, D>
+where
+    T: Copy + 'static,
+    S: Shape,
+    D: PtrConv + Default + ApplyFunctionTo<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    #[inline]
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+
+    fn fuses_with(&self, next: &dyn Map<D>) -> bool {
+        next.as_any()
+            .downcast_ref::<Self>()
+            .map_or(false, |next| next.buf.ptr.size() == self.out.ptr.size())
+    }
+
+    fn is_observable(&self, pending: usize) -> bool {
+        // the cache and the recorded operations hold the only copies of the pointer
+        !self.cached
+            || self
+                .out
+                .ptr
+                .allocation()
+                .map_or(true, |allocation| allocation.handles() != 1 + pending)
+    }
+
+    fn fuse(&mut self, next: Box<dyn Map<D>>) {
+        let next = next
+            .into_any()
+            .downcast::<Self>()
+            .expect("`fuses_with` checks the type");
+        self.out = next.out;
+        self.cached = next.cached;
+        self.stages.extend(next.stages);
+    }
+
+    fn exec(self: Box<Self>, device: &D) -> crate::Result<()> {
+        let MapOp {
+            buf,
+            out,
+            kernel,
+            stages,
+            ..
+        } = *self;
+        let (buf, mut out) = (buf.into_buf(device), out.into_buf(device));
+
+        if let [_] = stages[..] {
+            return kernel(device, &buf, &mut out);
+        }
+        device.try_apply_fn_to(&buf, &mut out, |input| Fused {
+            stages: &stages,
+            input,
+        })
+    }
+}
+
+/// The closure of a [`MapOp`], without its type.
+struct Stage<T> {
+    eval: Box<dyn Fn(T) -> T>,
+    source: Box<dyn Fn(Resolve<T>) -> String>,
+}
+
+/// The marker of the input of a stage, which is replaced by the source of the previous stages.
+const STAGE_INPUT: &str = "lazy_stage_input";
+
+/// Applies all stages to the input of a fused kernel.
+struct Fused<'a, T> {
+    stages: &'a [Stage<T>],
+    input: Resolve<T>,
+}
+
+impl<T: Copy> Eval<T> for Fused<'_, T> {
+    #[inline]
+    fn eval(self) -> T {
+        self.stages
+            .iter()
+            .fold(self.input.val, |x, stage| (stage.eval)(x))
+    }
+}
+
+impl<T: Copy> ToCLSource for Fused<'_, T> {
+    fn to_cl_source(&self) -> String {
+        self.stages
+            .iter()
+            .fold(self.input.to_cl_source(), |source, stage| {
+                let input = Resolve {
+                    val: self.input.val,
+                    marker: STAGE_INPUT,
+                };
+                (stage.source)(input).replace(STAGE_INPUT, &format!("({source})"))
+            })
+    }
+}
@@ -0,0 +1,452 @@
+//! The lazy module provides [`Lazy`], a wrapper device that records operations and executes them on [`realize`](Lazy::realize).
+
+mod fuse;
+mod ops;
+mod schedule;
+
+use core::{cell::RefCell, fmt::Debug};
+
+use crate::{
+    devices::cache::Cache, flag::AllocFlag, shape::Shape, Addons, AddonsReturn, Alloc, Allocation,
+    Buffer, CacheAble, Device, Ident, MemoryReturn, MemoryTracker, PerThread, PtrConv, PtrType,
+    Wrapper,
+};
+
+use self::fuse::Map;
+
+/// Executes a recorded operation on the wrapped device.
+type Exec<D> = Box<dyn FnOnce(&D) -> crate::Result<()>>;
+
+/// A recorded operation of a [`Lazy`] device.
+struct LazyOp<D> {
+    name: &'static str,
+    /// `None` if the operation accesses memory that is not tracked. It is then executed after all operations that were recorded before it.
+    access: Option<Access>,
+    kind: Kind<D>,
+}
+
+/// How a recorded operation is executed.
+enum Kind<D> {
+    Exec(Exec<D>),
+    /// `out[i] = f(buf[i])`, which can be executed by a single kernel together with the following maps.
+    Map(Box<dyn Map<D>>),
+}
+
+/// The memory that an operation reads and writes, identified by the keys of the [`Allocation`]s.
+struct Access {
+    reads: Vec<usize>,
+    writes: Vec<usize>,
+}
+
+impl Access {
+    /// Returns `None` if the allocation of a `Buffer` is not tracked.
+    fn new<const R: usize, const W: usize>(
+        reads: [Option<usize>; R],
+        writes: [Option<usize>; W],
+    ) -> Option<Access> {
+        Some(Access {
+            reads: reads.into_iter().collect::<Option<_>>()?,
+            writes: writes.into_iter().collect::<Option<_>>()?,
+        })
+    }
+}
+
+/// A device that wraps the device `D` and defers operations until their results are needed. Enabled by the `lazy` feature.
+///
+/// `WriteBuf`, `ClearBuf`, `CopySlice` and `CloneBuf` only record the operation.
+/// Operations that receive a closure are recorded by the inherent methods, e.g. [`Lazy::apply_fn`], which require a `'static` closure.
+/// Called through their traits (`ApplyFunction`, `BinaryElementWise`, `Reduce`, their `*To` variants and `UnaryGrad`), e.g. in generic code,
+/// these operations are executed immediately, after the pending operations.
+/// The pending operations are executed by `D` once [`realize`](Lazy::realize) is called
+/// or a `Buffer` is read with [`Read`](crate::Read) or [`Transfer`](crate::Transfer).
+/// Then, the whole pending graph is scheduled at once (see [`schedule`](Lazy::schedule)):
+/// every operation is executed after the operations that access the same memory before it, chains of `apply_fn` are executed by a single kernel.
+/// Errors of an operation (e.g. a failed kernel launch) are returned by `realize` or the read, the remaining operations are discarded then.
+/// Arguments are still checked when an operation is recorded, e.g. the ranges of [`CopySlice::try_copy_slice_to`](crate::CopySlice::try_copy_slice_to).
+///
+/// The output `Buffer` of an operation is retrieved from the cache of `Lazy` when the operation is recorded, hence it can be returned and used by further operations.
+/// Therefore, all memory of the graph is allocated up front. The wrapped device writes the results to these `Buffer`s directly, it does not allocate on `realize`.
+/// `Lazy` does not implement [`MainMemory`](crate::MainMemory), as the host memory of a `Buffer` may not contain its result yet.
+///
+/// Dropping an owned `Buffer` of `Lazy` realizes the pending operations first, because they may use its memory.
+/// An error of this realization is returned by the next call of `realize`.
+/// Pending operations of a dropped `Lazy` device are discarded.
+/// As the recorded operations are not `Send`, neither is `Lazy`.
+/// # Example
+#[cfg_attr(all(feature = "cpu", feature = "macro"), doc = "```")]
+#[cfg_attr(not(all(feature = "cpu", feature = "macro")), doc = "```ignore")]
+/// use custos::{Buffer, Combiner, Lazy, CPU};
+///
+/// let device = Lazy::new(CPU::new());
+/// let buf = Buffer::from((&device, [1., 2., 3.]));
+///
+/// let out = {
+///     let doubled = device.apply_fn(&buf, |x| x.mul(2.));
+///     device.apply_fn(&doubled, |x| x.add(1.))
+/// };
+/// assert_eq!(device.pending(), ["apply_fn", "apply_fn"]);
+///
+/// // `doubled` can't be read anymore, hence both operations are executed by one kernel
+/// assert_eq!(device.schedule(), [["apply_fn", "apply_fn"]]);
+///
+/// // reading `out` executes the operations
+/// assert_eq!(out.read(), [3., 5., 7.]);
+/// assert!(device.pending().is_empty());
+/// ```
+pub struct Lazy<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Provides additional functionality for the `Lazy` device. e.g. a cache, a gradient [`Tape`](crate::Tape) or an optimizeable [`Graph`](crate::Graph).
+    pub addons: PerThread<Addons<Lazy<D>>>,
+    inner: D,
+    ops: RefCell<Vec<LazyOp<D>>>,
+    error: RefCell<Option<crate::Error>>,
+}
+
+impl<D> Lazy<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Wraps `inner`.
+    #[must_use]
+    pub fn new(inner: D) -> Lazy<D> {
+        Lazy {
+            addons: PerThread::default(),
+            inner,
+            ops: Default::default(),
+            error: Default::default(),
+        }
+    }
+
+    /// Returns the names of the pending operations in the order they were recorded, e.g. `"apply_fn"`.
+    pub fn pending(&self) -> Vec<&'static str> {
+        self.ops.borrow().iter().map(|op| op.name).collect()
+    }
+
+    /// Executes all pending operations in the order of the [`schedule`](Lazy::schedule).
+    /// Returns immediately if there are none.
+    /// # Errors
+    /// The first error of an operation. The remaining operations are discarded.
+    /// An error of a realization that was triggered by dropping a `Buffer` is returned by the next call, after the pending operations were executed.
+    /// # Example
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::{Buffer, ClearBuf, Lazy, CPU};
+    ///
+    /// let device = Lazy::new(CPU::new());
+    /// let mut buf = Buffer::from((&device, [1, 2, 3]));
+    ///
+    /// device.clear(&mut buf);
+    /// assert_eq!(device.pending(), ["clear"]);
+    ///
+    /// device.realize().unwrap();
+    /// assert!(device.pending().is_empty());
+    /// assert_eq!(buf.read(), [0, 0, 0]);
+    /// ```
+    pub fn realize(&self) -> crate::Result<()> {
+        let realized = self.execute_pending();
+        match self.error.borrow_mut().take() {
+            Some(err) => Err(err),
+            None => realized,
+        }
+    }
+
+    fn execute_pending(&self) -> crate::Result<()> {
+        let ops = core::mem::take(&mut *self.ops.borrow_mut());
+        if ops.is_empty() {
+            return Ok(());
+        }
+
+        let _span = crate::trace::span(self, "realize", 0, None);
+        let kernels = schedule::plan(&ops, self.fuses());
+
+        let mut ops = ops.into_iter().map(Some).collect::<Vec<_>>();
+        for kernel in kernels {
+            let mut ops = kernel.into_iter().filter_map(|idx| ops[idx].take());
+            match ops.next().map(|op| op.kind) {
+                Some(Kind::Exec(exec)) => exec(&self.inner)?,
+                Some(Kind::Map(mut map)) => {
+                    for op in ops {
+                        if let Kind::Map(next) = op.kind {
+                            map.fuse(next);
+                        }
+                    }
+                    map.exec(&self.inner)?
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the kernels that [`realize`](Lazy::realize) would execute now, in this order.
+    /// Every kernel is described by the names of the pending operations it executes.
+    ///
+    /// Operations are executed after the operations that were recorded before them and access the same memory,
+    /// at least one of them writing it. Operations on memory that is not tracked by a [`MemoryTracker`] keep their recorded position.
+    /// Apart from that, the consumer of an `apply_fn` output is preferred, so that both operations are executed by one kernel.
+    /// This requires that nothing else can read the intermediate output:
+    /// it must be retrieved from the cache, no `Buffer` of it may be alive and no other pending operation (or gradient function) may access it.
+    /// The intermediate output is not written then.
+    /// # Example
+    #[cfg_attr(all(feature = "cpu", feature = "macro"), doc = "```")]
+    #[cfg_attr(not(all(feature = "cpu", feature = "macro")), doc = "```ignore")]
+    /// use custos::{ApplyFunction, Buffer, ClearBuf, Combiner, Lazy, CPU};
+    ///
+    /// let device = Lazy::new(CPU::new());
+    /// let buf = Buffer::from((&device, [1, 2, 3]));
+    /// let mut other = Buffer::from((&device, [4, 5, 6]));
+    ///
+    /// let squared = device.apply_fn(&buf, |x| x.mul(x));
+    /// device.clear(&mut other);
+    /// let out = device.apply_fn(&squared, |x| x.add(1));
+    /// drop(squared);
+    ///
+    /// assert_eq!(device.schedule(), [vec!["apply_fn", "apply_fn"], vec!["clear"]]);
+    /// assert_eq!(out.read(), [2, 5, 10]);
+    /// ```
+    pub fn schedule(&self) -> Vec<Vec<&'static str>> {
+        let ops = self.ops.borrow();
+        schedule::plan(&ops, self.fuses())
+            .into_iter()
+            .map(|kernel| kernel.into_iter().map(|idx| ops[idx].name).collect())
+            .collect()
+    }
+
+    /// Pending gradient functions may read any `Buffer`, hence outputs are not fused away while there are some
+    /// or while they are executed (the tape is borrowed then).
+    fn fuses(&self) -> bool {
+        #[cfg(feature = "autograd")]
+        {
+            self.addons()
+                .tape
+                .try_borrow()
+                .map_or(false, |tape| tape.is_empty())
+        }
+        #[cfg(not(feature = "autograd"))]
+        true
+    }
+
+    /// Discards all pending operations without executing them.
+    #[inline]
+    pub fn discard(&self) {
+        self.ops.borrow_mut().clear()
+    }
+
+    /// Records the operation `name`, which accesses `access` and is executed by the wrapped device on [`realize`](Lazy::realize).
+    fn record(
+        &self,
+        name: &'static str,
+        access: Option<Access>,
+        exec: impl FnOnce(&D) -> crate::Result<()> + 'static,
+    ) {
+        self.ops.borrow_mut().push(LazyOp {
+            name,
+            access,
+            kind: Kind::Exec(Box::new(exec)),
+        })
+    }
+
+    /// Records the map `name`, which reads from `input` and writes to `output`.
+    fn record_map(
+        &self,
+        name: &'static str,
+        input: Option<usize>,
+        output: Option<usize>,
+        map: Box<dyn Map<D>>,
+    ) {
+        self.ops.borrow_mut().push(LazyOp {
+            name,
+            access: Access::new([input], [output]),
+            kind: Kind::Map(map),
+        })
+    }
+}
+
+impl<D> Wrapper for Lazy<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    type Inner = D;
+
+    #[inline]
+    fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    #[inline]
+    fn addons_mut(&mut self) -> &mut PerThread<Addons<Lazy<D>>> {
+        &mut self.addons
+    }
+}
+
+/// The memory of a `Buffer` of [`Lazy`] that is captured by a recorded operation.
+struct Recorded<T, S: Shape, D: Device> {
+    ptr: D::Ptr<T, S>,
+    ident: Option<Ident>,
+}
+
+impl<T, S, D> Recorded<T, S, D>
+where
+    S: Shape,
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Captures the memory of `buf` without owning it.
+    fn new(buf: &Buffer<T, Lazy<D>, S>) -> Self {
+        Recorded {
+            ptr: unsafe { D::convert(&buf.ptr, AllocFlag::Wrapper) },
+            ident: buf.ident,
+        }
+    }
+
+    /// Identifies the captured memory, see [`Access`]. `None` if the memory is not tracked.
+    #[inline]
+    fn key(&self) -> Option<usize> {
+        self.ptr.allocation().map(Allocation::key)
+    }
+
+    /// Returns the captured memory as a `Buffer` of the wrapped device.
+    #[inline]
+    fn into_buf(self, device: &D) -> Buffer<'_, T, D, S> {
+        Buffer {
+            ptr: self.ptr,
+            device: Some(device),
+            ident: self.ident,
+        }
+    }
+}
+
+impl<D> Default for Lazy<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn default() -> Self {
+        Lazy::new(D::default())
+    }
+}
+
+impl<D> Debug for Lazy<D>
+where
+    D: PtrConv + Default + Debug,
+    D::Ptr<u8, ()>: Default,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Lazy")
+            .field("inner", &self.inner)
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
+
+impl<D> Device for Lazy<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    type Ptr<U, S: Shape> = D::Ptr<U, S>;
+    type Cache = Cache<Lazy<D>>;
+
+    fn new() -> crate::Result<Self> {
+        Ok(Lazy::new(D::new()?))
+    }
+
+    /// Realizes the pending operations and waits until the wrapped device has finished them.
+    #[inline]
+    fn synchronize(&self) -> crate::Result<()> {
+        self.realize()?;
+        self.inner.synchronize()
+    }
+
+    fn remove(&self, ident: Ident) {
+        // a pending operation may use the memory of the dropped `Buffer`
+        if std::thread::panicking() {
+            self.discard();
+        } else if let Err(err) = self.realize() {
+            // `remove` can't fail, the error is returned by the next `realize`
+            self.error.borrow_mut().get_or_insert(err);
+        }
+        <Self::Cache as CacheAble<Self>>::remove(self, ident);
+    }
+}
+
+impl<D> AddonsReturn for Lazy<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn addons(&self) -> &Addons<Self> {
+        self.addons.local()
+    }
+}
+
+impl<D> MemoryReturn for Lazy<D>
+where
+    D: PtrConv + Default + MemoryReturn,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn memory(&self) -> &MemoryTracker {
+        self.inner.memory()
+    }
+}
+
+impl<'a, T, S: Shape, D> Alloc<'a, T, S> for Lazy<D>
+where
+    D: PtrConv + Default + Alloc<'a, T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    #[track_caller]
+    fn try_alloc(&'a self, len: usize, flag: AllocFlag) -> crate::Result<D::Ptr<T, S>> {
+        self.inner.try_alloc(len, flag)
+    }
+
+    #[inline]
+    #[track_caller]
+    fn try_with_slice(&'a self, data: &[T]) -> crate::Result<D::Ptr<T, S>>
+    where
+        T: Clone,
+    {
+        self.inner.try_with_slice(data)
+    }
+
+    #[inline]
+    #[track_caller]
+    fn try_alloc_with_vec(&'a self, vec: Vec<T>) -> crate::Result<D::Ptr<T, S>>
+    where
+        T: Clone,
+    {
+        self.inner.try_alloc_with_vec(vec)
+    }
+}
+
+impl<D> PtrConv for Lazy<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    unsafe fn convert<T, IS: Shape, Conv, OS: Shape>(
+        ptr: &D::Ptr<T, IS>,
+        flag: AllocFlag,
+    ) -> D::Ptr<Conv, OS> {
+        D::convert::<T, IS, Conv, OS>(ptr, flag)
+    }
+
+    #[inline]
+    unsafe fn convert_with_len<T, IS: Shape, Conv, OS: Shape>(
+        ptr: &D::Ptr<T, IS>,
+        len: usize,
+        flag: AllocFlag,
+    ) -> D::Ptr<Conv, OS> {
+        D::convert_with_len::<T, IS, Conv, OS>(ptr, len, flag)
+    }
+}
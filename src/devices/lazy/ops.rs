@@ -0,0 +1,658 @@
+use core::ops::{Range, RangeBounds};
+
+use crate::{
+    check_len, checked_range, Alloc, Allocation, ApplyFunction, ApplyFunctionTo, BinaryElementWise,
+    BinaryElementWiseTo, Buffer, ClearBuf, CloneBuf, CopySlice, Device, Eval, InnerBuf,
+    MayToCLSource, Named, PtrConv, PtrType, Read, Reduce, ReduceTo, Resolve, Shape, Transfer,
+    UnaryGrad, WriteBuf,
+};
+
+use super::{fuse::MapOp, Access, Lazy, Recorded};
+
+impl<T, S, D> Read<T, S> for Lazy<D>
+where
+    T: Clone + Default,
+    S: Shape,
+    D: PtrConv + Default + Read<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    type Read<'a>
+        = Vec<T>
+    where
+        T: 'a,
+        S: 'a;
+
+    /// Realizes the pending operations and reads the data of `buf`.
+    #[inline]
+    fn try_read(&self, buf: &Buffer<T, Lazy<D>, S>) -> crate::Result<Vec<T>> {
+        self.realize()?;
+        Ok(self.inner.read_to_vec(&self.inner_buf(buf)))
+    }
+
+    /// Realizes the pending operations and reads the data of `buf`.
+    #[inline]
+    fn read_to_vec(&self, buf: &Buffer<T, Lazy<D>, S>) -> Vec<T>
+    where
+        T: Default + Clone,
+    {
+        self.realize().unwrap();
+        self.inner.read_to_vec(&self.inner_buf(buf))
+    }
+}
+
+impl<T, S, D> WriteBuf<T, S> for Lazy<D>
+where
+    T: Clone + 'static,
+    S: Shape,
+    D: PtrConv + Default + WriteBuf<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Records a write of `data`, which is copied.
+    fn try_write(&self, buf: &mut Buffer<T, Lazy<D>, S>, data: &[T]) -> crate::Result<()> {
+        check_len(buf.len(), data.len())?;
+
+        let (buf, data) = (Recorded::new(buf), data.to_vec());
+        self.record("write", Access::new([], [buf.key()]), move |device| {
+            device.try_write(&mut buf.into_buf(device), &data)
+        });
+        Ok(())
+    }
+
+    fn write_buf(&self, dst: &mut Buffer<T, Lazy<D>, S>, src: &Buffer<T, Lazy<D>, S>) {
+        let (dst, src) = (Recorded::new(dst), Recorded::new(src));
+        let access = Access::new([src.key()], [dst.key()]);
+        self.record("write_buf", access, move |device| {
+            device.write_buf(&mut dst.into_buf(device), &src.into_buf(device));
+            Ok(())
+        });
+    }
+}
+
+impl<T, S, D, Dst> Transfer<T, Dst, S> for Lazy<D>
+where
+    T: Clone + Default,
+    S: Shape,
+    D: PtrConv + Default + Read<T, S>,
+    D::Ptr<u8, ()>: Default,
+    Dst: for<'b> Alloc<'b, T, S>,
+{
+    #[inline]
+    fn try_transfer<'b>(
+        &self,
+        buf: &Buffer<T, Lazy<D>, S>,
+        dst: &'b Dst,
+    ) -> crate::Result<Buffer<'b, T, Dst, S>> {
+        Buffer::try_from_vec(dst, self.try_read(buf)?)
+    }
+}
+
+impl<T, S, D> ClearBuf<T, S> for Lazy<D>
+where
+    T: 'static,
+    S: Shape,
+    D: PtrConv + Default + ClearBuf<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    fn clear(&self, buf: &mut Buffer<T, Lazy<D>, S>) {
+        let buf = Recorded::new(buf);
+        self.record("clear", Access::new([], [buf.key()]), move |device| {
+            device.clear(&mut buf.into_buf(device));
+            Ok(())
+        });
+    }
+}
+
+impl<T, D> CopySlice<T> for Lazy<D>
+where
+    T: 'static,
+    D: PtrConv + Default + CopySlice<T>,
+    D::Ptr<u8, ()>: Default,
+{
+    fn try_copy_slice_to<SR: RangeBounds<usize>, DR: RangeBounds<usize>>(
+        &self,
+        source: &Buffer<T, Lazy<D>>,
+        source_range: SR,
+        dest: &mut Buffer<T, Lazy<D>>,
+        dest_range: DR,
+    ) -> crate::Result<()> {
+        let source_range = checked_range(source_range, source.len())?;
+        let dest_range = checked_range(dest_range, dest.len())?;
+        check_len(source_range.len(), dest_range.len())?;
+
+        let (source, dest) = (Recorded::new(source), Recorded::new(dest));
+        let access = Access::new([source.key()], [dest.key()]);
+        self.record("copy_slice", access, move |device| {
+            device.try_copy_slice_to(
+                &source.into_buf(device),
+                source_range,
+                &mut dest.into_buf(device),
+                dest_range,
+            )
+        });
+        Ok(())
+    }
+
+    #[track_caller]
+    fn copy_slice_all<I: IntoIterator<Item = (Range<usize>, Range<usize>)>>(
+        &self,
+        source: &Buffer<T, Lazy<D>>,
+        dest: &mut Buffer<T, Lazy<D>>,
+        ranges: I,
+    ) {
+        let ranges = ranges
+            .into_iter()
+            .map(|(source_range, dest_range)| {
+                let source_range = checked_range(source_range, source.len())?;
+                let dest_range = checked_range(dest_range, dest.len())?;
+                check_len(source_range.len(), dest_range.len())?;
+                Ok((source_range, dest_range))
+            })
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+
+        let (source, dest) = (Recorded::new(source), Recorded::new(dest));
+        let access = Access::new([source.key()], [dest.key()]);
+        self.record("copy_slice", access, move |device| {
+            device.copy_slice_all(&source.into_buf(device), &mut dest.into_buf(device), ranges);
+            Ok(())
+        });
+    }
+}
+
+impl<'a, T, S, D> CloneBuf<'a, T, S> for Lazy<D>
+where
+    T: Clone + 'static,
+    S: Shape,
+    D: PtrConv + Default + Alloc<'a, T, S> + WriteBuf<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    #[track_caller]
+    fn clone_buf(&'a self, buf: &Buffer<'a, T, Lazy<D>, S>) -> Buffer<'a, T, Lazy<D>, S> {
+        let mut cloned = Buffer::new(self, buf.len());
+        self.write_buf(&mut cloned, buf);
+        cloned
+    }
+}
+
+impl<T, S, D> ApplyFunction<T, S> for Lazy<D>
+where
+    S: Shape,
+    D: PtrConv + Default + ApplyFunctionTo<T, S>,
+    D: for<'b> Alloc<'b, T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Executes the operation immediately, after the pending operations. [`Lazy::apply_fn`] records it instead.
+    #[track_caller]
+    fn try_apply_fn<F>(
+        &self,
+        buf: &Buffer<T, Lazy<D>, S>,
+        f: impl Fn(Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<'_, T, Lazy<D>, S>>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        let mut out = self.try_retrieve(buf.len(), Named("apply_fn", buf))?;
+        ApplyFunctionTo::try_apply_fn_to(self, buf, &mut out, f)?;
+        Ok(out)
+    }
+}
+
+impl<T, S, D> ApplyFunctionTo<T, S> for Lazy<D>
+where
+    S: Shape,
+    D: PtrConv + Default + ApplyFunctionTo<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Executes the operation immediately, after the pending operations. [`Lazy::apply_fn_to`] records it instead.
+    fn try_apply_fn_to<F>(
+        &self,
+        buf: &Buffer<T, Lazy<D>, S>,
+        out: &mut Buffer<T, Lazy<D>, S>,
+        f: impl Fn(Resolve<T>) -> F,
+    ) -> crate::Result<()>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        check_len(out.len(), buf.len())?;
+        self.realize()?;
+        self.inner
+            .try_apply_fn_to(&self.inner_buf(buf), &mut self.inner_buf(out), f)
+    }
+}
+
+impl<T, S, D> BinaryElementWise<T, S> for Lazy<D>
+where
+    S: Shape,
+    D: PtrConv + Default + BinaryElementWiseTo<T, S>,
+    D: for<'b> Alloc<'b, T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Executes the operation immediately, after the pending operations. [`Lazy::binary_ew`] records it instead.
+    #[track_caller]
+    fn try_binary_ew<F>(
+        &self,
+        lhs: &Buffer<T, Lazy<D>, S>,
+        rhs: &Buffer<T, Lazy<D>, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<'_, T, Lazy<D>, S>>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        check_len(lhs.len(), rhs.len())?;
+        let mut out = self.try_retrieve(lhs.len(), Named("binary_ew", (lhs, rhs)))?;
+        BinaryElementWiseTo::try_binary_ew_to(self, lhs, rhs, &mut out, f)?;
+        Ok(out)
+    }
+}
+
+impl<T, S, D> BinaryElementWiseTo<T, S> for Lazy<D>
+where
+    S: Shape,
+    D: PtrConv + Default + BinaryElementWiseTo<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Executes the operation immediately, after the pending operations. [`Lazy::binary_ew_to`] records it instead.
+    fn try_binary_ew_to<F>(
+        &self,
+        lhs: &Buffer<T, Lazy<D>, S>,
+        rhs: &Buffer<T, Lazy<D>, S>,
+        out: &mut Buffer<T, Lazy<D>, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<()>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        check_len(out.len(), lhs.len())?;
+        check_len(out.len(), rhs.len())?;
+        self.realize()?;
+        self.inner.try_binary_ew_to(
+            &self.inner_buf(lhs),
+            &self.inner_buf(rhs),
+            &mut self.inner_buf(out),
+            f,
+        )
+    }
+}
+
+impl<T, S, D> Reduce<T, S> for Lazy<D>
+where
+    S: Shape,
+    D: PtrConv + Default + ReduceTo<T, S>,
+    D: for<'b> Alloc<'b, T>,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Executes the operation immediately, after the pending operations. [`Lazy::reduce`] records it instead.
+    #[track_caller]
+    fn try_reduce<F>(
+        &self,
+        buf: &Buffer<T, Lazy<D>, S>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<'_, T, Lazy<D>>>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        let mut out = self.try_retrieve::<T, ()>(1, Named("reduce", buf))?;
+        ReduceTo::try_reduce_to(self, buf, &mut out, init, f)?;
+        Ok(out)
+    }
+}
+
+impl<T, S, D> ReduceTo<T, S> for Lazy<D>
+where
+    S: Shape,
+    D: PtrConv + Default + ReduceTo<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Executes the operation immediately, after the pending operations. [`Lazy::reduce_to`] records it instead.
+    fn try_reduce_to<F>(
+        &self,
+        buf: &Buffer<T, Lazy<D>, S>,
+        out: &mut Buffer<T, Lazy<D>>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<()>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        check_len(1, out.len())?;
+        self.realize()?;
+        self.inner
+            .try_reduce_to(&self.inner_buf(buf), &mut self.inner_buf(out), init, f)
+    }
+}
+
+impl<T, S, D> UnaryGrad<T, S> for Lazy<D>
+where
+    S: Shape,
+    D: PtrConv + Default + UnaryGrad<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Executes the operation immediately, after the pending operations. [`Lazy::add_unary_grad`] records it instead.
+    fn add_unary_grad<F>(
+        &self,
+        lhs: &Buffer<T, Lazy<D>, S>,
+        lhs_grad: &mut Buffer<T, Lazy<D>, S>,
+        out_grad: &Buffer<T, Lazy<D>, S>,
+        lhs_grad_fn: impl Fn(Resolve<T>) -> F,
+    ) where
+        F: Eval<T> + MayToCLSource,
+    {
+        self.realize().unwrap();
+        self.inner.add_unary_grad(
+            &self.inner_buf(lhs),
+            &mut self.inner_buf(lhs_grad),
+            &self.inner_buf(out_grad),
+            lhs_grad_fn,
+        )
+    }
+}
+
+/// The operations that receive a closure are recorded by these methods, which take precedence over the op traits.
+/// The closure is kept until the operation is executed, hence it must be `'static`, i.e. it may only capture owned values.
+impl<D> Lazy<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Records `out[i] = f(buf[i])`, see [`ApplyFunction::apply_fn`].
+    /// # Example
+    #[cfg_attr(all(feature = "cpu", feature = "macro"), doc = "```")]
+    #[cfg_attr(not(all(feature = "cpu", feature = "macro")), doc = "```ignore")]
+    /// use custos::{Buffer, Combiner, Lazy, CPU};
+    ///
+    /// let device = Lazy::new(CPU::new());
+    /// let buf = Buffer::from((&device, [1., 2., 3.]));
+    ///
+    /// let factor = 2.;
+    /// let out = device.apply_fn(&buf, move |x| x.mul(factor));
+    /// assert_eq!(device.pending(), ["apply_fn"]);
+    /// assert_eq!(out.read(), [2., 4., 6.]);
+    /// ```
+    #[inline]
+    #[track_caller]
+    pub fn apply_fn<T, S, F>(
+        &self,
+        buf: &Buffer<T, Lazy<D>, S>,
+        f: impl Fn(Resolve<T>) -> F + 'static,
+    ) -> Buffer<'_, T, Lazy<D>, S>
+    where
+        T: Copy + 'static,
+        S: Shape,
+        D: ApplyFunctionTo<T, S> + for<'b> Alloc<'b, T, S>,
+        F: Eval<T> + MayToCLSource + 'static,
+    {
+        self.try_apply_fn(buf, f).unwrap()
+    }
+
+    /// Records `out[i] = f(buf[i])`, the output is allocated now and written by the wrapped device on [`realize`](Lazy::realize).
+    /// The output is not written at all if the operation is fused with the next `apply_fn`, see [`Lazy::schedule`].
+    #[track_caller]
+    pub fn try_apply_fn<T, S, F>(
+        &self,
+        buf: &Buffer<T, Lazy<D>, S>,
+        f: impl Fn(Resolve<T>) -> F + 'static,
+    ) -> crate::Result<Buffer<'_, T, Lazy<D>, S>>
+    where
+        T: Copy + 'static,
+        S: Shape,
+        D: ApplyFunctionTo<T, S> + for<'b> Alloc<'b, T, S>,
+        F: Eval<T> + MayToCLSource + 'static,
+    {
+        let mut out = self.try_retrieve(buf.len(), Named("apply_fn", buf))?;
+        self.record_apply_fn_to(buf, &mut out, true, f);
+        Ok(out)
+    }
+
+    /// Records `out[i] = f(buf[i])`, see [`ApplyFunctionTo::apply_fn_to`].
+    #[inline]
+    #[track_caller]
+    pub fn apply_fn_to<T, S, F>(
+        &self,
+        buf: &Buffer<T, Lazy<D>, S>,
+        out: &mut Buffer<T, Lazy<D>, S>,
+        f: impl Fn(Resolve<T>) -> F + 'static,
+    ) where
+        T: Copy + 'static,
+        S: Shape,
+        D: ApplyFunctionTo<T, S>,
+        F: Eval<T> + MayToCLSource + 'static,
+    {
+        self.try_apply_fn_to(buf, out, f).unwrap()
+    }
+
+    /// Records `out[i] = f(buf[i])`. Returns an error if the lengths of `buf` and `out` differ.
+    pub fn try_apply_fn_to<T, S, F>(
+        &self,
+        buf: &Buffer<T, Lazy<D>, S>,
+        out: &mut Buffer<T, Lazy<D>, S>,
+        f: impl Fn(Resolve<T>) -> F + 'static,
+    ) -> crate::Result<()>
+    where
+        T: Copy + 'static,
+        S: Shape,
+        D: ApplyFunctionTo<T, S>,
+        F: Eval<T> + MayToCLSource + 'static,
+    {
+        check_len(out.len(), buf.len())?;
+        self.record_apply_fn_to(buf, out, false, f);
+        Ok(())
+    }
+
+    /// Records `apply_fn_to` as a map, which can be fused with other maps.
+    /// `cached` is set if `out` was retrieved from the cache for this operation.
+    fn record_apply_fn_to<T, S, F>(
+        &self,
+        buf: &Buffer<T, Lazy<D>, S>,
+        out: &mut Buffer<T, Lazy<D>, S>,
+        cached: bool,
+        f: impl Fn(Resolve<T>) -> F + 'static,
+    ) where
+        T: Copy + 'static,
+        S: Shape,
+        D: ApplyFunctionTo<T, S>,
+        F: Eval<T> + MayToCLSource + 'static,
+    {
+        let (input, output) = (
+            buf.ptr.allocation().map(Allocation::key),
+            out.ptr.allocation().map(Allocation::key),
+        );
+        let map = MapOp::new(buf, out, cached, f);
+        self.record_map("apply_fn", input, output, Box::new(map));
+    }
+
+    /// Records `out[i] = f(lhs[i], rhs[i])`, see [`BinaryElementWise::binary_ew`].
+    #[inline]
+    #[track_caller]
+    pub fn binary_ew<T, S, F>(
+        &self,
+        lhs: &Buffer<T, Lazy<D>, S>,
+        rhs: &Buffer<T, Lazy<D>, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F + 'static,
+    ) -> Buffer<'_, T, Lazy<D>, S>
+    where
+        T: 'static,
+        S: Shape,
+        D: BinaryElementWiseTo<T, S> + for<'b> Alloc<'b, T, S>,
+        F: Eval<T> + MayToCLSource + 'static,
+    {
+        self.try_binary_ew(lhs, rhs, f).unwrap()
+    }
+
+    /// Records `out[i] = f(lhs[i], rhs[i])`, the output is allocated now and written by the wrapped device on [`realize`](Lazy::realize).
+    #[track_caller]
+    pub fn try_binary_ew<T, S, F>(
+        &self,
+        lhs: &Buffer<T, Lazy<D>, S>,
+        rhs: &Buffer<T, Lazy<D>, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F + 'static,
+    ) -> crate::Result<Buffer<'_, T, Lazy<D>, S>>
+    where
+        T: 'static,
+        S: Shape,
+        D: BinaryElementWiseTo<T, S> + for<'b> Alloc<'b, T, S>,
+        F: Eval<T> + MayToCLSource + 'static,
+    {
+        check_len(lhs.len(), rhs.len())?;
+        let mut out = self.try_retrieve(lhs.len(), Named("binary_ew", (lhs, rhs)))?;
+        self.try_binary_ew_to(lhs, rhs, &mut out, f)?;
+        Ok(out)
+    }
+
+    /// Records `out[i] = f(lhs[i], rhs[i])`, see [`BinaryElementWiseTo::binary_ew_to`].
+    #[inline]
+    #[track_caller]
+    pub fn binary_ew_to<T, S, F>(
+        &self,
+        lhs: &Buffer<T, Lazy<D>, S>,
+        rhs: &Buffer<T, Lazy<D>, S>,
+        out: &mut Buffer<T, Lazy<D>, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F + 'static,
+    ) where
+        T: 'static,
+        S: Shape,
+        D: BinaryElementWiseTo<T, S>,
+        F: Eval<T> + MayToCLSource + 'static,
+    {
+        self.try_binary_ew_to(lhs, rhs, out, f).unwrap()
+    }
+
+    /// Records `out[i] = f(lhs[i], rhs[i])`. Returns an error if the lengths of the buffers differ.
+    pub fn try_binary_ew_to<T, S, F>(
+        &self,
+        lhs: &Buffer<T, Lazy<D>, S>,
+        rhs: &Buffer<T, Lazy<D>, S>,
+        out: &mut Buffer<T, Lazy<D>, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F + 'static,
+    ) -> crate::Result<()>
+    where
+        T: 'static,
+        S: Shape,
+        D: BinaryElementWiseTo<T, S>,
+        F: Eval<T> + MayToCLSource + 'static,
+    {
+        check_len(out.len(), lhs.len())?;
+        check_len(out.len(), rhs.len())?;
+
+        let (lhs, rhs, out) = (Recorded::new(lhs), Recorded::new(rhs), Recorded::new(out));
+        let access = Access::new([lhs.key(), rhs.key()], [out.key()]);
+        self.record("binary_ew", access, move |device| {
+            device.try_binary_ew_to(
+                &lhs.into_buf(device),
+                &rhs.into_buf(device),
+                &mut out.into_buf(device),
+                f,
+            )
+        });
+        Ok(())
+    }
+
+    /// Records folding `buf` with `f`, starting with `init`, see [`Reduce::reduce`].
+    #[inline]
+    #[track_caller]
+    pub fn reduce<T, S, F>(
+        &self,
+        buf: &Buffer<T, Lazy<D>, S>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F + 'static,
+    ) -> Buffer<'_, T, Lazy<D>>
+    where
+        T: 'static,
+        S: Shape,
+        D: ReduceTo<T, S> + for<'b> Alloc<'b, T>,
+        F: Eval<T> + MayToCLSource + 'static,
+    {
+        self.try_reduce(buf, init, f).unwrap()
+    }
+
+    /// Records folding `buf` with `f`, the output is allocated now and written by the wrapped device on [`realize`](Lazy::realize).
+    #[track_caller]
+    pub fn try_reduce<T, S, F>(
+        &self,
+        buf: &Buffer<T, Lazy<D>, S>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F + 'static,
+    ) -> crate::Result<Buffer<'_, T, Lazy<D>>>
+    where
+        T: 'static,
+        S: Shape,
+        D: ReduceTo<T, S> + for<'b> Alloc<'b, T>,
+        F: Eval<T> + MayToCLSource + 'static,
+    {
+        let mut out = self.try_retrieve::<T, ()>(1, Named("reduce", buf))?;
+        self.try_reduce_to(buf, &mut out, init, f)?;
+        Ok(out)
+    }
+
+    /// Records folding `buf` with `f` into `out[0]`, see [`ReduceTo::reduce_to`].
+    #[inline]
+    #[track_caller]
+    pub fn reduce_to<T, S, F>(
+        &self,
+        buf: &Buffer<T, Lazy<D>, S>,
+        out: &mut Buffer<T, Lazy<D>>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F + 'static,
+    ) where
+        T: 'static,
+        S: Shape,
+        D: ReduceTo<T, S>,
+        F: Eval<T> + MayToCLSource + 'static,
+    {
+        self.try_reduce_to(buf, out, init, f).unwrap()
+    }
+
+    /// Records folding `buf` with `f` into `out[0]`. Returns an error if `out` does not contain exactly one value.
+    pub fn try_reduce_to<T, S, F>(
+        &self,
+        buf: &Buffer<T, Lazy<D>, S>,
+        out: &mut Buffer<T, Lazy<D>>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F + 'static,
+    ) -> crate::Result<()>
+    where
+        T: 'static,
+        S: Shape,
+        D: ReduceTo<T, S>,
+        F: Eval<T> + MayToCLSource + 'static,
+    {
+        check_len(1, out.len())?;
+
+        let (buf, out) = (Recorded::new(buf), Recorded::new(out));
+        let access = Access::new([buf.key()], [out.key()]);
+        self.record("reduce", access, move |device| {
+            device.try_reduce_to(&buf.into_buf(device), &mut out.into_buf(device), init, f)
+        });
+        Ok(())
+    }
+
+    /// Records `lhs_grad[i] += out_grad[i] * lhs_grad_fn(lhs[i])`, see [`UnaryGrad::add_unary_grad`].
+    pub fn add_unary_grad<T, S, F>(
+        &self,
+        lhs: &Buffer<T, Lazy<D>, S>,
+        lhs_grad: &mut Buffer<T, Lazy<D>, S>,
+        out_grad: &Buffer<T, Lazy<D>, S>,
+        lhs_grad_fn: impl Fn(Resolve<T>) -> F + 'static,
+    ) where
+        T: 'static,
+        S: Shape,
+        D: UnaryGrad<T, S>,
+        F: Eval<T> + MayToCLSource + 'static,
+    {
+        let (lhs, lhs_grad, out_grad) = (
+            Recorded::new(lhs),
+            Recorded::new(lhs_grad),
+            Recorded::new(out_grad),
+        );
+        let access = Access::new([lhs.key(), out_grad.key()], [lhs_grad.key()]);
+        self.record("add_unary_grad", access, move |device| {
+            device.add_unary_grad(
+                &lhs.into_buf(device),
+                &mut lhs_grad.into_buf(device),
+                &out_grad.into_buf(device),
+                lhs_grad_fn,
+            );
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,93 @@
+use super::{Access, Kind, LazyOp};
+
+/// Returns `true` if `a` and `b` must be executed in the order they were recorded.
+fn conflicts(a: &Option<Access>, b: &Option<Access>) -> bool {
+    let (Some(a), Some(b)) = (a, b) else {
+        return true;
+    };
+    let overlaps = |lhs: &[usize], rhs: &[usize]| lhs.iter().any(|key| rhs.contains(key));
+    overlaps(&a.writes, &b.writes) || overlaps(&a.writes, &b.reads) || overlaps(&a.reads, &b.writes)
+}
+
+/// The key of the memory that a map reads and writes.
+fn map_keys<D>(op: &LazyOp<D>) -> Option<(usize, usize)> {
+    match (&op.kind, &op.access) {
+        (Kind::Map(_), Some(access)) => Some((access.reads[0], access.writes[0])),
+        _ => None,
+    }
+}
+
+/// Orders `ops` and groups them into kernels, every kernel contains the indices of the operations it executes.
+/// Every map of a kernel reads the output of the previous one, which is not written if `fuse` is set.
+pub(super) fn plan<D>(ops: &[LazyOp<D>], fuse: bool) -> Vec<Vec<usize>> {
+    let deps = (0..ops.len())
+        .map(|idx| {
+            (0..idx)
+                .filter(|&before| conflicts(&ops[before].access, &ops[idx].access))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut done = vec![false; ops.len()];
+    let mut order = Vec::with_capacity(ops.len());
+
+    while order.len() < ops.len() {
+        let mut ready =
+            (0..ops.len()).filter(|&idx| !done[idx] && deps[idx].iter().all(|&dep| done[dep]));
+
+        // prefer the consumer of the previous map, they may be fused
+        let prev_out = order
+            .last()
+            .and_then(|&prev| map_keys(&ops[prev]))
+            .map(|(_, out)| out);
+        let next = ready
+            .clone()
+            .find(|&idx| matches!((map_keys(&ops[idx]), prev_out), (Some((input, _)), Some(out)) if input == out))
+            .or_else(|| ready.next())
+            .expect("the dependencies point to earlier operations only");
+
+        done[next] = true;
+        order.push(next);
+    }
+
+    let mut kernels: Vec<Vec<usize>> = Vec::new();
+    for idx in order {
+        if let Some(kernel) = kernels.last_mut() {
+            if fuse && fuses(ops, kernel, idx) {
+                kernel.push(idx);
+                continue;
+            }
+        }
+        kernels.push(vec![idx]);
+    }
+    kernels
+}
+
+/// Returns `true` if the map `next` can be appended to the maps of `kernel`.
+fn fuses<D>(ops: &[LazyOp<D>], kernel: &[usize], next: usize) -> bool {
+    let last = &ops[kernel[kernel.len() - 1]];
+    let (Kind::Map(last_map), Kind::Map(next_map)) = (&last.kind, &ops[next].kind) else {
+        return false;
+    };
+    let (Some((kernel_input, _)), Some((_, last_out)), Some((input, out))) = (
+        map_keys(&ops[kernel[0]]),
+        map_keys(last),
+        map_keys(&ops[next]),
+    ) else {
+        return false;
+    };
+
+    // `next` reads the output of `last` and does not overwrite the input of the kernel
+    if input != last_out || out == kernel_input {
+        return false;
+    }
+
+    // the output of `last` is only written by `last` and read by `next`
+    let pending = ops
+        .iter()
+        .filter_map(|op| op.access.as_ref())
+        .flat_map(|access| access.reads.iter().chain(&access.writes))
+        .filter(|&&key| key == last_out)
+        .count();
+    pending == 2 && last_map.fuses_with(&**next_map) && !last_map.is_observable(pending)
+}
@@ -0,0 +1,389 @@
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[cfg(feature = "sanitize")]
+use core::{fmt::Display, panic::Location};
+#[cfg(feature = "sanitize")]
+use std::{collections::BTreeMap, sync::Mutex};
+
+use crate::Device;
+
+/// The memory a device has allocated through [`Alloc`](crate::Alloc), in bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// The bytes of all allocations that were not freed yet.
+    pub live: usize,
+    /// The highest value `live` has reached since the creation of the device or the last [`MemoryTracker::reset_peak`].
+    pub peak: usize,
+    /// Allocations that would raise `live` above this value fail with [`Error::Alloc`](crate::Error::Alloc).
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug)]
+struct MemoryCounter {
+    live: AtomicUsize,
+    peak: AtomicUsize,
+    // `usize::MAX` if there is no limit
+    limit: AtomicUsize,
+    #[cfg(feature = "sanitize")]
+    generation: AtomicUsize,
+    // the records of all allocations that were not freed yet, keyed by generation
+    #[cfg(feature = "sanitize")]
+    records: Mutex<BTreeMap<usize, Arc<AllocationRecord>>>,
+}
+
+impl Default for MemoryCounter {
+    fn default() -> Self {
+        MemoryCounter {
+            live: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            limit: AtomicUsize::new(usize::MAX),
+            #[cfg(feature = "sanitize")]
+            generation: AtomicUsize::new(0),
+            #[cfg(feature = "sanitize")]
+            records: Default::default(),
+        }
+    }
+}
+
+/// Counts the live and peak bytes of a device.
+///
+/// Every device that allocates memory owns a `MemoryTracker`.
+/// An allocation reserves its bytes with [`try_reserve`](MemoryTracker::try_reserve).
+/// The pointer that owns the memory keeps the returned [`Allocation`] to release the bytes when it frees the memory.
+///
+/// With the `sanitize` feature, every allocation is additionally recorded with a generation and its origin.
+/// Dropping a `MemoryTracker` (i.e. its device) reports allocations that are still outstanding.
+/// # Example
+#[cfg_attr(feature = "cpu", doc = "```")]
+#[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+/// use custos::{Buffer, MemoryReturn, CPU};
+///
+/// let device = CPU::new();
+///
+/// let buf = Buffer::<f32, _>::new(&device, 10);
+/// assert_eq!(device.memory_usage().live, 40);
+///
+/// drop(buf);
+/// let usage = device.memory_usage();
+/// assert_eq!(usage.live, 0);
+/// assert_eq!(usage.peak, 40);
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryTracker {
+    counter: Arc<MemoryCounter>,
+}
+
+impl MemoryTracker {
+    /// Reserves `bytes` for a new allocation.
+    /// # Errors
+    /// [`Error::Alloc`](crate::Error::Alloc) with a [`LimitExceeded`](crate::LimitExceeded) if the limit would be exceeded. Nothing is reserved in this case.
+    #[track_caller]
+    pub fn try_reserve(&self, bytes: usize) -> crate::Result<Allocation> {
+        let limit = self.counter.limit.load(Ordering::Relaxed);
+        let mut live = self.counter.live.load(Ordering::Relaxed);
+
+        loop {
+            let new_live = match live.checked_add(bytes) {
+                Some(new_live) if new_live <= limit => new_live,
+                _ => {
+                    return Err(crate::Error::Alloc {
+                        bytes,
+                        limit: Some(crate::LimitExceeded { live, limit }),
+                    })
+                }
+            };
+
+            match self.counter.live.compare_exchange_weak(
+                live,
+                new_live,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    self.counter.peak.fetch_max(new_live, Ordering::Relaxed);
+                    return Ok(Allocation {
+                        counter: self.counter.clone(),
+                        bytes,
+                        freed: Arc::new(AtomicBool::new(false)),
+                        #[cfg(feature = "sanitize")]
+                        record: self.record(bytes),
+                    });
+                }
+                Err(current) => live = current,
+            }
+        }
+    }
+
+    /// Reserves `bytes` and runs `alloc`. If `alloc` fails, the bytes are released again.
+    #[track_caller]
+    pub fn track<P>(
+        &self,
+        bytes: usize,
+        alloc: impl FnOnce() -> crate::Result<P>,
+    ) -> crate::Result<(P, Allocation)> {
+        let allocation = self.try_reserve(bytes)?;
+        match alloc() {
+            Ok(ptr) => Ok((ptr, allocation)),
+            Err(err) => {
+                allocation.release();
+                Err(err)
+            }
+        }
+    }
+
+    /// Returns the current [`MemoryStats`].
+    pub fn usage(&self) -> MemoryStats {
+        let limit = self.counter.limit.load(Ordering::Relaxed);
+        MemoryStats {
+            live: self.counter.live.load(Ordering::Relaxed),
+            peak: self.counter.peak.load(Ordering::Relaxed),
+            limit: (limit != usize::MAX).then_some(limit),
+        }
+    }
+
+    /// Sets the maximum number of live bytes. `None` removes the limit.
+    /// Memory that is already allocated is not affected, even if it exceeds the new limit.
+    #[inline]
+    pub fn set_limit(&self, limit: Option<usize>) {
+        self.counter
+            .limit
+            .store(limit.unwrap_or(usize::MAX), Ordering::Relaxed);
+    }
+
+    /// Sets the peak to the current live bytes.
+    #[inline]
+    pub fn reset_peak(&self) {
+        let live = self.counter.live.load(Ordering::Relaxed);
+        self.counter.peak.store(live, Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "sanitize")]
+    #[track_caller]
+    fn record(&self, bytes: usize) -> Arc<AllocationRecord> {
+        let record = Arc::new(AllocationRecord {
+            info: AllocationInfo {
+                generation: self.counter.generation.fetch_add(1, Ordering::Relaxed),
+                bytes,
+                origin: Location::caller(),
+            },
+        });
+        self.counter
+            .records
+            .lock()
+            .unwrap()
+            .insert(record.info.generation, record.clone());
+        record
+    }
+
+    /// Returns the allocations that were not freed yet, oldest first.
+    /// # Example
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::{Buffer, MemoryReturn, CPU};
+    ///
+    /// let device = CPU::new();
+    /// let buf = Buffer::<f32, _>::new(&device, 10);
+    ///
+    /// let outstanding = device.memory().outstanding();
+    /// assert_eq!(outstanding.len(), 1);
+    /// assert_eq!(outstanding[0].bytes, 40);
+    /// assert_eq!(outstanding[0].origin.line(), line!() - 5);
+    /// ```
+    #[cfg(feature = "sanitize")]
+    pub fn outstanding(&self) -> Vec<AllocationInfo> {
+        self.counter
+            .records
+            .lock()
+            .unwrap()
+            .values()
+            .map(|record| record.info)
+            .collect()
+    }
+}
+
+#[cfg(feature = "sanitize")]
+impl Drop for MemoryTracker {
+    fn drop(&mut self) {
+        let outstanding = self.outstanding();
+        if outstanding.is_empty() {
+            return;
+        }
+
+        eprintln!(
+            "custos sanitizer: device dropped with {} outstanding allocation(s):",
+            outstanding.len()
+        );
+        for info in outstanding {
+            eprintln!("    {info}");
+        }
+    }
+}
+
+/// Describes an allocation recorded by the `sanitize` feature.
+#[cfg(feature = "sanitize")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocationInfo {
+    /// The allocations of a device are numbered in order, starting at 0.
+    pub generation: usize,
+    /// The number of reserved bytes.
+    pub bytes: usize,
+    /// The location that allocated the memory.
+    pub origin: &'static Location<'static>,
+}
+
+#[cfg(feature = "sanitize")]
+impl Display for AllocationInfo {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} bytes (generation {}) allocated at {}",
+            self.bytes, self.generation, self.origin
+        )
+    }
+}
+
+#[cfg(feature = "sanitize")]
+#[derive(Debug)]
+struct AllocationRecord {
+    info: AllocationInfo,
+}
+
+/// The bytes that an allocation reserved from a [`MemoryTracker`].
+///
+/// Pointer types keep an `Allocation` next to the memory they own and call [`release`](Allocation::release) when they free it.
+/// Copies of a pointer (e.g. with [`AllocFlag::Wrapper`](crate::flag::AllocFlag::Wrapper)) carry the `Allocation` as well,
+/// hence it is released by whichever copy frees the memory.
+/// The number of these copies is available via [`handles`](Allocation::handles).
+/// With the `sanitize` feature, these copies use the `Allocation` to detect that the memory was freed ([`check`](Allocation::check)).
+#[derive(Debug, Clone)]
+pub struct Allocation {
+    counter: Arc<MemoryCounter>,
+    bytes: usize,
+    // shared by all copies, set once the memory is freed
+    freed: Arc<AtomicBool>,
+    #[cfg(feature = "sanitize")]
+    record: Arc<AllocationRecord>,
+}
+
+// Pointers that contain an `Allocation` derive `PartialEq`.
+impl PartialEq for Allocation {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.freed, &other.freed)
+    }
+}
+
+impl Eq for Allocation {}
+
+impl Allocation {
+    /// The number of reserved bytes.
+    #[inline]
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// The number of pointers that carry this `Allocation`, i.e. the pointer that owns the memory and its copies that are alive.
+    /// Operations recorded by a [`Plan`](crate::Plan) carry the `Allocation` of the memory they access as well.
+    /// A [`Cache`](crate::Cache) only evicts an entry if no copy of its pointer is alive.
+    #[inline]
+    pub fn handles(&self) -> usize {
+        Arc::strong_count(&self.freed)
+    }
+
+    /// Identifies the memory. All copies of an `Allocation` return the same key, as long as one of them is alive.
+    #[cfg(feature = "lazy")]
+    #[inline]
+    pub(crate) fn key(&self) -> usize {
+        Arc::as_ptr(&self.freed) as usize
+    }
+
+    /// Releases the reserved bytes. Must be called exactly once, when the memory is freed.
+    #[inline]
+    pub fn release(&self) {
+        self.counter.live.fetch_sub(self.bytes, Ordering::AcqRel);
+        self.freed.store(true, Ordering::Release);
+
+        #[cfg(feature = "sanitize")]
+        {
+            self.counter
+                .records
+                .lock()
+                .unwrap()
+                .remove(&self.record.info.generation);
+        }
+    }
+
+    /// Returns the [`AllocationInfo`] recorded for this allocation.
+    #[cfg(feature = "sanitize")]
+    #[inline]
+    pub fn info(&self) -> AllocationInfo {
+        self.record.info
+    }
+
+    /// Returns `true` if the memory of this allocation was freed.
+    #[inline]
+    pub fn is_freed(&self) -> bool {
+        self.freed.load(Ordering::Acquire)
+    }
+
+    /// Panics if the memory of this allocation was freed.
+    /// Called before memory is accessed through a pointer, e.g. by [`Read`](crate::Read), `Deref` or a kernel launch.
+    /// # Panics
+    /// If the memory was freed, e.g. a [`shallow`](crate::Buffer::shallow) copy is used after its owner was dropped.
+    #[cfg(feature = "sanitize")]
+    #[track_caller]
+    #[inline]
+    pub fn check(&self) {
+        if self.is_freed() {
+            panic!(
+                "custos sanitizer: use of freed memory: {}",
+                self.record.info
+            );
+        }
+    }
+}
+
+/// Provides access to the [`MemoryTracker`] of a device.
+pub trait MemoryReturn: Device {
+    /// Returns the [`MemoryTracker`] that counts the allocations of the device.
+    fn memory(&self) -> &MemoryTracker;
+
+    /// Returns the live and peak bytes allocated by the device.
+    /// # Example
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::{Buffer, MemoryReturn, CPU};
+    ///
+    /// let device = CPU::new();
+    /// let _buf = Buffer::from((&device, [1u8, 2, 3]));
+    ///
+    /// assert_eq!(device.memory_usage().live, 3);
+    /// ```
+    #[inline]
+    fn memory_usage(&self) -> MemoryStats {
+        self.memory().usage()
+    }
+
+    /// Limits the live bytes of the device. Allocations that would exceed the limit return [`Error::Alloc`](crate::Error::Alloc).
+    /// # Example
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::{Buffer, Error, LimitExceeded, MemoryReturn, CPU};
+    ///
+    /// let device = CPU::new();
+    /// device.set_memory_limit(Some(64));
+    ///
+    /// let _buf = Buffer::<f32, _>::new(&device, 16);
+    ///
+    /// let err = Buffer::<f32, _>::try_new(&device, 1).unwrap_err();
+    /// assert!(matches!(
+    ///     err,
+    ///     Error::Alloc { bytes: 4, limit: Some(LimitExceeded { live: 64, limit: 64 }) }
+    /// ));
+    /// ```
+    #[inline]
+    fn set_memory_limit(&self, limit: Option<usize>) {
+        self.memory().set_limit(limit)
+    }
+}
@@ -51,22 +51,65 @@ pub mod wgpu;
 #[cfg(feature = "network")]
 pub mod network;
 
+#[cfg(feature = "sim")]
+#[cfg(not(feature = "no-std"))]
+pub mod sim;
+
+#[cfg(feature = "faulty")]
+#[cfg(not(feature = "no-std"))]
+pub mod faulty;
+
+#[cfg(feature = "nan-check")]
+#[cfg(not(feature = "no-std"))]
+pub mod nan_check;
+
+#[cfg(feature = "lazy")]
+#[cfg(not(feature = "no-std"))]
+pub mod lazy;
+
+#[cfg(feature = "cpu")]
+#[cfg(not(feature = "no-std"))]
+pub mod any_device;
+
 mod stack_array;
 pub use stack_array::*;
 
 mod cdatatype;
 pub use cdatatype::*;
 
+#[cfg(not(feature = "no-std"))]
 mod caller_cache;
+#[cfg(not(feature = "no-std"))]
+pub use caller_cache::{CallerCacheReturn, TrackCallerCache, TrackCallerCacheAble};
 
 #[cfg(all(any(feature = "cpu", feature = "stack"), feature = "macro"))]
 mod cpu_stack_ops;
+#[cfg(all(feature = "cpu", feature = "macro"))]
+pub(crate) use cpu_stack_ops::MayCapture;
+
+#[cfg(not(feature = "no-std"))]
+mod per_thread;
+#[cfg(not(feature = "no-std"))]
+pub use per_thread::*;
 
 #[cfg(not(feature = "no-std"))]
 mod ident;
 #[cfg(not(feature = "no-std"))]
 pub use ident::*;
 
+#[cfg(not(feature = "no-std"))]
+mod memory;
+#[cfg(not(feature = "no-std"))]
+pub use memory::*;
+
+#[cfg(not(feature = "no-std"))]
+mod wrapper;
+#[cfg(not(feature = "no-std"))]
+#[cfg(any(feature = "faulty", feature = "nan-check", feature = "lazy"))]
+pub(crate) use wrapper::InnerBuf;
+#[cfg(not(feature = "no-std"))]
+pub use wrapper::Wrapper;
+
 /// Used to convert a device pointer to the a pointer of a different type.
 pub trait PtrConv: Device {
     /// Converts a pointer to a pointer with a different type.
@@ -77,6 +120,16 @@ pub trait PtrConv: Device {
         ptr: &Self::Ptr<T, IS>,
         flag: AllocFlag,
     ) -> Self::Ptr<Conv, OS>;
+
+    /// Converts a pointer to a pointer with a different type and a different element count.
+    /// This is used to let a larger allocation back a smaller [`Buffer`](crate::Buffer).
+    /// # Safety
+    /// Same as [`PtrConv::convert`]. Additionally, `len` must not exceed the element count of `ptr`.
+    unsafe fn convert_with_len<T, IS: Shape, Conv, OS: Shape>(
+        ptr: &Self::Ptr<T, IS>,
+        len: usize,
+        flag: AllocFlag,
+    ) -> Self::Ptr<Conv, OS>;
 }
 
 /// Implementors of this trait can be used as cache for a device.
@@ -102,7 +155,22 @@ pub trait CacheAble<D: Device> {
     /// assert_eq!(buf.ptr.ptr, buf_2.ptr.ptr);
     ///
     /// ```
+    #[inline]
+    #[track_caller]
     fn retrieve<T, S: Shape>(device: &D, len: usize, add_node: impl AddGraph) -> Buffer<T, D, S>
+    where
+        for<'a> D: Alloc<'a, T, S>,
+    {
+        Self::try_retrieve(device, len, add_node).unwrap()
+    }
+
+    /// Like [`retrieve`](CacheAble::retrieve), but returns an error if a new buffer could not be allocated.
+    #[track_caller]
+    fn try_retrieve<T, S: Shape>(
+        device: &D,
+        len: usize,
+        add_node: impl AddGraph,
+    ) -> crate::Result<Buffer<T, D, S>>
     where
         for<'a> D: Alloc<'a, T, S>;
 
@@ -130,11 +198,15 @@ pub trait CacheAble<D: Device> {
 // TODO: Mind num implement?
 impl<D: Device> CacheAble<D> for () {
     #[inline]
-    fn retrieve<T, S: Shape>(device: &D, len: usize, _add_node: impl AddGraph) -> Buffer<T, D, S>
+    fn try_retrieve<T, S: Shape>(
+        device: &D,
+        len: usize,
+        _add_node: impl AddGraph,
+    ) -> crate::Result<Buffer<T, D, S>>
     where
         for<'a> D: Alloc<'a, T, S>,
     {
-        Buffer::new(device, len)
+        Buffer::try_new(device, len)
     }
 
     #[cfg(not(feature = "no-std"))]
@@ -0,0 +1,374 @@
+//! The nan_check module provides [`NanCheck`], a wrapper device that validates the results of operations.
+
+mod ops;
+
+use core::{
+    fmt::{Debug, Display},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    devices::cache::Cache, flag::AllocFlag, shape::Shape, Addons, AddonsReturn, Alloc, Buffer,
+    Device, Ident, InnerBuf, MainMemory, MemoryReturn, MemoryTracker, PerThread, PtrConv, Read,
+    Wrapper,
+};
+
+/// A value that is most likely the result of a numerical problem, found by a [`NanCheck`] device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anomaly {
+    /// Not a number.
+    NaN,
+    /// Positive or negative infinity.
+    Infinite,
+    /// A subnormal (denormal) value. Only reported if enabled with [`NanCheck::check_subnormals`].
+    Subnormal,
+}
+
+impl Display for Anomaly {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Anomaly::NaN => write!(f, "NaN"),
+            Anomaly::Infinite => write!(f, "an infinite value"),
+            Anomaly::Subnormal => write!(f, "a subnormal value"),
+        }
+    }
+}
+
+/// Describes where a [`NanCheck`] device found an [`Anomaly`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnomalyReport {
+    /// The name of the operation that produced the value, e.g. `"apply_fn"`.
+    pub op: &'static str,
+    /// The [`Ident`] of the checked [`Buffer`], if it has one.
+    pub ident: Option<Ident>,
+    /// The index of the first offending value.
+    pub index: usize,
+    /// The kind of the first offending value.
+    pub anomaly: Anomaly,
+}
+
+impl Display for AnomalyReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "`{}` produced {} at index {}",
+            self.op, self.anomaly, self.index
+        )?;
+        match self.ident {
+            Some(ident) => write!(f, " (ident: {}, len: {})", ident.idx, ident.len),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Numbers that can be checked for an [`Anomaly`].
+/// Integers never contain one.
+pub trait CheckAnomaly {
+    /// Returns the [`Anomaly`] of `self`, if there is one. Subnormal values are only reported if `subnormals` is `true`.
+    fn anomaly(&self, subnormals: bool) -> Option<Anomaly>;
+}
+
+macro_rules! impl_check_anomaly_float {
+    ($($t:ty),*) => {
+        $(
+            impl CheckAnomaly for $t {
+                #[inline]
+                fn anomaly(&self, subnormals: bool) -> Option<Anomaly> {
+                    if self.is_nan() {
+                        Some(Anomaly::NaN)
+                    } else if self.is_infinite() {
+                        Some(Anomaly::Infinite)
+                    } else if subnormals && self.is_subnormal() {
+                        Some(Anomaly::Subnormal)
+                    } else {
+                        None
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_check_anomaly_float!(f32, f64);
+
+macro_rules! impl_check_anomaly_int {
+    ($($t:ty),*) => {
+        $(
+            impl CheckAnomaly for $t {
+                #[inline]
+                fn anomaly(&self, _subnormals: bool) -> Option<Anomaly> {
+                    None
+                }
+            }
+        )*
+    };
+}
+
+impl_check_anomaly_int!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// A device that wraps the device `D` and checks the results of operations for NaN and infinite values. Enabled by the `nan-check` feature.
+///
+/// This is used to find the operation that causes a computation (e.g. a training run) to diverge.
+/// The output of `ApplyFunction` and the gradient written by `UnaryGrad` are checked after every call.
+/// With the `autograd` feature, this checks the gradients of every step of [`Tape::backward`](crate::Tape::backward) that uses these operations.
+/// Custom operations can check their results with [`NanCheck::check`].
+///
+/// The first offending value is reported with the name of the operation, the [`Ident`] of the `Buffer` and its index.
+/// Fallible methods, e.g. [`ApplyFunction::try_apply_fn`](crate::ApplyFunction::try_apply_fn), return [`Error::Anomaly`](crate::Error::Anomaly),
+/// the infallible ones panic.
+///
+/// Operations are executed by `D`. Their results are copied into buffers of `NanCheck`, which has its own [`Addons`].
+/// Checking reads the data of a `Buffer` with [`Read::read_to_vec`], hence it is copied to host memory on devices without [`MainMemory`].
+/// `Read`, `WriteBuf`, `ClearBuf`, `CopySlice`, `MainMemory` and `MemoryReturn` are forwarded to `D` without checks.
+/// # Example
+#[cfg_attr(all(feature = "cpu", feature = "macro"), doc = "```")]
+#[cfg_attr(not(all(feature = "cpu", feature = "macro")), doc = "```ignore")]
+/// use custos::{Anomaly, ApplyFunction, Buffer, Combiner, Error, NanCheck, CPU};
+///
+/// let device = NanCheck::new(CPU::new());
+/// let buf = Buffer::from((&device, [1., 0., -1.]));
+///
+/// let out = device.apply_fn(&buf, |x| x.add(1.));
+/// assert_eq!(out.read(), [2., 1., 0.]);
+///
+/// let Err(Error::Anomaly(report)) = device.try_apply_fn(&buf, |x| x.pow(-1.)) else {
+///     panic!("1 / 0 is not finite");
+/// };
+/// assert_eq!(report.op, "apply_fn");
+/// assert_eq!(report.index, 1);
+/// assert_eq!(report.anomaly, Anomaly::Infinite);
+/// ```
+pub struct NanCheck<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Provides additional functionality for the `NanCheck` device. e.g. a cache, a gradient [`Tape`](crate::Tape) or an optimizeable [`Graph`](crate::Graph).
+    pub addons: PerThread<Addons<NanCheck<D>>>,
+    inner: D,
+    subnormals: AtomicBool,
+}
+
+impl<D> NanCheck<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    /// Wraps `inner`. Subnormal values are not reported until [`check_subnormals`](NanCheck::check_subnormals) is enabled.
+    #[must_use]
+    pub fn new(inner: D) -> NanCheck<D> {
+        NanCheck {
+            addons: PerThread::default(),
+            inner,
+            subnormals: AtomicBool::new(false),
+        }
+    }
+
+    /// Sets whether subnormal values are reported as [`Anomaly::Subnormal`].
+    #[inline]
+    pub fn check_subnormals(&self, check: bool) {
+        self.subnormals.store(check, Ordering::Relaxed);
+    }
+
+    /// Checks `buf` for an [`Anomaly`]. `op` is the name of the operation that wrote `buf`.
+    /// # Errors
+    /// [`Error::Anomaly`](crate::Error::Anomaly) with the first offending value.
+    /// # Example
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::{Anomaly, Buffer, Error, NanCheck, CPU};
+    ///
+    /// let device = NanCheck::new(CPU::new());
+    ///
+    /// let buf = Buffer::from((&device, [1., f32::NAN, f32::INFINITY]));
+    /// let Err(Error::Anomaly(report)) = device.check("my_op", &buf) else {
+    ///     panic!("buf contains NaN");
+    /// };
+    /// assert_eq!(report.index, 1);
+    /// assert_eq!(report.anomaly, Anomaly::NaN);
+    /// assert_eq!(report.ident, buf.ident);
+    ///
+    /// assert!(device.check("my_op", &Buffer::from((&device, [1e-40f32]))).is_ok());
+    /// device.check_subnormals(true);
+    /// assert!(device.check("my_op", &Buffer::from((&device, [1e-40f32]))).is_err());
+    /// ```
+    pub fn check<T, S>(
+        &self,
+        op: &'static str,
+        buf: &Buffer<T, NanCheck<D>, S>,
+    ) -> crate::Result<()>
+    where
+        T: CheckAnomaly + Clone + Default,
+        S: Shape,
+        D: Read<T, S>,
+    {
+        let subnormals = self.subnormals.load(Ordering::Relaxed);
+        let data = self.inner.read_to_vec(&self.inner_buf(buf));
+
+        let found = data
+            .iter()
+            .enumerate()
+            .find_map(|(index, value)| Some((index, value.anomaly(subnormals)?)));
+
+        match found {
+            Some((index, anomaly)) => Err(crate::Error::Anomaly(AnomalyReport {
+                op,
+                ident: buf.ident,
+                index,
+                anomaly,
+            })),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<D> Wrapper for NanCheck<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    type Inner = D;
+
+    #[inline]
+    fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    #[inline]
+    fn addons_mut(&mut self) -> &mut PerThread<Addons<NanCheck<D>>> {
+        &mut self.addons
+    }
+}
+
+impl<D> Default for NanCheck<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn default() -> Self {
+        NanCheck::new(D::default())
+    }
+}
+
+impl<D> Debug for NanCheck<D>
+where
+    D: PtrConv + Default + Debug,
+    D::Ptr<u8, ()>: Default,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("NanCheck")
+            .field("inner", &self.inner)
+            .field("subnormals", &self.subnormals)
+            .finish()
+    }
+}
+
+impl<D> Device for NanCheck<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    type Ptr<U, S: Shape> = D::Ptr<U, S>;
+    type Cache = Cache<NanCheck<D>>;
+
+    fn new() -> crate::Result<Self> {
+        Ok(NanCheck::new(D::new()?))
+    }
+
+    #[inline]
+    fn synchronize(&self) -> crate::Result<()> {
+        self.inner.synchronize()
+    }
+}
+
+impl<D> AddonsReturn for NanCheck<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn addons(&self) -> &Addons<Self> {
+        self.addons.local()
+    }
+}
+
+impl<D> MemoryReturn for NanCheck<D>
+where
+    D: PtrConv + Default + MemoryReturn,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn memory(&self) -> &MemoryTracker {
+        self.inner.memory()
+    }
+}
+
+impl<'a, T, S: Shape, D> Alloc<'a, T, S> for NanCheck<D>
+where
+    D: PtrConv + Default + Alloc<'a, T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    #[track_caller]
+    fn try_alloc(&'a self, len: usize, flag: AllocFlag) -> crate::Result<D::Ptr<T, S>> {
+        self.inner.try_alloc(len, flag)
+    }
+
+    #[inline]
+    #[track_caller]
+    fn try_with_slice(&'a self, data: &[T]) -> crate::Result<D::Ptr<T, S>>
+    where
+        T: Clone,
+    {
+        self.inner.try_with_slice(data)
+    }
+
+    #[inline]
+    #[track_caller]
+    fn try_alloc_with_vec(&'a self, vec: Vec<T>) -> crate::Result<D::Ptr<T, S>>
+    where
+        T: Clone,
+    {
+        self.inner.try_alloc_with_vec(vec)
+    }
+}
+
+impl<D> PtrConv for NanCheck<D>
+where
+    D: PtrConv + Default,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    unsafe fn convert<T, IS: Shape, Conv, OS: Shape>(
+        ptr: &D::Ptr<T, IS>,
+        flag: AllocFlag,
+    ) -> D::Ptr<Conv, OS> {
+        D::convert::<T, IS, Conv, OS>(ptr, flag)
+    }
+
+    #[inline]
+    unsafe fn convert_with_len<T, IS: Shape, Conv, OS: Shape>(
+        ptr: &D::Ptr<T, IS>,
+        len: usize,
+        flag: AllocFlag,
+    ) -> D::Ptr<Conv, OS> {
+        D::convert_with_len::<T, IS, Conv, OS>(ptr, len, flag)
+    }
+}
+
+impl<D> MainMemory for NanCheck<D>
+where
+    D: PtrConv + Default + MainMemory,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn as_ptr<T, S: Shape>(ptr: &D::Ptr<T, S>) -> *const T {
+        D::as_ptr(ptr)
+    }
+
+    #[inline]
+    fn as_ptr_mut<T, S: Shape>(ptr: &mut D::Ptr<T, S>) -> *mut T {
+        D::as_ptr_mut(ptr)
+    }
+}
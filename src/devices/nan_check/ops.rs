@@ -0,0 +1,186 @@
+use core::ops::{Range, RangeBounds};
+
+use crate::{
+    Alloc, ApplyFunction, Buffer, ClearBuf, CloneBuf, CopySlice, Device, Eval, InnerBuf,
+    MayToCLSource, Named, PtrConv, Read, Resolve, Shape, Transfer, UnaryGrad, WriteBuf,
+};
+
+use super::{CheckAnomaly, NanCheck};
+
+impl<T, S, D> Read<T, S> for NanCheck<D>
+where
+    T: Clone + Default,
+    S: Shape,
+    D: PtrConv + Default + Read<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    type Read<'a>
+        = Vec<T>
+    where
+        T: 'a,
+        S: 'a;
+
+    #[inline]
+    fn try_read(&self, buf: &Buffer<T, NanCheck<D>, S>) -> crate::Result<Vec<T>> {
+        Ok(self.read_to_vec(buf))
+    }
+
+    #[inline]
+    fn read_to_vec(&self, buf: &Buffer<T, NanCheck<D>, S>) -> Vec<T>
+    where
+        T: Default + Clone,
+    {
+        self.inner.read_to_vec(&self.inner_buf(buf))
+    }
+}
+
+impl<T, S, D> WriteBuf<T, S> for NanCheck<D>
+where
+    S: Shape,
+    D: PtrConv + Default + WriteBuf<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn try_write(&self, buf: &mut Buffer<T, NanCheck<D>, S>, data: &[T]) -> crate::Result<()> {
+        self.inner.try_write(&mut self.inner_buf(buf), data)
+    }
+
+    #[inline]
+    fn write_buf(&self, dst: &mut Buffer<T, NanCheck<D>, S>, src: &Buffer<T, NanCheck<D>, S>) {
+        self.inner
+            .write_buf(&mut self.inner_buf(dst), &self.inner_buf(src))
+    }
+}
+
+impl<T, S, D, Dst> Transfer<T, Dst, S> for NanCheck<D>
+where
+    T: Clone + Default,
+    S: Shape,
+    D: PtrConv + Default + Read<T, S>,
+    D::Ptr<u8, ()>: Default,
+    Dst: for<'b> Alloc<'b, T, S>,
+{
+    #[inline]
+    fn try_transfer<'b>(
+        &self,
+        buf: &Buffer<T, NanCheck<D>, S>,
+        dst: &'b Dst,
+    ) -> crate::Result<Buffer<'b, T, Dst, S>> {
+        Buffer::try_from_vec(dst, self.try_read(buf)?)
+    }
+}
+
+impl<T, S, D> ClearBuf<T, S> for NanCheck<D>
+where
+    S: Shape,
+    D: PtrConv + Default + ClearBuf<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn clear(&self, buf: &mut Buffer<T, NanCheck<D>, S>) {
+        self.inner.clear(&mut self.inner_buf(buf))
+    }
+}
+
+impl<T, D> CopySlice<T> for NanCheck<D>
+where
+    D: PtrConv + Default + CopySlice<T>,
+    D::Ptr<u8, ()>: Default,
+{
+    #[inline]
+    fn try_copy_slice_to<SR: RangeBounds<usize>, DR: RangeBounds<usize>>(
+        &self,
+        source: &Buffer<T, NanCheck<D>>,
+        source_range: SR,
+        dest: &mut Buffer<T, NanCheck<D>>,
+        dest_range: DR,
+    ) -> crate::Result<()> {
+        self.inner.try_copy_slice_to(
+            &self.inner_buf(source),
+            source_range,
+            &mut self.inner_buf(dest),
+            dest_range,
+        )
+    }
+
+    #[inline]
+    fn copy_slice_all<I: IntoIterator<Item = (Range<usize>, Range<usize>)>>(
+        &self,
+        source: &Buffer<T, NanCheck<D>>,
+        dest: &mut Buffer<T, NanCheck<D>>,
+        ranges: I,
+    ) {
+        self.inner
+            .copy_slice_all(&self.inner_buf(source), &mut self.inner_buf(dest), ranges)
+    }
+}
+
+impl<'a, T, S, D> CloneBuf<'a, T, S> for NanCheck<D>
+where
+    S: Shape,
+    D: PtrConv + Default + Alloc<'a, T, S> + WriteBuf<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    #[track_caller]
+    fn clone_buf(&'a self, buf: &Buffer<'a, T, NanCheck<D>, S>) -> Buffer<'a, T, NanCheck<D>, S> {
+        let mut cloned = Buffer::new(self, buf.len());
+        self.write_buf(&mut cloned, buf);
+        cloned
+    }
+}
+
+impl<T, S, D> ApplyFunction<T, S> for NanCheck<D>
+where
+    T: CheckAnomaly + Clone + Default,
+    S: Shape,
+    D: PtrConv + Default + ApplyFunction<T, S> + Read<T, S> + WriteBuf<T, S>,
+    D: for<'b> Alloc<'b, T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    #[track_caller]
+    fn try_apply_fn<F>(
+        &self,
+        buf: &Buffer<T, NanCheck<D>, S>,
+        f: impl Fn(Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<'_, T, NanCheck<D>, S>>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        let inner_out = self.inner.try_apply_fn(&self.inner_buf(buf), f)?;
+
+        // the result is copied, because `out` is part of the cache and graph of `NanCheck`
+        let out = self.try_retrieve(buf.len(), Named("apply_fn", buf))?;
+        self.inner.write_buf(&mut self.inner_buf(&out), &inner_out);
+
+        self.check("apply_fn", &out)?;
+        Ok(out)
+    }
+}
+
+impl<T, S, D> UnaryGrad<T, S> for NanCheck<D>
+where
+    T: CheckAnomaly + Clone + Default,
+    S: Shape,
+    D: PtrConv + Default + UnaryGrad<T, S> + Read<T, S>,
+    D::Ptr<u8, ()>: Default,
+{
+    #[track_caller]
+    fn add_unary_grad<F>(
+        &self,
+        lhs: &Buffer<T, NanCheck<D>, S>,
+        lhs_grad: &mut Buffer<T, NanCheck<D>, S>,
+        out_grad: &Buffer<T, NanCheck<D>, S>,
+        lhs_grad_fn: impl Fn(Resolve<T>) -> F,
+    ) where
+        F: Eval<T> + MayToCLSource,
+    {
+        self.inner.add_unary_grad(
+            &self.inner_buf(lhs),
+            &mut self.inner_buf(lhs_grad),
+            &self.inner_buf(out_grad),
+            lhs_grad_fn,
+        );
+
+        self.check("add_unary_grad", lhs_grad).unwrap();
+    }
+}
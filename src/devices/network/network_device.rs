@@ -41,30 +41,27 @@ impl<'a> Device for Network<'a> {
 }
 
 impl<'a, T: cuw::AsDataType, S: Shape> Alloc<'a, T, S> for Network<'a> {
-    fn alloc(&'a self, len: usize, flag: AllocFlag) -> <Self as Device>::Ptr<T, S> {
-        let id = self
-            .cuw_client
-            .borrow_mut()
-            .alloc_buf::<T>(len as u32)
-            .unwrap();
-
-        NetworkArray {
+    fn try_alloc(
+        &'a self,
+        len: usize,
+        flag: AllocFlag,
+    ) -> crate::Result<<Self as Device>::Ptr<T, S>> {
+        let id = self.cuw_client.borrow_mut().alloc_buf::<T>(len as u32)?;
+
+        Ok(NetworkArray {
             id,
             client: &self.cuw_client,
             _p: PhantomData,
-        }
+        })
     }
 
-    fn with_slice(&'a self, data: &[T]) -> <Self as Device>::Ptr<T, S>
+    fn try_with_slice(&'a self, data: &[T]) -> crate::Result<<Self as Device>::Ptr<T, S>>
     where
         T: Clone,
     {
-        let array: NetworkArray<T> = Alloc::<T, ()>::alloc(self, data.len(), AllocFlag::None);
-        self.cuw_client
-            .borrow_mut()
-            .write_buf(array.id, data)
-            .unwrap();
-        array
+        let array: NetworkArray<T> = Alloc::<T, ()>::try_alloc(self, data.len(), AllocFlag::None)?;
+        self.cuw_client.borrow_mut().write_buf(array.id, data)?;
+        Ok(array)
     }
 }
 
@@ -81,14 +78,15 @@ impl<'a> GraphReturn for Network<'a> {
 }
 
 impl<'b, T: AsDataType + Clone + Default> Read<T> for Network<'b> {
-    type Read<'a> = Vec<T>
+    type Read<'a>
+        = Vec<T>
     where
         T: 'a,
         Network<'b>: 'a;
 
     #[inline]
-    fn read<'a>(&self, buf: &'a Buffer<T, Network<'b>>) -> Self::Read<'a> {
-        self.read_to_vec(buf)
+    fn try_read<'a>(&self, buf: &'a Buffer<T, Network<'b>>) -> crate::Result<Self::Read<'a>> {
+        Ok(self.cuw_client.borrow_mut().read_buf(buf.ptr.id)?)
     }
 
     #[inline]
@@ -96,7 +94,7 @@ impl<'b, T: AsDataType + Clone + Default> Read<T> for Network<'b> {
     where
         T: Default + Clone,
     {
-        self.cuw_client.borrow_mut().read_buf(buf.ptr.id).unwrap()
+        self.read(buf)
     }
 }
 
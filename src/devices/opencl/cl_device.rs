@@ -1,15 +1,19 @@
 use min_cl::CLDevice;
 
 use min_cl::api::{
-    create_buffer, enqueue_full_copy_buffer, CLIntDevice, CommandQueue, Context, MemFlags,
+    create_buffer, enqueue_full_copy_buffer, finish, CLIntDevice, CommandQueue, Context, MemFlags,
 };
 
 use super::{chosen_cl_idx, enqueue_kernel, AsClCvoidPtr, CLPtr, KernelCacheCL};
 use crate::flag::AllocFlag;
-use crate::{cache::Cache, Alloc, Buffer, CloneBuf, Device, Error, CPU};
-use crate::{Addons, AddonsReturn, PtrConv, Shape};
+use crate::{cache::Cache, Alloc, Buffer, CacheStrategy, CloneBuf, Device, Error, CPU};
+use crate::{Addons, AddonsReturn, MemoryReturn, MemoryTracker, PerThread, PtrConv, Shape};
 
-use std::{cell::RefCell, fmt::Debug};
+use std::{
+    cell::RefCell,
+    fmt::Debug,
+    mem::{size_of, size_of_val},
+};
 
 #[cfg(unified_cl)]
 use min_cl::api::unified_ptr;
@@ -31,15 +35,21 @@ use min_cl::api::unified_ptr;
 /// }
 /// ```
 pub struct OpenCL {
-    pub(crate) kernel_cache: RefCell<KernelCacheCL>,
+    /// Kernels are compiled per thread, as setting the arguments of a kernel is not thread-safe.
+    pub(crate) kernel_cache: PerThread<RefCell<KernelCacheCL>>,
     /// The underlying OpenCL device.
     pub inner: CLDevice,
     /// A [`CPU`] used for unified memory device switching.
     pub cpu: CPU,
     /// Provides additional functionality for the OpenCL device. e.g. a cache, a gradient [`Tape`](crate::Tape), an optimizeable [`Graph`](crate::Graph) and a [`Cache`](crate::Cache).
-    pub addons: Addons<OpenCL>,
+    pub addons: PerThread<Addons<OpenCL>>,
+    memory: MemoryTracker,
 }
 
+// SAFETY: The context and command queue of an OpenCL device are thread-safe.
+// Kernels, whose arguments must not be set concurrently, are cached per thread.
+unsafe impl Send for OpenCL {}
+
 /// Short form for `OpenCL`
 pub type CL = OpenCL;
 
@@ -55,15 +65,35 @@ impl OpenCL {
             kernel_cache: Default::default(),
             cpu: Default::default(),
             addons: Default::default(),
+            memory: MemoryTracker::default(),
         })
     }
 
+    /// Sets the [`CacheStrategy`] that is used by [`Device::retrieve`](crate::Device::retrieve) on every thread.
+    /// The strategy is part of the construction of the device, previously cached entries are discarded.
+    /// # Example
+    /// ```
+    /// use custos::{CacheReturn, CacheStrategy, OpenCL};
+    ///
+    /// fn main() -> custos::Result<()> {
+    ///     let device = OpenCL::new(0)?.with_cache_strategy(CacheStrategy::None);
+    ///     assert_eq!(device.cache_strategy(), CacheStrategy::None);
+    ///     Ok(())
+    /// }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_cache_strategy(mut self, strategy: CacheStrategy) -> OpenCL {
+        self.addons = Addons::per_thread(strategy);
+        self
+    }
+
     /// Sets the values of the attributes cache, kernel cache, graph and CPU to their default.
-    /// This cleans up any accumulated allocations.
+    /// This cleans up any accumulated allocations. The [`CacheStrategy`] is kept.
     pub fn reset(&'static mut self) {
         self.kernel_cache = Default::default();
         self.cpu = Default::default();
-        self.addons = Default::default();
+        self.addons = Addons::per_thread(self.addons.cache_strategy);
     }
 
     /// Context of the OpenCL device.
@@ -96,12 +126,12 @@ impl OpenCL {
 
     /// Returns the name of the OpenCL device.
     pub fn name(&self) -> Result<String, Error> {
-        self.device().get_name()
+        Ok(self.device().get_name()?)
     }
 
     /// Returns the OpenCL version of the device.
     pub fn version(&self) -> Result<String, Error> {
-        self.device().get_version()
+        Ok(self.device().get_version()?)
     }
 
     /// Checks whether the device supports unified memory.
@@ -167,12 +197,26 @@ impl Device for OpenCL {
     fn new() -> crate::Result<Self> {
         OpenCL::new(chosen_cl_idx())
     }
+
+    /// Waits until every command of the command queue has finished (`clFinish`).
+    #[inline]
+    fn synchronize(&self) -> crate::Result<()> {
+        finish(self.queue().clone());
+        Ok(())
+    }
+}
+
+impl MemoryReturn for OpenCL {
+    #[inline]
+    fn memory(&self) -> &MemoryTracker {
+        &self.memory
+    }
 }
 
 impl AddonsReturn for OpenCL {
     #[inline]
     fn addons(&self) -> &Addons<Self> {
-        &self.addons
+        self.addons.local()
     }
 }
 
@@ -191,6 +235,27 @@ impl PtrConv for OpenCL {
             host_ptr: ptr.host_ptr.cast(),
             len: ptr.len,
             flag,
+            allocation: ptr.allocation.clone(),
+        }
+    }
+
+    #[inline]
+    unsafe fn convert_with_len<T, IS, Conv, OS>(
+        ptr: &Self::Ptr<T, IS>,
+        len: usize,
+        flag: AllocFlag,
+    ) -> Self::Ptr<Conv, OS>
+    where
+        IS: Shape,
+        OS: Shape,
+    {
+        debug_assert!(len <= ptr.len);
+        CLPtr {
+            ptr: ptr.ptr,
+            host_ptr: ptr.host_ptr.cast(),
+            len,
+            flag,
+            allocation: ptr.allocation.clone(),
         }
     }
 }
@@ -214,50 +279,75 @@ impl Debug for OpenCL {
 }
 
 impl<T, S: Shape> Alloc<'_, T, S> for OpenCL {
-    fn alloc(&self, mut len: usize, flag: AllocFlag) -> CLPtr<T> {
-        assert!(len > 0, "invalid buffer len: 0");
-
+    #[track_caller]
+    fn try_alloc(&self, mut len: usize, flag: AllocFlag) -> crate::Result<CLPtr<T>> {
         if S::LEN > len {
             len = S::LEN
         }
 
-        let ptr = create_buffer::<T>(self.ctx(), MemFlags::MemReadWrite as u64, len, None).unwrap();
+        if len == 0 {
+            return Err(crate::Error::Alloc {
+                bytes: 0,
+                limit: None,
+            });
+        }
+
+        let bytes = len.saturating_mul(size_of::<T>());
+        let (ptr, allocation) = self.memory.track(bytes, || {
+            Ok(create_buffer::<T>(
+                self.ctx(),
+                MemFlags::MemReadWrite as u64,
+                len,
+                None,
+            )?)
+        })?;
 
         #[cfg(unified_cl)]
-        let host_ptr = unified_ptr::<T>(self.queue(), ptr, len).unwrap();
+        let host_ptr = unified_ptr::<T>(self.queue(), ptr, len)?;
 
         #[cfg(not(unified_cl))]
         let host_ptr = std::ptr::null_mut();
 
-        CLPtr {
+        Ok(CLPtr {
             ptr,
             host_ptr,
             len,
             flag,
-        }
+            allocation: Some(allocation),
+        })
     }
 
-    fn with_slice(&self, data: &[T]) -> CLPtr<T> {
-        let ptr = create_buffer::<T>(
-            self.ctx(),
-            MemFlags::MemReadWrite | MemFlags::MemCopyHostPtr,
-            data.len(),
-            Some(data),
-        )
-        .unwrap();
+    #[track_caller]
+    fn try_with_slice(&self, data: &[T]) -> crate::Result<CLPtr<T>> {
+        if data.is_empty() {
+            return Err(crate::Error::Alloc {
+                bytes: 0,
+                limit: None,
+            });
+        }
+
+        let (ptr, allocation) = self.memory.track(size_of_val(data), || {
+            Ok(create_buffer::<T>(
+                self.ctx(),
+                MemFlags::MemReadWrite | MemFlags::MemCopyHostPtr,
+                data.len(),
+                Some(data),
+            )?)
+        })?;
 
         #[cfg(unified_cl)]
-        let host_ptr = unified_ptr::<T>(self.queue(), ptr, data.len()).unwrap();
+        let host_ptr = unified_ptr::<T>(self.queue(), ptr, data.len())?;
 
         #[cfg(not(unified_cl))]
         let host_ptr = std::ptr::null_mut();
 
-        CLPtr {
+        Ok(CLPtr {
             ptr,
             host_ptr,
             len: data.len(),
             flag: AllocFlag::None,
-        }
+            allocation: Some(allocation),
+        })
     }
 }
 
@@ -295,6 +385,7 @@ mod tests {
             kernel_cache: Default::default(),
             cpu: Default::default(),
             addons: Default::default(),
+            memory: Default::default(),
         };
 
         let buf = Buffer::from((&cl, &[1, 2, 3, 4, 5, 6, 7]));
@@ -307,6 +398,7 @@ mod tests {
             kernel_cache: Default::default(),
             cpu: Default::default(),
             addons: Default::default(),
+            memory: Default::default(),
         };
 
         let buf = Buffer::from((&cl1, &[2, 2, 4, 4, 2, 1, 3]));
@@ -0,0 +1,136 @@
+use std::{ffi::c_void, ptr::null_mut, task::Waker};
+
+use min_cl::api::{
+    enqueue_read_buffer,
+    ffi::{cl_command_queue, cl_event, cl_int, cl_uint, size_t},
+    wait_for_events, Event, OCLErrorKind,
+};
+
+use crate::{Buffer, DeviceEvent, PendingRead, ReadAsync, RecordEvent, Shape};
+
+use super::OpenCL;
+
+const CL_EVENT_COMMAND_EXECUTION_STATUS: cl_uint = 0x11D3;
+const CL_COMPLETE: cl_int = 0x0;
+
+#[cfg_attr(target_os = "macos", link(name = "OpenCL", kind = "framework"))]
+#[cfg_attr(not(target_os = "macos"), link(name = "OpenCL"))]
+extern "system" {
+    fn clEnqueueMarkerWithWaitList(
+        command_queue: cl_command_queue,
+        num_events_in_wait_list: cl_uint,
+        event_wait_list: *const cl_event,
+        event: *mut cl_event,
+    ) -> cl_int;
+
+    fn clEnqueueBarrierWithWaitList(
+        command_queue: cl_command_queue,
+        num_events_in_wait_list: cl_uint,
+        event_wait_list: *const cl_event,
+        event: *mut cl_event,
+    ) -> cl_int;
+
+    fn clSetEventCallback(
+        event: cl_event,
+        command_exec_callback_type: cl_int,
+        pfn_notify: extern "system" fn(cl_event, cl_int, *mut c_void),
+        user_data: *mut c_void,
+    ) -> cl_int;
+
+    fn clGetEventInfo(
+        event: cl_event,
+        param_name: cl_uint,
+        param_value_size: size_t,
+        param_value: *mut c_void,
+        param_value_size_ret: *mut size_t,
+    ) -> cl_int;
+}
+
+/// An OpenCL event, recorded into the command queue of an [`OpenCL`] device.
+#[derive(Debug)]
+pub struct CLEvent {
+    /// The underlying OpenCL event
+    pub event: Event,
+}
+
+impl From<Event> for CLEvent {
+    #[inline]
+    fn from(event: Event) -> Self {
+        CLEvent { event }
+    }
+}
+
+impl DeviceEvent for CLEvent {
+    #[inline]
+    fn wait(&self) -> crate::Result<()> {
+        Ok(wait_for_events(core::slice::from_ref(&self.event))?)
+    }
+
+    fn is_complete(&self) -> bool {
+        let mut status: cl_int = -1;
+        let value = unsafe {
+            clGetEventInfo(
+                self.event.0,
+                CL_EVENT_COMMAND_EXECUTION_STATUS,
+                core::mem::size_of::<cl_int>(),
+                &mut status as *mut cl_int as *mut c_void,
+                null_mut(),
+            )
+        };
+        // an error status (negative value) completes the event as well
+        value == 0 && status <= CL_COMPLETE
+    }
+
+    /// Registers a callback that wakes `waker`, which is called immediately if the event is already complete.
+    fn wake_on_complete(&self, waker: &Waker) {
+        extern "system" fn wake(_event: cl_event, _status: cl_int, user_data: *mut c_void) {
+            unsafe { Box::from_raw(user_data as *mut Waker) }.wake();
+        }
+
+        let user_data = Box::into_raw(Box::new(waker.clone()));
+        let value = unsafe {
+            clSetEventCallback(self.event.0, CL_COMPLETE, wake, user_data as *mut c_void)
+        };
+
+        if value != 0 {
+            // the callback is never called, the future is polled again instead
+            unsafe { Box::from_raw(user_data) }.wake();
+        }
+    }
+}
+
+impl RecordEvent for OpenCL {
+    type Event = CLEvent;
+
+    fn record_event(&self) -> crate::Result<CLEvent> {
+        let mut event = null_mut();
+        let value = unsafe {
+            clEnqueueMarkerWithWaitList(self.queue().0, 0, core::ptr::null(), &mut event)
+        };
+        if value != 0 {
+            return Err(OCLErrorKind::from_value(value).into());
+        }
+        Ok(Event(event).into())
+    }
+
+    fn wait_event(&self, event: &CLEvent) -> crate::Result<()> {
+        let value =
+            unsafe { clEnqueueBarrierWithWaitList(self.queue().0, 1, &event.event.0, null_mut()) };
+        if value != 0 {
+            return Err(OCLErrorKind::from_value(value).into());
+        }
+        Ok(())
+    }
+}
+
+impl<T: Clone + Default, S: Shape> ReadAsync<T, S> for OpenCL {
+    /// Enqueues a non-blocking `clEnqueueReadBuffer`.
+    unsafe fn read_async<'a>(
+        &'a self,
+        buf: &'a Buffer<T, OpenCL, S>,
+    ) -> crate::Result<PendingRead<'a, T>> {
+        let mut data = vec![T::default(); buf.len()];
+        let event = enqueue_read_buffer(self.queue(), buf.cl_ptr(), &mut data, false)?;
+        Ok(PendingRead::new(data, CLEvent::from(event)))
+    }
+}
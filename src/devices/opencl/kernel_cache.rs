@@ -1,8 +1,12 @@
 use crate::{Error, OpenCL};
 use min_cl::api::{
-    build_program, create_kernels_in_program, create_program_with_source, Kernel, OCLErrorKind,
+    build_program, create_kernels_in_program, create_program_with_source,
+    ffi::{clGetProgramBuildInfo, cl_uint},
+    CLIntDevice, Kernel, OCLErrorKind, Program,
 };
-use std::collections::HashMap;
+use std::{collections::HashMap, ffi::c_void, ptr::null_mut};
+
+const CL_PROGRAM_BUILD_LOG: cl_uint = 0x1183;
 
 #[derive(Debug, Default)]
 /// This stores the previously compiled OpenCL kernels.
@@ -43,8 +47,15 @@ impl KernelCacheCL {
             return Ok(kernel);
         }*/
 
+        let _span = crate::trace::span(device, "compile_kernel", src.len(), None);
         let program = create_program_with_source(device.ctx(), src)?;
-        build_program(&program, &[device.device()], Some("-cl-std=CL1.2"))?; //-cl-single-precision-constant
+        //-cl-single-precision-constant
+        if let Err(err) = build_program(&program, &[device.device()], Some("-cl-std=CL1.2")) {
+            return Err(match program_build_log(&program, device.device()) {
+                Some(log) => Error::KernelCompile { log },
+                None => err.into(),
+            });
+        }
 
         let kernel = create_kernels_in_program(&program)?
             .into_iter()
@@ -56,6 +67,43 @@ impl KernelCacheCL {
     }
 }
 
+/// Returns the build log of `program`, or `None` if it is empty or could not be retrieved.
+fn program_build_log(program: &Program, device: CLIntDevice) -> Option<String> {
+    let mut size = 0;
+    let value = unsafe {
+        clGetProgramBuildInfo(
+            program.0,
+            device.0,
+            CL_PROGRAM_BUILD_LOG,
+            0,
+            null_mut(),
+            &mut size,
+        )
+    };
+    if value != 0 || size <= 1 {
+        return None;
+    }
+
+    let mut log = vec![0u8; size];
+    let value = unsafe {
+        clGetProgramBuildInfo(
+            program.0,
+            device.0,
+            CL_PROGRAM_BUILD_LOG,
+            size,
+            log.as_mut_ptr() as *mut c_void,
+            null_mut(),
+        )
+    };
+    if value != 0 {
+        return None;
+    }
+
+    // the log is null terminated
+    log.pop();
+    Some(String::from_utf8_lossy(&log).into_owned())
+}
+
 #[cfg(test)]
 mod tests {
     use super::KernelCacheCL;
@@ -107,4 +155,23 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_kernel_compile_error_contains_log() -> crate::Result<()> {
+        let device = OpenCL::new(0)?;
+        let mut kernel_cache = KernelCacheCL::default();
+
+        let err = kernel_cache
+            .kernel(
+                &device,
+                "__kernel void broken(__global float* x) { x[0] = ; }",
+            )
+            .unwrap_err();
+
+        match err {
+            crate::Error::KernelCompile { log } => assert!(!log.is_empty()),
+            err => panic!("expected a compile error, found: {err}"),
+        }
+        Ok(())
+    }
 }
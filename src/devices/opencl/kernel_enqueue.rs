@@ -75,6 +75,7 @@ pub trait AsClCvoidPtr {
 impl<'a, T, S: Shape> AsClCvoidPtr for &Buffer<'a, T, OpenCL, S> {
     #[inline]
     fn as_cvoid_ptr(&self) -> *const c_void {
+        crate::sanitize(&self.ptr);
         self.ptr.ptr
     }
 }
@@ -82,6 +83,7 @@ impl<'a, T, S: Shape> AsClCvoidPtr for &Buffer<'a, T, OpenCL, S> {
 impl<'a, T, S: Shape> AsClCvoidPtr for Buffer<'a, T, OpenCL, S> {
     #[inline]
     fn as_cvoid_ptr(&self) -> *const c_void {
+        crate::sanitize(&self.ptr);
         self.ptr.ptr
     }
 }
@@ -3,11 +3,13 @@
 use std::{ffi::c_void, ptr::null_mut};
 
 pub use cl_device::{OpenCL, CL};
+pub use event::*;
 pub use kernel_cache::*;
 pub use kernel_enqueue::*;
 
 //pub mod api;
 mod cl_device;
+mod event;
 mod kernel_cache;
 mod kernel_enqueue;
 
@@ -26,7 +28,7 @@ use min_cl::api::release_mem_object;
 pub use unified::*;
 
 //use self::api::release_mem_object;
-use crate::{flag::AllocFlag, Buffer, CommonPtrs, PtrType, ShallowCopy};
+use crate::{flag::AllocFlag, Allocation, Buffer, CommonPtrs, PtrType, ShallowCopy};
 
 /// Another type for Buffer<'a, T, OpenCL, S>
 pub type CLBuffer<'a, T, S = ()> = Buffer<'a, T, OpenCL, S>;
@@ -52,8 +54,15 @@ pub struct CLPtr<T> {
     pub len: usize,
     /// The flag of the memory object
     pub flag: AllocFlag,
+    /// The bytes reserved for the memory object, released when it is freed.
+    pub(crate) allocation: Option<Allocation>,
 }
 
+// SAFETY: OpenCL memory objects can be used from any thread.
+// The host pointer of unified memory behaves like the pointer of a `CPUPtr`.
+unsafe impl<T: Send> Send for CLPtr<T> {}
+unsafe impl<T: Sync> Sync for CLPtr<T> {}
+
 impl<T> Default for CLPtr<T> {
     #[inline]
     fn default() -> Self {
@@ -62,6 +71,7 @@ impl<T> Default for CLPtr<T> {
             host_ptr: null_mut(),
             len: 0,
             flag: AllocFlag::default(),
+            allocation: None,
         }
     }
 }
@@ -74,6 +84,7 @@ impl<T> ShallowCopy for CLPtr<T> {
             host_ptr: self.host_ptr,
             len: self.len,
             flag: AllocFlag::Wrapper,
+            allocation: self.allocation.clone(),
         }
     }
 }
@@ -88,6 +99,11 @@ impl<T> PtrType for CLPtr<T> {
     fn flag(&self) -> AllocFlag {
         self.flag
     }
+
+    #[inline]
+    fn allocation(&self) -> Option<&Allocation> {
+        self.allocation.as_ref()
+    }
 }
 
 impl<T> Drop for CLPtr<T> {
@@ -102,6 +118,10 @@ impl<T> Drop for CLPtr<T> {
         unsafe {
             release_mem_object(self.ptr).unwrap();
         }
+
+        if let Some(allocation) = &self.allocation {
+            allocation.release();
+        }
     }
 }
 
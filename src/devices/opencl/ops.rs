@@ -6,8 +6,10 @@ use min_cl::api::{
 };
 
 use crate::{
-    bounds_to_range, prelude::Number, ApplyFunction, Buffer, CDatatype, ClearBuf, CopySlice,
-    Device, OpenCL, Read, Resolve, Shape, ToCLSource, ToMarker, UnaryGrad, WriteBuf,
+    check_len, checked_range, prelude::Number, Alloc, ApplyFunction, ApplyFunctionTo,
+    BinaryElementWise, BinaryElementWiseTo, Buffer, CDatatype, ClearBuf, CopySlice, Device,
+    Inplace, Named, OpenCL, Read, Reduce, ReduceTo, Resolve, Shape, ToCLSource, ToMarker, Transfer,
+    UnaryGrad, WriteBuf,
 };
 
 use super::{enqueue_kernel, CLBuffer};
@@ -55,34 +57,39 @@ pub fn try_cl_clear<T: CDatatype>(
 
 impl<T, S: Shape> WriteBuf<T, S> for OpenCL {
     #[inline]
-    fn write(&self, buf: &mut Buffer<T, OpenCL, S>, data: &[T]) {
-        let event =
-            unsafe { enqueue_write_buffer(self.queue(), buf.cl_ptr(), data, true).unwrap() };
-        wait_for_event(event).unwrap();
+    fn try_write(&self, buf: &mut Buffer<T, OpenCL, S>, data: &[T]) -> crate::Result<()> {
+        let _span = crate::trace::span(self, "write", core::mem::size_of_val(data), buf.ident);
+        check_len(buf.len(), data.len())?;
+        let event = unsafe { enqueue_write_buffer(self.queue(), buf.cl_ptr(), data, true)? };
+        wait_for_event(event)?;
+        Ok(())
     }
 
     #[inline]
     fn write_buf(&self, dst: &mut Buffer<T, Self, S>, src: &Buffer<T, Self, S>) {
+        let _span = crate::trace::span(
+            self,
+            "write_buf",
+            src.len() * core::mem::size_of::<T>(),
+            dst.ident,
+        );
         debug_assert_eq!(dst.len(), src.len());
         enqueue_full_copy_buffer::<T>(self.queue(), src.cl_ptr(), dst.cl_ptr(), dst.len()).unwrap();
     }
 }
 
 impl<T> CopySlice<T> for OpenCL {
-    fn copy_slice_to<SR: RangeBounds<usize>, DR: RangeBounds<usize>>(
+    fn try_copy_slice_to<SR: RangeBounds<usize>, DR: RangeBounds<usize>>(
         &self,
         source: &Buffer<T, Self>,
         source_range: SR,
         dest: &mut Buffer<T, Self>,
         dest_range: DR,
-    ) {
-        let source_range = bounds_to_range(source_range, source.len());
-        let dest_range = bounds_to_range(dest_range, dest.len());
+    ) -> crate::Result<()> {
+        let source_range = checked_range(source_range, source.len())?;
+        let dest_range = checked_range(dest_range, dest.len())?;
 
-        assert_eq!(
-            source_range.end - source_range.start,
-            dest_range.end - dest_range.start
-        );
+        check_len(source_range.len(), dest_range.len())?;
 
         enqueue_copy_buffer::<T>(
             self.queue(),
@@ -91,8 +98,8 @@ impl<T> CopySlice<T> for OpenCL {
             source_range.start,
             dest_range.start,
             source_range.end - source_range.start,
-        )
-        .unwrap();
+        )?;
+        Ok(())
     }
 
     fn copy_slice_all<I: IntoIterator<Item = (Range<usize>, Range<usize>)>>(
@@ -113,19 +120,32 @@ impl<T> CopySlice<T> for OpenCL {
 
 impl<T: Clone + Default, S: Shape> Read<T, S> for OpenCL {
     #[cfg(not(unified_cl))]
-    type Read<'a> = Vec<T> where T: 'a;
+    type Read<'a>
+        = Vec<T>
+    where
+        T: 'a;
     #[cfg(unified_cl)]
-    type Read<'a> = &'a [T] where T: 'a;
+    type Read<'a>
+        = &'a [T]
+    where
+        T: 'a;
 
     #[cfg(not(unified_cl))]
-    fn read<'a>(&self, buf: &'a Buffer<T, OpenCL, S>) -> Self::Read<'a> {
-        self.read_to_vec(buf)
+    #[inline]
+    fn try_read<'a>(&self, buf: &'a Buffer<T, OpenCL, S>) -> crate::Result<Self::Read<'a>> {
+        try_read_cl_buf_to_vec(self, buf)
     }
 
     #[cfg(unified_cl)]
     #[inline]
-    fn read<'a>(&self, buf: &'a Buffer<T, OpenCL, S>) -> Self::Read<'a> {
-        buf.as_slice()
+    fn try_read<'a>(&self, buf: &'a Buffer<T, OpenCL, S>) -> crate::Result<Self::Read<'a>> {
+        let _span = crate::trace::span(
+            self,
+            "read",
+            buf.len() * core::mem::size_of::<T>(),
+            buf.ident,
+        );
+        Ok(buf.as_slice())
     }
 
     #[inline]
@@ -134,13 +154,38 @@ impl<T: Clone + Default, S: Shape> Read<T, S> for OpenCL {
     }
 }
 
+impl<T: Clone + Default, S: Shape, Dst> Transfer<T, Dst, S> for OpenCL
+where
+    Dst: for<'b> Alloc<'b, T, S>,
+{
+    fn try_transfer<'b>(
+        &self,
+        buf: &Buffer<T, OpenCL, S>,
+        dst: &'b Dst,
+    ) -> crate::Result<Buffer<'b, T, Dst, S>> {
+        // with unified memory, the data is copied from the host pointer without staging
+        if self.unified_mem() {
+            let host = unsafe { core::slice::from_raw_parts(buf.ptr.host_ptr, buf.len()) };
+            return Buffer::try_from_slice(dst, host);
+        }
+
+        Buffer::try_from_vec(dst, try_read_cl_buf_to_vec(self, buf)?)
+    }
+}
+
 fn try_read_cl_buf_to_vec<T: Clone + Default, S: Shape>(
     device: &OpenCL,
     buf: &Buffer<T, OpenCL, S>,
 ) -> crate::Result<Vec<T>> {
+    let _span = crate::trace::span(
+        device,
+        "read",
+        buf.len() * core::mem::size_of::<T>(),
+        buf.ident,
+    );
     let mut read = vec![T::default(); buf.len()];
     let event = unsafe { enqueue_read_buffer(device.queue(), buf.cl_ptr(), &mut read, false)? };
-    wait_for_event(event).unwrap();
+    wait_for_event(event)?;
     Ok(read)
 }
 
@@ -150,25 +195,78 @@ where
     S: Shape,
 {
     #[inline]
-    fn apply_fn<F>(
+    #[track_caller]
+    fn try_apply_fn<F>(
         &self,
         buf: &Buffer<T, Self, S>,
         f: impl Fn(Resolve<T>) -> F,
-    ) -> Buffer<T, Self, S>
+    ) -> crate::Result<Buffer<T, Self, S>>
     where
         F: ToCLSource,
     {
-        try_cl_apply_fn(self, buf, f).unwrap()
+        let mut span = crate::trace::span(
+            self,
+            "apply_fn",
+            buf.len() * core::mem::size_of::<T>(),
+            None,
+        );
+        let out = try_cl_apply_fn(self, buf, f)?;
+        span.set_ident(out.ident);
+        Ok(out)
     }
 }
 
 /// A failable OpenCL version of [`apply_fn`](ApplyFunction::apply_fn).
 /// It applies a function to a buffer and returns a new buffer.
+#[track_caller]
 pub fn try_cl_apply_fn<'a, T, S, F: ToCLSource>(
     device: &'a OpenCL,
     x: &CLBuffer<T, S>,
     f: impl Fn(Resolve<T>) -> F,
 ) -> crate::Result<CLBuffer<'a, T, S>>
+where
+    T: CDatatype + Number,
+    S: Shape,
+{
+    let mut out = device.try_retrieve::<T, S>(x.len(), Inplace(Named("apply_fn", x)))?;
+    try_cl_apply_fn_to(device, x, &mut out, f)?;
+    Ok(out)
+}
+
+impl<T, S> ApplyFunctionTo<T, S> for OpenCL
+where
+    T: CDatatype + Number,
+    S: Shape,
+{
+    #[inline]
+    fn try_apply_fn_to<F>(
+        &self,
+        buf: &Buffer<T, Self, S>,
+        out: &mut Buffer<T, Self, S>,
+        f: impl Fn(Resolve<T>) -> F,
+    ) -> crate::Result<()>
+    where
+        F: ToCLSource,
+    {
+        check_len(out.len(), buf.len())?;
+        let _span = crate::trace::span(
+            self,
+            "apply_fn",
+            buf.len() * core::mem::size_of::<T>(),
+            out.ident,
+        );
+        try_cl_apply_fn_to(self, buf, out, f)
+    }
+}
+
+/// A failable OpenCL version of [`apply_fn_to`](ApplyFunctionTo::apply_fn_to).
+/// It applies a function to a buffer and writes the result to `out`.
+pub fn try_cl_apply_fn_to<T, S, F: ToCLSource>(
+    device: &OpenCL,
+    x: &CLBuffer<T, S>,
+    out: &mut CLBuffer<T, S>,
+    f: impl Fn(Resolve<T>) -> F,
+) -> crate::Result<()>
 where
     T: CDatatype + Number,
     S: Shape,
@@ -184,9 +282,177 @@ where
         operation = f("lhs[id]".to_marker()).to_cl_source()
     );
 
-    let out = device.retrieve::<T, S>(x.len(), x);
-    enqueue_kernel(device, &src, [x.len(), 0, 0], None, &[x, &out])?;
-    Ok(out)
+    enqueue_kernel(device, &src, [x.len(), 0, 0], None, &[x, out])
+}
+
+impl<T, S> BinaryElementWise<T, S> for OpenCL
+where
+    T: CDatatype + Number,
+    S: Shape,
+{
+    #[track_caller]
+    fn try_binary_ew<F>(
+        &self,
+        lhs: &Buffer<T, Self, S>,
+        rhs: &Buffer<T, Self, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<T, Self, S>>
+    where
+        F: ToCLSource,
+    {
+        check_len(lhs.len(), rhs.len())?;
+        let mut span = crate::trace::span(
+            self,
+            "binary_ew",
+            lhs.len() * core::mem::size_of::<T>(),
+            None,
+        );
+        let mut out = self.try_retrieve::<T, S>(lhs.len(), Named("binary_ew", (lhs, rhs)))?;
+        span.set_ident(out.ident);
+        try_cl_binary_ew_to(self, lhs, rhs, &mut out, f)?;
+        Ok(out)
+    }
+}
+
+impl<T, S> BinaryElementWiseTo<T, S> for OpenCL
+where
+    T: CDatatype + Number,
+    S: Shape,
+{
+    fn try_binary_ew_to<F>(
+        &self,
+        lhs: &Buffer<T, Self, S>,
+        rhs: &Buffer<T, Self, S>,
+        out: &mut Buffer<T, Self, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<()>
+    where
+        F: ToCLSource,
+    {
+        check_len(out.len(), lhs.len())?;
+        check_len(out.len(), rhs.len())?;
+        let _span = crate::trace::span(
+            self,
+            "binary_ew",
+            out.len() * core::mem::size_of::<T>(),
+            out.ident,
+        );
+        try_cl_binary_ew_to(self, lhs, rhs, out, f)
+    }
+}
+
+/// A failable OpenCL version of [`binary_ew_to`](BinaryElementWiseTo::binary_ew_to).
+/// It applies a function to the elements of two buffers and writes the result to `out`.
+pub fn try_cl_binary_ew_to<T, S, F: ToCLSource>(
+    device: &OpenCL,
+    lhs: &CLBuffer<T, S>,
+    rhs: &CLBuffer<T, S>,
+    out: &mut CLBuffer<T, S>,
+    f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+) -> crate::Result<()>
+where
+    T: CDatatype + Number,
+    S: Shape,
+{
+    let src = format!(
+        "
+        __kernel void binary_ew(__global const {datatype}* lhs, __global const {datatype}* rhs, __global {datatype}* out) {{
+            size_t id = get_global_id(0);
+            out[id] = {operation};
+        }}
+    ",
+        datatype = T::as_c_type_str(),
+        operation = f("lhs[id]".to_marker(), "rhs[id]".to_marker()).to_cl_source()
+    );
+
+    enqueue_kernel(device, &src, [out.len(), 0, 0], None, &[lhs, rhs, out])
+}
+
+impl<T, S> Reduce<T, S> for OpenCL
+where
+    T: CDatatype + Number,
+    S: Shape,
+{
+    #[track_caller]
+    fn try_reduce<F>(
+        &self,
+        buf: &Buffer<T, Self, S>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<T, Self>>
+    where
+        F: ToCLSource,
+    {
+        let mut span =
+            crate::trace::span(self, "reduce", buf.len() * core::mem::size_of::<T>(), None);
+        let mut out = self.try_retrieve::<T, ()>(1, Named("reduce", buf))?;
+        span.set_ident(out.ident);
+        try_cl_reduce_to(self, buf, &mut out, init, f)?;
+        Ok(out)
+    }
+}
+
+impl<T, S> ReduceTo<T, S> for OpenCL
+where
+    T: CDatatype + Number,
+    S: Shape,
+{
+    fn try_reduce_to<F>(
+        &self,
+        buf: &Buffer<T, Self, S>,
+        out: &mut Buffer<T, Self>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<()>
+    where
+        F: ToCLSource,
+    {
+        check_len(1, out.len())?;
+        let _span = crate::trace::span(
+            self,
+            "reduce",
+            buf.len() * core::mem::size_of::<T>(),
+            out.ident,
+        );
+        try_cl_reduce_to(self, buf, out, init, f)
+    }
+}
+
+/// A failable OpenCL version of [`reduce_to`](ReduceTo::reduce_to).
+/// It folds the elements of a buffer in order and writes the result to the only element of `out`.
+pub fn try_cl_reduce_to<T, S, F: ToCLSource>(
+    device: &OpenCL,
+    x: &CLBuffer<T, S>,
+    out: &mut CLBuffer<T>,
+    init: T,
+    f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+) -> crate::Result<()>
+where
+    T: CDatatype + Number,
+    S: Shape,
+{
+    // a single work item folds all elements, which keeps the order of `f`
+    let src = format!(
+        "
+        __kernel void reduce(__global const {datatype}* x, __global {datatype}* out, const {datatype} init, const int len) {{
+            {datatype} acc = init;
+            for (int i = 0; i < len; i++) {{
+                acc = {operation};
+            }}
+            out[0] = acc;
+        }}
+    ",
+        datatype = T::as_c_type_str(),
+        operation = f("acc".to_marker(), "x[i]".to_marker()).to_cl_source()
+    );
+
+    enqueue_kernel(
+        device,
+        &src,
+        [1, 0, 0],
+        None,
+        &[x, out, &init, &(x.len() as i32)],
+    )
 }
 
 impl<T, S> UnaryGrad<T, S> for OpenCL
@@ -241,7 +507,7 @@ where
 mod test {
     use crate::{
         opencl::{try_cl_add_unary_grad, try_cl_apply_fn},
-        Buffer, Combiner, OpenCL,
+        BinaryElementWise, Buffer, Combiner, OpenCL, Reduce,
     };
 
     #[test]
@@ -271,4 +537,29 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_cl_binary_ew() -> crate::Result<()> {
+        let device = OpenCL::new(0)?;
+
+        let lhs = Buffer::from((&device, [1, 2, 3, 4]));
+        let rhs = Buffer::from((&device, [4, 3, 2, 1]));
+
+        let out = device.try_binary_ew(&lhs, &rhs, |lhs, rhs| lhs.mul(rhs).add(1))?;
+        assert_eq!(out.read(), [5, 7, 7, 5]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cl_reduce() -> crate::Result<()> {
+        let device = OpenCL::new(0)?;
+
+        let buf = Buffer::from((&device, [1, 2, 3, 4, 5, 6]));
+
+        let sum = device.try_reduce(&buf, 1, |acc, x| acc.add(x))?;
+        assert_eq!(sum.read(), [22]);
+
+        Ok(())
+    }
 }
@@ -1,4 +1,4 @@
-use std::{ffi::c_void, rc::Rc};
+use std::{ffi::c_void, sync::Arc};
 
 #[cfg(not(feature = "realloc"))]
 use crate::{AddGraph, AllocFlag, DeviceError, GraphReturn};
@@ -23,13 +23,18 @@ pub unsafe fn to_cached_unified<T, S: Shape>(
         Some(&no_drop),
     )?;
 
-    let old_ptr = device.addons.cache.borrow_mut().nodes.insert(
-        Ident::new(no_drop.len()),
-        Rc::new(CLPtr {
+    let ident = Ident::new(no_drop.len());
+    let mut cache = device.addons.cache.borrow_mut();
+    cache.track_use::<T, S>(ident);
+
+    let old_ptr = cache.insert(
+        ident,
+        Arc::new(CLPtr {
             ptr: cl_ptr,
             host_ptr: no_drop.host_ptr() as *mut u8,
             len: no_drop.len(),
             flag: AllocFlag::None,
+            allocation: None,
         }),
     );
 
@@ -90,6 +95,7 @@ pub unsafe fn construct_buffer<'a, T, S: Shape>(
                 host_ptr: rawcl.host_ptr as *mut T,
                 len: no_drop.len(),
                 flag: no_drop.ptr.flag,
+                allocation: None,
             },
             device: Some(device),
             ident: Some(Ident::new(no_drop.len())),
@@ -110,6 +116,7 @@ pub unsafe fn construct_buffer<'a, T, S: Shape>(
             host_ptr,
             len,
             flag: AllocFlag::Wrapper,
+            allocation: None,
         },
         device: Some(device),
         ident: Some(Ident {
@@ -143,6 +150,7 @@ mod tests {
                 host_ptr,
                 len,
                 flag: AllocFlag::Wrapper,
+                allocation: None,
             },
             device: Some(&device),
             ident: Some(Ident::new_bumped(len)),
@@ -172,7 +180,7 @@ mod tests {
     #[cfg(not(feature = "realloc"))]
     #[test]
     fn test_cpu_to_unified_leak() -> crate::Result<()> {
-        use std::{collections::HashMap, hash::BuildHasherDefault, rc::Rc};
+        use std::{collections::HashMap, hash::BuildHasherDefault, sync::Arc};
 
         use crate::{range, set_count, Device, Ident, IdentHasher};
 
@@ -190,7 +198,7 @@ mod tests {
                 let mut hm = HashMap::<Ident, _, BuildHasherDefault<IdentHasher>>::default();
                 std::mem::swap(&mut cpu.addons.cache.borrow_mut().nodes, &mut hm);
                 for mut value in hm {
-                    let ptr = Rc::get_mut(&mut value.1).unwrap();
+                    let ptr = Arc::get_mut(&mut value.1).unwrap();
                     ptr.ptr = std::ptr::null_mut();
                 }
                 cl_cpu_buf
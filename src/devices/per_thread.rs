@@ -0,0 +1,154 @@
+use core::{fmt::Debug, ops::Deref};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    thread::{self, ThreadId},
+};
+
+/// Stores a separate value of `T` for every thread that accesses it.
+/// The thread that created the `PerThread` uses an inline value; other threads receive their own value on their first access.
+/// These values are created with [`Default`] or the function passed to [`with_init`](PerThread::with_init).
+/// Therefore, a value is never accessed by two threads at the same time and `PerThread<T>` is `Sync` as long as `T` is `Send`.
+/// This is used to give every thread its own [`Addons`](crate::Addons) (and therefore its own caches) of a shared device.
+///
+/// The inline value is accessed without locking, the values of other threads are looked up in a map behind a [`Mutex`].
+/// The values of other threads are kept until the `PerThread` is dropped, even if their thread exited.
+/// Hence, a device should be shared by a fixed set of threads, e.g. a thread pool, rather than a new thread per task.
+/// # Example
+/// ```
+/// use std::cell::Cell;
+/// use custos::PerThread;
+///
+/// let counter = PerThread::<Cell<usize>>::default();
+/// counter.set(3);
+///
+/// std::thread::scope(|s| {
+///     s.spawn(|| {
+///         assert_eq!(counter.get(), 0);
+///         counter.set(5);
+///     });
+/// });
+///
+/// assert_eq!(counter.get(), 3);
+/// assert_eq!(counter.threads(), 2);
+/// ```
+pub struct PerThread<T> {
+    owner: ThreadId,
+    value: T,
+    // The boxed values are only inserted, never moved out or removed before the `PerThread` is dropped.
+    others: Mutex<HashMap<ThreadId, Box<T>>>,
+    init: Init<T>,
+}
+
+/// Creates the values of threads other than the owner.
+enum Init<T> {
+    Default(fn() -> T),
+    With(Box<dyn Fn() -> T + Send + Sync>),
+}
+
+impl<T: Default> PerThread<T> {
+    /// Creates a `PerThread` that uses `value` for the current thread. Other threads start with `T::default()`.
+    #[inline]
+    pub fn new(value: T) -> PerThread<T> {
+        PerThread::with_value(value, Init::Default(T::default))
+    }
+}
+
+impl<T> PerThread<T> {
+    /// Creates a `PerThread` that calls `init` to create the value of every thread, including the current one.
+    /// # Example
+    /// ```
+    /// use custos::PerThread;
+    ///
+    /// let value = PerThread::with_init(|| 7);
+    ///
+    /// std::thread::scope(|s| {
+    ///     s.spawn(|| assert_eq!(*value, 7));
+    /// });
+    /// assert_eq!(*value, 7);
+    /// ```
+    #[inline]
+    pub fn with_init(init: impl Fn() -> T + Send + Sync + 'static) -> PerThread<T> {
+        PerThread::with_value(init(), Init::With(Box::new(init)))
+    }
+
+    fn with_value(value: T, init: Init<T>) -> PerThread<T> {
+        PerThread {
+            owner: thread::current().id(),
+            value,
+            others: Default::default(),
+            init,
+        }
+    }
+
+    /// Returns the value of the current thread, if it was already created.
+    pub fn try_local(&self) -> Option<&T> {
+        let id = thread::current().id();
+        if id == self.owner {
+            return Some(&self.value);
+        }
+
+        let others = self.others.lock().unwrap_or_else(|err| err.into_inner());
+        // SAFETY: The boxed value is never moved or removed while `self` is borrowed.
+        others
+            .get(&id)
+            .map(|value| unsafe { &*(&**value as *const T) })
+    }
+
+    /// Returns the amount of values, including the value of the owning thread.
+    pub fn threads(&self) -> usize {
+        self.others
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .len()
+            + 1
+    }
+
+    /// Returns the value of the current thread. It is created if this is the first access of the thread.
+    pub fn local(&self) -> &T {
+        let id = thread::current().id();
+        if id == self.owner {
+            return &self.value;
+        }
+
+        let mut others = self.others.lock().unwrap_or_else(|err| err.into_inner());
+        let value = others.entry(id).or_insert_with(|| {
+            Box::new(match &self.init {
+                Init::Default(init) => init(),
+                Init::With(init) => init(),
+            })
+        });
+        // SAFETY: The boxed value is never moved or removed while `self` is borrowed.
+        unsafe { &*(&**value as *const T) }
+    }
+}
+
+impl<T: Default> Default for PerThread<T> {
+    #[inline]
+    fn default() -> Self {
+        PerThread::new(T::default())
+    }
+}
+
+impl<T> Deref for PerThread<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.local()
+    }
+}
+
+impl<T: Debug> Debug for PerThread<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PerThread")
+            .field("local", &self.try_local())
+            .field("threads", &self.threads())
+            .finish()
+    }
+}
+
+// SAFETY: Each value is only accessed by the thread it belongs to, as `local` and `try_local` only return the value of the current thread.
+// All values are dropped by the thread that drops the `PerThread`, hence `T: Send` is required.
+unsafe impl<T: Send> Send for PerThread<T> {}
+unsafe impl<T: Send> Sync for PerThread<T> {}
@@ -0,0 +1,162 @@
+//! The sim module provides the [`SimDevice`], a host memory backed device that behaves like a GPU.
+//!
+//! Its memory is not accessible as a slice ([`SimDevice`] does not implement [`MainMemory`](crate::MainMemory)),
+//! every copy between host and device is explicit and counted, and each copy can be delayed by an artificial latency.
+//! This allows testing code that is generic over `D: Device` without a GPU.
+
+mod ops;
+mod sim_device;
+
+pub use sim_device::*;
+
+use core::{alloc::Layout, marker::PhantomData, ptr::null_mut};
+
+use crate::{flag::AllocFlag, Allocation, MemoryTracker, PtrType, ShallowCopy};
+
+/// The alignment of every [`SimPtr`] allocation.
+/// A fixed alignment allows converting a [`SimPtr`] to another type without tracking the original layout.
+const SIM_ALIGN: usize = 16;
+
+/// The opaque pointer used for [`SimDevice`] [`Buffer`](crate::Buffer)s.
+/// The memory can only be accessed through the device, e.g. with [`Read`](crate::Read) or [`WriteBuf`](crate::WriteBuf).
+#[derive(Debug, PartialEq, Eq)]
+pub struct SimPtr<T> {
+    ptr: *mut u8,
+    len: usize,
+    /// The size of the allocation in bytes. Required to free the memory of converted pointers.
+    bytes: usize,
+    flag: AllocFlag,
+    /// The bytes reserved for the memory, released when the memory is freed.
+    allocation: Option<Allocation>,
+    _p: PhantomData<T>,
+}
+
+// SAFETY: A `SimPtr` behaves like a `Box<[T]>` (or a reference to one if it is not the owner).
+unsafe impl<T: Send> Send for SimPtr<T> {}
+unsafe impl<T: Sync> Sync for SimPtr<T> {}
+
+impl<T> SimPtr<T> {
+    /// Allocates zeroed memory for `len` elements of type `T`. The allocation is counted by `tracker`.
+    /// Returns an error if the memory could not be allocated or the limit of `tracker` would be exceeded.
+    #[track_caller]
+    pub(crate) fn try_new_zeroed(
+        len: usize,
+        flag: AllocFlag,
+        tracker: &MemoryTracker,
+    ) -> crate::Result<SimPtr<T>> {
+        assert!(
+            core::mem::align_of::<T>() <= SIM_ALIGN,
+            "SimDevice does not support types with an alignment greater than {SIM_ALIGN}"
+        );
+
+        let bytes = len
+            .checked_mul(core::mem::size_of::<T>())
+            .ok_or(crate::Error::Alloc {
+                bytes: usize::MAX,
+                limit: None,
+            })?
+            .max(1);
+        let layout = Layout::from_size_align(bytes, SIM_ALIGN)
+            .map_err(|_| crate::Error::Alloc { bytes, limit: None })?;
+
+        let (ptr, allocation) = tracker.track(bytes, || {
+            let ptr = unsafe { std::alloc::alloc_zeroed(layout) };
+            if ptr.is_null() {
+                return Err(crate::Error::Alloc { bytes, limit: None });
+            }
+            Ok(ptr)
+        })?;
+
+        Ok(SimPtr {
+            ptr,
+            len,
+            bytes,
+            flag,
+            allocation: Some(allocation),
+            _p: PhantomData,
+        })
+    }
+
+    #[inline]
+    pub(crate) fn as_ptr(&self) -> *const T {
+        crate::sanitize(self);
+        self.ptr.cast()
+    }
+
+    #[inline]
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut T {
+        crate::sanitize(self);
+        self.ptr.cast()
+    }
+
+    /// Returns a pointer of another type that points to the same memory.
+    /// # Safety
+    /// The returned pointer must not be dropped as owner if `self` is the owner as well.
+    pub(crate) unsafe fn cast<Conv>(&self, len: usize, flag: AllocFlag) -> SimPtr<Conv> {
+        SimPtr {
+            ptr: self.ptr,
+            len,
+            bytes: self.bytes,
+            flag,
+            allocation: self.allocation.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for SimPtr<T> {
+    #[inline]
+    fn default() -> Self {
+        SimPtr {
+            ptr: null_mut(),
+            len: 0,
+            bytes: 0,
+            flag: AllocFlag::default(),
+            allocation: None,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for SimPtr<T> {
+    fn drop(&mut self) {
+        if !matches!(self.flag, AllocFlag::None | AllocFlag::BorrowedCache) {
+            return;
+        }
+
+        if self.ptr.is_null() {
+            return;
+        }
+
+        let layout = Layout::from_size_align(self.bytes, SIM_ALIGN).unwrap();
+        unsafe { std::alloc::dealloc(self.ptr, layout) };
+
+        if let Some(allocation) = &self.allocation {
+            allocation.release();
+        }
+    }
+}
+
+impl<T> PtrType for SimPtr<T> {
+    #[inline]
+    fn size(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    fn flag(&self) -> AllocFlag {
+        self.flag
+    }
+
+    #[inline]
+    fn allocation(&self) -> Option<&Allocation> {
+        self.allocation.as_ref()
+    }
+}
+
+impl<T> ShallowCopy for SimPtr<T> {
+    #[inline]
+    unsafe fn shallow(&self) -> Self {
+        self.cast(self.len, AllocFlag::Wrapper)
+    }
+}
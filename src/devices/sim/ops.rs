@@ -0,0 +1,125 @@
+use core::ops::{Range, RangeBounds};
+
+use crate::{
+    check_len, checked_range, Alloc, Buffer, CDatatype, ClearBuf, CloneBuf, CopySlice, Read, Shape,
+    Transfer, WriteBuf,
+};
+
+use super::SimDevice;
+
+impl<T: Clone + Default, S: Shape> Read<T, S> for SimDevice {
+    type Read<'a>
+        = Vec<T>
+    where
+        T: 'a,
+        S: 'a;
+
+    #[inline]
+    fn try_read(&self, buf: &Buffer<T, SimDevice, S>) -> crate::Result<Vec<T>> {
+        Ok(self.read_to_vec(buf))
+    }
+
+    fn read_to_vec(&self, buf: &Buffer<T, SimDevice, S>) -> Vec<T>
+    where
+        T: Default + Clone,
+    {
+        let _span = crate::trace::span(
+            self,
+            "read",
+            buf.len() * core::mem::size_of::<T>(),
+            buf.ident,
+        );
+        self.copy_to_host(buf.len() * core::mem::size_of::<T>());
+        unsafe { std::slice::from_raw_parts(buf.ptr.as_ptr(), buf.len()) }.to_vec()
+    }
+}
+
+impl<T: Clone, S: Shape> WriteBuf<T, S> for SimDevice {
+    fn try_write(&self, buf: &mut Buffer<T, SimDevice, S>, data: &[T]) -> crate::Result<()> {
+        let _span = crate::trace::span(self, "write", core::mem::size_of_val(data), buf.ident);
+        check_len(buf.len(), data.len())?;
+
+        self.copy_to_device(core::mem::size_of_val(data));
+        unsafe { std::slice::from_raw_parts_mut(buf.ptr.as_mut_ptr(), buf.len()) }
+            .clone_from_slice(data);
+        Ok(())
+    }
+
+    /// Copies `src` to `dst` without leaving device memory, hence it is not counted as transfer.
+    fn write_buf(&self, dst: &mut Buffer<T, SimDevice, S>, src: &Buffer<T, SimDevice, S>) {
+        let _span = crate::trace::span(
+            self,
+            "write_buf",
+            src.len() * core::mem::size_of::<T>(),
+            dst.ident,
+        );
+        let src = unsafe { std::slice::from_raw_parts(src.ptr.as_ptr(), src.len()) };
+        unsafe { std::slice::from_raw_parts_mut(dst.ptr.as_mut_ptr(), dst.len()) }
+            .clone_from_slice(src)
+    }
+}
+
+impl<T: Clone + Default, S: Shape, Dst> Transfer<T, Dst, S> for SimDevice
+where
+    Dst: for<'b> Alloc<'b, T, S>,
+{
+    #[inline]
+    fn try_transfer<'b>(
+        &self,
+        buf: &Buffer<T, SimDevice, S>,
+        dst: &'b Dst,
+    ) -> crate::Result<Buffer<'b, T, Dst, S>> {
+        Buffer::try_from_vec(dst, self.try_read(buf)?)
+    }
+}
+
+impl<T: CDatatype> ClearBuf<T> for SimDevice {
+    #[inline]
+    fn clear(&self, buf: &mut Buffer<T, SimDevice>) {
+        unsafe { core::ptr::write_bytes(buf.ptr.as_mut_ptr(), 0, buf.len()) }
+    }
+}
+
+impl<T: Clone> CopySlice<T> for SimDevice {
+    fn try_copy_slice_to<SR: RangeBounds<usize>, DR: RangeBounds<usize>>(
+        &self,
+        source: &Buffer<T, Self>,
+        source_range: SR,
+        dest: &mut Buffer<T, Self>,
+        dest_range: DR,
+    ) -> crate::Result<()> {
+        let source_range = checked_range(source_range, source.len())?;
+        let dest_range = checked_range(dest_range, dest.len())?;
+        check_len(source_range.len(), dest_range.len())?;
+
+        let source = unsafe { std::slice::from_raw_parts(source.ptr.as_ptr(), source.len()) };
+        let dest = unsafe { std::slice::from_raw_parts_mut(dest.ptr.as_mut_ptr(), dest.len()) };
+        dest[dest_range].clone_from_slice(&source[source_range]);
+        Ok(())
+    }
+
+    fn copy_slice_all<I: IntoIterator<Item = (Range<usize>, Range<usize>)>>(
+        &self,
+        source: &Buffer<T, Self>,
+        dest: &mut Buffer<T, Self>,
+        ranges: I,
+    ) {
+        for (source_range, dest_range) in ranges {
+            self.copy_slice_to(source, source_range, dest, dest_range);
+        }
+    }
+}
+
+impl<'a, T: Clone, S: Shape> CloneBuf<'a, T, S> for SimDevice {
+    fn clone_buf(&'a self, buf: &Buffer<'a, T, SimDevice, S>) -> Buffer<'a, T, SimDevice, S> {
+        let mut cloned = Buffer::new(self, buf.len());
+        self.write_buf(&mut cloned, buf);
+        cloned
+    }
+}
+
+#[cfg(feature = "cpu")]
+impl crate::exec_on_cpu::CPUFallback for SimDevice {}
+
+#[cfg(feature = "cpu")]
+impl crate::exec_on_cpu::FallbackOps for SimDevice {}
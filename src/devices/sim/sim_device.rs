@@ -0,0 +1,253 @@
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use crate::{
+    devices::cache::Cache, flag::AllocFlag, shape::Shape, Addons, AddonsReturn, Alloc, Device,
+    MemoryReturn, MemoryTracker, PerThread, PtrConv,
+};
+
+use super::SimPtr;
+
+/// The number of copies between host and [`SimDevice`] memory.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TransferStats {
+    /// The number of copies from host to device memory.
+    pub to_device: usize,
+    /// The number of copies from device to host memory.
+    pub to_host: usize,
+    /// The amount of bytes copied from host to device memory.
+    pub bytes_to_device: usize,
+    /// The amount of bytes copied from device to host memory.
+    pub bytes_to_host: usize,
+}
+
+#[derive(Debug, Default)]
+struct TransferCounter {
+    to_device: AtomicUsize,
+    to_host: AtomicUsize,
+    bytes_to_device: AtomicUsize,
+    bytes_to_host: AtomicUsize,
+}
+
+/// A device that simulates a GPU with host memory. Enabled by the `sim` feature.
+///
+/// [`SimDevice`] does not implement [`MainMemory`](crate::MainMemory), hence code that runs on a `SimDevice`
+/// takes the same paths as it would on a GPU: Data is copied explicitly between host and device memory.
+/// These copies are counted in [`TransferStats`] and may be slowed down with an artificial latency.
+///
+/// Besides reading and writing, `ClearBuf`, `CopySlice` and `CloneBuf` are implemented natively.
+/// Other operations, e.g. `ApplyFunction`, are executed via [`FallbackOps`](crate::exec_on_cpu::FallbackOps) (with the `cpu` and `macro` features).
+/// # Example
+/// ```
+/// use custos::{Buffer, SimDevice};
+///
+/// let device = SimDevice::new();
+/// let buf = Buffer::from((&device, [1, 2, 3]));
+///
+/// assert_eq!(buf.read(), vec![1, 2, 3]);
+///
+/// let stats = device.transfer_stats();
+/// assert_eq!(stats.to_device, 1);
+/// assert_eq!(stats.to_host, 1);
+/// assert_eq!(stats.bytes_to_host, 3 * std::mem::size_of::<i32>());
+/// ```
+#[derive(Debug)]
+pub struct SimDevice {
+    /// Provides additional functionality for the `SimDevice`. e.g. a cache, a gradient [`Tape`](crate::Tape) or an optimizeable [`Graph`](crate::Graph).
+    pub addons: PerThread<Addons<SimDevice>>,
+    latency: Option<Duration>,
+    transfers: TransferCounter,
+    memory: MemoryTracker,
+}
+
+impl SimDevice {
+    /// Creates a [`SimDevice`] without latency.
+    #[must_use]
+    pub fn new() -> SimDevice {
+        SimDevice {
+            addons: PerThread::default(),
+            latency: None,
+            transfers: TransferCounter::default(),
+            memory: MemoryTracker::default(),
+        }
+    }
+
+    /// Creates a [`SimDevice`] that sleeps for `latency` on every copy between host and device memory.
+    /// # Example
+    /// ```
+    /// use std::time::{Duration, Instant};
+    /// use custos::{Buffer, SimDevice};
+    ///
+    /// let device = SimDevice::with_latency(Duration::from_millis(5));
+    ///
+    /// let start = Instant::now();
+    /// let buf = Buffer::from((&device, [1, 2, 3]));
+    /// assert!(start.elapsed() >= Duration::from_millis(5));
+    /// ```
+    #[must_use]
+    pub fn with_latency(latency: Duration) -> SimDevice {
+        SimDevice {
+            latency: Some(latency),
+            ..SimDevice::new()
+        }
+    }
+
+    /// Sets the [`CacheStrategy`](crate::CacheStrategy) that is used by [`Device::retrieve`] on every thread.
+    /// The strategy is part of the construction of the device, previously cached entries are discarded.
+    #[inline]
+    #[must_use]
+    pub fn with_cache_strategy(mut self, strategy: crate::CacheStrategy) -> SimDevice {
+        self.addons = Addons::per_thread(strategy);
+        self
+    }
+
+    /// Returns the artificial latency of a copy between host and device memory.
+    #[inline]
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    /// Sets the artificial latency of a copy between host and device memory.
+    #[inline]
+    pub fn set_latency(&mut self, latency: Option<Duration>) {
+        self.latency = latency;
+    }
+
+    /// Returns the copies between host and device memory since the creation of the device or the last [`SimDevice::reset_transfer_stats`].
+    pub fn transfer_stats(&self) -> TransferStats {
+        TransferStats {
+            to_device: self.transfers.to_device.load(Ordering::Relaxed),
+            to_host: self.transfers.to_host.load(Ordering::Relaxed),
+            bytes_to_device: self.transfers.bytes_to_device.load(Ordering::Relaxed),
+            bytes_to_host: self.transfers.bytes_to_host.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Sets all transfer counters to zero.
+    pub fn reset_transfer_stats(&self) {
+        self.transfers.to_device.store(0, Ordering::Relaxed);
+        self.transfers.to_host.store(0, Ordering::Relaxed);
+        self.transfers.bytes_to_device.store(0, Ordering::Relaxed);
+        self.transfers.bytes_to_host.store(0, Ordering::Relaxed);
+    }
+
+    /// Records a copy of `bytes` bytes from host to device memory.
+    pub(crate) fn copy_to_device(&self, bytes: usize) {
+        self.transfers.to_device.fetch_add(1, Ordering::Relaxed);
+        self.transfers
+            .bytes_to_device
+            .fetch_add(bytes, Ordering::Relaxed);
+        self.wait();
+    }
+
+    /// Records a copy of `bytes` bytes from device to host memory.
+    pub(crate) fn copy_to_host(&self, bytes: usize) {
+        self.transfers.to_host.fetch_add(1, Ordering::Relaxed);
+        self.transfers
+            .bytes_to_host
+            .fetch_add(bytes, Ordering::Relaxed);
+        self.wait();
+    }
+
+    #[inline]
+    fn wait(&self) {
+        if let Some(latency) = self.latency {
+            std::thread::sleep(latency);
+        }
+    }
+}
+
+impl Default for SimDevice {
+    #[inline]
+    fn default() -> Self {
+        SimDevice::new()
+    }
+}
+
+impl Device for SimDevice {
+    type Ptr<U, S: Shape> = SimPtr<U>;
+    type Cache = Cache<SimDevice>;
+
+    fn new() -> crate::Result<Self> {
+        Ok(Self::new())
+    }
+}
+
+impl MemoryReturn for SimDevice {
+    #[inline]
+    fn memory(&self) -> &MemoryTracker {
+        &self.memory
+    }
+}
+
+impl AddonsReturn for SimDevice {
+    #[inline]
+    fn addons(&self) -> &Addons<Self> {
+        self.addons.local()
+    }
+}
+
+impl<T, S: Shape> Alloc<'_, T, S> for SimDevice {
+    #[track_caller]
+    fn try_alloc(&self, mut len: usize, flag: AllocFlag) -> crate::Result<SimPtr<T>> {
+        if S::LEN > len {
+            len = S::LEN
+        }
+
+        if len == 0 {
+            return Err(crate::Error::Alloc {
+                bytes: 0,
+                limit: None,
+            });
+        }
+
+        SimPtr::try_new_zeroed(len, flag, &self.memory)
+    }
+
+    fn try_with_slice(&self, data: &[T]) -> crate::Result<SimPtr<T>>
+    where
+        T: Clone,
+    {
+        if data.is_empty() {
+            return Err(crate::Error::Alloc {
+                bytes: 0,
+                limit: None,
+            });
+        }
+        if S::LEN > data.len() {
+            return Err(crate::Error::ShapeMismatch {
+                expected: S::LEN,
+                found: data.len(),
+            });
+        }
+
+        let mut ptr = SimPtr::try_new_zeroed(data.len(), AllocFlag::None, &self.memory)?;
+        self.copy_to_device(core::mem::size_of_val(data));
+
+        unsafe { std::slice::from_raw_parts_mut(ptr.as_mut_ptr(), data.len()) }
+            .clone_from_slice(data);
+        Ok(ptr)
+    }
+}
+
+impl PtrConv for SimDevice {
+    #[inline]
+    unsafe fn convert<T, IS: Shape, Conv, OS: Shape>(
+        ptr: &SimPtr<T>,
+        flag: AllocFlag,
+    ) -> SimPtr<Conv> {
+        ptr.cast(ptr.len, flag)
+    }
+
+    #[inline]
+    unsafe fn convert_with_len<T, IS: Shape, Conv, OS: Shape>(
+        ptr: &SimPtr<T>,
+        len: usize,
+        flag: AllocFlag,
+    ) -> SimPtr<Conv> {
+        debug_assert!(len <= ptr.len);
+        ptr.cast(len, flag)
+    }
+}
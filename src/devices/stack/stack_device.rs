@@ -1,6 +1,6 @@
 use crate::{
     flag::AllocFlag, shape::Shape, Alloc, Buffer, CloneBuf, Device, DevicelessAble, MainMemory,
-    Read, StackArray, WriteBuf,
+    Read, StackArray, Transfer, WriteBuf,
 };
 
 /// A device that allocates memory on the stack.
@@ -18,6 +18,24 @@ impl Device for Stack {
     }
 }
 
+/// `Stack` has no addons, hence its operations are never traced.
+#[cfg(feature = "trace")]
+impl crate::TraceReturn for Stack {
+    #[inline]
+    fn tracer(&self) -> Option<std::sync::Arc<dyn crate::Tracer>> {
+        None
+    }
+
+    /// Drops `tracer`, as `Stack` can not store it.
+    #[inline]
+    fn set_tracer(
+        &self,
+        _tracer: Option<std::sync::Arc<dyn crate::Tracer>>,
+    ) -> Option<std::sync::Arc<dyn crate::Tracer>> {
+        None
+    }
+}
+
 impl MainMemory for Stack {
     #[inline]
     fn as_ptr<T, S: Shape>(ptr: &Self::Ptr<T, S>) -> *const T {
@@ -31,18 +49,25 @@ impl MainMemory for Stack {
 }
 
 impl<'a, S: Shape, T: Copy + Default> Alloc<'a, T, S> for Stack {
+    /// The length of a [`StackArray`] is determined by its [`Shape`], hence this never fails.
     #[inline]
-    fn alloc(&self, _len: usize, _flag: AllocFlag) -> StackArray<S, T> {
-        StackArray::new()
+    fn try_alloc(&self, _len: usize, _flag: AllocFlag) -> crate::Result<StackArray<S, T>> {
+        Ok(StackArray::new())
     }
 
     #[inline]
-    fn with_slice(&self, data: &[T]) -> Self::Ptr<T, S> {
-        let mut array: StackArray<S, T> =
-            <Stack as Alloc<'_, T, S>>::alloc(self, 0, AllocFlag::None);
+    fn try_with_slice(&self, data: &[T]) -> crate::Result<Self::Ptr<T, S>> {
+        if data.len() < S::LEN {
+            return Err(crate::Error::ShapeMismatch {
+                expected: S::LEN,
+                found: data.len(),
+            });
+        }
+
+        let mut array: StackArray<S, T> = StackArray::new();
         array.flatten_mut().copy_from_slice(&data[..S::LEN]);
 
-        array
+        Ok(array)
     }
 
     #[inline]
@@ -64,15 +89,16 @@ impl<T: Copy, S: Shape> Read<T, S> for Stack
 where
     S::ARR<T>: Copy,
 {
-    type Read<'a> = S::ARR<T>
+    type Read<'a>
+        = S::ARR<T>
     where
         T: 'a,
         Stack: 'a,
         S: 'a;
 
     #[inline]
-    fn read<'a>(&self, buf: &'a Buffer<T, Stack, S>) -> Self::Read<'a> {
-        buf.ptr.array
+    fn try_read<'a>(&self, buf: &'a Buffer<T, Stack, S>) -> crate::Result<Self::Read<'a>> {
+        Ok(buf.ptr.array)
     }
 
     #[inline]
@@ -101,8 +127,10 @@ where
 
 impl<T: Copy, S: Shape> WriteBuf<T, S> for Stack {
     #[inline]
-    fn write(&self, buf: &mut Buffer<T, Self, S>, data: &[T]) {
-        buf.copy_from_slice(data)
+    fn try_write(&self, buf: &mut Buffer<T, Self, S>, data: &[T]) -> crate::Result<()> {
+        crate::check_len(buf.len(), data.len())?;
+        buf.copy_from_slice(data);
+        Ok(())
     }
 
     #[inline]
@@ -111,6 +139,20 @@ impl<T: Copy, S: Shape> WriteBuf<T, S> for Stack {
     }
 }
 
+impl<T: Clone, S: Shape, Dst> Transfer<T, Dst, S> for Stack
+where
+    Dst: for<'b> Alloc<'b, T, S>,
+{
+    #[inline]
+    fn try_transfer<'b>(
+        &self,
+        buf: &Buffer<T, Stack, S>,
+        dst: &'b Dst,
+    ) -> crate::Result<Buffer<'b, T, Dst, S>> {
+        Buffer::try_from_slice(dst, buf.as_slice())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[cfg(not(feature = "no-std"))]
@@ -1,12 +1,18 @@
-use core::{cell::RefCell, fmt::Debug, ptr::null_mut};
+use core::{
+    cell::RefCell,
+    fmt::Debug,
+    mem::{size_of, size_of_val},
+    ptr::null_mut,
+};
 
 use super::{
     launch_shader, shader_cache::ShaderCache, wgpu_buffer::*, wgpu_clear, AsBindingResource,
 };
 
 use crate::{
-    flag::AllocFlag, Addons, AddonsReturn, Alloc, Cache, ClearBuf, Device, DeviceError, PtrConv,
-    PtrType, Read, Shape,
+    flag::AllocFlag, Addons, AddonsReturn, Alloc, Allocation, Buffer, Cache, CacheStrategy,
+    ClearBuf, Device, DeviceError, MemoryReturn, MemoryTracker, PerThread, PtrConv, PtrType, Read,
+    Shape, Transfer, WriteBuf,
 };
 use wgpu::{Adapter, Backends, Queue};
 
@@ -33,10 +39,11 @@ pub struct WGPU {
     pub device: wgpu::Device,
     /// The WGPU queue
     pub queue: Queue,
-    /// Caches compiled shaders for reuse
-    pub shader_cache: RefCell<ShaderCache>,
+    /// Caches compiled shaders for reuse. Each thread has its own shader cache.
+    pub shader_cache: PerThread<RefCell<ShaderCache>>,
     /// Provides additional functionality for the WGPU device. e.g. a cache, a gradient [`Tape`](crate::Tape), an optimizeable [`Graph`](crate::Graph) and a [`Cache`](crate::Cache).
-    pub addons: Addons<WGPU>,
+    pub addons: PerThread<Addons<WGPU>>,
+    memory: MemoryTracker,
 }
 
 impl WGPU {
@@ -66,9 +73,19 @@ impl WGPU {
             queue,
             shader_cache: Default::default(),
             addons: Default::default(),
+            memory: MemoryTracker::default(),
         })
     }
 
+    /// Sets the [`CacheStrategy`] that is used by [`Device::retrieve`] on every thread.
+    /// The strategy is part of the construction of the device, previously cached entries are discarded.
+    #[inline]
+    #[must_use]
+    pub fn with_cache_strategy(mut self, strategy: CacheStrategy) -> WGPU {
+        self.addons = Addons::per_thread(strategy);
+        self
+    }
+
     /// Launches a shader with the specified 'global work size' (`dispatch workgroups`) and arguments.
     #[inline]
     pub fn launch_kernel(&self, src: &str, gws: [u32; 3], args: &[impl AsBindingResource]) {
@@ -91,35 +108,73 @@ impl Device for WGPU {
     fn new() -> crate::Result<Self> {
         Ok(WGPU::default())
     }
+
+    /// Waits until every submitted command buffer has finished.
+    #[inline]
+    fn synchronize(&self) -> crate::Result<()> {
+        self.device.poll(wgpu::Maintain::Wait);
+        Ok(())
+    }
+}
+
+impl MemoryReturn for WGPU {
+    #[inline]
+    fn memory(&self) -> &MemoryTracker {
+        &self.memory
+    }
 }
 
 impl AddonsReturn for WGPU {
     #[inline]
     fn addons(&self) -> &Addons<Self> {
-        &self.addons
+        self.addons.local()
     }
 }
 
 impl<T, S: Shape> Alloc<'_, T, S> for WGPU {
-    fn alloc(&self, len: usize, flag: AllocFlag) -> WGPUBufPtr<T> {
+    #[track_caller]
+    fn try_alloc(&self, len: usize, flag: AllocFlag) -> crate::Result<WGPUBufPtr<T>> {
+        if len == 0 {
+            return Err(crate::Error::Alloc {
+                bytes: 0,
+                limit: None,
+            });
+        }
+
+        let allocation = self
+            .memory
+            .try_reserve(len.saturating_mul(size_of::<T>()))?;
+
         let wgpu_buf = WGPUBuffer::new(&self.device, len as u64);
-        WGPUBufPtr {
+        Ok(WGPUBufPtr {
             ptr: Box::leak(Box::new(wgpu_buf)),
             len,
             flag,
-        }
+            allocation: Some(allocation),
+        })
     }
 
-    fn with_slice(&self, data: &[T]) -> WGPUBufPtr<T>
+    #[track_caller]
+    fn try_with_slice(&self, data: &[T]) -> crate::Result<WGPUBufPtr<T>>
     where
         T: Clone,
     {
+        if data.is_empty() {
+            return Err(crate::Error::Alloc {
+                bytes: 0,
+                limit: None,
+            });
+        }
+
+        let allocation = self.memory.try_reserve(size_of_val(data))?;
+
         let wgpu_buf = WGPUBuffer::with_slice(&self.device, data);
-        WGPUBufPtr {
+        Ok(WGPUBufPtr {
             ptr: Box::into_raw(Box::new(wgpu_buf)),
             len: data.len(),
             flag: AllocFlag::None,
-        }
+            allocation: Some(allocation),
+        })
     }
 }
 
@@ -131,14 +186,21 @@ pub struct WGPUBufPtr<T> {
     pub len: usize,
     /// The allocation flag of the buffer
     pub flag: AllocFlag,
+    /// The bytes reserved for the buffer, released when it is freed.
+    pub(crate) allocation: Option<Allocation>,
 }
 
+// SAFETY: WGPU buffers can be used from any thread.
+unsafe impl<T: Send> Send for WGPUBufPtr<T> {}
+unsafe impl<T: Sync> Sync for WGPUBufPtr<T> {}
+
 impl<T> WGPUBufPtr<T> {
     /// Returns a reference to the WGPU buffer.
     /// # Safety
     /// The buffer must not be dropped.
     #[inline]
     pub unsafe fn buf(&self) -> &wgpu::Buffer {
+        crate::sanitize(self);
         &(*self.ptr).buf
     }
 }
@@ -150,6 +212,7 @@ impl Default for WGPUBufPtr<u8> {
             ptr: null_mut(),
             len: 0,
             flag: AllocFlag::Wrapper,
+            allocation: None,
         }
     }
 }
@@ -164,6 +227,11 @@ impl<T> PtrType for WGPUBufPtr<T> {
     fn flag(&self) -> AllocFlag {
         self.flag
     }
+
+    #[inline]
+    fn allocation(&self) -> Option<&Allocation> {
+        self.allocation.as_ref()
+    }
 }
 
 impl<T> Drop for WGPUBufPtr<T> {
@@ -173,6 +241,10 @@ impl<T> Drop for WGPUBufPtr<T> {
         }
 
         unsafe { drop(Box::from_raw(self.ptr)) }
+
+        if let Some(allocation) = &self.allocation {
+            allocation.release();
+        }
     }
 }
 
@@ -186,6 +258,22 @@ impl PtrConv for WGPU {
             ptr: ptr.ptr.cast(),
             len: ptr.len,
             flag,
+            allocation: ptr.allocation.clone(),
+        }
+    }
+
+    #[inline]
+    unsafe fn convert_with_len<T, IS: Shape, Conv, OS: Shape>(
+        ptr: &Self::Ptr<T, IS>,
+        len: usize,
+        flag: AllocFlag,
+    ) -> Self::Ptr<Conv, OS> {
+        debug_assert!(len <= ptr.len);
+        WGPUBufPtr {
+            ptr: ptr.ptr.cast(),
+            len,
+            flag,
+            allocation: ptr.allocation.clone(),
         }
     }
 }
@@ -210,21 +298,29 @@ impl<T: Default + Debug, S: Shape> ClearBuf<T, S> for WGPU {
     }
 }
 
-impl<T: Default + Clone> Read<T> for WGPU {
-    type Read<'a> = Vec<T>
+impl<T: Default + Clone, S: Shape> Read<T, S> for WGPU {
+    type Read<'a>
+        = Vec<T>
     where
         T: 'a,
-        Self: 'a;
+        Self: 'a,
+        S: 'a;
 
     #[inline]
-    fn read<'a>(&self, buf: &'a crate::Buffer<T, Self>) -> Self::Read<'a> {
-        self.read_to_vec(buf)
-    }
-
-    fn read_to_vec(&self, buf: &crate::Buffer<T, Self>) -> Vec<T>
+    fn read_to_vec(&self, buf: &Buffer<T, Self, S>) -> Vec<T>
     where
         T: Default + Clone,
     {
+        self.read(buf)
+    }
+
+    fn try_read<'a>(&self, buf: &'a Buffer<T, Self, S>) -> crate::Result<Self::Read<'a>> {
+        let _span = crate::trace::span(
+            self,
+            "read",
+            buf.len() * core::mem::size_of::<T>(),
+            buf.ident,
+        );
         self.queue.submit(None);
 
         let buf = unsafe { buf.ptr.buf() };
@@ -235,14 +331,71 @@ impl<T: Default + Clone> Read<T> for WGPU {
 
         self.device.poll(wgpu::Maintain::Wait);
 
-        let Some(Ok(())) = pollster::block_on(receiver.receive()) else {
-            panic!("Failed to read")
-        };
+        match pollster::block_on(receiver.receive()) {
+            Some(Ok(())) => (),
+            Some(Err(err)) => return Err(crate::Error::Other(Box::new(err))),
+            None => return Err(crate::Error::Other("Failed to read".into())),
+        }
 
         let data = buf_slice.get_mapped_range();
         let read = slice_gen_cast::<T>(&data).to_vec();
         drop(data);
         buf.unmap();
-        read
+        Ok(read)
+    }
+}
+
+impl<T: Default + Clone, S: Shape, Dst> Transfer<T, Dst, S> for WGPU
+where
+    Dst: for<'b> Alloc<'b, T, S>,
+{
+    #[inline]
+    fn try_transfer<'b>(
+        &self,
+        buf: &Buffer<T, WGPU, S>,
+        dst: &'b Dst,
+    ) -> crate::Result<Buffer<'b, T, Dst, S>> {
+        Buffer::try_from_vec(dst, self.try_read(buf)?)
     }
 }
+
+impl<T, S: Shape> WriteBuf<T, S> for WGPU {
+    #[inline]
+    fn try_write(&self, buf: &mut Buffer<T, Self, S>, data: &[T]) -> crate::Result<()> {
+        let _span = crate::trace::span(self, "write", core::mem::size_of_val(data), buf.ident);
+        crate::check_len(buf.len(), data.len())?;
+        self.queue
+            .write_buffer(unsafe { buf.ptr.buf() }, 0, slice_u8_cast(data));
+        Ok(())
+    }
+
+    fn write_buf(&self, dst: &mut Buffer<T, Self, S>, src: &Buffer<T, Self, S>) {
+        let _span = crate::trace::span(
+            self,
+            "write_buf",
+            src.len() * core::mem::size_of::<T>(),
+            dst.ident,
+        );
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        encoder.copy_buffer_to_buffer(
+            unsafe { src.ptr.buf() },
+            0,
+            unsafe { dst.ptr.buf() },
+            0,
+            (src.len() * core::mem::size_of::<T>()) as u64,
+        );
+        self.queue.submit(Some(encoder.finish()));
+    }
+}
+
+#[cfg(feature = "cpu")]
+impl crate::exec_on_cpu::CPUFallback for WGPU {}
+
+#[cfg(feature = "cpu")]
+impl crate::exec_on_cpu::FallbackOps for WGPU {}
+
+#[cfg(feature = "cpu")]
+impl crate::exec_on_cpu::FallbackCopySlice for WGPU {}
@@ -0,0 +1,50 @@
+use crate::{Addons, CacheStrategy, Device, PerThread, PtrConv};
+
+#[cfg(any(feature = "faulty", feature = "nan-check", feature = "lazy"))]
+use crate::{flag::AllocFlag, Buffer, Shape};
+
+/// A device that wraps another device and shares its memory, e.g. [`Faulty`](crate::Faulty), [`NanCheck`](crate::NanCheck) or [`Lazy`](crate::Lazy).
+/// The `Buffer`s of the wrapper use the pointers of the wrapped device, but the wrapper has its own [`Addons`].
+pub trait Wrapper: Device + Default + Sized {
+    /// The wrapped device.
+    type Inner: PtrConv;
+
+    /// Returns the wrapped device.
+    fn inner(&self) -> &Self::Inner;
+
+    /// Returns the [`Addons`] of the wrapper, which are replaced by [`with_cache_strategy`](Wrapper::with_cache_strategy).
+    fn addons_mut(&mut self) -> &mut PerThread<Addons<Self>>;
+
+    /// Sets the [`CacheStrategy`] that is used by [`Device::retrieve`] on every thread.
+    /// The strategy is part of the construction of the device, previously cached entries are discarded.
+    #[inline]
+    #[must_use]
+    fn with_cache_strategy(mut self, strategy: CacheStrategy) -> Self
+    where
+        Self: 'static,
+        Self::Ptr<u8, ()>: Default,
+    {
+        *self.addons_mut() = Addons::per_thread(strategy);
+        self
+    }
+}
+
+/// Borrows the `Buffer`s of a [`Wrapper`] as `Buffer`s of the wrapped device.
+#[cfg(any(feature = "faulty", feature = "nan-check", feature = "lazy"))]
+pub(crate) trait InnerBuf: Wrapper {
+    /// Borrows `buf` as a `Buffer` of the wrapped device. The returned `Buffer` does not own its memory.
+    #[inline]
+    fn inner_buf<T, S: Shape>(&self, buf: &Buffer<T, Self, S>) -> Buffer<'_, T, Self::Inner, S>
+    where
+        Self: Device<Ptr<T, S> = <Self::Inner as Device>::Ptr<T, S>>,
+    {
+        Buffer {
+            ptr: unsafe { Self::Inner::convert(&buf.ptr, AllocFlag::Wrapper) },
+            device: Some(self.inner()),
+            ident: buf.ident,
+        }
+    }
+}
+
+#[cfg(any(feature = "faulty", feature = "nan-check", feature = "lazy"))]
+impl<W: Wrapper> InnerBuf for W {}
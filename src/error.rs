@@ -1,7 +1,6 @@
 #[cfg(not(feature = "no-std"))]
 mod std_err {
-    /// A type alias for Box<dyn std::error::Error + Send + Sync>
-    pub type Error = Box<dyn std::error::Error + Send + Sync>;
+    use super::Error;
 
     /// A trait for downcasting errors.
     pub trait ErrorKind {
@@ -11,29 +10,274 @@ mod std_err {
 
     impl ErrorKind for Error {
         fn kind<E: std::error::Error + PartialEq + 'static>(&self) -> Option<&E> {
-            self.downcast_ref::<E>()
+            std::error::Error::source(self)?.downcast_ref::<E>()
         }
     }
 
     impl std::error::Error for crate::DeviceError {}
+
+    impl std::error::Error for Error {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            match self {
+                Error::Device(err) => Some(err),
+                Error::TypeMismatch(mismatch) => Some(mismatch),
+                #[cfg(feature = "opencl")]
+                Error::OpenCL(err) => Some(err),
+                #[cfg(feature = "cuda")]
+                Error::CUDA(err) => Some(err),
+                #[cfg(feature = "cuda")]
+                Error::Cublas(err) => Some(err),
+                #[cfg(feature = "cuda")]
+                Error::Nvrtc(err) => Some(err),
+                #[cfg(feature = "wgpu")]
+                Error::WGPU(err) => Some(err),
+                Error::Other(err) => Some(&**err),
+                _ => None,
+            }
+        }
+    }
+
+    impl From<Box<dyn std::error::Error + Send + Sync>> for Error {
+        /// Recovers the structured error if `err` contains one of the error types of custos or its backends.
+        /// Otherwise, `err` is stored as [`Error::Other`].
+        fn from(err: Box<dyn std::error::Error + Send + Sync>) -> Self {
+            let err = match err.downcast::<Error>() {
+                Ok(err) => return *err,
+                Err(err) => err,
+            };
+
+            let err = match err.downcast::<crate::DeviceError>() {
+                Ok(err) => return Error::Device(*err),
+                Err(err) => err,
+            };
+
+            #[cfg(feature = "opencl")]
+            let err = match err.downcast::<min_cl::api::OCLErrorKind>() {
+                Ok(err) => return Error::OpenCL(*err),
+                Err(err) => err,
+            };
+
+            Error::Other(err)
+        }
+    }
 }
 
 #[cfg(not(feature = "no-std"))]
 pub use std_err::*;
 
-/// A type alias for `Result<T, Error>`.
-#[cfg(not(feature = "no-std"))]
-pub type Result<T> = core::result::Result<T, self::std_err::Error>;
+/// The error type of custos.
+/// Backend errors keep the error kind of the backend, e.g. [`Error::OpenCL`] or [`Error::CUDA`].
+/// As some variants depend on the enabled features, matches outside of custos require a wildcard arm.
+///
+/// # Example
+#[cfg_attr(feature = "cpu", doc = "```")]
+#[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+/// use custos::{DeviceError, Error};
+///
+/// let err = Error::from(DeviceError::UnknownDevice);
+///
+/// match err {
+///     Error::Device(DeviceError::UnknownDevice) => (),
+///     _ => panic!("wrong error"),
+/// }
+/// ```
+#[non_exhaustive]
+pub enum Error {
+    /// The device could not allocate `bytes` bytes. `bytes` is 0 if a buffer with a length of 0 was requested.
+    Alloc {
+        /// The number of bytes that were requested
+        bytes: usize,
+        /// Set if the allocation would raise the live bytes of the device above its limit, see [`MemoryReturn`](crate::MemoryReturn).
+        limit: Option<LimitExceeded>,
+    },
+    /// The lengths of two buffers (or of a buffer and a slice) differ.
+    LengthMismatch {
+        /// The required length
+        expected: usize,
+        /// The supplied length
+        found: usize,
+    },
+    /// The range `start..end` does not lie within a buffer of length `len`.
+    OutOfRange {
+        /// The start of the range
+        start: usize,
+        /// The (exclusive) end of the range
+        end: usize,
+        /// The length of the buffer
+        len: usize,
+    },
+    /// The element count of a [`Shape`](crate::Shape) does not match the supplied data.
+    ShapeMismatch {
+        /// The element count of the shape
+        expected: usize,
+        /// The number of supplied elements
+        found: usize,
+    },
+    /// A cache entry was retrieved with another element type or [`Shape`](crate::Shape) than it was allocated with.
+    #[cfg(not(feature = "no-std"))]
+    TypeMismatch(crate::TypeMismatch),
+    /// A kernel could not be compiled.
+    #[cfg(not(feature = "no-std"))]
+    KernelCompile {
+        /// The build log of the compiler
+        log: String,
+    },
+    /// A failure that was injected by a [`Faulty`](crate::Faulty) device.
+    #[cfg(feature = "faulty")]
+    #[cfg(not(feature = "no-std"))]
+    InjectedFault(crate::Fault),
+    /// A [`NanCheck`](crate::NanCheck) device found a NaN, infinite or subnormal value.
+    #[cfg(feature = "nan-check")]
+    #[cfg(not(feature = "no-std"))]
+    Anomaly(crate::AnomalyReport),
+    /// A 'generic' device error, see [`DeviceError`].
+    Device(DeviceError),
+    /// An OpenCL error
+    #[cfg(feature = "opencl")]
+    OpenCL(min_cl::api::OCLErrorKind),
+    /// A CUDA driver API error
+    #[cfg(feature = "cuda")]
+    CUDA(crate::cuda::api::CudaErrorKind),
+    /// A cuBLAS error
+    #[cfg(feature = "cuda")]
+    Cublas(crate::cuda::api::cublas::CublasErrorKind),
+    /// An NVRTC error
+    #[cfg(feature = "cuda")]
+    Nvrtc(crate::cuda::api::nvrtc::NvrtcErrorKind),
+    /// A WGPU device could not be requested.
+    #[cfg(feature = "wgpu")]
+    WGPU(wgpu::RequestDeviceError),
+    /// Any other error, e.g. from a dependency.
+    #[cfg(not(feature = "no-std"))]
+    Other(Box<dyn std::error::Error + Send + Sync>),
+}
 
-/// An error for no-std.
-#[cfg(feature = "no-std")]
-#[derive(Debug)]
-pub struct Error {}
+/// The memory limit of a device that an allocation would have exceeded, see [`Error::Alloc`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimitExceeded {
+    /// The number of bytes that were allocated when the allocation was requested
+    pub live: usize,
+    /// The memory limit of the device
+    pub limit: usize,
+}
 
 /// A type alias for `Result<T, Error>`.
-#[cfg(feature = "no-std")]
 pub type Result<T> = core::result::Result<T, Error>;
 
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Alloc {
+                bytes,
+                limit: Some(LimitExceeded { live, limit }),
+            } => write!(
+                f,
+                "Out of memory: Allocating {bytes} bytes would exceed the limit of {limit} bytes ({live} bytes are in use)."
+            ),
+            Error::Alloc { bytes: 0, .. } => {
+                write!(f, "Cannot allocate a buffer with a length of 0.")
+            }
+            Error::Alloc { bytes, .. } => write!(f, "Failed to allocate {bytes} bytes."),
+            Error::LengthMismatch { expected, found } => {
+                write!(f, "Expected a length of {expected}, found {found}.")
+            }
+            Error::OutOfRange { start, end, len } => write!(
+                f,
+                "The range {start}..{end} is out of bounds for a buffer of length {len}."
+            ),
+            Error::ShapeMismatch { expected, found } => write!(
+                f,
+                "The shape holds {expected} elements, but {found} elements were supplied."
+            ),
+            #[cfg(not(feature = "no-std"))]
+            Error::TypeMismatch(mismatch) => write!(f, "{mismatch}"),
+            #[cfg(not(feature = "no-std"))]
+            Error::KernelCompile { log } => write!(f, "Failed to compile kernel:\n{log}"),
+            #[cfg(feature = "faulty")]
+            #[cfg(not(feature = "no-std"))]
+            Error::InjectedFault(fault) => write!(f, "Injected {fault} failure."),
+            #[cfg(feature = "nan-check")]
+            #[cfg(not(feature = "no-std"))]
+            Error::Anomaly(report) => write!(f, "{report}."),
+            Error::Device(err) => write!(f, "{err}"),
+            #[cfg(feature = "opencl")]
+            Error::OpenCL(err) => write!(f, "{err}"),
+            #[cfg(feature = "cuda")]
+            Error::CUDA(err) => write!(f, "{err}"),
+            #[cfg(feature = "cuda")]
+            Error::Cublas(err) => write!(f, "{err}"),
+            #[cfg(feature = "cuda")]
+            Error::Nvrtc(err) => write!(f, "{err}"),
+            #[cfg(feature = "wgpu")]
+            Error::WGPU(err) => write!(f, "{err}"),
+            #[cfg(not(feature = "no-std"))]
+            Error::Other(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+// `fn main() -> custos::Result<()>` prints the error message instead of the variant
+impl core::fmt::Debug for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl From<DeviceError> for Error {
+    #[inline]
+    fn from(err: DeviceError) -> Self {
+        Error::Device(err)
+    }
+}
+
+#[cfg(not(feature = "no-std"))]
+impl From<crate::TypeMismatch> for Error {
+    #[inline]
+    fn from(mismatch: crate::TypeMismatch) -> Self {
+        Error::TypeMismatch(mismatch)
+    }
+}
+
+#[cfg(feature = "opencl")]
+impl From<min_cl::api::OCLErrorKind> for Error {
+    #[inline]
+    fn from(err: min_cl::api::OCLErrorKind) -> Self {
+        Error::OpenCL(err)
+    }
+}
+
+#[cfg(feature = "cuda")]
+impl From<crate::cuda::api::CudaErrorKind> for Error {
+    #[inline]
+    fn from(err: crate::cuda::api::CudaErrorKind) -> Self {
+        Error::CUDA(err)
+    }
+}
+
+#[cfg(feature = "cuda")]
+impl From<crate::cuda::api::cublas::CublasErrorKind> for Error {
+    #[inline]
+    fn from(err: crate::cuda::api::cublas::CublasErrorKind) -> Self {
+        Error::Cublas(err)
+    }
+}
+
+#[cfg(feature = "cuda")]
+impl From<crate::cuda::api::nvrtc::NvrtcErrorKind> for Error {
+    #[inline]
+    fn from(err: crate::cuda::api::nvrtc::NvrtcErrorKind) -> Self {
+        Error::Nvrtc(err)
+    }
+}
+
+#[cfg(feature = "wgpu")]
+impl From<wgpu::RequestDeviceError> for Error {
+    #[inline]
+    fn from(err: wgpu::RequestDeviceError) -> Self {
+        Error::WGPU(err)
+    }
+}
+
 /// 'generic' device errors that can occur on any device.
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum DeviceError {
@@ -49,6 +293,8 @@ pub enum DeviceError {
     WGPUDeviceReturn,
     /// The 'cpu' feature is disabled. Hence this CPU can't be created.
     CPUDeviceNotAvailable,
+    /// The requested device is unknown or its feature is disabled.
+    UnknownDevice,
 }
 
 impl DeviceError {
@@ -67,6 +313,9 @@ impl DeviceError {
             DeviceError::CPUDeviceNotAvailable => {
                 "The 'cpu' feature is disabled. Hence this CPU can't be created."
             }
+            DeviceError::UnknownDevice => {
+                "The requested device is unknown or its feature is disabled."
+            }
         }
     }
 }
@@ -0,0 +1,178 @@
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use crate::{Buffer, Device, Shape};
+
+/// Marks a point in the queue of a device.
+/// The event is complete as soon as every operation enqueued before it has finished.
+pub trait DeviceEvent {
+    /// Blocks until the event is complete.
+    fn wait(&self) -> crate::Result<()>;
+
+    /// Returns `true` if the event is complete. Does not block.
+    fn is_complete(&self) -> bool;
+
+    /// Wakes `waker` once the event is complete, or immediately if it is already complete.
+    /// Used by the [`Future`] implementation of [`PendingRead`].
+    fn wake_on_complete(&self, waker: &Waker);
+}
+
+/// A [`DeviceEvent`] that is complete from the start.
+/// Used by devices that execute every operation on the calling thread.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CompletedEvent;
+
+impl DeviceEvent for CompletedEvent {
+    #[inline]
+    fn wait(&self) -> crate::Result<()> {
+        Ok(())
+    }
+
+    #[inline]
+    fn is_complete(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn wake_on_complete(&self, waker: &Waker) {
+        waker.wake_by_ref();
+    }
+}
+
+/// Records events into the queue of a device, which establish an order between operations.
+/// # Example
+#[cfg_attr(feature = "cpu", doc = "```")]
+#[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+/// use std::sync::{Arc, Mutex};
+/// use custos::{DeviceEvent, RecordEvent, CPU};
+///
+/// let device = CPU::new();
+/// let log = Arc::new(Mutex::new(Vec::new()));
+///
+/// let first = log.clone();
+/// device.enqueue(move || first.lock().unwrap().push(1));
+/// let event = device.record_event().unwrap();
+///
+/// event.wait().unwrap();
+/// assert!(event.is_complete());
+/// assert_eq!(*log.lock().unwrap(), [1]);
+/// ```
+pub trait RecordEvent: Device {
+    /// The event type of the device.
+    type Event: DeviceEvent;
+
+    /// Records an event that completes once every operation enqueued so far has finished.
+    fn record_event(&self) -> crate::Result<Self::Event>;
+
+    /// Makes every operation enqueued after this call wait until `event` is complete.
+    /// The calling thread is not blocked.
+    fn wait_event(&self, event: &Self::Event) -> crate::Result<()>;
+}
+
+/// Reads the contents of a `Buffer` without blocking the calling thread.
+pub trait ReadAsync<T, S: Shape = ()>: Device {
+    /// Starts copying `buf` to host memory. The returned [`PendingRead`] can be waited on or `.await`ed.
+    /// # Safety
+    /// The returned [`PendingRead`] must not be leaked, e.g. with [`core::mem::forget`].
+    /// Dropping it waits until the device has finished reading `buf`.
+    /// A leaked `PendingRead` ends the borrow of `buf` while the device may still read from it, so `buf` could be freed during the read.
+    /// # Example
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::{Buffer, ReadAsync, CPU};
+    ///
+    /// let device = CPU::new();
+    /// let buf = Buffer::from((&device, [1, 2, 3]));
+    ///
+    /// // SAFETY: `pending` is not leaked
+    /// let pending = unsafe { device.read_async(&buf) }.unwrap();
+    /// // ... enqueue other work ...
+    /// assert_eq!(pending.wait().unwrap(), [1, 2, 3]);
+    /// ```
+    unsafe fn read_async<'a>(
+        &'a self,
+        buf: &'a Buffer<T, Self, S>,
+    ) -> crate::Result<PendingRead<'a, T>>;
+}
+
+/// The result of a [`ReadAsync::read_async`] call.
+/// The data can be retrieved with [`PendingRead::wait`] or by `.await`ing the `PendingRead`.
+///
+/// The device writes into the host memory of the `PendingRead` until the read is complete,
+/// hence dropping an incomplete `PendingRead` blocks until the device has finished.
+pub struct PendingRead<'a, T> {
+    data: Vec<T>,
+    event: Box<dyn DeviceEvent + 'a>,
+    done: bool,
+}
+
+impl<'a, T> PendingRead<'a, T> {
+    /// Creates a `PendingRead` that is complete once `event` is complete.
+    /// # Safety
+    /// The device may write to `data` until `event` is complete. `data` must not be reallocated.
+    /// The caller must ensure that the returned `PendingRead` is not leaked while the device accesses memory that is borrowed for `'a`.
+    #[inline]
+    pub unsafe fn new(data: Vec<T>, event: impl DeviceEvent + 'a) -> PendingRead<'a, T> {
+        PendingRead {
+            data,
+            event: Box::new(event),
+            done: false,
+        }
+    }
+
+    /// Returns `true` if the read is complete. Does not block.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.done || self.event.is_complete()
+    }
+
+    /// Blocks until the read is complete and returns the data.
+    pub fn wait(mut self) -> crate::Result<Vec<T>> {
+        self.event.wait()?;
+        self.done = true;
+        Ok(core::mem::take(&mut self.data))
+    }
+}
+
+// `PendingRead` is never structurally pinned
+impl<'a, T> Unpin for PendingRead<'a, T> {}
+
+impl<'a, T> Future for PendingRead<'a, T> {
+    type Output = crate::Result<Vec<T>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        if !this.event.is_complete() {
+            this.event.wake_on_complete(cx.waker());
+            return Poll::Pending;
+        }
+
+        if let Err(err) = this.event.wait() {
+            return Poll::Ready(Err(err));
+        }
+        this.done = true;
+        Poll::Ready(Ok(core::mem::take(&mut this.data)))
+    }
+}
+
+impl<'a, T> Drop for PendingRead<'a, T> {
+    fn drop(&mut self) {
+        if !self.done {
+            // the device must not write into freed memory
+            self.event.wait().ok();
+        }
+    }
+}
+
+impl<'a, T> core::fmt::Debug for PendingRead<'a, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PendingRead")
+            .field("len", &self.data.len())
+            .field("complete", &self.is_complete())
+            .finish()
+    }
+}
@@ -2,12 +2,16 @@
 //! They move the supplied (CUDA, OpenCL, WGPU, ...) `Buffer`s to the CPU and execute the operation on the CPU.
 //! Most of the time, you should actually implement the operation for the device natively, as it is typically faster.
 
+mod fallback;
+
 #[cfg(feature = "opencl")]
 mod cl_may_unified;
 
 #[cfg(feature = "opencl")]
 pub use cl_may_unified::*;
 
+pub use fallback::*;
+
 use crate::{Alloc, Buffer, Device, Read, WriteBuf, CPU};
 
 /// Moves a `Buffer` stored on device `D` to a `CPU` `Buffer`
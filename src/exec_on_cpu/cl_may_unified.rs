@@ -1,7 +1,10 @@
+use core::array::from_fn;
+
 use super::{
     cpu_exec_binary, cpu_exec_binary_mut, cpu_exec_reduce, cpu_exec_unary, cpu_exec_unary_mut,
+    fallback_on_host, fallback_on_host_mut, report_fallback, with_host, CPUFallback,
 };
-use crate::{Buffer, OpenCL, CPU};
+use crate::{Alloc, Buffer, OpenCL, Read, Shape, WriteBuf, CPU};
 
 #[cfg(not(feature = "realloc"))]
 use crate::opencl::construct_buffer;
@@ -205,13 +208,13 @@ macro_rules! cl_cpu_exec_unified {
             #[cfg(feature = "realloc")]
             {
                 let buf = Buffer::from((&$device, $op));
-                $device.cpu.cache_mut().nodes.clear();
+                $device.cpu.cache_mut().clear();
                 buf
             }
 
         } else {
             let buf = $crate::cpu_exec!($device, cpu, $($t),*; $op);
-            $device.cpu.cache_mut().nodes.clear();
+            $device.cpu.cache_mut().clear();
             Ok(buf)
         }
     }};
@@ -234,7 +237,76 @@ macro_rules! cl_cpu_exec_unified_mut {
         } else {
             let cpu = CPU::new();
             $crate::cpu_exec_mut!($device, cpu, $($t),* WRITE_TO<$($write_to, $from),*> $op);
-            $device.cpu.cache_mut().nodes.clear();
+            $device.cpu.cache_mut().clear();
         }
     }};
 }
+
+/// If the device supports unified memory, the fallback operations use the host pointers of the `Buffer`s instead of copying them.
+impl CPUFallback for OpenCL {
+    #[track_caller]
+    fn fallback<'a, T, S, F, const N: usize>(
+        &'a self,
+        op: &'static str,
+        inputs: [&Buffer<T, Self, S>; N],
+        f: F,
+    ) -> crate::Result<Buffer<'a, T, Self, S>>
+    where
+        T: Clone + Default,
+        S: Shape,
+        F: for<'b> Fn(&'b CPU, [&Buffer<'_, T, CPU, S>; N]) -> Buffer<'b, T, CPU, S>,
+        Self: Read<T, S> + WriteBuf<T, S> + for<'c> Alloc<'c, T, S>,
+        for<'r> <Self as Read<T, S>>::Read<'r>: Into<Vec<T>>,
+    {
+        if !self.unified_mem() {
+            return fallback_on_host(self, op, inputs, f);
+        }
+
+        report_fallback::<OpenCL>(op, inputs.first().map_or(0, |input| input.len()));
+
+        let inputs = inputs.map(|input| unsafe {
+            Buffer::<T, CPU, S>::from_raw_host(input.ptr.host_ptr, input.len())
+        });
+
+        #[cfg(not(feature = "realloc"))]
+        {
+            // The CPU of the OpenCL device is used to get a (correct) cache entry, see [`cpu_exec_unary_may_unified`].
+            let no_drop = f(&self.cpu, from_fn(|idx| &inputs[idx]));
+            unsafe { construct_buffer(self, no_drop, ()) }
+        }
+
+        #[cfg(feature = "realloc")]
+        {
+            with_host(|cpu| Ok(Buffer::from((self, f(cpu, from_fn(|idx| &inputs[idx]))))))
+        }
+    }
+
+    fn fallback_mut<T, S, F, const N: usize>(
+        &self,
+        op: &'static str,
+        out: &mut Buffer<T, Self, S>,
+        inputs: [&Buffer<T, Self, S>; N],
+        f: F,
+    ) -> crate::Result<()>
+    where
+        T: Clone + Default,
+        S: Shape,
+        F: for<'b> Fn(&'b CPU, &mut Buffer<'_, T, CPU, S>, [&Buffer<'_, T, CPU, S>; N]),
+        Self: Read<T, S> + WriteBuf<T, S>,
+        for<'r> <Self as Read<T, S>>::Read<'r>: Into<Vec<T>>,
+    {
+        if !self.unified_mem() {
+            return fallback_on_host_mut(self, op, out, inputs, f);
+        }
+
+        report_fallback::<OpenCL>(op, out.len());
+
+        let inputs = inputs.map(|input| unsafe {
+            Buffer::<T, CPU, S>::from_raw_host(input.ptr.host_ptr, input.len())
+        });
+        let mut out = unsafe { Buffer::<T, CPU, S>::from_raw_host(out.ptr.host_ptr, out.len()) };
+
+        with_host(|cpu| f(cpu, &mut out, from_fn(|idx| &inputs[idx])));
+        Ok(())
+    }
+}
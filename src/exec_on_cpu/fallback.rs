@@ -0,0 +1,512 @@
+use core::{
+    array::from_fn,
+    ops::{Range, RangeBounds},
+};
+use std::sync::RwLock;
+
+use crate::{
+    checked_range, Alloc, Buffer, CacheStrategy, CopySlice, Device, Error, Read, Shape, WriteBuf,
+    CPU,
+};
+#[cfg(feature = "macro")]
+use crate::{
+    ApplyFunction, ApplyFunctionTo, BinaryElementWise, BinaryElementWiseTo, Eval, MayToCLSource,
+    Reduce, ReduceTo, Resolve, UnaryGrad,
+};
+
+/// Describes an operation that is executed on the CPU, because the device does not implement it natively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FallbackInfo {
+    /// The type name of the device that falls back to the CPU.
+    pub device: &'static str,
+    /// The name of the operation, e.g. `"apply_fn"`.
+    pub op: &'static str,
+    /// The length of the first `Buffer` involved in the operation.
+    pub len: usize,
+}
+
+/// A function that is called every time an operation falls back to the CPU.
+pub type FallbackHook = fn(&FallbackInfo);
+
+static FALLBACK_HOOK: RwLock<Option<FallbackHook>> = RwLock::new(None);
+
+/// Sets the hook that is called every time an operation falls back to the CPU and returns the previous hook.
+/// Passing `None` removes the hook.
+/// # Example
+/// ```
+/// use custos::exec_on_cpu::set_fallback_hook;
+///
+/// set_fallback_hook(Some(|fallback| {
+///     eprintln!("{} executed {} on the CPU", fallback.device, fallback.op)
+/// }));
+/// ```
+pub fn set_fallback_hook(hook: Option<FallbackHook>) -> Option<FallbackHook> {
+    let mut current = FALLBACK_HOOK.write().unwrap_or_else(|err| err.into_inner());
+    core::mem::replace(&mut *current, hook)
+}
+
+/// Reports a fallback of the device `D` to the hook set with [`set_fallback_hook`].
+/// Only needs to be called manually if [`CPUFallback`] methods are overridden.
+pub fn report_fallback<D>(op: &'static str, len: usize) {
+    let hook = *FALLBACK_HOOK.read().unwrap_or_else(|err| err.into_inner());
+
+    if let Some(hook) = hook {
+        hook(&FallbackInfo {
+            device: core::any::type_name::<D>(),
+            op,
+            len,
+        })
+    }
+}
+
+std::thread_local! {
+    /// The CPU that executes the fallbacks of the current thread.
+    /// It does not cache `Buffer`s, as every fallback returns its result to the device.
+    static HOST: CPU = CPU::new().with_cache_strategy(CacheStrategy::None);
+}
+
+/// Calls `f` with the CPU that executes the fallbacks of the current thread.
+#[inline]
+pub(crate) fn with_host<R>(f: impl FnOnce(&CPU) -> R) -> R {
+    HOST.with(f)
+}
+
+/// Executes operations on the CPU that are not implemented natively for a device.
+///
+/// The default methods move all `Buffer`s to the CPU, execute the CPU implementation and move the result back.
+/// Devices with host accessible memory (e.g. OpenCL with unified memory) override them to skip the copies.
+/// Implement [`FallbackOps`] or [`FallbackCopySlice`] to implement op traits in terms of this trait.
+pub trait CPUFallback: Device {
+    /// Executes `f` on the CPU with the `inputs` moved to the CPU and moves the returned `Buffer` back to `self`.
+    #[track_caller]
+    fn fallback<'a, T, S, F, const N: usize>(
+        &'a self,
+        op: &'static str,
+        inputs: [&Buffer<T, Self, S>; N],
+        f: F,
+    ) -> crate::Result<Buffer<'a, T, Self, S>>
+    where
+        T: Clone + Default,
+        S: Shape,
+        F: for<'b> Fn(&'b CPU, [&Buffer<'_, T, CPU, S>; N]) -> Buffer<'b, T, CPU, S>,
+        Self: Read<T, S> + WriteBuf<T, S> + for<'c> Alloc<'c, T, S>,
+        for<'r> <Self as Read<T, S>>::Read<'r>: Into<Vec<T>>,
+    {
+        fallback_on_host(self, op, inputs, f)
+    }
+
+    /// Executes `f` on the CPU with `out` and the `inputs` moved to the CPU. Afterwards, `out` is written back to `self`.
+    fn fallback_mut<T, S, F, const N: usize>(
+        &self,
+        op: &'static str,
+        out: &mut Buffer<T, Self, S>,
+        inputs: [&Buffer<T, Self, S>; N],
+        f: F,
+    ) -> crate::Result<()>
+    where
+        T: Clone + Default,
+        S: Shape,
+        F: for<'b> Fn(&'b CPU, &mut Buffer<'_, T, CPU, S>, [&Buffer<'_, T, CPU, S>; N]),
+        Self: Read<T, S> + WriteBuf<T, S>,
+        for<'r> <Self as Read<T, S>>::Read<'r>: Into<Vec<T>>,
+    {
+        fallback_on_host_mut(self, op, out, inputs, f)
+    }
+}
+
+/// Reads `buf` into a CPU `Buffer`. Fails if the device failed to read the data.
+fn read_to_host<'a, T, D, S>(
+    device: &D,
+    host: &'a CPU,
+    buf: &Buffer<T, D, S>,
+) -> crate::Result<Buffer<'a, T, CPU, S>>
+where
+    T: Clone + Default,
+    D: Read<T, S>,
+    S: Shape,
+    for<'r> D::Read<'r>: Into<Vec<T>>,
+{
+    let data: Vec<T> = device.try_read(buf)?.into();
+    Ok(Buffer::from((host, data)))
+}
+
+/// The default implementation of [`CPUFallback::fallback`]. Copies every `Buffer` between `device` and host memory.
+#[track_caller]
+pub fn fallback_on_host<'a, T, D, S, F, const N: usize>(
+    device: &'a D,
+    op: &'static str,
+    inputs: [&Buffer<T, D, S>; N],
+    f: F,
+) -> crate::Result<Buffer<'a, T, D, S>>
+where
+    T: Clone + Default,
+    D: Read<T, S> + WriteBuf<T, S> + for<'c> Alloc<'c, T, S>,
+    S: Shape,
+    F: for<'b> Fn(&'b CPU, [&Buffer<'_, T, CPU, S>; N]) -> Buffer<'b, T, CPU, S>,
+    for<'r> D::Read<'r>: Into<Vec<T>>,
+{
+    report_fallback::<D>(op, inputs.first().map_or(0, |input| input.len()));
+
+    with_host(|cpu| {
+        let mut cpu_inputs = Vec::with_capacity(N);
+        for input in inputs {
+            cpu_inputs.push(read_to_host(device, cpu, input)?);
+        }
+
+        let cpu_out = f(cpu, from_fn(|idx| &cpu_inputs[idx]));
+
+        // allocating and writing separately returns failures of the device as error
+        let mut out = Buffer::try_new(device, cpu_out.len())?;
+        device.try_write(&mut out, &cpu_out)?;
+        Ok(out)
+    })
+}
+
+/// The default implementation of [`CPUFallback::fallback_mut`]. Copies every `Buffer` between `device` and host memory.
+pub fn fallback_on_host_mut<T, D, S, F, const N: usize>(
+    device: &D,
+    op: &'static str,
+    out: &mut Buffer<T, D, S>,
+    inputs: [&Buffer<T, D, S>; N],
+    f: F,
+) -> crate::Result<()>
+where
+    T: Clone + Default,
+    D: Read<T, S> + WriteBuf<T, S>,
+    S: Shape,
+    F: for<'b> Fn(&'b CPU, &mut Buffer<'_, T, CPU, S>, [&Buffer<'_, T, CPU, S>; N]),
+    for<'r> D::Read<'r>: Into<Vec<T>>,
+{
+    report_fallback::<D>(op, out.len());
+
+    with_host(|cpu| {
+        let mut cpu_out = read_to_host(device, cpu, out)?;
+        let mut cpu_inputs = Vec::with_capacity(N);
+        for input in inputs {
+            cpu_inputs.push(read_to_host(device, cpu, input)?);
+        }
+
+        f(cpu, &mut cpu_out, from_fn(|idx| &cpu_inputs[idx]));
+
+        device.try_write(out, &cpu_out)
+    })
+}
+
+/// Implements `ApplyFunction`, `ApplyFunctionTo`, `BinaryElementWise`, `BinaryElementWiseTo`, `Reduce`, `ReduceTo` and `UnaryGrad`
+/// for a device by executing them on the CPU via [`CPUFallback`]. Requires the `macro` feature, as the CPU implementations do.
+/// `Reduce` and `ReduceTo` are only implemented for `Buffer`s without a static shape.
+/// # Example
+/// ```ignore
+/// use custos::exec_on_cpu::{CPUFallback, FallbackOps};
+///
+/// impl CPUFallback for MyDevice {}
+/// impl FallbackOps for MyDevice {}
+/// ```
+pub trait FallbackOps: CPUFallback {}
+
+/// Implements [`CopySlice`] for a device by executing it on the CPU via [`CPUFallback`].
+pub trait FallbackCopySlice: CPUFallback {}
+
+#[cfg(feature = "macro")]
+impl<T, S, D> ApplyFunction<T, S> for D
+where
+    T: Clone + Default,
+    S: Shape,
+    CPU: ApplyFunction<T, S>,
+    D: FallbackOps + Read<T, S> + WriteBuf<T, S> + for<'c> Alloc<'c, T, S>,
+    for<'r> <D as Read<T, S>>::Read<'r>: Into<Vec<T>>,
+{
+    #[track_caller]
+    fn try_apply_fn<F>(
+        &self,
+        buf: &Buffer<T, Self, S>,
+        f: impl Fn(Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<'_, T, Self, S>>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        self.fallback("apply_fn", [buf], |cpu, [buf]| cpu.apply_fn(buf, &f))
+    }
+}
+
+#[cfg(feature = "macro")]
+impl<T, S, D> ApplyFunctionTo<T, S> for D
+where
+    T: Clone + Default,
+    S: Shape,
+    CPU: ApplyFunctionTo<T, S>,
+    D: FallbackOps + Read<T, S> + WriteBuf<T, S>,
+    for<'r> <D as Read<T, S>>::Read<'r>: Into<Vec<T>>,
+{
+    fn try_apply_fn_to<F>(
+        &self,
+        buf: &Buffer<T, Self, S>,
+        out: &mut Buffer<T, Self, S>,
+        f: impl Fn(Resolve<T>) -> F,
+    ) -> crate::Result<()>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        if buf.len() != out.len() {
+            return Err(Error::LengthMismatch {
+                expected: out.len(),
+                found: buf.len(),
+            });
+        }
+
+        self.fallback_mut("apply_fn", out, [buf], |cpu, out, [buf]| {
+            cpu.apply_fn_to(buf, out, &f)
+        })
+    }
+}
+
+#[cfg(feature = "macro")]
+impl<T, S, D> BinaryElementWise<T, S> for D
+where
+    T: Clone + Default,
+    S: Shape,
+    CPU: BinaryElementWise<T, S>,
+    D: FallbackOps + Read<T, S> + WriteBuf<T, S> + for<'c> Alloc<'c, T, S>,
+    for<'r> <D as Read<T, S>>::Read<'r>: Into<Vec<T>>,
+{
+    #[track_caller]
+    fn try_binary_ew<F>(
+        &self,
+        lhs: &Buffer<T, Self, S>,
+        rhs: &Buffer<T, Self, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<'_, T, Self, S>>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        if lhs.len() != rhs.len() {
+            return Err(Error::LengthMismatch {
+                expected: lhs.len(),
+                found: rhs.len(),
+            });
+        }
+
+        self.fallback("binary_ew", [lhs, rhs], |cpu, [lhs, rhs]| {
+            cpu.binary_ew(lhs, rhs, &f)
+        })
+    }
+}
+
+#[cfg(feature = "macro")]
+impl<T, S, D> BinaryElementWiseTo<T, S> for D
+where
+    T: Clone + Default,
+    S: Shape,
+    CPU: BinaryElementWiseTo<T, S>,
+    D: FallbackOps + Read<T, S> + WriteBuf<T, S>,
+    for<'r> <D as Read<T, S>>::Read<'r>: Into<Vec<T>>,
+{
+    fn try_binary_ew_to<F>(
+        &self,
+        lhs: &Buffer<T, Self, S>,
+        rhs: &Buffer<T, Self, S>,
+        out: &mut Buffer<T, Self, S>,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<()>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        for found in [lhs.len(), rhs.len()] {
+            if found != out.len() {
+                return Err(Error::LengthMismatch {
+                    expected: out.len(),
+                    found,
+                });
+            }
+        }
+
+        self.fallback_mut("binary_ew", out, [lhs, rhs], |cpu, out, [lhs, rhs]| {
+            cpu.binary_ew_to(lhs, rhs, out, &f)
+        })
+    }
+}
+
+#[cfg(feature = "macro")]
+impl<T, D> Reduce<T> for D
+where
+    T: Clone + Default,
+    CPU: Reduce<T>,
+    D: FallbackOps + Read<T> + WriteBuf<T> + for<'c> Alloc<'c, T>,
+    for<'r> <D as Read<T>>::Read<'r>: Into<Vec<T>>,
+{
+    #[track_caller]
+    fn try_reduce<F>(
+        &self,
+        buf: &Buffer<T, Self>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<'_, T, Self>>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        self.fallback("reduce", [buf], |cpu, [buf]| {
+            cpu.reduce(buf, init.clone(), &f)
+        })
+    }
+}
+
+#[cfg(feature = "macro")]
+impl<T, D> ReduceTo<T> for D
+where
+    T: Clone + Default,
+    CPU: ReduceTo<T>,
+    D: FallbackOps + Read<T> + WriteBuf<T>,
+    for<'r> <D as Read<T>>::Read<'r>: Into<Vec<T>>,
+{
+    fn try_reduce_to<F>(
+        &self,
+        buf: &Buffer<T, Self>,
+        out: &mut Buffer<T, Self>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<()>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        if out.len() != 1 {
+            return Err(Error::LengthMismatch {
+                expected: 1,
+                found: out.len(),
+            });
+        }
+
+        self.fallback_mut("reduce", out, [buf], |cpu, out, [buf]| {
+            cpu.reduce_to(buf, out, init.clone(), &f)
+        })
+    }
+}
+
+#[cfg(feature = "macro")]
+impl<T, S, D> UnaryGrad<T, S> for D
+where
+    T: Clone + Default,
+    S: Shape,
+    CPU: UnaryGrad<T, S>,
+    D: FallbackOps + Read<T, S> + WriteBuf<T, S>,
+    for<'r> <D as Read<T, S>>::Read<'r>: Into<Vec<T>>,
+{
+    #[track_caller]
+    fn add_unary_grad<F>(
+        &self,
+        lhs: &Buffer<T, Self, S>,
+        lhs_grad: &mut Buffer<T, Self, S>,
+        out_grad: &Buffer<T, Self, S>,
+        lhs_grad_fn: impl Fn(Resolve<T>) -> F,
+    ) where
+        F: Eval<T> + MayToCLSource,
+    {
+        self.fallback_mut(
+            "add_unary_grad",
+            lhs_grad,
+            [lhs, out_grad],
+            |cpu, lhs_grad, [lhs, out_grad]| {
+                cpu.add_unary_grad(lhs, lhs_grad, out_grad, &lhs_grad_fn)
+            },
+        )
+        .unwrap()
+    }
+}
+
+impl<T, D> CopySlice<T> for D
+where
+    T: Clone + Default,
+    CPU: CopySlice<T>,
+    D: FallbackCopySlice + Read<T> + WriteBuf<T>,
+    for<'r> <D as Read<T>>::Read<'r>: Into<Vec<T>>,
+{
+    fn try_copy_slice_to<SR: RangeBounds<usize>, DR: RangeBounds<usize>>(
+        &self,
+        source: &Buffer<T, Self>,
+        source_range: SR,
+        dest: &mut Buffer<T, Self>,
+        dest_range: DR,
+    ) -> crate::Result<()> {
+        let source_range = checked_range(source_range, source.len())?;
+        let dest_range = checked_range(dest_range, dest.len())?;
+        if source_range.len() != dest_range.len() {
+            return Err(Error::LengthMismatch {
+                expected: dest_range.len(),
+                found: source_range.len(),
+            });
+        }
+
+        self.fallback_mut("copy_slice_to", dest, [source], |cpu, dest, [source]| {
+            cpu.copy_slice_to(source, source_range.clone(), dest, dest_range.clone())
+        })
+    }
+
+    fn copy_slice_all<I: IntoIterator<Item = (Range<usize>, Range<usize>)>>(
+        &self,
+        source: &Buffer<T, Self>,
+        dest: &mut Buffer<T, Self>,
+        ranges: I,
+    ) {
+        let ranges = ranges.into_iter().collect::<Vec<_>>();
+
+        self.fallback_mut("copy_slice_all", dest, [source], |cpu, dest, [source]| {
+            cpu.copy_slice_all(source, dest, ranges.iter().cloned())
+        })
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::{Buffer, CPU};
+
+    use super::{fallback_on_host, fallback_on_host_mut, set_fallback_hook, FallbackInfo};
+
+    static FALLBACKS: AtomicUsize = AtomicUsize::new(0);
+
+    fn count_fallback(fallback: &FallbackInfo) {
+        if fallback.op.starts_with("test_fallback") {
+            assert_eq!(fallback.device, core::any::type_name::<CPU>());
+            assert_eq!(fallback.len, 3);
+            FALLBACKS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_fallback_on_host_reports_to_hook() -> crate::Result<()> {
+        set_fallback_hook(Some(count_fallback));
+
+        let device = CPU::new();
+        let lhs = Buffer::from((&device, [1, 2, 3]));
+        let rhs = Buffer::from((&device, [4, 5, 6]));
+
+        let out = fallback_on_host(
+            &device,
+            "test_fallback_add",
+            [&lhs, &rhs],
+            |cpu, [lhs, rhs]| {
+                Buffer::from((cpu, [lhs[0] + rhs[0], lhs[1] + rhs[1], lhs[2] + rhs[2]]))
+            },
+        )?;
+        assert_eq!(out.as_slice(), [5, 7, 9]);
+
+        let mut lhs = lhs;
+        fallback_on_host_mut(
+            &device,
+            "test_fallback_add_mut",
+            &mut lhs,
+            [&rhs],
+            |_, lhs, [rhs]| {
+                for (lhs, rhs) in lhs.iter_mut().zip(rhs) {
+                    *lhs += rhs;
+                }
+            },
+        )?;
+        assert_eq!(lhs.as_slice(), [5, 7, 9]);
+
+        set_fallback_hook(None);
+        assert_eq!(FALLBACKS.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+}
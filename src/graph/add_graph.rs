@@ -62,3 +62,51 @@ impl<'a, T, D: Device, LS: Shape, RS: Shape> AddGraph
         (self.0.id().idx, self.1.id().idx)
     }
 }
+
+/// Marks the added [`Node`] as element-wise operation,
+/// which may write its output into the memory of its only input if that input is not used afterwards.
+/// Used by [`Device::retrieve_inplace_candidate`](crate::Device::retrieve_inplace_candidate).
+pub struct Inplace<A>(pub A);
+
+impl<A: AddGraph> AddGraph for Inplace<A> {
+    #[inline]
+    fn idxs(&self) -> (usize, usize) {
+        self.0.idxs()
+    }
+
+    #[inline]
+    fn add<IdxFrom: NodeIdx>(&self, graph: &mut Graph<IdxFrom>, len: usize) -> Node {
+        let node = self.0.add(graph, len);
+        graph.mark_inplace(node.idx);
+        node
+    }
+}
+
+/// Names the added [`Node`] after the operation that retrieved its [`Buffer`], e.g. `"apply_fn"`.
+/// The name is shown by the exporters of the [`Graph`], see [`Graph::to_dot`].
+/// # Example
+/// ```
+/// use custos::{Graph, Named, NodeCount};
+///
+/// let mut graph = Graph::<NodeCount>::new();
+/// let a = graph.add_leaf(10);
+/// let b = graph.add(10, Named("apply_fn", a.idx));
+///
+/// assert_eq!(graph.op_name(a.idx), None);
+/// assert_eq!(graph.op_name(b.idx), Some("apply_fn"));
+/// ```
+pub struct Named<A>(pub &'static str, pub A);
+
+impl<A: AddGraph> AddGraph for Named<A> {
+    #[inline]
+    fn idxs(&self) -> (usize, usize) {
+        self.1.idxs()
+    }
+
+    #[inline]
+    fn add<IdxFrom: NodeIdx>(&self, graph: &mut Graph<IdxFrom>, len: usize) -> Node {
+        let node = self.1.add(graph, len);
+        graph.set_op_name(node.idx, self.0);
+        node
+    }
+}
@@ -0,0 +1,303 @@
+use crate::{Graph, NodeIdx};
+
+/// The memory footprint of executing the [`Node`](crate::Node)s of a [`Graph`] in a specific order.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The maximum amount of bytes that are live at the same time.
+    pub peak_bytes: usize,
+    /// The amount of bytes that are allocated over the whole execution.
+    pub total_bytes: usize,
+}
+
+/// A proposed execution order for the [`Node`](crate::Node)s of a [`Graph`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExecOrder {
+    /// The indices of the nodes in the proposed execution order.
+    pub order: Vec<usize>,
+    /// The memory footprint of the order the graph was recorded in.
+    pub current: MemoryUsage,
+    /// The memory footprint of the proposed order.
+    pub proposed: MemoryUsage,
+}
+
+impl<IdxFrom: NodeIdx> Graph<IdxFrom> {
+    /// Returns the indices of the nodes a [`Node`](crate::Node) depends on, without duplicates and without itself.
+    pub(super) fn unique_deps(&self, idx: usize) -> impl Iterator<Item = usize> {
+        let [lhs, rhs] = self.nodes[idx].deps;
+        let len = self.nodes.len();
+        [Some(lhs), (lhs != rhs).then_some(rhs)]
+            .into_iter()
+            .flatten()
+            .filter(move |dep| *dep != idx && *dep < len)
+    }
+
+    /// Counts how many nodes use each node as a dependency.
+    fn consumer_counts(&self) -> Vec<usize> {
+        let mut consumers = vec![0; self.nodes.len()];
+        for node in &self.nodes {
+            for dep in self.unique_deps(node.idx) {
+                consumers[dep] += 1;
+            }
+        }
+        consumers
+    }
+
+    /// `true` if every [`Node`](crate::Node) appears exactly once and after all of its dependencies.
+    pub fn is_topological(&self, order: &[usize]) -> bool {
+        let mut executed = vec![false; self.nodes.len()];
+
+        for &idx in order {
+            if idx >= executed.len()
+                || executed[idx]
+                || self.unique_deps(idx).any(|dep| !executed[dep])
+            {
+                return false;
+            }
+            executed[idx] = true;
+        }
+
+        order.len() == self.nodes.len()
+    }
+
+    /// Simulates the execution of the [`Node`](crate::Node)s in the given order.
+    /// The memory of a node is allocated when it is executed and freed after its last consumer was executed.
+    /// Nodes without consumers stay live until the end.
+    /// # Example
+    /// ```
+    /// use custos::{Graph, NodeCount};
+    ///
+    /// let mut graph = Graph::<NodeCount>::new();
+    /// let a = graph.add_leaf(10);
+    /// let b = graph.add_node(100, a.idx, a.idx);
+    /// let _c = graph.add_node(10, b.idx, b.idx);
+    ///
+    /// let usage = graph.memory_usage(&[0, 1, 2]);
+    /// assert_eq!(usage.peak_bytes, 110);
+    /// assert_eq!(usage.total_bytes, 120);
+    /// ```
+    pub fn memory_usage(&self, order: &[usize]) -> MemoryUsage {
+        debug_assert!(self.is_topological(order), "The order is not topological.");
+
+        let mut consumers = self.consumer_counts();
+        let mut usage = MemoryUsage::default();
+        let mut live_bytes = 0;
+
+        for &idx in order {
+            let bytes = self.node_bytes(&self.nodes[idx]);
+            live_bytes += bytes;
+            usage.total_bytes += bytes;
+            usage.peak_bytes = usage.peak_bytes.max(live_bytes);
+
+            for dep in self.unique_deps(idx) {
+                consumers[dep] -= 1;
+                if consumers[dep] == 0 {
+                    live_bytes -= self.node_bytes(&self.nodes[dep]);
+                }
+            }
+        }
+
+        usage
+    }
+
+    /// Proposes a topological order of the [`Node`](crate::Node)s that keeps the peak memory low.
+    /// The order is built by [`min_memory_order`](Graph::min_memory_order).
+    /// If the recorded order has a lower (or the same) peak, the recorded order is proposed.
+    /// # Example
+    /// ```
+    /// use custos::{Graph, NodeCount};
+    ///
+    /// let mut graph = Graph::<NodeCount>::new();
+    /// let x = graph.add_leaf(10);
+    ///
+    /// let lhs = graph.add_node(1000, x.idx, x.idx);
+    /// let rhs = graph.add_node(1000, x.idx, x.idx);
+    ///
+    /// let lhs_sum = graph.add_node(1, lhs.idx, lhs.idx);
+    /// let rhs_sum = graph.add_node(1, rhs.idx, rhs.idx);
+    /// let _out = graph.add_node(1, lhs_sum.idx, rhs_sum.idx);
+    ///
+    /// let exec_order = graph.propose_order();
+    ///
+    /// assert_eq!(exec_order.order, [0, 1, 3, 2, 4, 5]);
+    /// assert!(exec_order.proposed.peak_bytes < exec_order.current.peak_bytes);
+    /// assert_eq!(exec_order.proposed.total_bytes, exec_order.current.total_bytes);
+    /// ```
+    pub fn propose_order(&self) -> ExecOrder {
+        let recorded = (0..self.nodes.len()).collect::<Vec<_>>();
+        let current = self.memory_usage(&recorded);
+
+        let order = self.min_memory_order();
+        let proposed = self.memory_usage(&order);
+
+        if proposed.peak_bytes < current.peak_bytes {
+            ExecOrder {
+                order,
+                current,
+                proposed,
+            }
+        } else {
+            ExecOrder {
+                order: recorded,
+                current,
+                proposed: current,
+            }
+        }
+    }
+
+    /// Greedily builds a topological order that tries to minimise the peak memory.
+    /// Nodes without dependencies (e.g. leafs) are placed right before their first consumer.
+    /// Of all other nodes that are ready to execute, the one that increases the live memory the least is executed next.
+    /// Ties are broken by the recorded order.
+    pub fn min_memory_order(&self) -> Vec<usize> {
+        let is_source = self
+            .nodes
+            .iter()
+            .map(|node| self.unique_deps(node.idx).next().is_none())
+            .collect::<Vec<_>>();
+
+        let mut consumers = self.consumer_counts();
+        let mut missing_deps = vec![0; self.nodes.len()];
+        let mut dependents = vec![vec![]; self.nodes.len()];
+
+        for node in &self.nodes {
+            for dep in self.unique_deps(node.idx) {
+                dependents[dep].push(node.idx);
+                if !is_source[dep] {
+                    missing_deps[node.idx] += 1;
+                }
+            }
+        }
+
+        let mut executed = vec![false; self.nodes.len()];
+        let mut ready = (0..self.nodes.len())
+            .filter(|idx| !is_source[*idx] && missing_deps[*idx] == 0)
+            .collect::<Vec<_>>();
+
+        let mut order = Vec::with_capacity(self.nodes.len());
+
+        while !ready.is_empty() {
+            let (pos, &idx) = ready
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, idx)| {
+                    let mut delta = self.node_bytes(&self.nodes[**idx]) as isize;
+                    for dep in self.unique_deps(**idx) {
+                        let dep_bytes = self.node_bytes(&self.nodes[dep]) as isize;
+                        if !executed[dep] {
+                            delta += dep_bytes;
+                        }
+                        if consumers[dep] == 1 {
+                            delta -= dep_bytes;
+                        }
+                    }
+                    (delta, **idx)
+                })
+                .unwrap();
+
+            ready.swap_remove(pos);
+
+            for dep in self.unique_deps(idx) {
+                if !executed[dep] {
+                    executed[dep] = true;
+                    order.push(dep);
+                }
+                consumers[dep] -= 1;
+            }
+
+            executed[idx] = true;
+            order.push(idx);
+
+            for &dependent in &dependents[idx] {
+                missing_deps[dependent] -= 1;
+                if missing_deps[dependent] == 0 {
+                    ready.push(dependent);
+                }
+            }
+        }
+
+        // sources without any consumers
+        order.extend((0..self.nodes.len()).filter(|idx| !executed[*idx]));
+
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Graph, MemoryUsage, NodeCount};
+
+    #[test]
+    fn test_memory_usage_recorded_order() {
+        let mut graph = Graph::<NodeCount>::new();
+        let x = graph.add_leaf(10);
+
+        let lhs = graph.add_node(1000, x.idx, x.idx);
+        let rhs = graph.add_node(1000, x.idx, x.idx);
+
+        let lhs_sum = graph.add_node(1, lhs.idx, lhs.idx);
+        let rhs_sum = graph.add_node(1, rhs.idx, rhs.idx);
+        let _out = graph.add_node(1, lhs_sum.idx, rhs_sum.idx);
+
+        assert_eq!(
+            graph.memory_usage(&[0, 1, 2, 3, 4, 5]),
+            MemoryUsage {
+                peak_bytes: 2010,
+                total_bytes: 2013
+            }
+        );
+        assert_eq!(
+            graph.memory_usage(&[0, 1, 3, 2, 4, 5]),
+            MemoryUsage {
+                peak_bytes: 1011,
+                total_bytes: 2013
+            }
+        );
+
+        assert!(!graph.is_topological(&[0, 3, 1, 2, 4, 5]));
+        assert!(!graph.is_topological(&[0, 1, 2, 3, 4]));
+        assert!(graph.is_topological(&graph.min_memory_order()));
+    }
+
+    #[test]
+    fn test_propose_order_keeps_recorded_order() {
+        let mut graph = Graph::<NodeCount>::new();
+        let a = graph.add_leaf(10);
+        let b = graph.add_node(10, a.idx, a.idx);
+        let _c = graph.add_node(10, b.idx, a.idx);
+
+        let exec_order = graph.propose_order();
+        assert_eq!(exec_order.order, [0, 1, 2]);
+        assert_eq!(exec_order.current, exec_order.proposed);
+    }
+
+    #[test]
+    fn test_propose_order_neural_net() {
+        let mut graph = Graph::<NodeCount>::new();
+        let inputs = graph.add_leaf(100 * 10);
+        let targets = graph.add_leaf(100);
+
+        // all weights are created before the forward pass
+        let w1 = graph.add_leaf(10 * 64);
+        let b1 = graph.add_leaf(64);
+        let w2 = graph.add_leaf(64 * 64);
+        let b2 = graph.add_leaf(64);
+
+        let a1 = graph.add_node(100 * 64, inputs.idx, w1.idx);
+        let a2 = graph.add_node(100 * 64, a1.idx, b1.idx);
+
+        let a3 = graph.add_node(100 * 64, a2.idx, w2.idx);
+        let a4 = graph.add_node(100 * 64, a3.idx, b2.idx);
+
+        let a5 = graph.add_node(100, a4.idx, a4.idx);
+        let _loss = graph.add_node(100, a5.idx, targets.idx);
+
+        let exec_order = graph.propose_order();
+
+        assert!(graph.is_topological(&exec_order.order));
+        assert!(exec_order.proposed.peak_bytes < exec_order.current.peak_bytes);
+        assert_eq!(
+            exec_order.proposed.total_bytes,
+            exec_order.current.total_bytes
+        );
+    }
+}
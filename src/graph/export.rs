@@ -0,0 +1,175 @@
+use core::fmt::Write;
+
+use crate::{json, Graph, Ident, NodeIdx};
+
+/// The version of the JSON schema written by [`Graph::to_json`] and `Tape::to_json` (`autograd` feature).
+/// It is increased whenever a key is renamed, removed or changes its meaning.
+pub const EXPORT_VERSION: usize = 1;
+
+/// The fill colours of the cache traces in DOT graphs. Repeats after 12 traces.
+const TRACE_COLORS: [&str; 12] = [
+    "#8dd3c7", "#ffffb3", "#bebada", "#fb8072", "#80b1d3", "#fdb462", "#b3de69", "#fccde5",
+    "#d9d9d9", "#bc80bd", "#ccebc5", "#ffed6f",
+];
+
+/// Escapes `value` for a quoted DOT string.
+pub(crate) fn dot_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl<IdxFrom: NodeIdx> Graph<IdxFrom> {
+    /// Returns the graph in the Graphviz DOT format, e.g. to render it with `dot -Tsvg`.
+    ///
+    /// Every [`Node`](crate::Node) is labeled with its index, the name of its operation (see [`Named`](crate::Named)), its length and element size.
+    /// An edge points from a dependency to the node that uses it.
+    /// Nodes of the same [`shared_cache_traces`](Graph::shared_cache_traces) entry, which share their memory after an optimization,
+    /// have the same fill colour. Nodes marked with [`mark_inplace`](Graph::mark_inplace) have a double border.
+    /// # Example
+    /// ```
+    /// use custos::{Graph, Named, NodeCount};
+    ///
+    /// let mut graph = Graph::<NodeCount>::new();
+    /// let a = graph.add_leaf(10);
+    /// let b = graph.add(10, Named("exp", a.idx));
+    /// let _c = graph.add(10, Named("ln", b.idx));
+    ///
+    /// let dot = graph.to_dot();
+    /// assert!(dot.starts_with("digraph custos {"));
+    /// // `ln` can write into the memory of `exp`, hence both share a colour
+    /// assert!(dot.contains(r##"n1 [label="#1 exp\n10 x 1 B", style=filled, fillcolor="#8dd3c7"];"##));
+    /// assert!(dot.contains(r##"n2 [label="#2 ln\n10 x 1 B", style=filled, fillcolor="#8dd3c7"];"##));
+    /// assert!(dot.contains("n0 -> n1;"));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let traces = self.node_traces();
+
+        let mut dot = String::from("digraph custos {\n    node [shape=box];\n");
+        for node in &self.nodes {
+            let op = self
+                .op_name(node.idx)
+                .unwrap_or(if node.is_leaf() { "leaf" } else { "node" });
+
+            write!(
+                dot,
+                r##"    n{} [label="#{} {}\n{} x {} B""##,
+                node.idx,
+                node.idx,
+                dot_escape(op),
+                node.len,
+                self.elem_size(node.idx)
+            )
+            .unwrap();
+
+            if let Some(trace) = traces[node.idx] {
+                write!(
+                    dot,
+                    r#", style=filled, fillcolor="{}""#,
+                    TRACE_COLORS[trace % TRACE_COLORS.len()]
+                )
+                .unwrap();
+            }
+            if self.inplace_ops.contains(&node.idx) {
+                dot.push_str(", peripheries=2");
+            }
+            dot.push_str("];\n");
+        }
+
+        for node in &self.nodes {
+            for dep in self.unique_deps(node.idx) {
+                writeln!(dot, "    n{dep} -> n{};", node.idx).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Returns the graph as JSON, e.g. to compare the graphs of two runs.
+    ///
+    /// The schema is `{"version": 1, "nodes": [..], "cache_traces": [..]}`, see [`EXPORT_VERSION`].
+    /// Every node is an object with the keys
+    /// - `idx`: the index of the [`Node`](crate::Node)
+    /// - `ident`: the cache [`Ident`] (`{"idx": .., "len": ..}`) of the node or `null`
+    /// - `op`: the name of the operation (see [`Named`](crate::Named)) or `null`
+    /// - `deps`: the indices of both dependencies
+    /// - `len`, `elem_size`: the amount of elements and the size of one element in bytes
+    /// - `leaf`, `inplace`: whether the node is a leaf or marked with [`mark_inplace`](Graph::mark_inplace)
+    /// - `trace`: the index of the cache trace that contains the node or `null`
+    ///
+    /// The cache traces are the [`shared_cache_traces`](Graph::shared_cache_traces) with the keys `cache_id` and `use_cache_ids`.
+    /// # Example
+    /// ```
+    /// use custos::{Graph, Named, NodeCount};
+    ///
+    /// let mut graph = Graph::<NodeCount>::new();
+    /// let a = graph.add_leaf(10);
+    /// let _b = graph.add(10, Named("exp", a.idx));
+    ///
+    /// assert_eq!(
+    ///     graph.to_json(),
+    ///     concat!(
+    ///         r#"{"version":1,"nodes":["#,
+    ///         r#"{"idx":0,"ident":{"idx":0,"len":10},"op":null,"deps":[0,0],"len":10,"elem_size":1,"leaf":true,"inplace":false,"trace":null},"#,
+    ///         r#"{"idx":1,"ident":{"idx":1,"len":10},"op":"exp","deps":[0,0],"len":10,"elem_size":1,"leaf":false,"inplace":false,"trace":null}"#,
+    ///         r#"],"cache_traces":[]}"#
+    ///     )
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let traces = self.node_traces();
+
+        let nodes = self.nodes.iter().map(|node| {
+            let ident = self.idx_trans.get(&node.idx).map(|idx| Ident {
+                idx: *idx,
+                len: node.len,
+            });
+
+            format!(
+                r#"{{"idx":{},"ident":{},"op":{},"deps":[{},{}],"len":{},"elem_size":{},"leaf":{},"inplace":{},"trace":{}}}"#,
+                node.idx,
+                json::opt_ident(ident),
+                json::opt_string(self.op_name(node.idx)),
+                node.deps[0],
+                node.deps[1],
+                node.len,
+                self.elem_size(node.idx),
+                node.is_leaf(),
+                self.inplace_ops.contains(&node.idx),
+                traces[node.idx].map_or_else(|| "null".into(), |trace| trace.to_string())
+            )
+        });
+
+        let cache_traces = self.shared_cache_traces().into_iter().map(|trace| {
+            format!(
+                r#"{{"cache_id":{},"use_cache_ids":{}}}"#,
+                json::ident(trace.cache_id),
+                json::array(trace.use_cache_ids.into_iter().map(json::ident))
+            )
+        });
+
+        format!(
+            r#"{{"version":{},"nodes":{},"cache_traces":{}}}"#,
+            EXPORT_VERSION,
+            json::array(nodes),
+            json::array(cache_traces)
+        )
+    }
+
+    /// Returns the index of the [`shared_cache_traces`](Graph::shared_cache_traces) entry of every [`Node`](crate::Node).
+    fn node_traces(&self) -> Vec<Option<usize>> {
+        let mut traces = vec![None; self.nodes.len()];
+
+        let shared = self
+            .shared_buffers()
+            .into_iter()
+            .filter(|buf| !buf.users.is_empty());
+
+        for (trace, buf) in shared.enumerate() {
+            for node in core::iter::once(&buf.owner).chain(&buf.users) {
+                if let Some(node_trace) = traces.get_mut(node.idx) {
+                    *node_trace = Some(trace);
+                }
+            }
+        }
+        traces
+    }
+}
@@ -1,7 +1,7 @@
-use core::{hash::BuildHasherDefault, marker::PhantomData};
+use core::{hash::BuildHasherDefault, iter::once, marker::PhantomData, ops::RangeInclusive};
 use std::collections::{HashMap, HashSet};
 
-use crate::{get_count, AddGraph, CacheTrace, GlobalCount, Ident, IdentHasher, Node, NodeIdx};
+use crate::{ident_idx, AddGraph, CacheTrace, GlobalCount, Ident, IdentHasher, Node, NodeIdx};
 
 /// A graph of [`Node`]s.
 /// It is typically built up during the forward process. (calling `device.retrieve(.., (lhs, rhs))`)
@@ -11,13 +11,28 @@ pub struct Graph<IdxFrom: NodeIdx> {
     pub nodes: Vec<Node>,
     /// Translates the index to a [`Node`] in the graph, to an index in the cache / global count.
     pub idx_trans: HashMap<usize, usize, BuildHasherDefault<IdentHasher>>,
+    /// The size of one element in bytes, keyed by the cache index of a [`Node`] (see `idx_trans`).
+    /// Entries without a size use one byte.
+    pub elem_sizes: HashMap<usize, usize, BuildHasherDefault<IdentHasher>>,
+    /// The indices of the [`Node`]s that were added with [`Inplace`](crate::Inplace).
+    pub inplace_ops: HashSet<usize, BuildHasherDefault<IdentHasher>>,
+    /// The name of the operation that added a [`Node`], e.g. `"apply_fn"`, keyed by the index of the [`Node`].
+    /// Set with [`Named`](crate::Named), leafs and unnamed operations have no entry.
+    pub op_names: HashMap<usize, &'static str, BuildHasherDefault<IdentHasher>>,
     _pd: PhantomData<IdxFrom>,
 }
 
+/// A cache allocation that is shared by multiple groups of [`Node`]s with disjoint lifetimes.
+pub(super) struct SharedBuffer {
+    pub(super) owner: Node,
+    pub(super) users: Vec<Node>,
+    last_use: usize,
+}
+
 impl NodeIdx for GlobalCount {
     #[inline]
     fn idx(_nodes: &[Node]) -> usize {
-        get_count()
+        ident_idx()
     }
 }
 
@@ -33,6 +48,9 @@ impl<IdxFrom: NodeIdx> Graph<IdxFrom> {
         Self {
             nodes: Vec::new(),
             idx_trans: HashMap::default(),
+            elem_sizes: HashMap::default(),
+            inplace_ops: HashSet::default(),
+            op_names: HashMap::default(),
             _pd: PhantomData,
         }
     }
@@ -73,10 +91,305 @@ impl<IdxFrom: NodeIdx> Graph<IdxFrom> {
         node
     }
 
+    /// Sets the size of one element of the [`Node`] at `idx` in bytes.
+    /// Nodes are created with an element size of one byte.
+    #[inline]
+    pub fn set_elem_size(&mut self, idx: usize, elem_size: usize) {
+        if let Some(ident_idx) = self.idx_trans.get(&idx) {
+            self.elem_sizes.insert(*ident_idx, elem_size);
+        }
+    }
+
+    /// Returns the size of one element of the [`Node`] at `idx` in bytes.
+    #[inline]
+    pub fn elem_size(&self, idx: usize) -> usize {
+        self.idx_trans
+            .get(&idx)
+            .map_or(1, |ident_idx| self.ident_elem_size(*ident_idx))
+    }
+
+    /// Returns the size of one element of the cache entry at `ident_idx` in bytes.
+    #[inline]
+    fn ident_elem_size(&self, ident_idx: usize) -> usize {
+        self.elem_sizes.get(&ident_idx).copied().unwrap_or(1)
+    }
+
+    /// Sets the name of the operation that added the [`Node`] at `idx`.
+    #[inline]
+    pub fn set_op_name(&mut self, idx: usize, name: &'static str) {
+        if idx < self.nodes.len() {
+            self.op_names.insert(idx, name);
+        }
+    }
+
+    /// Returns the name of the operation that added the [`Node`] at `idx`, if it was set.
+    #[inline]
+    pub fn op_name(&self, idx: usize) -> Option<&'static str> {
+        self.op_names.get(&idx).copied()
+    }
+
+    /// Returns the amount of bytes a [`Buffer`](crate::Buffer) of the given [`Node`] occupies.
+    #[inline]
+    pub fn node_bytes(&self, node: &Node) -> usize {
+        node.len * self.elem_size(node.idx)
+    }
+
+    /// Returns the amount of bytes the cache entry with the given [`Ident`] occupies.
+    #[inline]
+    pub fn ident_bytes(&self, ident: &Ident) -> usize {
+        ident.len * self.ident_elem_size(ident.idx)
+    }
+
+    /// Marks the [`Node`] at `idx` as element-wise operation, which could use the memory of its input as output.
+    #[inline]
+    pub fn mark_inplace(&mut self, idx: usize) {
+        self.inplace_ops.insert(idx);
+    }
+
+    /// Returns every marked [`Node`] (see [`mark_inplace`](Graph::mark_inplace)) whose only input dies at this node, together with this input.
+    /// Such a node can write its output into the memory of the input.
+    /// Leafs are never used as input, as they typically hold user data.
+    /// # Example
+    /// ```
+    /// use custos::{Graph, NodeCount};
+    ///
+    /// let mut graph = Graph::<NodeCount>::new();
+    /// let a = graph.add_leaf(10);
+    /// let b = graph.add_node(10, a.idx, a.idx);
+    ///
+    /// // idx: 2, uses b, but b is used later on
+    /// let _c = graph.add_node(10, b.idx, a.idx);
+    ///
+    /// // idx: 3, b dies here
+    /// let d = graph.add_node(10, b.idx, b.idx);
+    /// graph.mark_inplace(d.idx);
+    ///
+    /// assert_eq!(graph.inplace_candidates(), [(d, b)]);
+    /// ```
+    pub fn inplace_candidates(&self) -> Vec<(Node, Node)> {
+        let intervals = self.live_intervals();
+
+        self.nodes
+            .iter()
+            .filter(|node| self.inplace_ops.contains(&node.idx))
+            .filter_map(|node| {
+                let [input, rhs] = node.deps;
+                if input != rhs || input >= node.idx {
+                    return None;
+                }
+
+                let input = self.nodes[input];
+                let fits = !input.is_leaf()
+                    && *intervals[input.idx].end() == node.idx
+                    && input.len == node.len
+                    && self.elem_size(input.idx) == self.elem_size(node.idx);
+
+                fits.then_some((*node, input))
+            })
+            .collect()
+    }
+
+    /// Calculates the live interval of every [`Node`].
+    /// A node is live from its creation until the last node that uses it as a dependency.
+    /// Nodes without any consumers are considered live until the end of the graph.
+    /// # Example
+    /// ```
+    /// use custos::{Graph, NodeCount};
+    ///
+    /// let mut graph = Graph::<NodeCount>::new();
+    /// let a = graph.add_leaf(10);
+    /// let b = graph.add_node(10, a.idx, a.idx);
+    /// let _c = graph.add_node(10, b.idx, a.idx);
+    ///
+    /// assert_eq!(graph.live_intervals(), [0..=2, 1..=2, 2..=3]);
+    /// ```
+    pub fn live_intervals(&self) -> Vec<RangeInclusive<usize>> {
+        let mut last_uses = vec![None; self.nodes.len()];
+
+        for node in &self.nodes {
+            for dep in node.deps {
+                if dep == node.idx || dep >= last_uses.len() {
+                    continue;
+                }
+                last_uses[dep] = Some(node.idx);
+            }
+        }
+
+        self.nodes
+            .iter()
+            .zip(last_uses)
+            .map(|(node, last_use)| node.idx..=last_use.unwrap_or(self.nodes.len()))
+            .collect()
+    }
+
     /// Calculates multiple unique [`CacheTrace`]s.
     /// Unique meaning that no two [`CacheTrace`]s share some same [`Node`].
     pub fn cache_traces(&self) -> Vec<CacheTrace> {
-        let mut traces = vec![];
+        self.cache_groups()
+            .into_iter()
+            .map(|(node, trace)| CacheTrace {
+                cache_id: Ident {
+                    idx: node.idx,
+                    len: node.len,
+                },
+                use_cache_ids: trace.iter().map(|node| self.ident(node)).collect(),
+            })
+            .collect()
+    }
+
+    /// Calculates [`CacheTrace`]s that also share memory between [`Buffer`](crate::Buffer)s of different lengths.
+    /// Every group of [`cache_traces`](Graph::cache_traces) (and every non-leaf [`Node`] outside of a group) is
+    /// assigned to the smallest allocation that is large enough and whose previous users are not live anymore.
+    /// Groups starting with an [`inplace_candidate`](Graph::inplace_candidates) directly reuse the allocation of their input.
+    /// The `use_cache_ids` of a resulting [`CacheTrace`] may therefore be shorter than the `cache_id`.
+    ///
+    /// Contrary to [`cache_traces`](Graph::cache_traces), the `cache_id` is a translated cache [`Ident`].
+    /// # Example
+    /// ```
+    /// use custos::{CacheTrace, Graph, Ident, NodeCount};
+    ///
+    /// let mut graph = Graph::<NodeCount>::new();
+    /// let a = graph.add_leaf(100);
+    ///
+    /// let b = graph.add_node(100, a.idx, a.idx);
+    /// let c = graph.add_node(10, b.idx, b.idx);
+    /// let _d = graph.add_node(5, c.idx, c.idx);
+    ///
+    /// assert_eq!(
+    ///     graph.shared_cache_traces(),
+    ///     [CacheTrace {
+    ///         cache_id: Ident { idx: 1, len: 100 },
+    ///         use_cache_ids: vec![Ident { idx: 3, len: 5 }],
+    ///     }]
+    /// );
+    /// ```
+    pub fn shared_cache_traces(&self) -> Vec<CacheTrace> {
+        self.shared_buffers()
+            .into_iter()
+            .filter(|buf| !buf.users.is_empty())
+            .map(|buf| CacheTrace {
+                cache_id: self.ident(&buf.owner),
+                use_cache_ids: buf.users.iter().map(|node| self.ident(node)).collect(),
+            })
+            .collect()
+    }
+
+    /// Returns the amount of bytes the cache occupies for all non-leaf [`Node`]s,
+    /// if the entries in the `use_cache_ids` of the given [`CacheTrace`]s do not allocate memory on their own.
+    /// As the cache keeps every entry alive, this is the peak memory of the cached buffers.
+    /// # Example
+    /// ```
+    /// use custos::{Graph, NodeCount};
+    ///
+    /// let mut graph = Graph::<NodeCount>::new();
+    /// let a = graph.add_leaf(100);
+    ///
+    /// let b = graph.add_node(100, a.idx, a.idx);
+    /// let c = graph.add_node(10, b.idx, b.idx);
+    /// let _d = graph.add_node(5, c.idx, c.idx);
+    ///
+    /// assert_eq!(graph.cache_bytes(&[]), 115);
+    /// assert_eq!(graph.cache_bytes(&graph.cache_traces()), 115);
+    /// assert_eq!(graph.cache_bytes(&graph.shared_cache_traces()), 110);
+    /// ```
+    pub fn cache_bytes(&self, traces: &[CacheTrace]) -> usize {
+        let total = self
+            .nodes
+            .iter()
+            .filter(|node| !node.is_leaf())
+            .map(|node| self.node_bytes(node))
+            .sum::<usize>();
+
+        let shared = traces
+            .iter()
+            .flat_map(|trace| &trace.use_cache_ids)
+            .map(|ident| self.ident_bytes(ident))
+            .sum::<usize>();
+
+        total.saturating_sub(shared)
+    }
+
+    /// Greedily assigns the groups of [`Node`]s to allocations, ordered by their first use.
+    pub(super) fn shared_buffers(&self) -> Vec<SharedBuffer> {
+        let intervals = self.live_intervals();
+        let mut groups = self.cache_groups();
+
+        // non-leaf nodes that are not part of a cache trace still need their own allocation
+        let grouped = groups
+            .iter()
+            .flat_map(|(node, trace)| once(node.idx).chain(trace.iter().map(|node| node.idx)))
+            .collect::<HashSet<_>>();
+
+        groups.extend(
+            self.nodes
+                .iter()
+                .filter(|node| !node.is_leaf() && !grouped.contains(&node.idx))
+                .map(|node| (*node, vec![])),
+        );
+        groups.sort_by_key(|(node, _)| node.idx);
+
+        let inplace_inputs = self
+            .inplace_candidates()
+            .into_iter()
+            .map(|(node, input)| (node.idx, input.idx))
+            .collect::<HashMap<_, _>>();
+
+        let mut buffers: Vec<SharedBuffer> = vec![];
+
+        for (node, trace) in groups {
+            let last_use = once(&node)
+                .chain(&trace)
+                .map(|node| *intervals[node.idx].end())
+                .max()
+                .unwrap_or(node.idx);
+
+            // the input dies at this node, hence its allocation can be overwritten
+            let input_buf = inplace_inputs.get(&node.idx).and_then(|input| {
+                buffers.iter_mut().find(|buf| {
+                    buf.last_use == node.idx
+                        && (buf.owner.idx == *input
+                            || buf.users.iter().any(|user| user.idx == *input))
+                })
+            });
+
+            if let Some(buf) = input_buf {
+                buf.last_use = last_use;
+                buf.users.push(node);
+                buf.users.extend(trace);
+                continue;
+            }
+
+            let elem_size = self.elem_size(node.idx);
+
+            let best_fit = buffers
+                .iter_mut()
+                .filter(|buf| {
+                    buf.last_use < node.idx
+                        && buf.owner.len >= node.len
+                        && self.elem_size(buf.owner.idx) == elem_size
+                })
+                .min_by_key(|buf| buf.owner.len);
+
+            match best_fit {
+                Some(buf) => {
+                    buf.last_use = last_use;
+                    buf.users.push(node);
+                    buf.users.extend(trace);
+                }
+                None => buffers.push(SharedBuffer {
+                    owner: node,
+                    users: trace,
+                    last_use,
+                }),
+            }
+        }
+
+        buffers
+    }
+
+    /// Collects the starting [`Node`] and the remaining nodes of every unique cache trace.
+    fn cache_groups(&self) -> Vec<(Node, Vec<Node>)> {
+        let mut groups = vec![];
         let mut visited_nodes = HashSet::new();
 
         for node in self.nodes.iter().filter(|node| !node.is_leaf()) {
@@ -90,28 +403,24 @@ impl<IdxFrom: NodeIdx> Graph<IdxFrom> {
                 continue;
             }
 
-            traces.push(CacheTrace {
-                cache_id: Ident {
-                    idx: node.idx,
-                    len: node.len,
-                },
-                use_cache_ids: trace
-                    .into_iter()
-                    .filter_map(|node| {
-                        if visited_nodes.contains(&node) {
-                            return None;
-                        }
-                        visited_nodes.insert(node);
-                        Some(Ident {
-                            idx: *self.idx_trans.get(&node.idx).unwrap(),
-                            len: node.len,
-                        })
-                    })
-                    .collect(),
-            });
+            let trace = trace
+                .into_iter()
+                .filter(|node| visited_nodes.insert(*node))
+                .collect();
+
+            groups.push((*node, trace));
         }
 
-        traces
+        groups
+    }
+
+    /// Returns the cache [`Ident`] of a [`Node`].
+    #[inline]
+    pub(super) fn ident(&self, node: &Node) -> Ident {
+        Ident {
+            idx: *self.idx_trans.get(&node.idx).unwrap(),
+            len: node.len,
+        }
     }
 
     /// Calculates the cache trace for a starting node.
@@ -4,7 +4,9 @@ use crate::Ident;
 use core::cell::{Ref, RefMut};
 
 #[cfg(feature = "opt-cache")]
-use crate::{CacheReturn, DeviceError};
+use crate::{flag::AllocFlag, CacheReturn, DeviceError, PtrType};
+#[cfg(feature = "opt-cache")]
+use std::sync::Arc;
 
 pub use add_graph::*;
 pub use node::*;
@@ -12,9 +14,19 @@ pub use node::*;
 mod add_graph;
 mod node;
 
+#[cfg(not(feature = "no-std"))]
+mod exec_order;
+#[cfg(not(feature = "no-std"))]
+mod export;
 #[cfg(not(feature = "no-std"))]
 mod graph_struct;
 
+#[cfg(not(feature = "no-std"))]
+pub use exec_order::*;
+
+#[cfg(not(feature = "no-std"))]
+pub use export::*;
+
 #[cfg(not(feature = "no-std"))]
 pub use graph_struct::*;
 
@@ -53,6 +65,14 @@ impl<IdxFrom: NodeIdx> Graph<IdxFrom> {
     pub fn add_node(&mut self, _len: usize, _lhs_idx: usize, _rhs_idx: usize) -> Node {
         unimplemented!("Not available in no-std mode")
     }
+
+    /// This function does nothing in no-std mode.
+    #[inline]
+    pub fn mark_inplace(&mut self, _idx: usize) {}
+
+    /// This function does nothing in no-std mode.
+    #[inline]
+    pub fn set_op_name(&mut self, _idx: usize, _name: &'static str) {}
 }
 
 /// A `CacheTrace` is a list of nodes that shows which [`Buffer`](crate::Buffer)s could use the same cache.
@@ -77,24 +97,46 @@ pub trait GraphReturn<IdxFrom: NodeIdx = GlobalCount> {
 #[cfg(feature = "opt-cache")]
 pub trait GraphOpt {
     /// Optimizes [`Graph`] and [`Cache`](crate::Cache) to achive a lower memory footprint.
-    fn optimize(&self) -> crate::Result<()>
+    /// Cache entries with non-overlapping lifetimes share the same allocation, even if their lengths differ.
+    /// Returns the amount of bytes that were freed.
+    fn optimize(&self) -> crate::Result<usize>
     where
         Self: GraphReturn + CacheReturn + crate::PtrConv,
     {
+        let graph = self.graph();
         let mut cache = self.cache_mut();
-        for trace in self.graph().cache_traces() {
-            for node in &trace.use_cache_ids {
+        let mut saved_bytes = 0;
+
+        for trace in graph.shared_cache_traces() {
+            let cache_ptr = cache
+                .nodes
+                .get(&trace.cache_id)
+                .ok_or(DeviceError::GraphOptimization)?
+                .clone();
+
+            for ident in &trace.use_cache_ids {
+                // a shorter buffer receives a length-clamped wrapper of the common pointer,
+                // which stays owned by the cache entry of `cache_id`
+                let ptr = if ident.len == trace.cache_id.len {
+                    cache_ptr.clone()
+                } else {
+                    // the wrapper is not counted as reference, hence the entry must not be evicted
+                    cache.pinned.insert(trace.cache_id);
+                    Arc::new(unsafe {
+                        Self::convert_with_len(&*cache_ptr, ident.len, AllocFlag::Wrapper)
+                    })
+                };
+
                 // insert the common / optimized pointer in all the other nodes
                 // this deallocates the old pointers
-                let ptr = cache
-                    .nodes
-                    .get(&trace.cache_id)
-                    .ok_or(DeviceError::GraphOptimization)?
-                    .clone();
-                cache.nodes.insert(*node, ptr);
+                if let Some(old_ptr) = cache.insert(*ident, ptr) {
+                    if Arc::strong_count(&old_ptr) == 1 && old_ptr.flag() != AllocFlag::Wrapper {
+                        saved_bytes += graph.ident_bytes(ident);
+                    }
+                }
             }
         }
-        Ok(())
+        Ok(saved_bytes)
     }
 }
 
@@ -336,7 +378,7 @@ mod tests {
         let b2 = graph.add_leaf(64);
         let w3 = graph.add_leaf(64 * 64);
         let b3 = graph.add_leaf(64);
-        let w4 = graph.add_leaf(64 * 1);
+        let w4 = graph.add_leaf(64);
         let b4 = graph.add_leaf(1);
 
         let a1 = graph.add_node(100 * 64, inputs.idx, w1.idx);
@@ -350,8 +392,8 @@ mod tests {
         let a5 = graph.add_node(100 * 64, a4.idx, w3.idx);
         let a6 = graph.add_node(100 * 64, a5.idx, b3.idx);
         let a6 = graph.add_node(100 * 64, a6.idx, a6.idx);
-        let a7 = graph.add_node(100 * 1, a6.idx, w4.idx);
-        let a8 = graph.add_node(100 * 1, a7.idx, b4.idx);
+        let a7 = graph.add_node(100, a6.idx, w4.idx);
+        let a8 = graph.add_node(100, a7.idx, b4.idx);
 
         let _loss = graph.add_node(100, a8.idx, targets.idx);
 
@@ -382,12 +424,189 @@ mod tests {
                     ]
                 }
             ]
-        )
+        );
+
+        assert_eq!(graph.cache_bytes(&[]), 9 * 6400 + 3 * 100);
+        assert_eq!(graph.cache_bytes(&traces), 6400 + 100);
+        assert_eq!(graph.cache_bytes(&graph.shared_cache_traces()), 6400 + 100);
 
         // graph.add_node(10*10, gemm.idx, gemm.idx);
         // bump_count();
     }
 
+    #[test]
+    fn test_shared_cache_traces_diff_len() {
+        let mut graph = Graph::<NodeCount>::new();
+        let a = graph.add_leaf(12);
+
+        // idx: 1, deps: [0, 0]
+        let b = graph.add_node(12, a.idx, a.idx);
+        // idx: 2, deps: [1, 1]
+        let c = graph.add_node(12, b.idx, b.idx);
+
+        // idx: 3, deps: [2, 2]
+        let d = graph.add_node(10, c.idx, c.idx);
+        // idx: 4, deps: [3, 3]
+        let e = graph.add_node(10, d.idx, d.idx);
+
+        // idx: 5, deps: [4, 4], 1 and 2 are not live anymore
+        let _f = graph.add_node(8, e.idx, e.idx);
+
+        assert_eq!(graph.live_intervals()[2], 2..=3);
+
+        assert_eq!(
+            graph.shared_cache_traces(),
+            [
+                CacheTrace {
+                    cache_id: Ident { idx: 1, len: 12 },
+                    use_cache_ids: vec![Ident { idx: 2, len: 12 }, Ident { idx: 5, len: 8 }]
+                },
+                CacheTrace {
+                    cache_id: Ident { idx: 3, len: 10 },
+                    use_cache_ids: vec![Ident { idx: 4, len: 10 }]
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shared_cache_traces_diff_elem_size() {
+        let mut graph = Graph::<NodeCount>::new();
+        let a = graph.add_leaf(12);
+
+        let b = graph.add_node(12, a.idx, a.idx);
+        let c = graph.add_node(10, b.idx, b.idx);
+        let d = graph.add_node(8, c.idx, c.idx);
+        graph.set_elem_size(d.idx, 4);
+
+        assert_eq!(graph.node_bytes(&d), 32);
+        assert_eq!(graph.shared_cache_traces(), []);
+    }
+
+    #[test]
+    fn test_ident_bytes_uses_cache_index() {
+        let mut graph = Graph::<NodeCount>::new();
+        let a = graph.add_leaf(12);
+        let b = graph.add_node(12, a.idx, a.idx);
+
+        // the cache entry of b is at index 7
+        graph.idx_trans.insert(b.idx, 7);
+        graph.set_elem_size(b.idx, 4);
+
+        assert_eq!(graph.node_bytes(&b), 48);
+        assert_eq!(graph.ident_bytes(&Ident { idx: 7, len: 12 }), 48);
+
+        // entries without a size use one byte per element
+        assert_eq!(
+            graph.ident_bytes(&Ident {
+                idx: b.idx,
+                len: 12
+            }),
+            12
+        );
+    }
+
+    #[test]
+    fn test_shared_cache_traces_inplace() {
+        let mut graph = Graph::<NodeCount>::new();
+        let a = graph.add_leaf(10);
+
+        // idx: 1, deps: [0, 0]
+        let b = graph.add_node(10, a.idx, a.idx);
+        // idx: 2, deps: [1, 1]
+        let _c = graph.add_node(10, b.idx, b.idx);
+
+        // idx: 3, deps: [1, 1], b dies here
+        let d = graph.add_node(10, b.idx, b.idx);
+        graph.mark_inplace(d.idx);
+
+        // idx: 4, deps: [3, 3]
+        let _e = graph.add_node(10, d.idx, d.idx);
+
+        assert_eq!(graph.cache_traces()[0].cache_id, Ident { idx: 3, len: 10 });
+        assert_eq!(graph.inplace_candidates(), [(d, b)]);
+
+        let traces = graph.shared_cache_traces();
+        assert_eq!(
+            traces,
+            [CacheTrace {
+                cache_id: Ident { idx: 1, len: 10 },
+                use_cache_ids: vec![Ident { idx: 3, len: 10 }, Ident { idx: 4, len: 10 }]
+            }]
+        );
+        assert_eq!(graph.cache_bytes(&traces), 20);
+    }
+
+    #[test]
+    fn test_no_inplace_if_input_is_used_later() {
+        let mut graph = Graph::<NodeCount>::new();
+        let a = graph.add_leaf(10);
+
+        let b = graph.add_node(10, a.idx, a.idx);
+        let c = graph.add_node(10, b.idx, b.idx);
+        graph.mark_inplace(c.idx);
+        let _d = graph.add_node(10, b.idx, c.idx);
+
+        // a leaf is never overwritten
+        let e = graph.add_node(10, a.idx, a.idx);
+        graph.mark_inplace(e.idx);
+
+        assert_eq!(graph.inplace_candidates(), []);
+    }
+
+    #[test]
+    fn test_shared_cache_traces_neural_net() {
+        let mut graph = Graph::<NodeCount>::new();
+        let inputs = graph.add_leaf(100 * 10);
+        let targets = graph.add_leaf(100);
+
+        let w1 = graph.add_leaf(10 * 64);
+        let b1 = graph.add_leaf(64);
+        let w2 = graph.add_leaf(64 * 32);
+        let b2 = graph.add_leaf(32);
+        let w3 = graph.add_leaf(32);
+        let b3 = graph.add_leaf(1);
+
+        let a1 = graph.add_node(100 * 64, inputs.idx, w1.idx);
+        let a2 = graph.add_node(100 * 64, a1.idx, b1.idx);
+        let a2 = graph.add_node(100 * 64, a2.idx, a2.idx);
+
+        let a3 = graph.add_node(100 * 32, a2.idx, w2.idx);
+        let a4 = graph.add_node(100 * 32, a3.idx, b2.idx);
+        let a4 = graph.add_node(100 * 32, a4.idx, a4.idx);
+
+        let a5 = graph.add_node(100, a4.idx, w3.idx);
+        let a6 = graph.add_node(100, a5.idx, b3.idx);
+        let _loss = graph.add_node(100, a6.idx, targets.idx);
+
+        let traces = graph.cache_traces();
+        let shared_traces = graph.shared_cache_traces();
+
+        assert_eq!(
+            shared_traces,
+            [
+                CacheTrace {
+                    cache_id: Ident { idx: 8, len: 6400 },
+                    use_cache_ids: vec![
+                        Ident { idx: 9, len: 6400 },
+                        Ident { idx: 10, len: 6400 },
+                        Ident { idx: 14, len: 100 },
+                        Ident { idx: 15, len: 100 },
+                        Ident { idx: 16, len: 100 },
+                    ]
+                },
+                CacheTrace {
+                    cache_id: Ident { idx: 11, len: 3200 },
+                    use_cache_ids: vec![Ident { idx: 12, len: 3200 }, Ident { idx: 13, len: 3200 },]
+                },
+            ]
+        );
+
+        assert_eq!(graph.cache_bytes(&[]), 3 * 6400 + 3 * 3200 + 3 * 100);
+        assert_eq!(graph.cache_bytes(&traces), 6400 + 3200 + 100);
+        assert_eq!(graph.cache_bytes(&shared_traces), 6400 + 3200);
+    }
+
     #[test]
     fn test_cache_trace_d() {
         // for: cargo test -- --test-threads=1
@@ -453,7 +672,7 @@ mod tests {
         let b2 = Buffer::from((&device, [1; 64]));
         let w3 = Buffer::from((&device, [1; 64 * 64]));
         let b3 = Buffer::from((&device, [1; 64]));
-        let w4 = Buffer::from((&device, [1; 64 * 1]));
+        let w4 = Buffer::from((&device, [1; 64]));
         let b4 = Buffer::from((&device, [1; 1]));
 
         let inputs = Buffer::from((&device, [1; 10 * 100]));
@@ -471,8 +690,8 @@ mod tests {
         let a6 = device.retrieve::<i32, ()>(100 * 64, (&a5, &b3));
         let a6 = device.retrieve::<i32, ()>(100 * 64, (&a6, &a6));
 
-        let a7 = device.retrieve::<i32, ()>(100 * 1, (&a6, &w4));
-        let a8 = device.retrieve::<i32, ()>(100 * 1, (&a7, &b4));
+        let a7 = device.retrieve::<i32, ()>(100, (&a6, &w4));
+        let a8 = device.retrieve::<i32, ()>(100, (&a7, &b4));
         let _loss = device.retrieve::<i32, ()>(100, (&a8, &targets));
 
         let cts = device.graph().cache_traces();
@@ -578,6 +797,62 @@ mod tests {
         assert_eq!(nodes.get(&add.id()), nodes.get(&mul_b.id()));
     }
 
+    #[cfg(feature = "cpu")]
+    #[cfg(feature = "opt-cache")]
+    #[test]
+    fn test_from_retrieve_neural_net_optimize_diff_len() {
+        use crate::{Buffer, CacheReturn, Device, GraphOpt, GraphReturn, CPU};
+
+        let device = CPU::new();
+
+        let w1 = Buffer::from((&device, [1; 10 * 64]));
+        let b1 = Buffer::from((&device, [1; 64]));
+        let w2 = Buffer::from((&device, [1; 64 * 32]));
+        let b2 = Buffer::from((&device, [1; 32]));
+        let w3 = Buffer::from((&device, [1; 32]));
+        let b3 = Buffer::from((&device, [1; 1]));
+
+        let inputs = Buffer::from((&device, [1; 10 * 100]));
+        let targets = Buffer::from((&device, [2; 100]));
+
+        let a1 = device.retrieve::<i32, ()>(100 * 64, (&inputs, &w1));
+        let a2 = device.retrieve::<i32, ()>(100 * 64, (&a1, &b1));
+        let a2 = device.retrieve::<i32, ()>(100 * 64, (&a2, &a2));
+
+        let a3 = device.retrieve::<i32, ()>(100 * 32, (&a2, &w2));
+        let a4 = device.retrieve::<i32, ()>(100 * 32, (&a3, &b2));
+        let a4 = device.retrieve::<i32, ()>(100 * 32, (&a4, &a4));
+
+        let a5 = device.retrieve::<i32, ()>(100, (&a4, &w3));
+        let a6 = device.retrieve::<i32, ()>(100, (&a5, &b3));
+        let loss = device.retrieve::<i32, ()>(100, (&a6, &targets));
+
+        let peak_bytes = device.graph().cache_bytes(&[]);
+        let saved_bytes = device.optimize().unwrap();
+
+        assert_eq!(saved_bytes, (2 * 6400 + 2 * 3200 + 3 * 100) * 4);
+        assert_eq!(
+            peak_bytes - saved_bytes,
+            device
+                .graph()
+                .cache_bytes(&device.graph().shared_cache_traces())
+        );
+
+        // already optimized
+        assert_eq!(device.optimize().unwrap(), 0);
+
+        let cache = device.cache();
+        let a1_ptr = cache.nodes.get(&a1.id()).unwrap();
+
+        for buf in [&a5, &a6, &loss] {
+            let ptr = cache.nodes.get(&buf.id()).unwrap();
+            assert_eq!(ptr.ptr, a1_ptr.ptr);
+            assert_eq!(ptr.len, 100);
+        }
+
+        assert_ne!(cache.nodes.get(&a3.id()).unwrap().ptr, a1_ptr.ptr);
+    }
+
     #[test]
     fn test_no_cache_trace_in_graph() {
         let mut graph = Graph::<NodeCount>::new();
@@ -0,0 +1,49 @@
+//! Helpers for writing JSON without a serialization dependency.
+
+use core::fmt::Write;
+
+use crate::Ident;
+
+/// Returns `value` as JSON string literal.
+pub(crate) fn string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Returns `value` as JSON string literal, or `null`.
+pub(crate) fn opt_string(value: Option<&str>) -> String {
+    value.map_or_else(|| "null".into(), string)
+}
+
+/// Returns `ident` as JSON object, e.g. `{"idx":2,"len":10}`.
+pub(crate) fn ident(ident: Ident) -> String {
+    format!(r#"{{"idx":{},"len":{}}}"#, ident.idx, ident.len)
+}
+
+/// Returns `ident` as JSON object, or `null`.
+pub(crate) fn opt_ident(ident: Option<Ident>) -> String {
+    ident.map_or_else(|| "null".into(), self::ident)
+}
+
+/// Returns the JSON array of `values`.
+pub(crate) fn array<I: IntoIterator<Item = String>>(values: I) -> String {
+    let mut json = String::from("[");
+    for (idx, value) in values.into_iter().enumerate() {
+        if idx != 0 {
+            json.push(',');
+        }
+        json.push_str(&value);
+    }
+    json.push(']');
+    json
+}
@@ -60,11 +60,18 @@ pub use devices::*;
 
 pub use error::*;
 
+#[cfg(not(feature = "no-std"))]
+pub use event::*;
+
 use flag::AllocFlag;
 pub use graph::*;
 
 #[cfg(feature = "cpu")]
-pub use devices::cpu::CPU;
+pub use devices::cpu::{Plan, CPU};
+
+#[cfg(feature = "cpu")]
+#[cfg(not(feature = "no-std"))]
+pub use devices::any_device::{AnyBuffer, AnyDevice};
 
 #[cfg(feature = "cuda")]
 pub use devices::cuda::CUDA;
@@ -80,9 +87,30 @@ pub use devices::stack::Stack;
 #[cfg(feature = "network")]
 pub use devices::network::Network;
 
+#[cfg(feature = "sim")]
+#[cfg(not(feature = "no-std"))]
+pub use devices::sim::SimDevice;
+
+#[cfg(feature = "faulty")]
+#[cfg(not(feature = "no-std"))]
+pub use devices::faulty::{Fault, Faulty};
+
+#[cfg(feature = "nan-check")]
+#[cfg(not(feature = "no-std"))]
+pub use devices::nan_check::{Anomaly, AnomalyReport, CheckAnomaly, NanCheck};
+
+#[cfg(feature = "lazy")]
+#[cfg(not(feature = "no-std"))]
+pub use devices::lazy::Lazy;
+
+#[cfg(feature = "trace")]
+pub use trace::{ChromeTrace, TraceEvent, TraceReturn, Tracer};
+
 #[cfg(feature = "autograd")]
 pub use autograd::*;
 
+pub use binary::*;
+pub use reduce::*;
 pub use unary::*;
 
 #[cfg(feature = "cpu")]
@@ -91,14 +119,23 @@ pub mod exec_on_cpu;
 
 pub mod devices;
 
+mod binary;
 mod buffer;
 mod count;
 mod error;
+#[cfg(not(feature = "no-std"))]
+mod event;
 
 pub mod flag;
 mod graph;
+#[cfg(not(feature = "no-std"))]
+mod json;
 mod op_traits;
+mod reduce;
 mod shape;
+// spans are only created by devices that are not available with `no-std`
+#[cfg_attr(feature = "no-std", allow(dead_code))]
+mod trace;
 mod two_way_ops;
 mod unary;
 
@@ -125,12 +162,35 @@ The automatic differentiation system requires caching of buffers, which is deact
 #[cfg(all(feature = "realloc", feature = "opt-cache"))]
 compile_error!("A typical 'cache' does not exist when the `realloc` feature is enabled.");
 
+#[cfg(all(feature = "sanitize", feature = "no-std"))]
+compile_error!("The `sanitize` feature tracks allocations with `std` types, hence it is not available with `no-std`.");
+
+#[cfg(all(feature = "trace", feature = "no-std"))]
+compile_error!("The `trace` feature measures operations with `std` types, hence it is not available with `no-std`.");
+
 /// This trait is implemented for every pointer type.
 pub trait PtrType {
     /// Returns the element count.
     fn size(&self) -> usize;
     /// Returns the [`AllocFlag`].
     fn flag(&self) -> AllocFlag;
+
+    /// Returns the [`Allocation`] of the memory the pointer points to, if it is tracked.
+    #[cfg(not(feature = "no-std"))]
+    #[inline]
+    fn allocation(&self) -> Option<&Allocation> {
+        None
+    }
+}
+
+/// Panics if `ptr` points to memory that was already freed. Does nothing without the `sanitize` feature.
+#[inline]
+#[cfg_attr(feature = "sanitize", track_caller)]
+pub(crate) fn sanitize<P: PtrType>(_ptr: &P) {
+    #[cfg(feature = "sanitize")]
+    if let Some(allocation) = _ptr.allocation() {
+        allocation.check();
+    }
 }
 
 /// Used to shallow-copy a pointer. Use is discouraged.
@@ -160,6 +220,13 @@ pub trait Device: Sized + 'static {
     /// Creates a new device.
     fn new() -> crate::Result<Self>;
 
+    /// Blocks until every operation enqueued on the device has finished.
+    /// Devices that execute every operation on the calling thread return immediately.
+    #[inline]
+    fn synchronize(&self) -> crate::Result<()> {
+        Ok(())
+    }
+
     /// Creates a new [`Buffer`] using `A`.
     ///
     /// # Example
@@ -202,6 +269,7 @@ pub trait Device: Sized + 'static {
     ///
     /// ```
     #[inline]
+    #[track_caller]
     fn retrieve<T, S: Shape>(&self, len: usize, add_node: impl AddGraph) -> Buffer<T, Self, S>
     where
         for<'a> Self: Alloc<'a, T, S>,
@@ -209,6 +277,49 @@ pub trait Device: Sized + 'static {
         Self::Cache::retrieve(self, len, add_node)
     }
 
+    /// Like [`retrieve`](Device::retrieve), but returns an error if a new `Buffer` could not be allocated.
+    /// # Example
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::{Device, CPU};
+    ///
+    /// let device = CPU::new();
+    ///
+    /// let buf = device.try_retrieve::<f32, ()>(10, ()).unwrap();
+    /// assert_eq!(buf.len(), 10);
+    ///
+    /// assert!(device.try_retrieve::<f32, ()>(0, ()).is_err());
+    /// ```
+    #[inline]
+    #[track_caller]
+    fn try_retrieve<T, S: Shape>(
+        &self,
+        len: usize,
+        add_node: impl AddGraph,
+    ) -> crate::Result<Buffer<T, Self, S>>
+    where
+        for<'a> Self: Alloc<'a, T, S>,
+    {
+        Self::Cache::try_retrieve(self, len, add_node)
+    }
+
+    /// Same as [`retrieve`](Device::retrieve), but the operation is marked as element-wise in the [`Graph`].
+    /// After an optimization (`opt-cache` feature), the returned [`Buffer`] may share the memory of its only input,
+    /// if this input is not used afterwards.
+    /// Therefore, an element of the output must only depend on the element of the input at the same index.
+    #[inline]
+    #[track_caller]
+    fn retrieve_inplace_candidate<T, S: Shape>(
+        &self,
+        len: usize,
+        add_node: impl AddGraph,
+    ) -> Buffer<T, Self, S>
+    where
+        for<'a> Self: Alloc<'a, T, S>,
+    {
+        self.retrieve(len, Inplace(add_node))
+    }
+
     /// May return an existing buffer using the provided [`Ident`].
     /// This function panics if no buffer with the provided `Ident` exists.
     ///
@@ -284,7 +395,32 @@ pub trait Alloc<'a, T, S: Shape = ()>: Device {
     /// };
     /// assert_eq!(vec![0.; 12], device.read(&buf));
     /// ```
-    fn alloc(&'a self, len: usize, flag: AllocFlag) -> <Self as Device>::Ptr<T, S>;
+    /// # Panics
+    /// If [`Alloc::try_alloc`] fails.
+    #[inline]
+    #[track_caller]
+    fn alloc(&'a self, len: usize, flag: AllocFlag) -> <Self as Device>::Ptr<T, S> {
+        self.try_alloc(len, flag).unwrap()
+    }
+
+    /// Allocate memory on the implemented device. Returns an error instead of panicking.
+    /// # Errors
+    /// - `len` is 0 ([`Error::Alloc`] with `bytes: 0`).
+    /// - The device is out of memory or the backend reports an error.
+    /// # Example
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::{CPU, Alloc, Error, flag::AllocFlag};
+    ///
+    /// let device = CPU::new();
+    /// let err = Alloc::<f32>::try_alloc(&device, 0, AllocFlag::None).unwrap_err();
+    /// assert!(matches!(err, Error::Alloc { bytes: 0, limit: None }));
+    /// ```
+    fn try_alloc(
+        &'a self,
+        len: usize,
+        flag: AllocFlag,
+    ) -> crate::Result<<Self as Device>::Ptr<T, S>>;
 
     /// Allocate new memory with data
     /// # Example
@@ -302,18 +438,58 @@ pub trait Alloc<'a, T, S: Shape = ()>: Device {
     /// };
     /// assert_eq!(vec![1, 5, 4, 3, 6, 9, 0, 4], device.read(&buf));
     /// ```
+    /// # Panics
+    /// If [`Alloc::try_with_slice`] fails.
+    #[inline]
+    #[track_caller]
     fn with_slice(&'a self, data: &[T]) -> <Self as Device>::Ptr<T, S>
+    where
+        T: Clone,
+    {
+        self.try_with_slice(data).unwrap()
+    }
+
+    /// Allocate new memory with data. Returns an error instead of panicking.
+    /// # Errors
+    /// - `data` is empty ([`Error::Alloc`] with `bytes: 0`).
+    /// - The device is out of memory, e.g. its memory limit is exceeded, or the backend reports an error.
+    /// # Example
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::{Alloc, Error, MemoryReturn, CPU};
+    ///
+    /// let device = CPU::new();
+    /// device.set_memory_limit(Some(8));
+    ///
+    /// let err = Alloc::<i32>::try_with_slice(&device, &[1, 2, 3]).unwrap_err();
+    /// assert!(matches!(err, Error::Alloc { bytes: 12, limit: Some(_) }));
+    /// ```
+    fn try_with_slice(&'a self, data: &[T]) -> crate::Result<<Self as Device>::Ptr<T, S>>
     where
         T: Clone;
 
     /// If the vector `vec` was allocated previously, this function can be used in order to reduce the amount of allocations, which may be faster than using a slice of `vec`.
+    /// # Panics
+    /// If [`Alloc::try_alloc_with_vec`] fails.
     #[inline]
+    #[track_caller]
     #[cfg(not(feature = "no-std"))]
     fn alloc_with_vec(&'a self, vec: Vec<T>) -> <Self as Device>::Ptr<T, S>
     where
         T: Clone,
     {
-        self.with_slice(&vec)
+        self.try_alloc_with_vec(vec).unwrap()
+    }
+
+    /// Like [`Alloc::alloc_with_vec`], but returns an error instead of panicking. See [`Alloc::try_with_slice`] for the errors.
+    #[inline]
+    #[track_caller]
+    #[cfg(not(feature = "no-std"))]
+    fn try_alloc_with_vec(&'a self, vec: Vec<T>) -> crate::Result<<Self as Device>::Ptr<T, S>>
+    where
+        T: Clone,
+    {
+        self.try_with_slice(&vec)
     }
 
     /// Allocates a pointer with the array provided by the `S:`[`Shape`] generic.
@@ -342,6 +518,20 @@ pub trait MayTapeReturn {}
 #[cfg(not(feature = "autograd"))]
 impl<D> MayTapeReturn for D {}
 
+/// If the `trace` feature is enabled, then this will be implemented for all types that implement [`TraceReturn`].
+/// On the other hand, if the `trace` feature is disabled, operations are not traced.
+#[cfg(feature = "trace")]
+pub trait MayTrace: crate::TraceReturn {}
+#[cfg(feature = "trace")]
+impl<D: crate::TraceReturn> MayTrace for D {}
+
+/// If the `trace` feature is enabled, then this will be implemented for all types that implement [`TraceReturn`].
+/// On the other hand, if the `trace` feature is disabled, operations are not traced.
+#[cfg(not(feature = "trace"))]
+pub trait MayTrace {}
+#[cfg(not(feature = "trace"))]
+impl<D> MayTrace for D {}
+
 /// If the OpenCL device selected by the environment variable `CUSTOS_CL_DEVICE_IDX` supports unified memory, then this will be `true`.
 /// In your case, this is `false`.
 #[cfg(not(unified_cl))]
@@ -384,15 +574,15 @@ pub mod prelude {
 
     pub use crate::{
         number::*, range, shape::*, Alloc, Buffer, CDatatype, ClearBuf, CopySlice, Device,
-        GraphReturn, Ident, MainMemory, MayTapeReturn, MayToCLSource, Read, ShallowCopy, WithShape,
-        WriteBuf,
+        GraphReturn, Ident, MainMemory, MayTapeReturn, MayToCLSource, MayTrace, Read, ShallowCopy,
+        WithShape, WriteBuf,
     };
 
     #[cfg(feature = "cpu")]
     pub use crate::{exec_on_cpu::*, CPU};
 
     #[cfg(not(feature = "no-std"))]
-    pub use crate::{cache::CacheReturn, get_count, set_count, Cache};
+    pub use crate::{cache::CacheReturn, get_count, set_count, Cache, CountScope};
 
     #[cfg(feature = "opencl")]
     pub use crate::opencl::{enqueue_kernel, CLBuffer, OpenCL, CL};
@@ -408,6 +598,25 @@ pub mod prelude {
     #[cfg(feature = "network")]
     pub use crate::network::{Network, NetworkArray};
 
+    #[cfg(feature = "sim")]
+    #[cfg(not(feature = "no-std"))]
+    pub use crate::sim::{SimDevice, TransferStats};
+
+    #[cfg(feature = "faulty")]
+    #[cfg(not(feature = "no-std"))]
+    pub use crate::faulty::{Fault, Faulty};
+
+    #[cfg(feature = "nan-check")]
+    #[cfg(not(feature = "no-std"))]
+    pub use crate::nan_check::{Anomaly, AnomalyReport, CheckAnomaly, NanCheck};
+
+    #[cfg(feature = "lazy")]
+    #[cfg(not(feature = "no-std"))]
+    pub use crate::lazy::Lazy;
+
+    #[cfg(feature = "trace")]
+    pub use crate::{ChromeTrace, TraceEvent, TraceReturn, Tracer};
+
     #[cfg(feature = "wgpu")]
     pub use crate::wgpu::{launch_shader, WGPU};
 
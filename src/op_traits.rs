@@ -34,6 +34,7 @@ pub trait CopySlice<T, D: Device = Self>: Sized + Device {
     /// let slice = device.copy_slice(&buf, 1..3);
     /// assert_eq!(slice.read(), &[2., 6.]);
     /// ```
+    #[track_caller]
     fn copy_slice<'a, R: RangeBounds<usize>>(
         &'a self,
         buf: &Buffer<T, D>,
@@ -42,10 +43,39 @@ pub trait CopySlice<T, D: Device = Self>: Sized + Device {
     where
         Self: for<'b> Alloc<'b, T>,
     {
-        let range = bounds_to_range(range, buf.len());
-        let mut copied = Buffer::new(self, range.end - range.start);
-        self.copy_slice_to(buf, range, &mut copied, ..);
-        copied
+        self.try_copy_slice(buf, range).unwrap()
+    }
+
+    /// Like [`copy_slice`](CopySlice::copy_slice), but returns an error instead of panicking.
+    /// # Errors
+    /// - The range is out of bounds ([`Error::OutOfRange`](crate::Error::OutOfRange)) or empty.
+    /// # Example
+    ///
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::{CPU, Buffer, CopySlice, Error};
+    ///
+    /// let device = CPU::new();
+    /// let buf = Buffer::from((&device, [1., 2., 6., 2., 4.,]));
+    ///
+    /// let slice = device.try_copy_slice(&buf, 3..).unwrap();
+    /// assert_eq!(slice.read(), &[2., 4.]);
+    ///
+    /// let err = device.try_copy_slice(&buf, 3..6).unwrap_err();
+    /// assert!(matches!(err, Error::OutOfRange { start: 3, end: 6, len: 5 }));
+    /// ```
+    fn try_copy_slice<'a, R: RangeBounds<usize>>(
+        &'a self,
+        buf: &Buffer<T, D>,
+        range: R,
+    ) -> crate::Result<Buffer<'a, T, Self>>
+    where
+        Self: for<'b> Alloc<'b, T>,
+    {
+        let range = checked_range(range, buf.len())?;
+        let mut copied = Buffer::try_new(self, range.end - range.start)?;
+        self.try_copy_slice_to(buf, range, &mut copied, ..)?;
+        Ok(copied)
     }
 
     /// Copy a slice of the source buffer into a slice of the destination buffer.
@@ -61,13 +91,29 @@ pub trait CopySlice<T, D: Device = Self>: Sized + Device {
     /// let slice = device.copy_slice_to(&source, 1..3, &mut dest, 3..5);
     /// assert_eq!(dest.read(), &[5., 4., 3., 2., 3.]);
     /// ```
+    #[track_caller]
     fn copy_slice_to<SR: RangeBounds<usize>, DR: RangeBounds<usize>>(
         &self,
         source: &Buffer<T, D>,
         source_range: SR,
         dest: &mut Buffer<T, Self>,
         dest_range: DR,
-    );
+    ) {
+        self.try_copy_slice_to(source, source_range, dest, dest_range)
+            .unwrap()
+    }
+
+    /// Like [`copy_slice_to`](CopySlice::copy_slice_to), but returns an error instead of panicking.
+    /// # Errors
+    /// - A range is out of bounds ([`Error::OutOfRange`](crate::Error::OutOfRange)).
+    /// - The ranges have different lengths ([`Error::LengthMismatch`](crate::Error::LengthMismatch)).
+    fn try_copy_slice_to<SR: RangeBounds<usize>, DR: RangeBounds<usize>>(
+        &self,
+        source: &Buffer<T, D>,
+        source_range: SR,
+        dest: &mut Buffer<T, Self>,
+        dest_range: DR,
+    ) -> crate::Result<()>;
 
     /// Copy multiple slices of the source buffer into multiplie slices of the destination buffer.
     ///
@@ -97,7 +143,8 @@ pub trait CopySlice<T, D: Device = Self>: Sized + Device {
 }
 
 /// Trait for reading buffers.
-/// Syncronizationpoint for CUDA.
+/// On GPU backends, reading waits until the operations enqueued before have finished.
+/// See [`ReadAsync`](crate::ReadAsync) for a non-blocking read.
 pub trait Read<T, S: Shape = (), D: Device = Self>: Device {
     /// The type of the read data.
     /// Usually `Vec<T>` or `&'a [T]`.
@@ -118,7 +165,14 @@ pub trait Read<T, S: Shape = (), D: Device = Self>: Device {
     /// let read = device.read(&a);
     /// assert_eq!(&[1., 2., 3., 3., 2., 1.,], read);
     /// ```
-    fn read<'a>(&self, buf: &'a Buffer<T, D, S>) -> Self::Read<'a>;
+    #[inline]
+    #[track_caller]
+    fn read<'a>(&self, buf: &'a Buffer<T, D, S>) -> Self::Read<'a> {
+        self.try_read(buf).unwrap()
+    }
+
+    /// Like [`read`](Read::read), but returns an error if the device failed to read the data.
+    fn try_read<'a>(&self, buf: &'a Buffer<T, D, S>) -> crate::Result<Self::Read<'a>>;
 
     /// Read the data of a buffer into a vector
     /// # Example
@@ -151,7 +205,28 @@ pub trait WriteBuf<T, S: Shape = (), D: Device = Self>: Device {
     /// assert_eq!(buf.as_slice(), &[9, 3, 2, -4])
     ///
     /// ```
-    fn write(&self, buf: &mut Buffer<T, D, S>, data: &[T]);
+    #[inline]
+    #[track_caller]
+    fn write(&self, buf: &mut Buffer<T, D, S>, data: &[T]) {
+        self.try_write(buf, data).unwrap()
+    }
+
+    /// Like [`write`](WriteBuf::write), but returns an error instead of panicking.
+    /// # Errors
+    /// - `data` and `buf` have different lengths ([`Error::LengthMismatch`](crate::Error::LengthMismatch)).
+    /// - The device failed to write the data.
+    /// # Example
+    #[cfg_attr(feature = "cpu", doc = "```")]
+    #[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+    /// use custos::{CPU, Buffer, Error, WriteBuf};
+    ///
+    /// let device = CPU::new();
+    /// let mut buf: Buffer<i32> = Buffer::new(&device, 4);
+    ///
+    /// let err = device.try_write(&mut buf, &[1, 2, 3]).unwrap_err();
+    /// assert!(matches!(err, Error::LengthMismatch { expected: 4, found: 3 }));
+    /// ```
+    fn try_write(&self, buf: &mut Buffer<T, D, S>, data: &[T]) -> crate::Result<()>;
 
     /// Writes data from `<Device>` Buffer to other `<Device>` Buffer.
     /// The buffers must have the same size.
@@ -190,6 +265,39 @@ pub trait CloneBuf<'a, T, S: Shape = ()>: Sized + Device {
     fn clone_buf(&'a self, buf: &Buffer<'a, T, Self, S>) -> Buffer<'a, T, Self, S>;
 }
 
+/// Moves the data of a `Buffer` from the device `Self` to the device `Dst`. The [`Shape`] of the `Buffer` is preserved.
+///
+/// Devices with host accessible memory (CPU, Stack, OpenCL with unified memory) copy their memory directly to `Dst`.
+/// Other devices stage the data in host memory.
+pub trait Transfer<T, Dst: Device, S: Shape = ()>: Device {
+    /// Copies the data of `buf` into a new `Buffer` allocated on `dst`.
+    /// # Example
+    #[cfg_attr(all(feature = "cpu", feature = "stack"), doc = "```")]
+    #[cfg_attr(not(all(feature = "cpu", feature = "stack")), doc = "```ignore")]
+    /// use custos::{Buffer, Dim1, Stack, Transfer, CPU};
+    ///
+    /// let device = CPU::new();
+    /// let buf = Buffer::<_, _, Dim1<3>>::from_slice(&device, &[1, 2, 3]);
+    ///
+    /// let on_stack = device.transfer(&buf, &Stack);
+    /// assert_eq!(on_stack.read(), [1, 2, 3]);
+    /// ```
+    /// # Panics
+    /// If [`Transfer::try_transfer`] fails.
+    #[inline]
+    #[track_caller]
+    fn transfer<'b>(&self, buf: &Buffer<T, Self, S>, dst: &'b Dst) -> Buffer<'b, T, Dst, S> {
+        self.try_transfer(buf, dst).unwrap()
+    }
+
+    /// Like [`Transfer::transfer`], but returns an error if the data could not be read or the memory on `dst` could not be allocated.
+    fn try_transfer<'b>(
+        &self,
+        buf: &Buffer<T, Self, S>,
+        dst: &'b Dst,
+    ) -> crate::Result<Buffer<'b, T, Dst, S>>;
+}
+
 /// Convert a possibly-indefinite [`RangeBounds`] into a [`Range`] with a start and stop index.
 #[inline]
 pub(crate) fn bounds_to_range<B: RangeBounds<usize>>(bounds: B, len: usize) -> Range<usize> {
@@ -208,6 +316,32 @@ pub(crate) fn bounds_to_range<B: RangeBounds<usize>>(bounds: B, len: usize) -> R
     start..end
 }
 
+/// Like [`bounds_to_range`], but returns an error if the range is out of bounds for a buffer of length `len`.
+#[inline]
+pub(crate) fn checked_range<B: RangeBounds<usize>>(
+    bounds: B,
+    len: usize,
+) -> crate::Result<Range<usize>> {
+    let range = bounds_to_range(bounds, len);
+    if range.start > range.end || range.end > len {
+        return Err(crate::Error::OutOfRange {
+            start: range.start,
+            end: range.end,
+            len,
+        });
+    }
+    Ok(range)
+}
+
+/// Returns an error if two lengths differ.
+#[inline]
+pub(crate) fn check_len(expected: usize, found: usize) -> crate::Result<()> {
+    if expected != found {
+        return Err(crate::Error::LengthMismatch { expected, found });
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -0,0 +1,84 @@
+use crate::{Buffer, Device, Eval, MayToCLSource, Resolve, Shape};
+
+/// Folds the elements of a buffer into a buffer with a single element.
+pub trait Reduce<T, S: Shape = (), D: Device = Self>: Device {
+    /// Folds the elements of `buf`, starting with `init`, and returns a buffer that contains the result as only element.
+    /// The elements are folded in order, `f` receives the accumulated value and the next element.
+    /// # Example
+    #[cfg_attr(all(feature = "cpu", feature = "macro"), doc = "```")]
+    #[cfg_attr(not(all(feature = "cpu", feature = "macro")), doc = "```ignore")]
+    /// use custos::{CPU, Buffer, Reduce, Combiner};
+    ///
+    /// let device = CPU::new();
+    /// let buf = Buffer::from((&device, [1., 2., 3., 4.]));
+    ///
+    /// let sum = device.reduce(&buf, 0., |acc, x| acc.add(x));
+    /// assert_eq!(&*sum, &[10.]);
+    /// ```
+    #[inline]
+    #[track_caller]
+    fn reduce<F>(
+        &self,
+        buf: &Buffer<T, D, S>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> Buffer<'_, T, Self>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        self.try_reduce(buf, init, f).unwrap()
+    }
+
+    /// Like [`reduce`](Reduce::reduce), but returns an error if the output could not be allocated or the kernel failed.
+    #[track_caller]
+    fn try_reduce<F>(
+        &self,
+        buf: &Buffer<T, D, S>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<'_, T, Self>>
+    where
+        F: Eval<T> + MayToCLSource;
+}
+
+/// Folds the elements of a buffer into an existing buffer with a single element.
+/// [`Lazy`](crate::Lazy) uses it to write the result of a recorded operation to the buffer that was returned on record.
+pub trait ReduceTo<T, S: Shape = (), D: Device = Self>: Device {
+    /// Folds the elements of `buf`, starting with `init`, and writes the result to the only element of `out`.
+    /// # Example
+    #[cfg_attr(all(feature = "cpu", feature = "macro"), doc = "```")]
+    #[cfg_attr(not(all(feature = "cpu", feature = "macro")), doc = "```ignore")]
+    /// use custos::{CPU, Buffer, ReduceTo, Combiner};
+    ///
+    /// let device = CPU::new();
+    /// let buf = Buffer::from((&device, [1, 2, 3, 4]));
+    /// let mut product = Buffer::new(&device, 1);
+    ///
+    /// device.reduce_to(&buf, &mut product, 1, |acc, x| acc.mul(x));
+    /// assert_eq!(&*product, &[24]);
+    /// ```
+    #[inline]
+    #[track_caller]
+    fn reduce_to<F>(
+        &self,
+        buf: &Buffer<T, D, S>,
+        out: &mut Buffer<T, Self>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) where
+        F: Eval<T> + MayToCLSource,
+    {
+        self.try_reduce_to(buf, out, init, f).unwrap()
+    }
+
+    /// Like [`reduce_to`](ReduceTo::reduce_to), but returns an error if `out` does not contain exactly one element or the kernel failed.
+    fn try_reduce_to<F>(
+        &self,
+        buf: &Buffer<T, D, S>,
+        out: &mut Buffer<T, Self>,
+        init: T,
+        f: impl Fn(Resolve<T>, Resolve<T>) -> F,
+    ) -> crate::Result<()>
+    where
+        F: Eval<T> + MayToCLSource;
+}
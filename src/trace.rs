@@ -0,0 +1,247 @@
+//! Reports operations, allocations and transfers of a device to a [`Tracer`].
+//! Without the `trace` feature, spans are zero sized and nothing is recorded.
+
+use crate::Ident;
+
+#[cfg(feature = "trace")]
+use crate::json;
+#[cfg(feature = "trace")]
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// A traced operation of a device.
+#[cfg(feature = "trace")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    /// The name of the operation, e.g. `"retrieve"` or `"apply_fn"`.
+    pub name: &'static str,
+    /// The type name of the device that executed the operation.
+    pub device: &'static str,
+    /// The amount of bytes that were allocated, moved or compiled.
+    pub bytes: usize,
+    /// The [`Ident`] of the `Buffer` the operation produced or accessed, if there is one.
+    pub ident: Option<Ident>,
+    /// Identifies the thread the operation was executed on. Starts at 0 and is assigned in order of the first traced operation.
+    pub thread: usize,
+    /// The point in time the operation started.
+    pub start: Instant,
+    /// The wall time the operation took.
+    pub duration: Duration,
+}
+
+/// Receives a [`TraceEvent`] for every traced operation of a device.
+/// Set a tracer with [`TraceReturn::set_tracer`].
+#[cfg(feature = "trace")]
+pub trait Tracer: Send + Sync {
+    /// Called after an operation has finished.
+    fn record(&self, event: TraceEvent);
+}
+
+/// This trait is implemented for all devices that can report to a [`Tracer`].
+#[cfg(feature = "trace")]
+pub trait TraceReturn: crate::Device {
+    /// Returns the [`Tracer`] operations are reported to.
+    fn tracer(&self) -> Option<Arc<dyn Tracer>>;
+
+    /// Sets the [`Tracer`] operations are reported to and returns the previous one. Passing `None` disables tracing.
+    /// As the addons of a device exist once per thread, the tracer is only set for the calling thread.
+    fn set_tracer(&self, tracer: Option<Arc<dyn Tracer>>) -> Option<Arc<dyn Tracer>>;
+}
+
+/// A [`Tracer`] that collects all events and writes them in the Chrome `trace_event` format.
+/// The output can be opened with `chrome://tracing` or [Perfetto](https://ui.perfetto.dev).
+/// # Example
+#[cfg_attr(feature = "cpu", doc = "```")]
+#[cfg_attr(not(feature = "cpu"), doc = "```ignore")]
+/// use std::sync::Arc;
+/// use custos::{Buffer, ChromeTrace, Read, TraceReturn, CPU};
+///
+/// let device = CPU::new();
+/// let trace = Arc::new(ChromeTrace::new());
+/// device.set_tracer(Some(trace.clone()));
+///
+/// let buf = Buffer::from((&device, [1, 2, 3]));
+/// assert_eq!(device.read_to_vec(&buf), [1, 2, 3]);
+///
+/// assert_eq!(trace.events()[0].name, "read");
+/// assert!(trace.to_json().starts_with(r#"{"traceEvents":["#));
+/// ```
+#[cfg(feature = "trace")]
+#[derive(Debug)]
+pub struct ChromeTrace {
+    start: Instant,
+    events: Mutex<Vec<TraceEvent>>,
+}
+
+#[cfg(feature = "trace")]
+impl Default for ChromeTrace {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "trace")]
+impl ChromeTrace {
+    /// Creates an empty trace. Timestamps are relative to the creation of the trace.
+    #[inline]
+    pub fn new() -> Self {
+        ChromeTrace {
+            start: Instant::now(),
+            events: Default::default(),
+        }
+    }
+
+    /// Returns all recorded events in the order they finished.
+    pub fn events(&self) -> Vec<TraceEvent> {
+        self.events
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    /// Removes all recorded events.
+    pub fn clear(&self) {
+        self.events
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clear()
+    }
+
+    /// Returns the recorded events in the Chrome `trace_event` JSON format.
+    /// Every event is a complete event (`"ph": "X"`); timestamps and durations are in microseconds.
+    pub fn to_json(&self) -> String {
+        let events = self.events.lock().unwrap_or_else(|err| err.into_inner());
+
+        let events = events.iter().map(|event| {
+            let ts = event.start.saturating_duration_since(self.start);
+            format!(
+                r#"{{"name":{},"cat":"custos","ph":"X","ts":{},"dur":{},"pid":0,"tid":{},"args":{{"device":{},"bytes":{},"ident":{}}}}}"#,
+                json::string(event.name),
+                micros(ts),
+                micros(event.duration),
+                event.thread,
+                json::string(event.device),
+                event.bytes,
+                json::opt_ident(event.ident)
+            )
+        });
+        format!(r#"{{"traceEvents":{}}}"#, json::array(events))
+    }
+
+    /// Writes the JSON returned by [`to_json`](ChromeTrace::to_json) to `writer`, e.g. a file.
+    #[inline]
+    pub fn write(&self, mut writer: impl std::io::Write) -> std::io::Result<()> {
+        writer.write_all(self.to_json().as_bytes())
+    }
+}
+
+#[cfg(feature = "trace")]
+impl Tracer for ChromeTrace {
+    #[inline]
+    fn record(&self, event: TraceEvent) {
+        self.events
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(event)
+    }
+}
+
+#[cfg(feature = "trace")]
+fn micros(duration: Duration) -> f64 {
+    duration.as_nanos() as f64 / 1000.
+}
+
+#[cfg(feature = "trace")]
+fn thread_idx() -> usize {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    std::thread_local! {
+        static THREAD: usize = NEXT.fetch_add(1, Ordering::Relaxed);
+    }
+    THREAD.with(|thread| *thread)
+}
+
+/// Measures an operation and reports it to the [`Tracer`] of the device once it is dropped.
+/// Created by [`span`].
+#[cfg(feature = "trace")]
+pub(crate) struct Span {
+    tracer: Option<Arc<dyn Tracer>>,
+    name: &'static str,
+    device: &'static str,
+    bytes: usize,
+    ident: Option<Ident>,
+    start: Instant,
+}
+
+#[cfg(feature = "trace")]
+impl Span {
+    /// Sets the [`Ident`] of the `Buffer` the operation produced, if it is not known upfront.
+    #[inline]
+    pub(crate) fn set_ident(&mut self, ident: Option<Ident>) {
+        self.ident = ident;
+    }
+}
+
+#[cfg(feature = "trace")]
+impl Drop for Span {
+    fn drop(&mut self) {
+        let Some(tracer) = self.tracer.take() else {
+            return;
+        };
+
+        tracer.record(TraceEvent {
+            name: self.name,
+            device: self.device,
+            bytes: self.bytes,
+            ident: self.ident,
+            thread: thread_idx(),
+            start: self.start,
+            duration: self.start.elapsed(),
+        })
+    }
+}
+
+/// Starts measuring the operation `name` of `device`, which reports to the [`Tracer`] of the device once the returned span is dropped.
+#[cfg(feature = "trace")]
+#[inline]
+pub(crate) fn span<D: crate::MayTrace>(
+    device: &D,
+    name: &'static str,
+    bytes: usize,
+    ident: Option<Ident>,
+) -> Span {
+    Span {
+        tracer: device.tracer(),
+        name,
+        device: core::any::type_name::<D>(),
+        bytes,
+        ident,
+        start: Instant::now(),
+    }
+}
+
+/// Without the `trace` feature, nothing is measured.
+#[cfg(not(feature = "trace"))]
+pub(crate) struct Span;
+
+#[cfg(not(feature = "trace"))]
+impl Span {
+    #[inline]
+    pub(crate) fn set_ident(&mut self, _ident: Option<Ident>) {}
+}
+
+#[cfg(not(feature = "trace"))]
+#[inline]
+pub(crate) fn span<D>(
+    _device: &D,
+    _name: &'static str,
+    _bytes: usize,
+    _ident: Option<Ident>,
+) -> Span {
+    Span
+}
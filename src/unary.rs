@@ -14,7 +14,62 @@ pub trait ApplyFunction<T, S: Shape = (), D: Device = Self>: Device {
     /// let out = device.apply_fn(&a, |x| x.mul(2.));
     /// assert_eq!(&*out, &[2., 4., 6., 6., 4., 2.,]);
     /// ```
+    #[inline]
+    #[track_caller]
     fn apply_fn<F>(&self, buf: &Buffer<T, D, S>, f: impl Fn(Resolve<T>) -> F) -> Buffer<T, Self, S>
+    where
+        F: Eval<T> + MayToCLSource,
+    {
+        self.try_apply_fn(buf, f).unwrap()
+    }
+
+    /// Like [`apply_fn`](ApplyFunction::apply_fn), but returns an error if the output could not be allocated or the kernel failed.
+    #[track_caller]
+    fn try_apply_fn<F>(
+        &self,
+        buf: &Buffer<T, D, S>,
+        f: impl Fn(Resolve<T>) -> F,
+    ) -> crate::Result<Buffer<T, Self, S>>
+    where
+        F: Eval<T> + MayToCLSource;
+}
+
+/// Applies a function to a buffer and writes the result to an existing buffer.
+/// [`Lazy`](crate::Lazy) uses it to write the result of a recorded operation to the buffer that was returned on record.
+pub trait ApplyFunctionTo<T, S: Shape = (), D: Device = Self>: Device {
+    /// Applies a function to a buffer and writes the result to `out`.
+    /// # Example
+    #[cfg_attr(all(feature = "cpu", feature = "macro"), doc = "```")]
+    #[cfg_attr(not(all(feature = "cpu", feature = "macro")), doc = "```ignore")]
+    /// use custos::{CPU, Buffer, ApplyFunctionTo, Combiner};
+    ///
+    /// let device = CPU::new();
+    /// let a = Buffer::from((&device, [1., 2., 3., 3., 2., 1.,]));
+    /// let mut out = Buffer::new(&device, 6);
+    ///
+    /// device.apply_fn_to(&a, &mut out, |x| x.mul(2.));
+    /// assert_eq!(&*out, &[2., 4., 6., 6., 4., 2.,]);
+    /// ```
+    #[inline]
+    #[track_caller]
+    fn apply_fn_to<F>(
+        &self,
+        buf: &Buffer<T, D, S>,
+        out: &mut Buffer<T, Self, S>,
+        f: impl Fn(Resolve<T>) -> F,
+    ) where
+        F: Eval<T> + MayToCLSource,
+    {
+        self.try_apply_fn_to(buf, out, f).unwrap()
+    }
+
+    /// Like [`apply_fn_to`](ApplyFunctionTo::apply_fn_to), but returns an error if the lengths of `buf` and `out` differ or the kernel failed.
+    fn try_apply_fn_to<F>(
+        &self,
+        buf: &Buffer<T, D, S>,
+        out: &mut Buffer<T, Self, S>,
+        f: impl Fn(Resolve<T>) -> F,
+    ) -> crate::Result<()>
     where
         F: Eval<T> + MayToCLSource;
 }
@@ -75,6 +130,7 @@ pub trait UnaryElementWiseMayGrad<T, D: Device, S: Shape>: Device {
     /// out.backward();
     /// assert_eq!(&**buf.grad(), &[2.; 6]);
     /// ```
+    #[track_caller]
     fn unary_ew<FO, GO>(
         &self,
         buf: &Buffer<T, D, S>,
@@ -94,6 +150,7 @@ where
     S: Shape,
 {
     #[inline(always)]
+    #[track_caller]
     fn unary_ew<FO, GO>(
         &self,
         buf: &Buffer<T, D, S>,
@@ -109,7 +166,12 @@ where
         #[cfg(feature = "autograd")]
         {
             let ids = (buf.id(), out.id());
-            self.tape_mut().add_grad_fn(move |grads, device| {
+            let entry = crate::TapeEntry {
+                op: Some("unary_ew"),
+                inputs: vec![ids.0],
+                outputs: vec![ids.1],
+            };
+            self.tape_mut().add_entry(entry, move |grads, device| {
                 let (lhs, lhs_grad, out_grad) = grads.get_double::<T, S, S>(device, ids);
                 device.add_unary_grad(&lhs, lhs_grad, out_grad, _grad_fn);
             });
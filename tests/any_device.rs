@@ -0,0 +1,104 @@
+#![cfg(feature = "cpu")]
+
+use custos::{
+    AnyBuffer, AnyDevice, Buffer, CacheReturn, ClearBuf, CopySlice, Device, DeviceError, Error,
+    WriteBuf,
+};
+
+#[test]
+fn test_any_device_from_name() {
+    let device = AnyDevice::from_name("cpu").unwrap();
+    assert_eq!(device.name(), "cpu");
+
+    let device = AnyDevice::from_name(" CPU ").unwrap();
+    assert_eq!(device.name(), "cpu");
+
+    let device = AnyDevice::new().unwrap();
+    assert_eq!(device.name(), "cpu");
+}
+
+#[test]
+fn test_any_device_unknown_name() {
+    for name in ["tpu", "opencl:x", "cpu:3", ""] {
+        let err = AnyDevice::from_name(name).unwrap_err();
+        assert!(matches!(err, Error::Device(DeviceError::UnknownDevice)));
+    }
+
+    #[cfg(not(feature = "cuda"))]
+    assert!(AnyDevice::from_name("cuda").is_err());
+
+    #[cfg(not(feature = "opencl"))]
+    assert!(AnyDevice::from_name("opencl:0").is_err());
+}
+
+#[test]
+fn test_any_device_read_write() {
+    let device = AnyDevice::from_name("cpu").unwrap();
+
+    let mut buf: AnyBuffer = Buffer::from((&device, [1., 2., 3., 4.]));
+    assert_eq!(buf.read(), [1., 2., 3., 4.]);
+
+    device.write(&mut buf, &[5., 6., 7., 8.]);
+    assert_eq!(buf.read(), [5., 6., 7., 8.]);
+
+    let src: AnyBuffer = Buffer::from((&device, [9., 10., 11., 12.]));
+    device.write_buf(&mut buf, &src);
+    assert_eq!(buf.read(), [9., 10., 11., 12.]);
+
+    device.clear(&mut buf);
+    assert_eq!(buf.read(), [0.; 4]);
+}
+
+#[test]
+fn test_any_device_copy_slice() {
+    let device = AnyDevice::from_name("cpu").unwrap();
+
+    let src: AnyBuffer<i32> = Buffer::from((&device, [1, 2, 3, 4, 5]));
+    let mut dst = Buffer::<i32, _>::new(&device, 5);
+
+    device.copy_slice_to(&src, 1..3, &mut dst, 3..5);
+    assert_eq!(dst.read(), [0, 0, 0, 2, 3]);
+
+    device.copy_slice_all(&src, &mut dst, [(0..1, 0..1), (4..5, 1..2)]);
+    assert_eq!(dst.read(), [1, 5, 0, 2, 3]);
+
+    let cloned = src.clone();
+    assert_eq!(cloned.read(), [1, 2, 3, 4, 5]);
+}
+
+#[cfg(feature = "macro")]
+fn scale_and_shift(device: &AnyDevice, data: &[f32]) -> Vec<f32> {
+    use custos::{ApplyFunction, Combiner};
+
+    let buf: AnyBuffer = Buffer::from((device, data));
+    let out = device.apply_fn(&buf, |x| x.mul(2.).add(1.));
+    out.read()
+}
+
+#[cfg(feature = "macro")]
+#[test]
+fn test_any_device_app_code_written_once() {
+    for name in ["cpu", "opencl", "cuda"] {
+        // backends that are not compiled in (or have no device) are skipped
+        let Ok(device) = AnyDevice::from_name(name) else {
+            continue;
+        };
+        assert_eq!(scale_and_shift(&device, &[1., 2., 3.]), [3., 5., 7.]);
+    }
+}
+
+#[test]
+#[allow(irrefutable_let_patterns)]
+fn test_any_device_drop_removes_from_backend_cache() {
+    let device = AnyDevice::from_name("cpu").unwrap();
+    let AnyDevice::CPU(cpu) = &device else {
+        unreachable!()
+    };
+
+    let buf = Buffer::<f32, _>::new(&device, 10);
+    assert!(buf.ident.is_some());
+    assert_eq!(cpu.cache().nodes.len(), 1);
+
+    drop(buf);
+    assert_eq!(cpu.cache().nodes.len(), 0);
+}
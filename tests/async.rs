@@ -0,0 +1,244 @@
+#![cfg(feature = "cpu")]
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::channel,
+        Arc, Mutex,
+    },
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+    time::Duration,
+};
+
+use custos::{Buffer, Device, DeviceEvent, ReadAsync, RecordEvent, CPU};
+
+fn block_on<F: Future>(mut future: F) -> F::Output {
+    fn raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker {
+            raw_waker()
+        }
+        fn noop(_: *const ()) {}
+
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(core::ptr::null(), &VTABLE)
+    }
+
+    let waker = unsafe { Waker::from_raw(raw_waker()) };
+    let mut cx = Context::from_waker(&waker);
+
+    let mut future = unsafe { Pin::new_unchecked(&mut future) };
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        std::thread::yield_now();
+    }
+}
+
+#[test]
+fn test_cpu_queue_runs_jobs_in_order() {
+    let device = CPU::new();
+    let log = Arc::new(Mutex::new(Vec::new()));
+
+    for i in 0..10 {
+        let log = log.clone();
+        device.enqueue(move || log.lock().unwrap().push(i));
+    }
+
+    device.synchronize().unwrap();
+    assert_eq!(*log.lock().unwrap(), (0..10).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_cpu_event_completes_after_previous_jobs() {
+    let device = CPU::new();
+    let (sender, receiver) = channel::<()>();
+
+    // blocks the worker until the test sends a message
+    device.enqueue(move || receiver.recv().unwrap());
+    let event = device.record_event().unwrap();
+
+    std::thread::sleep(Duration::from_millis(10));
+    assert!(!event.is_complete());
+
+    sender.send(()).unwrap();
+    event.wait().unwrap();
+    assert!(event.is_complete());
+}
+
+#[test]
+fn test_cpu_event_without_jobs_is_complete() {
+    let device = CPU::new();
+    assert!(device.record_event().unwrap().is_complete());
+    device.synchronize().unwrap();
+}
+
+#[test]
+fn test_cpu_wait_event_orders_queues() {
+    let first = CPU::new();
+    let second = CPU::new();
+
+    let (sender, receiver) = channel::<()>();
+    let counter = Arc::new(AtomicUsize::new(0));
+
+    let job_counter = counter.clone();
+    first.enqueue(move || {
+        receiver.recv().unwrap();
+        job_counter.store(1, Ordering::SeqCst);
+    });
+    let event = first.record_event().unwrap();
+
+    second.wait_event(&event).unwrap();
+    let job_counter = counter.clone();
+    let observed = Arc::new(AtomicUsize::new(usize::MAX));
+    let job_observed = observed.clone();
+    let done = second
+        .enqueue(move || job_observed.store(job_counter.load(Ordering::SeqCst), Ordering::SeqCst));
+
+    std::thread::sleep(Duration::from_millis(10));
+    assert!(!done.is_complete());
+
+    sender.send(()).unwrap();
+    done.wait().unwrap();
+    assert_eq!(observed.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_cpu_panicking_job_does_not_stop_queue() {
+    let device = CPU::new();
+    device.enqueue(|| panic!("job failed"));
+
+    let counter = Arc::new(AtomicUsize::new(0));
+    let job_counter = counter.clone();
+    device.enqueue(move || {
+        job_counter.fetch_add(1, Ordering::SeqCst);
+    });
+
+    device.synchronize().unwrap();
+    assert_eq!(counter.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_cpu_read_async_wait() {
+    let device = CPU::new();
+    let buf = Buffer::from((&device, [1, 2, 3, 4]));
+
+    let pending = unsafe { device.read_async(&buf) }.unwrap();
+    assert_eq!(pending.wait().unwrap(), [1, 2, 3, 4]);
+}
+
+#[test]
+fn test_cpu_read_async_await() {
+    let device = CPU::new();
+    let buf = Buffer::from((&device, [1., 2., 3.]));
+
+    let read = block_on(async { unsafe { device.read_async(&buf) }.unwrap().await });
+    assert_eq!(read.unwrap(), [1., 2., 3.]);
+}
+
+#[test]
+fn test_cpu_read_async_is_ordered_after_jobs() {
+    let device = CPU::new();
+    let (sender, receiver) = channel::<()>();
+
+    device.enqueue(move || receiver.recv().unwrap());
+
+    let buf = Buffer::from((&device, [5, 6, 7]));
+    let pending = unsafe { device.read_async(&buf) }.unwrap();
+
+    std::thread::sleep(Duration::from_millis(10));
+    assert!(!pending.is_complete());
+
+    sender.send(()).unwrap();
+    assert_eq!(pending.wait().unwrap(), [5, 6, 7]);
+}
+
+#[test]
+fn test_cpu_read_async_is_woken_by_the_worker() {
+    use std::task::Wake;
+
+    #[derive(Default)]
+    struct CountWakes(AtomicUsize);
+
+    impl Wake for CountWakes {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let device = CPU::new();
+    let (sender, receiver) = channel::<()>();
+
+    device.enqueue(move || receiver.recv().unwrap());
+
+    let buf = Buffer::from((&device, [5, 6, 7]));
+    let mut pending = unsafe { device.read_async(&buf) }.unwrap();
+
+    let wakes = Arc::new(CountWakes::default());
+    let waker = Waker::from(wakes.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    // polling twice registers the waker once
+    assert!(Pin::new(&mut pending).poll(&mut cx).is_pending());
+    assert!(Pin::new(&mut pending).poll(&mut cx).is_pending());
+
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 0);
+
+    sender.send(()).unwrap();
+    // the worker wakes the waker after the read has finished
+    let start = std::time::Instant::now();
+    while wakes.0.load(Ordering::SeqCst) == 0 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "the waker was not woken"
+        );
+        std::thread::yield_now();
+    }
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+
+    match Pin::new(&mut pending).poll(&mut cx) {
+        Poll::Ready(read) => assert_eq!(read.unwrap(), [5, 6, 7]),
+        Poll::Pending => panic!("the read should be complete"),
+    }
+}
+
+#[cfg(feature = "opencl")]
+#[test]
+fn test_cl_read_async_and_events() -> custos::Result<()> {
+    use custos::OpenCL;
+
+    let device = OpenCL::new(0)?;
+    let buf = Buffer::from((&device, [1, 2, 3, 4]));
+
+    let event = device.record_event()?;
+    device.wait_event(&event)?;
+
+    let pending = unsafe { device.read_async(&buf) }?;
+    assert_eq!(pending.wait()?, [1, 2, 3, 4]);
+
+    event.wait()?;
+    assert!(event.is_complete());
+    device.synchronize()
+}
+
+#[cfg(feature = "cuda")]
+#[test]
+fn test_cuda_read_async_and_events() -> custos::Result<()> {
+    use custos::CUDA;
+
+    let device = CUDA::new(0)?;
+    let buf = Buffer::from((&device, [1, 2, 3, 4]));
+
+    let event = device.record_event()?;
+    device.wait_event(&event)?;
+
+    let pending = unsafe { device.read_async(&buf) }?;
+    assert_eq!(pending.wait()?, [1, 2, 3, 4]);
+
+    event.wait()?;
+    assert!(event.is_complete());
+    device.synchronize()
+}
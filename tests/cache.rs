@@ -37,3 +37,267 @@ fn test_caching_cpu() {
         assert_eq!(len, 3);
     }
 }
+
+#[cfg(feature = "cpu")]
+#[cfg(not(feature = "realloc"))]
+#[test]
+fn test_cache_budget_many_lengths() {
+    use custos::{CacheReturn, Device};
+
+    let device = CPU::new();
+    device.cache_mut().set_budget(Some(4 * 100 * 4));
+
+    for len in 1..=100 {
+        for _ in range(3) {
+            let buf = device.retrieve::<f32, ()>(len, ());
+            assert_eq!(buf.len(), len);
+        }
+        assert!(device.cache().allocated_bytes() <= 4 * 100 * 4);
+    }
+
+    // 97, 98, 99 and 100 fit into the budget
+    assert_eq!(device.cache().nodes.len(), 4);
+}
+
+#[cfg(feature = "cpu")]
+#[cfg(not(feature = "realloc"))]
+#[test]
+fn test_cache_evicts_least_recently_used() {
+    use custos::{set_count, CacheReturn, Device, Ident};
+
+    let device = CPU::new();
+
+    unsafe { set_count(0) };
+    let first = device.retrieve::<f32, ()>(10, ()).id();
+    let second = device.retrieve::<f32, ()>(10, ()).id();
+
+    // use the first entry again
+    unsafe { set_count(0) };
+    device.retrieve::<f32, ()>(10, ());
+
+    unsafe { set_count(2) };
+    device.cache_mut().set_budget(Some(80));
+    let third = device.retrieve::<f32, ()>(10, ()).id();
+
+    let cache = device.cache();
+    assert!(cache.nodes.contains_key(&first));
+    assert!(!cache.nodes.contains_key(&second));
+    assert!(cache.nodes.contains_key(&third));
+    assert_eq!(third, Ident { idx: 2, len: 10 });
+}
+
+#[cfg(feature = "cpu")]
+#[cfg(not(feature = "realloc"))]
+#[test]
+fn test_cache_budget_keeps_retrieved_buffers() {
+    use custos::{CacheReturn, Device};
+
+    let device = CPU::new();
+    device.cache_mut().set_budget(Some(40));
+
+    let mut first = device.retrieve::<f32, ()>(10, ());
+    first.write(&[1.; 10]);
+
+    // exceeds the budget, but the first entry is still referenced by `first`
+    let second = device.retrieve::<f32, ()>(10, ());
+    assert!(device.cache().nodes.contains_key(&first.id()));
+    assert_eq!(device.cache().allocated_bytes(), 80);
+
+    assert_eq!(first.read(), [1.; 10]);
+
+    drop(first);
+    assert_eq!(device.cache_mut().trim(), 40);
+    assert!(device.cache().nodes.contains_key(&second.id()));
+}
+
+#[cfg(feature = "cpu")]
+#[cfg(not(feature = "realloc"))]
+#[test]
+fn test_cache_clear_unused_keeps_referenced() {
+    use custos::{CacheReturn, Device};
+
+    let device = CPU::new();
+
+    let owned = Buffer::from((&device, [1f32, 2., 3.]));
+    let shared = device.retrieve::<f32, ()>(10, ()).id();
+    let sharing = device.retrieve::<f32, ()>(10, ()).id();
+    let pinned = device.retrieve::<f32, ()>(20, ()).id();
+    let unused = device.retrieve::<f32, ()>(30, ()).id();
+
+    {
+        let mut cache = device.cache_mut();
+        let ptr = cache.nodes[&shared].clone();
+        cache.insert(sharing, ptr);
+        cache.pinned.insert(pinned);
+    }
+
+    assert_eq!(device.cache().allocated_bytes(), 40 + 80 + 120);
+    assert_eq!(device.cache_mut().clear_unused(), 120);
+
+    let cache = device.cache();
+    assert!(cache.nodes.contains_key(&owned.id()));
+    assert!(cache.nodes.contains_key(&shared));
+    assert!(cache.nodes.contains_key(&sharing));
+    assert!(cache.nodes.contains_key(&pinned));
+    assert!(!cache.nodes.contains_key(&unused));
+}
+
+#[cfg(feature = "cpu")]
+#[cfg(not(feature = "realloc"))]
+#[test]
+fn test_cache_stats_missing_range() {
+    use custos::{CacheReturn, Device, Ident};
+
+    let device = CPU::new();
+
+    for _ in range(10) {
+        device.retrieve::<f32, ()>(100, ());
+        device.retrieve::<f64, ()>(10, ());
+    }
+
+    // without range: every iteration allocates new entries
+    for _ in 0..10 {
+        device.retrieve::<u8, ()>(7, ());
+    }
+
+    let stats = device.cache().stats();
+    assert_eq!(stats.hits, 18);
+    assert_eq!(stats.misses, 12);
+    assert_eq!(stats.live_entries, 12);
+    assert_eq!(stats.total_bytes, 400 + 80 + 10 * 7);
+    assert_eq!(stats.high_water_mark, stats.total_bytes);
+    assert_eq!(stats.bytes_by_elem_size[&1], 70);
+    assert_eq!(stats.bytes_by_elem_size[&4], 400);
+    assert_eq!(stats.bytes_by_elem_size[&8], 80);
+
+    let dump = device.cache().dump();
+    assert_eq!(dump[0].0, Ident { idx: 0, len: 100 });
+    assert_eq!(dump[0].1.hits, 9);
+
+    // the entries allocated without range are never reused
+    assert_eq!(dump.iter().filter(|(_, info)| info.hits == 0).count(), 10);
+
+    device.cache_mut().clear_unused();
+
+    let stats = device.cache().stats();
+    assert_eq!(stats.total_bytes, 0);
+    assert_eq!(stats.high_water_mark, 400 + 80 + 10 * 7);
+}
+
+#[cfg(feature = "cpu")]
+#[cfg(not(feature = "realloc"))]
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "idx 1")]
+fn test_cache_type_mismatch_panics() {
+    use custos::Device;
+
+    let device = CPU::new();
+
+    device.retrieve::<f32, ()>(10, ());
+    device.retrieve::<f32, ()>(10, ());
+
+    // the count drifted: the second entry was allocated as f32
+    unsafe { custos::set_count(1) };
+    device.retrieve::<f64, ()>(10, ());
+}
+
+#[cfg(feature = "cpu")]
+#[cfg(not(feature = "realloc"))]
+#[test]
+fn test_cache_checked_type() {
+    use custos::{bump_count, CacheReturn, Dim1, Ident};
+
+    let device = CPU::new();
+    let ident = Ident { idx: 0, len: 10 };
+
+    let _buf: Buffer<f32> = device.cache_mut().get(&device, ident, (), bump_count);
+
+    assert!(device.cache().check_type::<f32, ()>(ident).is_ok());
+    assert!(device.cache().check_type::<u32, ()>(ident).is_err());
+
+    let mismatch = device
+        .cache()
+        .check_type::<f32, Dim1<10>>(ident)
+        .unwrap_err();
+    assert_eq!(mismatch.ident, ident);
+    assert_eq!(mismatch.allocated.shape_name, "()");
+
+    let existing = unsafe {
+        device
+            .cache()
+            .try_get_existing_buf::<f32, ()>(&device, ident)
+    };
+    assert!(existing.unwrap().is_some());
+
+    let missing = Ident { idx: 1, len: 10 };
+    let existing = unsafe {
+        device
+            .cache()
+            .try_get_existing_buf::<f32, ()>(&device, missing)
+    };
+    assert!(existing.unwrap().is_none());
+
+    let existing = unsafe {
+        device
+            .cache()
+            .try_get_existing_buf::<i32, ()>(&device, ident)
+    };
+    assert!(existing.is_err());
+}
+
+#[cfg(feature = "cpu")]
+#[cfg(not(feature = "realloc"))]
+#[test]
+fn test_count_scope_keeps_caller_idents() {
+    use custos::{CacheReturn, CountScope, Device, Ident};
+
+    fn helper(device: &CPU, times: usize) -> *mut f32 {
+        let _scope = CountScope::new();
+        let first = device.retrieve::<f32, ()>(10, ()).ptr.ptr;
+        for _ in 1..times {
+            device.retrieve::<f32, ()>(10, ());
+        }
+        first
+    }
+
+    let device = CPU::new();
+
+    let mut helper_ptrs = vec![];
+    let mut caller_ptrs = vec![];
+    for epoch in range(1..4) {
+        // the amount of buffers retrieved by the helper differs in every epoch
+        helper_ptrs.push(helper(&device, epoch));
+        caller_ptrs.push(device.retrieve::<f32, ()>(10, ()).ptr.ptr);
+    }
+
+    assert!(helper_ptrs.iter().all(|ptr| *ptr == helper_ptrs[0]));
+    assert!(caller_ptrs.iter().all(|ptr| *ptr == caller_ptrs[0]));
+    assert_ne!(helper_ptrs[0], caller_ptrs[0]);
+
+    // the scope took up the count 0, the entry of the caller keeps its plain ident
+    let cache = device.cache();
+    assert!(cache.nodes.contains_key(&Ident { idx: 1, len: 10 }));
+    assert!(!cache.nodes.contains_key(&Ident { idx: 0, len: 10 }));
+    assert_eq!(cache.nodes.len(), 4);
+}
+
+#[cfg(feature = "cpu")]
+#[cfg(not(feature = "realloc"))]
+#[test]
+fn test_keyed_count_scope_entries() {
+    use custos::{CountScope, Device};
+
+    let device = CPU::new();
+
+    let keyed = || {
+        let _scope = CountScope::with_key(7);
+        device.retrieve::<f32, ()>(10, ()).ptr.ptr
+    };
+
+    let first = keyed();
+    let unscoped = device.retrieve::<f32, ()>(10, ()).ptr.ptr;
+    // the count of the caller changed, the keyed scope still finds its entry
+    assert_eq!(keyed(), first);
+    assert_ne!(unscoped, first);
+}
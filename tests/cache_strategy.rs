@@ -0,0 +1,157 @@
+#![cfg(not(feature = "realloc"))]
+
+use custos::{
+    range, Alloc, ApplyFunction, Buffer, CacheReturn, CacheStrategy, Combiner, Read, WriteBuf,
+};
+
+const STRATEGIES: [CacheStrategy; 3] = [
+    CacheStrategy::Count,
+    CacheStrategy::Caller,
+    CacheStrategy::None,
+];
+
+/// Every operation is called from its own location, hence no strategy may share memory between the results.
+fn chained_ops<D>(device: &D)
+where
+    D: ApplyFunction<f32> + Read<f32> + WriteBuf<f32> + for<'a> Alloc<'a, f32>,
+{
+    let mut x = Buffer::<f32, D>::new(device, 4);
+    device.write(&mut x, &[1., 2., 3., 4.]);
+
+    let squared = device.apply_fn(&x, |x| x.mul(x));
+    let added = device.apply_fn(&squared, |x| x.add(1.));
+    let doubled = device.apply_fn(&x, |x| x.mul(2.));
+
+    assert_eq!(device.read_to_vec(&squared), [1., 4., 9., 16.]);
+    assert_eq!(device.read_to_vec(&added), [2., 5., 10., 17.]);
+    assert_eq!(device.read_to_vec(&doubled), [2., 4., 6., 8.]);
+}
+
+#[cfg(feature = "cpu")]
+#[cfg(feature = "macro")]
+#[test]
+fn test_strategies_chained_ops_cpu() {
+    for strategy in STRATEGIES {
+        let device = custos::CPU::new().with_cache_strategy(strategy);
+        assert_eq!(device.cache_strategy(), strategy);
+
+        chained_ops(&device);
+    }
+}
+
+#[cfg(feature = "opencl")]
+#[test]
+fn test_strategies_chained_ops_cl() -> custos::Result<()> {
+    for strategy in STRATEGIES {
+        let device = custos::OpenCL::new(0)?.with_cache_strategy(strategy);
+        chained_ops(&device);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "cpu")]
+#[cfg(feature = "macro")]
+#[test]
+fn test_strategies_loop_cpu() {
+    use custos::{CallerCacheReturn, CPU};
+
+    for strategy in STRATEGIES {
+        let device = CPU::new().with_cache_strategy(strategy);
+        let x = Buffer::from((&device, [1., 2., 3., 4.]));
+
+        let mut ptrs = vec![];
+        for _ in range(10) {
+            let out = device.apply_fn(&x, |x| x.mul(3.));
+            assert_eq!(out.read(), [3., 6., 9., 12.]);
+            ptrs.push(out.ptr.ptr);
+        }
+
+        let all_reused = ptrs.iter().all(|ptr| *ptr == ptrs[0]);
+
+        match strategy {
+            CacheStrategy::Count => {
+                assert!(all_reused);
+                assert_eq!(device.caller_cache().stats().live_entries, 0);
+            }
+            CacheStrategy::Caller => {
+                assert!(all_reused);
+                assert_eq!(device.caller_cache().stats().live_entries, 1);
+            }
+            CacheStrategy::None => {
+                assert_eq!(device.cache().stats().hits, 0);
+                assert_eq!(device.caller_cache().stats().live_entries, 0);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "cpu")]
+#[cfg(feature = "macro")]
+#[test]
+fn test_caller_strategy_without_range() {
+    use custos::{CallerCacheReturn, CPU};
+
+    let device = CPU::new().with_cache_strategy(CacheStrategy::Caller);
+    let x = Buffer::from((&device, [1., 2., 3., 4.]));
+
+    for _ in 0..10 {
+        let squared = device.apply_fn(&x, |x| x.mul(x));
+        let out = device.apply_fn(&squared, |x| x.add(1.));
+        assert_eq!(out.read(), [2., 5., 10., 17.]);
+    }
+
+    let stats = device.caller_cache().stats();
+    assert_eq!(stats.live_entries, 2);
+    assert_eq!(stats.misses, 2);
+    assert_eq!(stats.hits, 18);
+
+    // the count cache is not used
+    assert_eq!(device.cache().stats().misses, 0);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_strategy_per_device() {
+    use custos::{Device, CPU};
+
+    let counted = CPU::new();
+    let uncached = CPU::new().with_cache_strategy(CacheStrategy::None);
+
+    unsafe { custos::set_count(0) };
+    let first = counted.retrieve::<f32, ()>(10, ());
+    let allocated = uncached.retrieve::<f32, ()>(10, ());
+
+    unsafe { custos::set_count(0) };
+    let first_again = counted.retrieve::<f32, ()>(10, ());
+    let allocated_again = uncached.retrieve::<f32, ()>(10, ());
+
+    assert_eq!(first.ptr.ptr, first_again.ptr.ptr);
+    assert_ne!(allocated.ptr.ptr, allocated_again.ptr.ptr);
+    assert_eq!(uncached.cache().stats().misses, 0);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_caller_strategy_different_lengths() {
+    use custos::{Device, CPU};
+
+    let device = CPU::new().with_cache_strategy(CacheStrategy::Caller);
+
+    for len in [2, 1000, 2, 1000] {
+        let mut buf = device.retrieve::<f32, ()>(len, ());
+        assert_eq!(buf.len(), len);
+        buf[len - 1] = 1.;
+    }
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_strategy_on_other_threads() {
+    use custos::CPU;
+
+    let device = CPU::new().with_cache_strategy(CacheStrategy::Caller);
+
+    std::thread::scope(|s| {
+        s.spawn(|| assert_eq!(device.cache_strategy(), CacheStrategy::Caller));
+    });
+}
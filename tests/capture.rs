@@ -0,0 +1,212 @@
+#![cfg(all(feature = "cpu", feature = "macro", not(feature = "realloc")))]
+
+use std::cell::Cell;
+
+use custos::{ApplyFunction, Buffer, CacheReturn, ClearBuf, Combiner, CopySlice, Device, CPU};
+
+fn step<'a>(device: &'a CPU, x: &Buffer<f32>) -> Buffer<'a, f32> {
+    let doubled = device.apply_fn(x, |x| x.mul(2.));
+    device.apply_fn(&doubled, |x| x.add(1.))
+}
+
+#[test]
+fn test_replay_reuses_captured_buffers() {
+    let device = CPU::new();
+    let x = Buffer::from((&device, [1., 2., 3.]));
+
+    let mut plan = device.capture(|| step(&device, &x).ptrs());
+    assert_eq!(plan.idents().len(), 2);
+
+    let captured = *plan.replay();
+    for _ in 0..10 {
+        assert_eq!(*plan.replay(), captured);
+    }
+}
+
+#[test]
+fn test_replay_executes_recorded_ops() {
+    let device = CPU::new();
+    let x = Buffer::from((&device, [1., 2., 3.]));
+    let steps = Cell::new(0);
+
+    let mut plan = device.capture(|| {
+        steps.set(steps.get() + 1);
+        let mut out = device.retrieve::<f32, ()>(3, ());
+        device.clear(&mut out);
+        device.copy_slice_to(&x, 1..3, &mut out, ..2);
+        out
+    });
+    assert!(plan.is_recorded());
+    assert_eq!(plan.ops(), ["clear", "copy_slice"]);
+
+    let next = Buffer::from((&device, [4., 5., 6.]));
+    plan.bind(&x, &next).unwrap();
+    assert_eq!(plan.replay().read(), [5., 6., 0.]);
+    assert_eq!(steps.get(), 1);
+}
+
+#[test]
+fn test_replay_executes_step_if_ops_are_not_recorded() {
+    let device = CPU::new();
+    let x = Buffer::from((&device, [1., 2., 3.]));
+    let factor = Cell::new(2.);
+
+    // closures are not kept beyond the call of `apply_fn`
+    let mut plan = device.capture(|| {
+        let factor = factor.get();
+        device.apply_fn(&x, move |x| x.mul(factor))
+    });
+    assert!(!plan.is_recorded());
+
+    factor.set(3.);
+    assert_eq!(plan.replay().read(), [3., 6., 9.]);
+
+    // the temporary `Buffer` is freed after the capture
+    let mut plan = device.capture(|| {
+        let tmp = Buffer::from((&device, [1., 2., 3.]));
+        let mut out = device.retrieve::<f32, ()>(3, ());
+        device.copy_slice_to(&tmp, .., &mut out, ..);
+        out
+    });
+    assert!(!plan.is_recorded());
+    assert_eq!(plan.replay().read(), [1., 2., 3.]);
+}
+
+#[test]
+fn test_replay_executes_step_after_an_input_is_freed() {
+    let device = CPU::new();
+    let x = Buffer::from((&device, [1., 2., 3.]));
+    let input = std::cell::RefCell::new(Some(x));
+    let steps = Cell::new(0);
+
+    let mut plan = device.capture(|| {
+        steps.set(steps.get() + 1);
+        let mut out = device.retrieve::<f32, ()>(3, ());
+        match input.borrow().as_ref() {
+            Some(input) => device.copy_slice_to(input, .., &mut out, ..),
+            None => device.clear(&mut out),
+        }
+        out
+    });
+    assert_eq!(plan.replay().read(), [1., 2., 3.]);
+    assert_eq!(steps.get(), 1);
+
+    input.borrow_mut().take();
+    assert_eq!(plan.replay().read(), [0., 0., 0.]);
+    assert_eq!(steps.get(), 2);
+    assert!(!plan.is_recorded());
+}
+
+#[test]
+fn test_panic_during_capture_resets_the_cache() {
+    let device = CPU::new();
+    let x = Buffer::from((&device, [1., 2., 3.]));
+
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        device.capture(|| {
+            step(&device, &x);
+            panic!("step failed")
+        });
+    }));
+    assert!(res.is_err());
+
+    // the retrieved entries are no longer recorded, a new capture works as usual
+    let mut plan = device.capture(|| step(&device, &x));
+    assert_eq!(plan.idents().len(), 2);
+    assert_eq!(plan.replay().read(), [3., 5., 7.]);
+}
+
+#[test]
+fn test_replay_skips_cache_lookup() {
+    let device = CPU::new();
+    let x = Buffer::from((&device, [1., 2., 3.]));
+
+    let mut plan = device.capture(|| step(&device, &x).read().to_vec());
+    assert!(!plan.is_recorded());
+    let stats = device.cache().stats();
+
+    assert_eq!(*plan.replay(), [3., 5., 7.]);
+    assert_eq!(*plan.replay(), [3., 5., 7.]);
+
+    // the replayed entries were not looked up in the cache
+    assert_eq!(device.cache().stats().hits, stats.hits);
+    assert_eq!(device.cache().stats().misses, stats.misses);
+}
+
+#[test]
+fn test_bind_input() {
+    let device = CPU::new();
+    let x = Buffer::from((&device, [1., 2., 3.]));
+
+    let mut plan = device.capture(|| step(&device, &x));
+
+    let first = Buffer::from((&device, [4., 5., 6.]));
+    let second = Buffer::from((&device, [0., 0., 0.]));
+    plan.bind(&x, &first).unwrap();
+    assert_eq!(plan.replay().read(), [9., 11., 13.]);
+    assert_eq!(x.read(), [4., 5., 6.]);
+
+    // binding `x` again replaces the previous source
+    plan.bind(&x, &second).unwrap();
+    assert_eq!(plan.replay().read(), [1., 1., 1.]);
+
+    let short = Buffer::from((&device, [1., 2.]));
+    assert!(plan.bind(&x, &short).is_err());
+
+    // `x` keeps the data of the last replay
+    plan.bind(&x, &first).unwrap();
+    plan.unbind_all();
+    assert_eq!(plan.replay().read(), [1., 1., 1.]);
+}
+
+#[test]
+fn test_retrieve_after_capture_keeps_entries() {
+    let device = CPU::new();
+    let x = Buffer::from((&device, [1., 2., 3.]));
+
+    let mut plan = device.capture(|| step(&device, &x).ptrs());
+    let captured = *plan.replay();
+
+    let other = device.retrieve::<f32, ()>(3, ());
+    plan.replay();
+    let after = device.retrieve::<f32, ()>(3, ());
+
+    assert_ne!(other.ptrs(), after.ptrs());
+    assert_ne!(other.ptrs(), captured);
+}
+
+#[test]
+fn test_diverging_replay_uses_cache() {
+    let device = CPU::new();
+    let len = Cell::new(3);
+
+    // the retrieved `Buffer` is not written by a recorded operation, hence the step is executed on every replay
+    let mut plan = device.capture(|| device.retrieve::<f32, ()>(len.get(), ()).ptrs());
+    assert!(!plan.is_recorded());
+    let captured = *plan.replay();
+    let misses = device.cache().stats().misses;
+
+    // the retrieved `Buffer` does not match the recorded entry anymore
+    len.set(4);
+    assert_ne!(*plan.replay(), captured);
+    assert_eq!(device.cache().stats().misses, misses + 1);
+
+    len.set(3);
+    assert_eq!(*plan.replay(), captured);
+}
+
+#[cfg(feature = "opt-cache")]
+#[test]
+fn test_plan_nodes() {
+    let device = CPU::new();
+    let x = Buffer::from((&device, [1., 2., 3.]));
+
+    let plan = device.capture(|| {
+        step(&device, &x);
+    });
+
+    let nodes = plan.nodes();
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(nodes[1].deps, [nodes[0].idx; 2]);
+    assert!(nodes.iter().all(|node| node.len == 3));
+}
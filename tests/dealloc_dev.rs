@@ -68,7 +68,7 @@ fn test_dealloc_device_cache_cu() -> custos::Result<()> {
     let device = CUDA::new(0)?;
 
     assert_eq!(device.cache().nodes.len(), 0);
-    let a = device.retrieve::<f32, _>(10, ());
+    let a = device.retrieve::<f32, ()>(10, ());
     assert_eq!(device.cache().nodes.len(), 1);
 
     drop(a);
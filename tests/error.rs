@@ -58,11 +58,106 @@ fn test_print_error() {
 #[cfg(not(feature = "no-std"))]
 #[test]
 fn test_std_err() {
+    use custos::ErrorKind;
+
     let err = Error::from(DeviceError::ConstructError);
     assert_eq!(
-        err.downcast_ref::<DeviceError>(),
+        err.kind::<DeviceError>(),
         Some(&DeviceError::ConstructError)
     );
+    assert!(matches!(err, Error::Device(DeviceError::ConstructError)));
+}
+
+#[test]
+fn test_structured_err() {
+    let err = Error::LengthMismatch {
+        expected: 4,
+        found: 3,
+    };
+    assert!(matches!(
+        err,
+        Error::LengthMismatch {
+            expected: 4,
+            found: 3
+        }
+    ));
+
+    let err = Error::ShapeMismatch {
+        expected: 6,
+        found: 5,
+    };
+    assert!(matches!(err, Error::ShapeMismatch { .. }));
+}
+
+#[cfg(not(feature = "no-std"))]
+#[test]
+fn test_print_structured_err() {
+    use custos::LimitExceeded;
+
+    let err = Error::LengthMismatch {
+        expected: 4,
+        found: 3,
+    };
+    assert_eq!("Expected a length of 4, found 3.", &format!("{err}"));
+
+    let err = Error::Alloc {
+        bytes: 1024,
+        limit: None,
+    };
+    assert_eq!("Failed to allocate 1024 bytes.", &format!("{err:?}"));
+
+    let err = Error::Alloc {
+        bytes: 4,
+        limit: Some(LimitExceeded {
+            live: 64,
+            limit: 64,
+        }),
+    };
+    assert_eq!(
+        "Out of memory: Allocating 4 bytes would exceed the limit of 64 bytes (64 bytes are in use).",
+        &format!("{err}")
+    );
+}
+
+#[cfg(not(feature = "no-std"))]
+#[test]
+fn test_err_from_boxed() {
+    use custos::ErrorKind;
+
+    let boxed: Box<dyn std::error::Error + Send + Sync> = Box::new(DeviceError::UnknownDevice);
+    assert!(matches!(
+        Error::from(boxed),
+        Error::Device(DeviceError::UnknownDevice)
+    ));
+
+    let boxed: Box<dyn std::error::Error + Send + Sync> = "custom".into();
+    let err = Error::from(boxed);
+    assert!(matches!(err, Error::Other(_)));
+    assert_eq!(err.kind::<DeviceError>(), None);
+    assert_eq!("custom", &format!("{err}"));
+}
+
+#[cfg(not(feature = "no-std"))]
+#[test]
+fn test_err_into_boxed() {
+    fn fails() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err(Error::from(DeviceError::UnknownDevice))?;
+        Ok(())
+    }
+    let err = Error::from(fails().unwrap_err());
+    assert!(matches!(err, Error::Device(DeviceError::UnknownDevice)));
+}
+
+#[cfg(feature = "opencl")]
+#[test]
+fn test_ocl_error_variant() {
+    use custos::OpenCL;
+    use min_cl::api::OCLErrorKind;
+
+    let Err(err) = OpenCL::new(1000000000000000000) else {
+        panic!("created an OpenCL device with an invalid index")
+    };
+    assert!(matches!(err, Error::OpenCL(OCLErrorKind::InvalidDeviceIdx)));
 }
 
 #[cfg(feature = "opencl")]
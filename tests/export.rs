@@ -0,0 +1,88 @@
+#![cfg(all(feature = "cpu", feature = "macro"))]
+
+#[cfg(any(feature = "opt-cache", feature = "autograd"))]
+use custos::{Buffer, Combiner, CPU};
+
+#[cfg(feature = "opt-cache")]
+#[test]
+fn test_graph_export() {
+    use custos::{ApplyFunction, GraphReturn};
+
+    let device = CPU::new();
+    // idx: 0
+    let x = Buffer::from((&device, [1., 2., 3., 4.]));
+
+    // idx: 1, 2, 3, all share the memory of idx 1
+    let a = device.apply_fn(&x, |x| x.add(1.));
+    let b = device.apply_fn(&a, |x| x.mul(2.));
+    let _c = device.apply_fn(&b, |x| x.mul(3.));
+
+    let graph = device.graph();
+
+    let dot = graph.to_dot();
+    assert!(dot.contains(r##"n0 [label="#0 leaf\n4 x 8 B"];"##));
+    for idx in 1..4 {
+        assert!(dot.contains(&format!(
+            r##"n{idx} [label="#{idx} apply_fn\n4 x 8 B", style=filled, fillcolor="#8dd3c7", peripheries=2];"##
+        )));
+        assert!(dot.contains(&format!("n{} -> n{idx};", idx - 1)));
+    }
+
+    let json = graph.to_json();
+    assert!(
+        json.starts_with(r#"{"version":1,"nodes":[{"idx":0,"ident":{"idx":0,"len":4},"op":null,"#)
+    );
+    assert!(json.contains(
+        r#"{"idx":2,"ident":{"idx":2,"len":4},"op":"apply_fn","deps":[1,1],"len":4,"elem_size":8,"leaf":false,"inplace":true,"trace":0}"#
+    ));
+    assert!(json.ends_with(
+        r#""cache_traces":[{"cache_id":{"idx":1,"len":4},"use_cache_ids":[{"idx":2,"len":4},{"idx":3,"len":4}]}]}"#
+    ));
+}
+
+#[cfg(feature = "autograd")]
+#[test]
+fn test_tape_export() {
+    use custos::{TapeEntry, TapeReturn, UnaryElementWiseMayGrad};
+
+    let device = CPU::new();
+    let x = Buffer::from((&device, [1., 2., 3., 4.]));
+    let a = device.unary_ew(&x, |x| x.exp(), |x| x.exp());
+    let b = device.unary_ew(&a, |x| x.mul(x), |x| x.mul(2.));
+
+    {
+        let tape = device.tape();
+        assert_eq!(
+            tape.entries(),
+            [
+                TapeEntry {
+                    op: Some("unary_ew"),
+                    inputs: vec![x.id()],
+                    outputs: vec![a.id()],
+                },
+                TapeEntry {
+                    op: Some("unary_ew"),
+                    inputs: vec![a.id()],
+                    outputs: vec![b.id()],
+                },
+            ]
+        );
+
+        let (x, a, b) = (x.id().idx, a.id().idx, b.id().idx);
+        let dot = tape.to_dot();
+        assert!(dot.contains(r##"g1 [label="#1 unary_ew"];"##));
+        assert!(dot.contains(&format!(r#"b{a}_4 [label="{a} (4)", shape=ellipse];"#)));
+        assert!(dot.contains(&format!("b{x}_4 -> g0;\n    g0 -> b{a}_4;")));
+        assert!(dot.contains(&format!("b{a}_4 -> g1;\n    g1 -> b{b}_4;")));
+
+        assert_eq!(
+            tape.to_json(),
+            format!(
+                r#"{{"version":1,"entries":[{{"idx":0,"op":"unary_ew","inputs":[{{"idx":{x},"len":4}}],"outputs":[{{"idx":{a},"len":4}}]}},{{"idx":1,"op":"unary_ew","inputs":[{{"idx":{a},"len":4}}],"outputs":[{{"idx":{b},"len":4}}]}}]}}"#
+            )
+        );
+    }
+
+    b.backward();
+    assert!(device.tape().entries().is_empty());
+}
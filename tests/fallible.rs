@@ -0,0 +1,262 @@
+use custos::{Alloc, Buffer, CopySlice, Device, Error, Read, WriteBuf};
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_try_alloc_zero_len() {
+    use custos::{flag::AllocFlag, CPU};
+
+    let device = CPU::new();
+
+    let err = Alloc::<f32>::try_alloc(&device, 0, AllocFlag::None).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::Alloc {
+            bytes: 0,
+            limit: None
+        }
+    ));
+
+    let err = Buffer::<f32, _>::try_new(&device, 0).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::Alloc {
+            bytes: 0,
+            limit: None
+        }
+    ));
+
+    assert!(Alloc::<f32>::try_alloc(&device, usize::MAX, AllocFlag::None).is_err());
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+#[should_panic]
+fn test_alloc_zero_len_panics() {
+    use custos::CPU;
+
+    let device = CPU::new();
+    Buffer::<f32, _>::new(&device, 0);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_try_retrieve() {
+    use custos::CPU;
+
+    let device = CPU::new();
+
+    let buf = device.try_retrieve::<f32, ()>(10, ()).unwrap();
+    assert_eq!(buf.len(), 10);
+
+    let err = device.try_retrieve::<f32, ()>(0, ()).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::Alloc {
+            bytes: 0,
+            limit: None
+        }
+    ));
+}
+
+#[cfg(feature = "cpu")]
+#[cfg(not(feature = "realloc"))]
+#[test]
+fn test_cache_try_get() {
+    use custos::{bump_count, CacheReturn, Ident, CPU};
+
+    let device = CPU::new();
+    let ident = Ident { idx: 0, len: 0 };
+
+    let err = device
+        .cache_mut()
+        .try_get::<f32, ()>(&device, ident, (), bump_count)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::Alloc {
+            bytes: 0,
+            limit: None
+        }
+    ));
+    assert!(!device.cache().nodes.contains_key(&ident));
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_try_read_write() {
+    use custos::CPU;
+
+    let device = CPU::new();
+    let mut buf = Buffer::<i32, _>::new(&device, 4);
+
+    device.try_write(&mut buf, &[1, 2, 3, 4]).unwrap();
+    assert_eq!(device.try_read(&buf).unwrap(), [1, 2, 3, 4]);
+
+    let err = device.try_write(&mut buf, &[1, 2]).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::LengthMismatch {
+            expected: 4,
+            found: 2
+        }
+    ));
+    // a failed write leaves the buffer untouched
+    assert_eq!(buf.read(), [1, 2, 3, 4]);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_try_copy_slice() {
+    use custos::CPU;
+
+    let device = CPU::new();
+    let source = Buffer::from((&device, [1, 2, 3, 4, 5]));
+    let mut dest = Buffer::<i32, _>::new(&device, 3);
+
+    device
+        .try_copy_slice_to(&source, 1..3, &mut dest, 1..)
+        .unwrap();
+    assert_eq!(dest.read(), [0, 2, 3]);
+
+    let err = device
+        .try_copy_slice_to(&source, 4..7, &mut dest, ..3)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::OutOfRange {
+            start: 4,
+            end: 7,
+            len: 5
+        }
+    ));
+
+    let err = device
+        .try_copy_slice_to(&source, ..2, &mut dest, ..)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::LengthMismatch {
+            expected: 2,
+            found: 3
+        }
+    ));
+
+    let slice = device.try_copy_slice(&source, 2..).unwrap();
+    assert_eq!(slice.read(), [3, 4, 5]);
+
+    assert!(device.try_copy_slice(&source, 3..3).is_err());
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+#[should_panic]
+fn test_copy_slice_out_of_range_panics() {
+    use custos::CPU;
+
+    let device = CPU::new();
+    let source = Buffer::from((&device, [1, 2, 3]));
+    device.copy_slice(&source, 2..4);
+}
+
+#[cfg(all(feature = "cpu", feature = "macro"))]
+#[test]
+fn test_try_apply_fn() {
+    use custos::{ApplyFunction, Combiner, CPU};
+
+    let device = CPU::new();
+    let buf = Buffer::from((&device, [1., 2., 3.]));
+
+    let out = device.try_apply_fn(&buf, |x| x.mul(2.)).unwrap();
+    assert_eq!(out.read(), [2., 4., 6.]);
+}
+
+#[cfg(feature = "stack")]
+#[test]
+fn test_stack_try_ops() {
+    use custos::{Dim1, Stack};
+
+    let device = Stack;
+    let mut buf = Buffer::<i32, Stack, Dim1<4>>::from((&device, [1, 2, 3, 4]));
+
+    assert_eq!(device.try_read(&buf).unwrap(), [1, 2, 3, 4]);
+
+    let err = device.try_write(&mut buf, &[5, 6, 7]).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::LengthMismatch {
+            expected: 4,
+            found: 3
+        }
+    ));
+
+    device.try_write(&mut buf, &[5, 6, 7, 8]).unwrap();
+    assert_eq!(device.read(&buf), [5, 6, 7, 8]);
+}
+
+#[cfg(all(feature = "stack", feature = "macro"))]
+#[test]
+fn test_stack_try_apply_fn() {
+    use custos::{ApplyFunction, Combiner, Dim1, Stack};
+
+    let buf = Buffer::<f32, Stack, Dim1<3>>::from((&Stack, [1., 2., 3.]));
+    let out = Stack.try_apply_fn(&buf, |x| x.add(1.)).unwrap();
+    assert_eq!(out.read(), [2., 3., 4.]);
+}
+
+#[cfg(feature = "opencl")]
+#[test]
+fn test_cl_try_ops() -> custos::Result<()> {
+    use custos::OpenCL;
+
+    let device = OpenCL::new(0)?;
+    let mut buf = Buffer::<i32, _>::new(&device, 4);
+
+    assert!(matches!(
+        device.try_write(&mut buf, &[1, 2]),
+        Err(Error::LengthMismatch { .. })
+    ));
+    device.try_write(&mut buf, &[1, 2, 3, 4])?;
+    assert_eq!(device.try_read(&buf)?.to_vec(), [1, 2, 3, 4]);
+
+    assert!(matches!(
+        device.try_copy_slice(&buf, 2..5),
+        Err(Error::OutOfRange { .. })
+    ));
+    assert!(matches!(
+        Buffer::<i32, _>::try_new(&device, 0),
+        Err(Error::Alloc {
+            bytes: 0,
+            limit: None
+        })
+    ));
+    Ok(())
+}
+
+#[cfg(feature = "cuda")]
+#[test]
+fn test_cuda_try_ops() -> custos::Result<()> {
+    use custos::CUDA;
+
+    let device = CUDA::new(0)?;
+    let mut buf = Buffer::<i32, _>::new(&device, 4);
+
+    assert!(matches!(
+        device.try_write(&mut buf, &[1, 2]),
+        Err(Error::LengthMismatch { .. })
+    ));
+    device.try_write(&mut buf, &[1, 2, 3, 4])?;
+    assert_eq!(device.try_read(&buf)?, [1, 2, 3, 4]);
+
+    assert!(matches!(
+        device.try_copy_slice(&buf, 2..5),
+        Err(Error::OutOfRange { .. })
+    ));
+    assert!(matches!(
+        Buffer::<i32, _>::try_new(&device, 0),
+        Err(Error::Alloc {
+            bytes: 0,
+            limit: None
+        })
+    ));
+    Ok(())
+}
@@ -0,0 +1,157 @@
+#![cfg(feature = "faulty")]
+
+use custos::{Buffer, Device, Error, Fault, Faulty, Read, WriteBuf};
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_fail_nth_alloc() {
+    use custos::{flag::AllocFlag, Alloc, CPU};
+
+    let device = Faulty::new(CPU::new());
+    let _a = Buffer::<f32, _>::new(&device, 4);
+    assert_eq!(device.allocs(), 1);
+
+    device.fail_alloc(2);
+
+    assert!(Alloc::<f32>::try_alloc(&device, 4, AllocFlag::None).is_ok());
+    assert!(Buffer::<f32, _>::try_new(&device, 4).is_ok());
+    assert!(matches!(
+        Buffer::<f32, _>::try_new(&device, 4),
+        Err(Error::InjectedFault(Fault::Alloc))
+    ));
+    assert!(Buffer::<f32, _>::try_new(&device, 4).is_ok());
+    assert_eq!(device.allocs(), 5);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_fail_retrieve() {
+    use custos::CPU;
+
+    let device = Faulty::new(CPU::new());
+
+    device.fail_alloc(0);
+    let err = device.try_retrieve::<f32, ()>(10, ()).unwrap_err();
+    assert!(matches!(err, Error::InjectedFault(Fault::Alloc)));
+    assert_eq!(err.to_string(), "Injected allocation failure.");
+
+    // the failed allocation did not leave an entry in the cache
+    let buf = device.try_retrieve::<f32, ()>(10, ()).unwrap();
+    assert_eq!(buf.read(), [0.; 10]);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+#[should_panic]
+fn test_fail_alloc_with_slice_panics() {
+    use custos::CPU;
+
+    let device = Faulty::new(CPU::new());
+    device.fail_alloc(0);
+    let _buf = Buffer::from((&device, [1, 2, 3]));
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_fail_reads_and_writes() {
+    use custos::{CopySlice, CPU};
+
+    let device = Faulty::new(CPU::new());
+    let mut buf = Buffer::from((&device, [1, 2, 3]));
+
+    device.fail_reads(true);
+    assert!(matches!(
+        device.try_read(&buf),
+        Err(Error::InjectedFault(Fault::Read))
+    ));
+    device.fail_reads(false);
+    assert_eq!(device.try_read(&buf).unwrap(), [1, 2, 3]);
+
+    device.fail_writes(true);
+    assert!(matches!(
+        device.try_write(&mut buf, &[4, 5, 6]),
+        Err(Error::InjectedFault(Fault::Write))
+    ));
+    // a failed write leaves the buffer untouched
+    assert_eq!(buf.read(), [1, 2, 3]);
+
+    let mut dest = Buffer::<i32, _>::new(&device, 3);
+    assert!(matches!(
+        device.try_copy_slice_to(&buf, .., &mut dest, ..),
+        Err(Error::InjectedFault(Fault::Write))
+    ));
+
+    device.clear_faults();
+    device.write(&mut buf, &[4, 5, 6]);
+    assert_eq!(buf.as_slice(), &[4, 5, 6]);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+#[should_panic]
+fn test_fail_reads_read_panics() {
+    use custos::CPU;
+
+    let device = Faulty::new(CPU::new());
+    let buf = Buffer::from((&device, [1, 2, 3]));
+
+    device.fail_reads(true);
+    buf.read();
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+#[should_panic]
+fn test_fail_writes_clear_panics() {
+    use custos::{ClearBuf, CPU};
+
+    let device = Faulty::new(CPU::new());
+    let mut buf = Buffer::from((&device, [1, 2, 3]));
+
+    device.fail_writes(true);
+    device.clear(&mut buf);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_corrupt() {
+    use custos::CPU;
+
+    let device = Faulty::new(CPU::new());
+    let mut buf = Buffer::from((&device, [1, 2, 3]));
+
+    device.fail_writes(true);
+    device.corrupt(&mut buf, |x| *x = -*x).unwrap();
+    assert_eq!(buf.as_slice(), &[-1, -2, -3]);
+}
+
+#[cfg(all(feature = "cpu", feature = "macro"))]
+#[test]
+fn test_fallback_surfaces_injected_faults() {
+    use custos::{ApplyFunction, Combiner, CPU};
+
+    let device = Faulty::new(CPU::new());
+    let buf = Buffer::from((&device, [1., 2., 3.]));
+
+    let out = device.try_apply_fn(&buf, |x| x.mul(2.)).unwrap();
+    assert_eq!(out.read(), [2., 4., 6.]);
+
+    device.fail_alloc(0);
+    assert!(matches!(
+        device.try_apply_fn(&buf, |x| x.mul(2.)),
+        Err(Error::InjectedFault(Fault::Alloc))
+    ));
+
+    device.fail_writes(true);
+    assert!(matches!(
+        device.try_apply_fn(&buf, |x| x.mul(2.)),
+        Err(Error::InjectedFault(Fault::Write))
+    ));
+    device.fail_writes(false);
+
+    device.fail_reads(true);
+    assert!(matches!(
+        device.try_apply_fn(&buf, |x| x.mul(2.)),
+        Err(Error::InjectedFault(Fault::Read))
+    ));
+}
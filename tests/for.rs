@@ -28,3 +28,48 @@ fn test_range_inclusive() {
         assert!(epoch < 11)
     }
 }
+
+#[test]
+fn test_count_scope_restores_count() {
+    use custos::{bump_count, get_count, ident_idx, CountScope};
+
+    bump_count();
+    let outer_idx = ident_idx();
+
+    {
+        let _scope = CountScope::new();
+        assert_eq!(get_count(), 0);
+        assert_ne!(ident_idx(), outer_idx);
+
+        {
+            let _nested = CountScope::new();
+            bump_count();
+            bump_count();
+        }
+        assert_eq!(get_count(), 1);
+    }
+
+    // the scope took up one count of the enclosing scope
+    assert_eq!(get_count(), 2);
+}
+
+#[test]
+fn test_count_scope_with_key_is_stable() {
+    use custos::{bump_count, get_count, ident_idx, CountScope};
+
+    let keyed_idx = || {
+        let _scope = CountScope::with_key(7);
+        ident_idx()
+    };
+
+    let first = keyed_idx();
+
+    // the enclosing count does not change the idents of a keyed scope
+    bump_count();
+    bump_count();
+    assert_eq!(keyed_idx(), first);
+    assert_eq!(get_count(), 2);
+
+    let _other = CountScope::with_key(8);
+    assert_ne!(ident_idx(), first);
+}
@@ -101,3 +101,85 @@ fn test_graph_cu() -> custos::Result<()> {
     }
     Ok(())
 }
+
+#[cfg(feature = "macro")]
+#[test]
+fn test_graph_inplace_apply_fn() -> custos::Result<()> {
+    use custos::{ApplyFunction, CacheReturn, Combiner};
+    use std::collections::HashSet;
+
+    let device = CPU::new();
+
+    // idx: 0
+    let x = Buffer::from((&device, [1., 2., 3., 4.]));
+
+    for ep in range(2) {
+        // idx: 1, deps: [0, 0]
+        let a = device.apply_fn(&x, |x| x.add(1.));
+        // idx: 2, deps: [1, 1]
+        let b = device.apply_fn(&a, |x| x.mul(2.));
+        // idx: 3, deps: [1, 1], a is not used afterwards
+        let c = device.apply_fn(&a, |x| x.mul(3.));
+        // idx: 4, deps: [3, 3]
+        let d = device.apply_fn(&c, |x| x.add(1.));
+
+        assert_eq!(b.read(), [4., 6., 8., 10.]);
+        assert_eq!(d.read(), [7., 10., 13., 16.]);
+
+        let allocations = [a.id(), b.id(), c.id(), d.id()]
+            .iter()
+            .map(|id| device.cache().nodes[id].ptr)
+            .collect::<HashSet<_>>();
+
+        if ep == 0 {
+            assert_eq!(allocations.len(), 4);
+            device.optimize()?;
+        } else {
+            // c and d are written into the allocation of a
+            assert_eq!(allocations.len(), 2);
+            assert_eq!(a.ptr.ptr, c.ptr.ptr);
+            assert_eq!(a.ptr.ptr, d.ptr.ptr);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "opencl")]
+#[test]
+fn test_graph_inplace_apply_fn_cl() -> custos::Result<()> {
+    use custos::{ApplyFunction, CacheReturn, Combiner};
+    use std::collections::HashSet;
+
+    let device = OpenCL::new(0)?;
+
+    // idx: 0
+    let x = Buffer::from((&device, [1., 2., 3., 4.]));
+
+    for ep in range(2) {
+        // idx: 1, deps: [0, 0]
+        let a = device.apply_fn(&x, |x| x.add(1.));
+        // idx: 2, deps: [1, 1]
+        let b = device.apply_fn(&a, |x| x.mul(2.));
+        // idx: 3, deps: [1, 1], a is not used afterwards
+        let c = device.apply_fn(&a, |x| x.mul(3.));
+        // idx: 4, deps: [3, 3]
+        let d = device.apply_fn(&c, |x| x.add(1.));
+
+        assert_eq!(b.read(), [4., 6., 8., 10.]);
+        assert_eq!(d.read(), [7., 10., 13., 16.]);
+
+        let allocations = [a.id(), b.id(), c.id(), d.id()]
+            .iter()
+            .map(|id| device.cache().nodes[id].ptr)
+            .collect::<HashSet<_>>();
+
+        if ep == 0 {
+            assert_eq!(allocations.len(), 4);
+            device.optimize()?;
+        } else {
+            assert_eq!(allocations.len(), 2);
+            assert_eq!(a.ptr.ptr, c.ptr.ptr);
+        }
+    }
+    Ok(())
+}
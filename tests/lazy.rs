@@ -0,0 +1,271 @@
+#![cfg(feature = "lazy")]
+
+use custos::{
+    ApplyFunction, Buffer, ClearBuf, Combiner, CopySlice, Device, Error, Lazy, Read, WriteBuf,
+};
+
+#[cfg(feature = "cpu")]
+fn apply_twice<D>(device: &D, buf: &Buffer<f32, D>) -> Vec<f32>
+where
+    D: ApplyFunction<f32> + Read<f32>,
+{
+    let doubled = device.apply_fn(buf, |x| x.mul(2.));
+    let out = device.apply_fn(&doubled, |x| x.add(1.));
+    device.read_to_vec(&out)
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_lazy_matches_inner() {
+    use custos::CPU;
+
+    let cpu = CPU::new();
+    let device = Lazy::new(CPU::new());
+
+    let expected = apply_twice(&cpu, &Buffer::from((&cpu, [1., 2., 3.])));
+    assert_eq!(
+        apply_twice(&device, &Buffer::from((&device, [1., 2., 3.]))),
+        expected
+    );
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_lazy_defers_until_read() {
+    use custos::CPU;
+
+    let device = Lazy::new(CPU::new());
+    let mut buf = Buffer::from((&device, [1, 2, 3]));
+
+    buf.write(&[4, 5, 6]);
+    let out = device.apply_fn(&buf, |x| x.mul(x));
+    device.clear(&mut buf);
+    assert_eq!(device.pending(), ["write", "apply_fn", "clear"]);
+
+    assert_eq!(out.read(), [16, 25, 36]);
+    assert_eq!(buf.read(), [0, 0, 0]);
+    assert!(device.pending().is_empty());
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_lazy_realize_and_discard() {
+    use custos::CPU;
+
+    let device = Lazy::new(CPU::new());
+    assert!(device.realize().is_ok());
+
+    let mut buf = Buffer::from((&device, [1., 2., 3.]));
+    buf.write(&[0.; 3]);
+    device.discard();
+    assert_eq!(buf.read(), [1., 2., 3.]);
+
+    device.clear(&mut buf);
+    device.synchronize().unwrap();
+    assert!(device.pending().is_empty());
+    assert_eq!(buf.read(), [0., 0., 0.]);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_lazy_checks_arguments_on_record() {
+    use custos::CPU;
+
+    let device = Lazy::new(CPU::new());
+    let source = Buffer::from((&device, [1, 2, 3, 4]));
+    let mut dest = Buffer::from((&device, [0; 4]));
+
+    assert!(matches!(
+        device.try_write(&mut dest, &[1, 2]),
+        Err(Error::LengthMismatch {
+            expected: 4,
+            found: 2
+        })
+    ));
+    assert!(matches!(
+        device.try_copy_slice_to(&source, 2..5, &mut dest, ..3),
+        Err(Error::OutOfRange { .. })
+    ));
+    assert!(device.pending().is_empty());
+
+    device.copy_slice_to(&source, 2.., &mut dest, ..2);
+    device.copy_slice_all(&source, &mut dest, [(0..1, 3..4)]);
+    assert_eq!(device.pending(), ["copy_slice", "copy_slice"]);
+    assert_eq!(dest.read(), [3, 4, 0, 1]);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_lazy_drop_realizes() {
+    use custos::CPU;
+
+    let device = Lazy::new(CPU::new());
+    let out = {
+        let buf = Buffer::from((&device, [1., 2.]));
+        device.apply_fn(&buf, |x| x.add(1.))
+    };
+
+    // dropping `buf` executed the pending `apply_fn`, which reads its memory
+    assert!(device.pending().is_empty());
+    assert_eq!(out.read(), [2., 3.]);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_lazy_records_capturing_closures() {
+    use custos::CPU;
+
+    let device = Lazy::new(CPU::new());
+    let buf = Buffer::from((&device, [1, 2, 3]));
+
+    let factor = 3;
+    let out = device.apply_fn(&buf, move |x| x.mul(factor));
+
+    assert_eq!(device.pending(), ["apply_fn"]);
+    assert_eq!(out.read(), [3, 6, 9]);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_lazy_executes_trait_calls_immediately() {
+    use custos::CPU;
+
+    let device = Lazy::new(CPU::new());
+    let mut buf = Buffer::from((&device, [1, 2, 3]));
+    buf.write(&[4, 5, 6]);
+
+    let out = ApplyFunction::apply_fn(&device, &buf, |x| x.mul(3));
+
+    // the closure is not `'static` here, hence the pending `write` and the `apply_fn` were executed
+    assert!(device.pending().is_empty());
+    assert_eq!(out.read(), [12, 15, 18]);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_lazy_writes_to_the_recorded_output() {
+    use custos::{MemoryReturn, CPU};
+
+    let device = Lazy::new(CPU::new());
+    let buf = Buffer::from((&device, [1., 2., 3.]));
+    let out = device.apply_fn(&buf, |x| x.mul(2.));
+    let usage = device.memory().usage();
+
+    // realizing does not allocate, the wrapped device writes to `out` directly
+    assert_eq!(out.read(), [2., 4., 6.]);
+    assert_eq!(device.memory().usage().peak, usage.live);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_lazy_records_binary_ew_and_reduce() {
+    use custos::CPU;
+
+    let device = Lazy::new(CPU::new());
+    let lhs = Buffer::from((&device, [1, 2, 3]));
+    let rhs = Buffer::from((&device, [4, 5, 6]));
+
+    let out = device.binary_ew(&lhs, &rhs, |lhs, rhs| lhs.mul(rhs));
+    let sum = device.reduce(&out, 0, |acc, x| acc.add(x));
+    assert_eq!(device.pending(), ["binary_ew", "reduce"]);
+
+    assert_eq!(sum.read(), [32]);
+    assert_eq!(out.read(), [4, 10, 18]);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_lazy_fuses_maps() {
+    use custos::CPU;
+
+    let device = Lazy::new(CPU::new());
+    let buf = Buffer::from((&device, [1., 2., 3.]));
+
+    let out = {
+        let squared = device.apply_fn(&buf, |x| x.mul(x));
+        let shifted = device.apply_fn(&squared, |x| x.add(1.));
+        device.apply_fn(&shifted, |x| x.mul(2.))
+    };
+    assert_eq!(device.schedule(), [["apply_fn", "apply_fn", "apply_fn"]]);
+    assert_eq!(out.read(), [4., 10., 20.]);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_lazy_does_not_fuse_away_live_outputs() {
+    use custos::CPU;
+
+    let device = Lazy::new(CPU::new());
+    let buf = Buffer::from((&device, [1, 2, 3]));
+
+    let squared = device.apply_fn(&buf, |x| x.mul(x));
+    let out = device.apply_fn(&squared, |x| x.add(1));
+    assert_eq!(device.schedule(), [["apply_fn"], ["apply_fn"]]);
+
+    assert_eq!(out.read(), [2, 5, 10]);
+    assert_eq!(squared.read(), [1, 4, 9]);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_lazy_schedule_keeps_dependencies() {
+    use custos::CPU;
+
+    let device = Lazy::new(CPU::new());
+    let mut buf = Buffer::from((&device, [1, 2, 3]));
+    let mut other = Buffer::from((&device, [1, 2, 3]));
+
+    buf.write(&[4, 5, 6]);
+    let out = device.apply_fn(&buf, |x| x.add(1));
+    buf.write(&[7, 8, 9]);
+    device.clear(&mut other);
+
+    assert_eq!(
+        device.schedule(),
+        [
+            vec!["write"],
+            vec!["apply_fn"],
+            vec!["write"],
+            vec!["clear"]
+        ]
+    );
+    assert_eq!(out.read(), [5, 6, 7]);
+    assert_eq!(buf.read(), [7, 8, 9]);
+    assert_eq!(other.read(), [0, 0, 0]);
+}
+
+#[cfg(all(feature = "cpu", feature = "faulty"))]
+#[test]
+fn test_lazy_drop_defers_errors() {
+    use custos::{Fault, Faulty, Wrapper, CPU};
+
+    let device = Lazy::new(Faulty::new(CPU::new()));
+    {
+        let mut buf = Buffer::<i32, _>::new(&device, 3);
+        buf.write(&[1, 2, 3]);
+        device.inner().fail_writes(true);
+    }
+    device.inner().fail_writes(false);
+
+    // the failed `write` of the dropped `Buffer` is returned by the next realization
+    assert!(matches!(
+        device.realize(),
+        Err(Error::InjectedFault(Fault::Write))
+    ));
+    assert!(device.realize().is_ok());
+}
+
+#[cfg(all(feature = "cpu", feature = "autograd", feature = "macro"))]
+#[test]
+fn test_lazy_backward() {
+    use custos::{UnaryElementWiseMayGrad, CPU};
+
+    let device = Lazy::new(CPU::new());
+    let buf = Buffer::from((&device, [1., 2., 3.]));
+
+    let out = device.unary_ew(&buf, |x| x.mul(x), |x| x.mul(2.));
+    out.backward();
+
+    assert_eq!(out.read(), [1., 4., 9.]);
+    assert_eq!(buf.grad().read(), [2., 4., 6.]);
+}
@@ -0,0 +1,196 @@
+use custos::{Alloc, Buffer, Device, Error, MemoryReturn};
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_live_and_peak_bytes() {
+    use custos::CPU;
+
+    let device = CPU::new();
+    assert_eq!(device.memory_usage().live, 0);
+
+    let a = Buffer::<f32, _>::new(&device, 100);
+    let b = Buffer::<u8, _>::new(&device, 10);
+    assert_eq!(device.memory_usage().live, 410);
+
+    drop(a);
+    let usage = device.memory_usage();
+    assert_eq!(usage.live, 10);
+    assert_eq!(usage.peak, 410);
+
+    device.memory().reset_peak();
+    assert_eq!(device.memory_usage().peak, 10);
+
+    drop(b);
+    assert_eq!(device.memory_usage().live, 0);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_with_slice_and_vec_are_counted() {
+    use custos::CPU;
+
+    let device = CPU::new();
+
+    let a = Buffer::from((&device, [1i32, 2, 3, 4]));
+    let b = Buffer::<_, _>::from((&device, vec![1u16; 8]));
+    assert_eq!(device.memory_usage().live, 32);
+
+    drop((a, b));
+    assert_eq!(device.memory_usage().live, 0);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_cached_bytes_released_on_cache_clear() {
+    use custos::{CacheReturn, CPU};
+
+    let device = CPU::new();
+
+    let buf = device.retrieve::<f64, ()>(8, ());
+    drop(buf);
+    // the buffer is cached, hence its memory is still allocated
+    assert_eq!(device.memory_usage().live, 64);
+
+    device.cache_mut().nodes.clear();
+    assert_eq!(device.memory_usage().live, 0);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_memory_limit() {
+    use custos::{flag::AllocFlag, LimitExceeded, CPU};
+
+    let device = CPU::new();
+    device.set_memory_limit(Some(100));
+
+    let a = Buffer::<u8, _>::new(&device, 60);
+
+    let err = Alloc::<u8>::try_alloc(&device, 41, AllocFlag::None).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::Alloc {
+            bytes: 41,
+            limit: Some(LimitExceeded {
+                live: 60,
+                limit: 100
+            })
+        }
+    ));
+    // a failed allocation does not reserve anything
+    assert_eq!(device.memory_usage().live, 60);
+
+    let b = Buffer::<u8, _>::try_new(&device, 40).unwrap();
+    assert_eq!(device.memory_usage().live, 100);
+
+    drop(a);
+    assert!(Buffer::<u8, _>::try_new(&device, 60).is_ok());
+
+    device.set_memory_limit(None);
+    assert_eq!(device.memory_usage().limit, None);
+    drop(b);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_memory_limit_with_slice() {
+    use custos::CPU;
+
+    let device = CPU::new();
+    device.set_memory_limit(Some(8));
+
+    assert!(matches!(
+        Buffer::<u32, _>::try_from_slice(&device, &[1, 2, 3]),
+        Err(Error::Alloc {
+            bytes: 12,
+            limit: Some(_)
+        })
+    ));
+    assert!(matches!(
+        Buffer::<u32, _>::try_from_vec(&device, vec![1, 2, 3]),
+        Err(Error::Alloc {
+            bytes: 12,
+            limit: Some(_)
+        })
+    ));
+    assert_eq!(device.memory_usage().live, 0);
+
+    let buf = Buffer::<u32, _>::try_from_slice(&device, &[1, 2]).unwrap();
+    assert_eq!(buf.as_slice(), [1, 2]);
+}
+
+#[cfg(all(feature = "cpu", feature = "stack"))]
+#[test]
+fn test_memory_limit_transfer() {
+    use custos::{Dim1, Stack, CPU};
+
+    let device = CPU::new();
+    device.set_memory_limit(Some(8));
+
+    let buf = Buffer::<u32, _, Dim1<3>>::from((&Stack, [1, 2, 3]));
+    assert!(matches!(
+        buf.try_transfer_to(&device),
+        Err(Error::Alloc { limit: Some(_), .. })
+    ));
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_memory_shared_between_threads() {
+    use std::sync::Arc;
+
+    use custos::CPU;
+
+    let device = Arc::new(CPU::new());
+
+    let handles = (0..4)
+        .map(|_| {
+            let device = device.clone();
+            std::thread::spawn(move || {
+                let buf = Buffer::<u8, _>::new(&*device, 16);
+                buf.len()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let usage = device.memory_usage();
+    assert_eq!(usage.live, 0);
+    assert!(usage.peak >= 16);
+}
+
+#[cfg(feature = "opencl")]
+#[test]
+fn test_cl_memory_usage() -> custos::Result<()> {
+    use custos::OpenCL;
+
+    let device = OpenCL::new(0)?;
+    let buf = Buffer::<f32, _>::new(&device, 16);
+    assert_eq!(device.memory_usage().live, 64);
+
+    device.set_memory_limit(Some(64));
+    assert!(matches!(
+        Buffer::<f32, _>::try_new(&device, 1),
+        Err(Error::Alloc { limit: Some(_), .. })
+    ));
+
+    drop(buf);
+    assert_eq!(device.memory_usage().live, 0);
+    Ok(())
+}
+
+#[cfg(feature = "cuda")]
+#[test]
+fn test_cuda_memory_usage() -> custos::Result<()> {
+    use custos::CUDA;
+
+    let device = CUDA::new(0)?;
+    let buf = Buffer::<f32, _>::new(&device, 16);
+    assert_eq!(device.memory_usage().live, 64);
+
+    drop(buf);
+    assert_eq!(device.memory_usage().live, 0);
+    Ok(())
+}
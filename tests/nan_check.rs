@@ -0,0 +1,102 @@
+#![cfg(feature = "nan-check")]
+
+use custos::{Anomaly, Buffer, Error, NanCheck};
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_check_reports_first_index() {
+    use custos::CPU;
+
+    let device = NanCheck::new(CPU::new());
+
+    let buf = Buffer::from((&device, [1., 2., f64::NEG_INFINITY, f64::NAN]));
+    let Err(Error::Anomaly(report)) = device.check("op", &buf) else {
+        panic!("buf contains an infinite value");
+    };
+    assert_eq!(report.op, "op");
+    assert_eq!(report.ident, buf.ident);
+    assert_eq!(report.index, 2);
+    assert_eq!(report.anomaly, Anomaly::Infinite);
+
+    let buf = Buffer::from((&device, [1, 2, 3]));
+    assert!(device.check("op", &buf).is_ok());
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_subnormals() {
+    use custos::CPU;
+
+    let device = NanCheck::new(CPU::new());
+    let buf = Buffer::from((&device, [1., f64::MIN_POSITIVE / 2.]));
+
+    assert!(device.check("op", &buf).is_ok());
+
+    device.check_subnormals(true);
+    let Err(Error::Anomaly(report)) = device.check("op", &buf) else {
+        panic!("buf contains a subnormal value");
+    };
+    assert_eq!(report.index, 1);
+    assert_eq!(report.anomaly, Anomaly::Subnormal);
+}
+
+#[cfg(all(feature = "cpu", feature = "macro"))]
+#[test]
+fn test_apply_fn_is_checked() {
+    use custos::{ApplyFunction, Combiner, CPU};
+
+    let device = NanCheck::new(CPU::new());
+    let buf = Buffer::from((&device, [4., 1., 0.]));
+
+    let out = device.apply_fn(&buf, |x| x.pow(0.5));
+    assert_eq!(out.read(), [2., 1., 0.]);
+
+    let Err(Error::Anomaly(report)) = device.try_apply_fn(&buf, |x| x.pow(-1.)) else {
+        panic!("1 / 0 is not finite");
+    };
+    assert_eq!(report.op, "apply_fn");
+    assert_eq!(report.index, 2);
+    assert_eq!(report.anomaly, Anomaly::Infinite);
+    assert!(report.ident.is_some());
+}
+
+#[cfg(all(feature = "cpu", feature = "macro"))]
+#[test]
+#[should_panic(expected = "`apply_fn` produced NaN at index 1")]
+fn test_apply_fn_panics() {
+    use custos::{ApplyFunction, Combiner, CPU};
+
+    let device = NanCheck::new(CPU::new());
+    let buf = Buffer::from((&device, [1., -1.]));
+
+    device.apply_fn(&buf, |x| x.pow(0.5));
+}
+
+#[cfg(all(feature = "cpu", feature = "macro"))]
+#[test]
+#[should_panic(expected = "`add_unary_grad` produced an infinite value at index 0")]
+fn test_unary_grad_panics() {
+    use custos::{Combiner, UnaryGrad, CPU};
+
+    let device = NanCheck::new(CPU::new());
+
+    let lhs = Buffer::from((&device, [0., 1.]));
+    let out_grad = Buffer::from((&device, [1., 1.]));
+    let mut lhs_grad = Buffer::from((&device, [0., 0.]));
+
+    device.add_unary_grad(&lhs, &mut lhs_grad, &out_grad, |x| x.pow(-1.));
+}
+
+#[cfg(all(feature = "cpu", feature = "macro", feature = "autograd"))]
+#[test]
+#[should_panic(expected = "`add_unary_grad` produced NaN at index 1")]
+fn test_gradients_are_checked_during_backward() {
+    use custos::{Combiner, UnaryElementWiseMayGrad, CPU};
+
+    let device = NanCheck::new(CPU::new());
+    let buf = Buffer::from((&device, [1., 0.]));
+
+    // 0 / 0 is NaN
+    let out = device.unary_ew(&buf, |x| x.mul(2.), |x| x.div(x));
+    out.backward();
+}
@@ -0,0 +1,121 @@
+#![cfg(feature = "cpu")]
+
+use std::{rc::Rc, sync::Arc};
+
+use custos::{Buffer, ClearBuf, OwnedBuffer, WriteBuf, CPU};
+
+struct Model {
+    weights: OwnedBuffer<f32>,
+    bias: OwnedBuffer<f32>,
+}
+
+fn create_model() -> Model {
+    let device = Arc::new(CPU::new());
+    Model {
+        weights: OwnedBuffer::from_slice(&device, &[1., 2., 3.]),
+        bias: OwnedBuffer::from_vec(&device, vec![0.5; 3]),
+    }
+}
+
+#[test]
+fn test_owned_buffer_outlives_device_creation() {
+    let model = create_model();
+
+    assert_eq!(model.weights.read(), [1., 2., 3.]);
+    assert_eq!(model.bias.read(), [0.5; 3]);
+    assert!(Arc::ptr_eq(model.weights.handle(), model.bias.handle()));
+}
+
+#[cfg(feature = "macro")]
+#[test]
+fn test_owned_buffer_ops_round_trip() {
+    use custos::{ApplyFunction, Combiner};
+
+    let device = Arc::new(CPU::new());
+    let x = OwnedBuffer::<f32>::from_slice(&device, &[1., 2., 3., 4.]);
+
+    let squared = device.apply_fn(x.as_buf(), |x| x.mul(x));
+    let squared = OwnedBuffer::from_buf(&device, squared);
+
+    drop(x);
+    assert_eq!(squared.read(), [1., 4., 9., 16.]);
+}
+
+#[test]
+fn test_owned_buffer_rc_keeps_device_alive() {
+    let device = Rc::new(CPU::new());
+    let buf = OwnedBuffer::<i32, CPU, (), Rc<CPU>>::from_slice(&device, &[1, 2, 3]);
+
+    drop(device);
+    assert_eq!(Rc::strong_count(buf.handle()), 1);
+    assert_eq!(&*buf, [1, 2, 3]);
+}
+
+#[test]
+fn test_owned_buffer_into_buf() {
+    let device = Arc::new(CPU::new());
+    let owned = OwnedBuffer::<f32>::from_slice(&device, &[1., 2., 3.]);
+    let ptr = owned.as_buf().ptr.ptr;
+
+    let buf: Buffer = owned.into_buf(&device);
+    assert_eq!(buf.ptr.ptr, ptr);
+    assert_eq!(buf.read(), [1., 2., 3.]);
+}
+
+#[test]
+#[should_panic(expected = "another device")]
+fn test_owned_buffer_into_buf_other_device() {
+    let device = Arc::new(CPU::new());
+    let owned = OwnedBuffer::<f32>::new(&device, 3);
+
+    let other = CPU::new();
+    owned.into_buf(&other);
+}
+
+#[test]
+#[should_panic(expected = "another device")]
+fn test_owned_buffer_from_buf_other_device() {
+    let device = Arc::new(CPU::new());
+    let other = CPU::new();
+
+    OwnedBuffer::from_buf(&device, Buffer::<f32>::new(&other, 3));
+}
+
+#[test]
+fn test_owned_buffer_mut() {
+    let device = Arc::new(CPU::new());
+    let mut buf = OwnedBuffer::<f32>::new(&device, 4);
+
+    buf.with_buf_mut(|buf| buf.device().write(buf, &[1., 2., 3., 4.]));
+    assert_eq!(buf.read(), [1., 2., 3., 4.]);
+
+    buf[0] = 5.;
+    assert_eq!(buf.read(), [5., 2., 3., 4.]);
+
+    buf.with_buf_mut(|buf| buf.device().clear(buf));
+    assert_eq!(buf.read(), [0.; 4]);
+}
+
+#[test]
+fn test_owned_buffer_clone() {
+    let device = Arc::new(CPU::new());
+    let buf = OwnedBuffer::<f32>::from_slice(&device, &[1., 2., 3.]);
+
+    let mut cloned = buf.clone();
+    cloned[0] = 4.;
+
+    assert_eq!(buf.read(), [1., 2., 3.]);
+    assert_eq!(cloned.read(), [4., 2., 3.]);
+}
+
+#[test]
+fn test_owned_buffer_send_to_thread() {
+    let device = Arc::new(CPU::new());
+    let buf = OwnedBuffer::<f32>::from_slice(&device, &[1., 2., 3.]);
+
+    let sum = std::thread::spawn(move || buf.iter().sum::<f32>())
+        .join()
+        .unwrap();
+
+    assert_eq!(sum, 6.);
+}
@@ -0,0 +1,101 @@
+#![cfg(feature = "sanitize")]
+
+use custos::{Buffer, MemoryReturn};
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_outstanding_allocations() {
+    use custos::CPU;
+
+    let device = CPU::new();
+
+    let a = Buffer::<f32, _>::new(&device, 4);
+    let line = line!() - 1;
+    let b = Buffer::from((&device, [1u8, 2, 3]));
+
+    let outstanding = device.memory().outstanding();
+    assert_eq!(outstanding.len(), 2);
+
+    assert_eq!(outstanding[0].bytes, 16);
+    assert_eq!(outstanding[0].origin.file(), file!());
+    assert_eq!(outstanding[0].origin.line(), line);
+    assert_eq!(outstanding[1].bytes, 3);
+    assert!(outstanding[0].generation < outstanding[1].generation);
+
+    drop(a);
+    let outstanding = device.memory().outstanding();
+    assert_eq!(outstanding.len(), 1);
+    assert_eq!(outstanding[0].bytes, 3);
+
+    drop(b);
+    assert!(device.memory().outstanding().is_empty());
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_shallow_copy_of_live_buffer() {
+    use custos::CPU;
+
+    let device = CPU::new();
+    let buf = Buffer::from((&device, [1, 2, 3]));
+
+    let shallow = unsafe { buf.shallow() };
+    assert_eq!(shallow.as_slice(), &[1, 2, 3]);
+
+    drop(shallow);
+    // the copy does not free the memory
+    assert_eq!(device.memory().outstanding().len(), 1);
+    assert_eq!(buf.as_slice(), &[1, 2, 3]);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+#[should_panic(expected = "use of freed memory")]
+fn test_shallow_copy_after_drop_panics() {
+    use custos::CPU;
+
+    let device = CPU::new();
+
+    let shallow = {
+        let buf = Buffer::from((&device, [1, 2, 3]));
+        unsafe { buf.shallow() }
+    };
+
+    let _ = shallow.as_slice();
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_panic_reports_origin() {
+    use custos::CPU;
+
+    let device = CPU::new();
+
+    let shallow = {
+        let buf = Buffer::<i32, _>::new(&device, 10);
+        unsafe { buf.shallow() }
+    };
+    let line = line!() - 3;
+
+    let err =
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| shallow.read())).unwrap_err();
+    let msg = err.downcast_ref::<String>().unwrap();
+
+    assert!(msg.contains("40 bytes"), "{msg}");
+    assert!(msg.contains(&format!("{}:{line}", file!())), "{msg}");
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+#[should_panic(expected = "use of freed memory")]
+fn test_cached_wrapper_after_cache_clear_panics() {
+    use custos::{CacheReturn, Device, CPU};
+
+    let device = CPU::new();
+
+    // `retrieve` returns a buffer that borrows its memory from the cache
+    let buf = device.retrieve::<f32, ()>(8, ());
+    device.cache_mut().nodes.clear();
+
+    let _ = buf.as_slice();
+}
@@ -0,0 +1,182 @@
+#![cfg(feature = "sim")]
+
+use std::time::{Duration, Instant};
+
+use custos::{
+    sim::TransferStats, Alloc, Buffer, ClearBuf, CloneBuf, CopySlice, Device, Read, SimDevice,
+    WriteBuf,
+};
+
+/// Written once for every device: Reverses `data` with device side copies only.
+fn reverse_on_device<'a, D>(device: &'a D, data: &[i32]) -> Vec<i32>
+where
+    D: Alloc<'a, i32> + Read<i32> + WriteBuf<i32> + CopySlice<i32>,
+{
+    let src = Buffer::from((device, data));
+    let mut dst = Buffer::<i32, D>::new(device, data.len());
+
+    let len = data.len();
+    device.copy_slice_all(
+        &src,
+        &mut dst,
+        (0..len).map(|i| (i..i + 1, len - i - 1..len - i)),
+    );
+    dst.read_to_vec()
+}
+
+#[test]
+fn test_sim_counts_transfers() {
+    let device = SimDevice::new();
+
+    let mut buf = Buffer::from((&device, [1, 2, 3, 4]));
+    assert_eq!(
+        device.transfer_stats(),
+        TransferStats {
+            to_device: 1,
+            to_host: 0,
+            bytes_to_device: 16,
+            bytes_to_host: 0,
+        }
+    );
+
+    buf.write(&[5, 6, 7, 8]);
+    assert_eq!(buf.read(), [5, 6, 7, 8]);
+
+    let stats = device.transfer_stats();
+    assert_eq!(stats.to_device, 2);
+    assert_eq!(stats.bytes_to_device, 32);
+    assert_eq!(stats.to_host, 1);
+    assert_eq!(stats.bytes_to_host, 16);
+
+    device.reset_transfer_stats();
+    assert_eq!(device.transfer_stats(), TransferStats::default());
+}
+
+#[test]
+fn test_sim_device_side_ops_are_not_transfers() {
+    let device = SimDevice::new();
+
+    let source = Buffer::from((&device, [1., 2., 3., 4., 5.]));
+    let mut dest = Buffer::from((&device, [5., 4., 3., 2., 1.]));
+    device.reset_transfer_stats();
+
+    device.copy_slice_to(&source, 1..3, &mut dest, 3..5);
+    let mut cloned = device.clone_buf(&dest);
+    device.write_buf(&mut cloned, &source);
+    device.clear(&mut dest);
+    let _retrieved = device.retrieve::<f32, ()>(5, ());
+
+    assert_eq!(device.transfer_stats(), TransferStats::default());
+
+    assert_eq!(dest.read(), [0.; 5]);
+    assert_eq!(cloned.read(), [1., 2., 3., 4., 5.]);
+}
+
+#[test]
+fn test_sim_runs_generic_code() {
+    let data = [1, 2, 3, 4, 5, 6];
+
+    let sim = SimDevice::new();
+    assert_eq!(reverse_on_device(&sim, &data), [6, 5, 4, 3, 2, 1]);
+
+    #[cfg(feature = "cpu")]
+    assert_eq!(
+        reverse_on_device(&custos::CPU::new(), &data),
+        reverse_on_device(&sim, &data)
+    );
+}
+
+#[test]
+fn test_sim_latency() {
+    let latency = Duration::from_millis(10);
+    let device = SimDevice::with_latency(latency);
+    assert_eq!(device.latency(), Some(latency));
+
+    let start = Instant::now();
+    let buf = Buffer::from((&device, [1, 2, 3]));
+    let _ = device.clone_buf(&buf);
+    assert!(start.elapsed() >= latency);
+
+    let start = Instant::now();
+    buf.read();
+    assert!(start.elapsed() >= latency);
+}
+
+#[test]
+fn test_sim_shared_between_threads() {
+    let device = std::sync::Arc::new(SimDevice::new());
+
+    let handles = (0..4)
+        .map(|i| {
+            let device = device.clone();
+            std::thread::spawn(move || {
+                let buf = Buffer::from((&*device, [i; 4]));
+                buf.read().iter().sum::<i32>()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let sums = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(sums, [0, 4, 8, 12]);
+
+    let stats = device.transfer_stats();
+    assert_eq!((stats.to_device, stats.to_host), (4, 4));
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_sim_transfer_to_cpu() {
+    use custos::CPU;
+
+    let device = SimDevice::new();
+    let cpu = CPU::new();
+
+    let buf = Buffer::from((&device, [1., 2., 3.]));
+    let on_cpu = buf.transfer_to(&cpu);
+    assert_eq!(on_cpu.as_slice(), [1., 2., 3.]);
+
+    let back = on_cpu.transfer_to(&device);
+    assert_eq!(back.read(), [1., 2., 3.]);
+
+    let stats = device.transfer_stats();
+    assert_eq!((stats.to_device, stats.to_host), (2, 2));
+}
+
+#[cfg(all(feature = "cpu", feature = "macro"))]
+#[test]
+fn test_sim_cpu_fallback() {
+    use custos::{ApplyFunction, Combiner, UnaryGrad};
+
+    let device = SimDevice::new();
+    let buf = Buffer::from((&device, [1., 2., 3.]));
+    device.reset_transfer_stats();
+
+    let out = device.apply_fn(&buf, |x| x.mul(x));
+    let stats = device.transfer_stats();
+    assert_eq!((stats.to_device, stats.to_host), (1, 1));
+    assert_eq!(out.read(), [1., 4., 9.]);
+
+    let mut grad = Buffer::from((&device, [1.; 3]));
+    let out_grad = Buffer::from((&device, [1., 0.5, 2.]));
+    device.add_unary_grad(&buf, &mut grad, &out_grad, |x| x.mul(2.));
+    assert_eq!(grad.read(), [3., 3., 13.]);
+}
+
+#[cfg(all(feature = "cpu", feature = "macro"))]
+#[test]
+fn test_sim_cpu_fallback_binary_ew_and_reduce() {
+    use custos::{BinaryElementWise, Combiner, Reduce};
+
+    let device = SimDevice::new();
+    let lhs = Buffer::from((&device, [1., 2., 3.]));
+    let rhs = Buffer::from((&device, [3., 2., 1.]));
+
+    let out = device.binary_ew(&lhs, &rhs, |lhs, rhs| lhs.mul(rhs));
+    assert_eq!(out.read(), [3., 4., 3.]);
+
+    let sum = device.reduce(&out, 0., |acc, x| acc.add(x));
+    assert_eq!(sum.read(), [10.]);
+}
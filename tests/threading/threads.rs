@@ -20,3 +20,157 @@ fn test_with_threads() {
     });
     a.join().unwrap();
 }
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_buffer_is_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<CPU>();
+    assert_send_sync::<Buffer<f32, CPU>>();
+    assert_send_sync::<std::sync::Arc<CPU>>();
+
+    #[cfg(feature = "opencl")]
+    assert_send_sync::<Buffer<f32, custos::OpenCL>>();
+
+    #[cfg(feature = "cuda")]
+    assert_send_sync::<Buffer<f32, custos::CUDA>>();
+
+    #[cfg(feature = "wgpu")]
+    assert_send_sync::<Buffer<f32, custos::WGPU>>();
+}
+
+#[cfg(feature = "cpu")]
+#[cfg(not(feature = "realloc"))]
+#[test]
+fn test_shared_cpu_concurrent_retrieve() {
+    use custos::{range, Device};
+    use std::sync::Arc;
+
+    const THREADS: usize = 8;
+
+    let device = Arc::new(CPU::new());
+
+    let handles = (0..THREADS)
+        .map(|thread| {
+            let device = device.clone();
+            std::thread::spawn(move || {
+                let mut ptrs = vec![];
+                for epoch in range(200) {
+                    let mut a = device.retrieve::<f32, ()>(100, ());
+                    let mut b = device.retrieve::<f32, ()>(50, ());
+
+                    a.fill((thread * 1000 + epoch) as f32);
+                    b.fill(thread as f32);
+                    std::thread::yield_now();
+
+                    // no other thread has written into the cached buffers
+                    assert!(a.iter().all(|x| *x == (thread * 1000 + epoch) as f32));
+                    assert!(b.iter().all(|x| *x == thread as f32));
+
+                    ptrs.push((a.ptr.ptr as usize, b.ptr.ptr as usize));
+                }
+                // the cache of this thread reused its entries in every epoch
+                assert!(ptrs.iter().all(|ptrs_epoch| *ptrs_epoch == ptrs[0]));
+                ptrs[0]
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut ptrs = handles
+        .into_iter()
+        .flat_map(|handle| {
+            let (a, b) = handle.join().unwrap();
+            [a, b]
+        })
+        .collect::<Vec<_>>();
+
+    ptrs.sort_unstable();
+    ptrs.dedup();
+    assert_eq!(ptrs.len(), THREADS * 2);
+
+    assert_eq!(device.addons.threads(), THREADS + 1);
+}
+
+#[cfg(feature = "cpu")]
+#[cfg(not(feature = "realloc"))]
+#[test]
+fn test_scoped_threads_have_own_caches() {
+    use custos::{set_count, CacheReturn, Device, Ident};
+
+    let device = CPU::new();
+
+    unsafe { set_count(0) };
+    let main_ptr = device.retrieve::<f32, ()>(10, ()).ptr.ptr as usize;
+
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                unsafe { set_count(0) };
+                let buf = device.retrieve::<f32, ()>(10, ());
+                assert_eq!(buf.id(), Ident { idx: 0, len: 10 });
+                assert_ne!(buf.ptr.ptr as usize, main_ptr);
+                assert_eq!(device.cache().nodes.len(), 1);
+            });
+        }
+    });
+
+    assert_eq!(device.cache().nodes.len(), 1);
+}
+
+#[cfg(feature = "cpu")]
+#[cfg(not(feature = "realloc"))]
+#[test]
+fn test_buffer_dropped_on_other_thread() {
+    use custos::{set_count, Device};
+
+    let device = CPU::new();
+
+    unsafe { set_count(0) };
+    let owned = Buffer::from((&device, [1f32, 2., 3., 4.]));
+    let owned_ptr = owned.ptr.ptr as usize;
+
+    let device_ref = &device;
+    std::thread::scope(|s| {
+        s.spawn(move || {
+            let device = device_ref;
+            unsafe { set_count(0) };
+            let cached = device.retrieve::<f32, ()>(4, ());
+            assert_eq!(owned.read(), [1., 2., 3., 4.]);
+            drop(owned);
+
+            // the unrelated cache entry of this thread with the same ident is not removed
+            unsafe { set_count(0) };
+            assert_eq!(device.retrieve::<f32, ()>(4, ()).ptr.ptr, cached.ptr.ptr);
+        });
+    });
+
+    // the entry of the dropped buffer is never handed out
+    unsafe { set_count(0) };
+    let cached = device.retrieve::<f32, ()>(4, ());
+    assert_ne!(cached.ptr.ptr as usize, owned_ptr);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_per_thread_values_of_exited_threads() {
+    use custos::PerThread;
+    use std::cell::Cell;
+
+    let value = PerThread::<Cell<usize>>::default();
+
+    for thread in 1..=10 {
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                // the value of an exited thread is never handed to another thread
+                assert_eq!(value.get(), 0);
+                value.set(thread);
+            })
+            .join()
+            .unwrap();
+        });
+    }
+
+    assert_eq!(value.get(), 0);
+    assert_eq!(value.threads(), 11);
+}
@@ -0,0 +1,155 @@
+#![cfg(feature = "trace")]
+
+use std::sync::Arc;
+
+use custos::{Buffer, ChromeTrace, Read, TraceReturn};
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_trace_read_write() {
+    use custos::CPU;
+
+    let device = CPU::new();
+    let trace = Arc::new(ChromeTrace::new());
+    assert!(device.set_tracer(Some(trace.clone())).is_none());
+
+    let mut buf = Buffer::from((&device, [1f32, 2., 3.]));
+    buf.write(&[4., 5., 6.]);
+    assert_eq!(device.read_to_vec(&buf), [4., 5., 6.]);
+
+    let events = trace.events();
+    assert_eq!(events.len(), 2);
+
+    assert_eq!(events[0].name, "write");
+    assert_eq!(events[1].name, "read");
+    assert!(events[0].start <= events[1].start);
+
+    for event in events {
+        assert_eq!(event.bytes, 12);
+        assert_eq!(event.ident, buf.ident);
+        assert_eq!(event.device, std::any::type_name::<CPU>());
+    }
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_trace_retrieve_and_apply_fn() {
+    use custos::{ApplyFunction, Combiner, Device, CPU};
+
+    let device = CPU::new();
+    let trace = Arc::new(ChromeTrace::new());
+    device.set_tracer(Some(trace.clone()));
+
+    let buf = device.retrieve::<f64, ()>(4, ());
+    let events = trace.events();
+    assert_eq!(events[0].name, "retrieve");
+    assert_eq!(events[0].bytes, 32);
+    assert_eq!(events[0].ident, buf.ident);
+
+    trace.clear();
+
+    let out = device.apply_fn(&buf, |x| x.add(1.));
+    let names = trace
+        .events()
+        .iter()
+        .map(|event| event.name)
+        .collect::<Vec<_>>();
+    // the retrieve call of `apply_fn` finishes first
+    assert_eq!(names, ["retrieve", "apply_fn"]);
+    assert_eq!(trace.events()[1].ident, out.ident);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_remove_tracer() {
+    use custos::CPU;
+
+    let device = CPU::new();
+    let trace = Arc::new(ChromeTrace::new());
+    device.set_tracer(Some(trace.clone()));
+
+    let buf = Buffer::from((&device, [1, 2, 3]));
+    device.read_to_vec(&buf);
+
+    assert!(device.set_tracer(None).is_some());
+    device.read_to_vec(&buf);
+
+    assert_eq!(trace.events().len(), 1);
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_chrome_trace_json() {
+    use custos::CPU;
+
+    let device = CPU::new();
+    let trace = Arc::new(ChromeTrace::new());
+    assert_eq!(trace.to_json(), r#"{"traceEvents":[]}"#);
+
+    device.set_tracer(Some(trace.clone()));
+
+    let buf = Buffer::from((&device, [1u8, 2, 3]));
+    device.read_to_vec(&buf);
+    device.read_to_vec(&buf);
+
+    let json = trace.to_json();
+    assert!(json.starts_with(r#"{"traceEvents":[{"name":"read","cat":"custos","ph":"X","ts":"#));
+    assert!(json.ends_with("}}]}"));
+    assert_eq!(json.matches(r#""ph":"X""#).count(), 2);
+    assert!(json.contains(&format!(
+        r#""args":{{"device":"{}","bytes":3,"ident":{{"idx":{},"len":3}}}}"#,
+        std::any::type_name::<CPU>(),
+        buf.ident.unwrap().idx
+    )));
+
+    let mut written = Vec::new();
+    trace.write(&mut written).unwrap();
+    assert_eq!(written, json.as_bytes());
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn test_tracer_per_thread() {
+    use custos::CPU;
+
+    let device = CPU::new();
+    let trace = Arc::new(ChromeTrace::new());
+    device.set_tracer(Some(trace.clone()));
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            assert!(device.tracer().is_none());
+            device.set_tracer(Some(trace.clone()));
+
+            let buf = Buffer::from((&device, [1, 2, 3]));
+            device.read_to_vec(&buf);
+        });
+    });
+
+    let buf = Buffer::from((&device, [1, 2, 3]));
+    device.read_to_vec(&buf);
+
+    let events = trace.events();
+    assert_eq!(events.len(), 2);
+    assert_ne!(events[0].thread, events[1].thread);
+}
+
+#[cfg(all(feature = "cpu", feature = "macro", feature = "autograd"))]
+#[test]
+fn test_trace_backward() {
+    use custos::{Combiner, UnaryElementWiseMayGrad, CPU};
+
+    let device = CPU::new();
+    let trace = Arc::new(ChromeTrace::new());
+    device.set_tracer(Some(trace.clone()));
+
+    let buf = Buffer::from((&device, [1., 2.]));
+    let out = device.unary_ew(&buf, |x| x.mul(2.), |_| 2.);
+    trace.clear();
+
+    out.backward();
+
+    let events = trace.events();
+    assert_eq!(events.last().unwrap().name, "backward");
+    assert!(events.iter().any(|event| event.name == "write"));
+}
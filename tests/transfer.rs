@@ -0,0 +1,78 @@
+#![cfg(feature = "cpu")]
+
+use custos::{Buffer, Transfer, CPU};
+
+#[test]
+fn test_transfer_cpu_to_cpu() {
+    let device = CPU::new();
+    let other = CPU::new();
+
+    let buf = Buffer::from((&device, [1, 2, 3, 4]));
+    let transferred = buf.transfer_to(&other);
+
+    assert_eq!(transferred.as_slice(), [1, 2, 3, 4]);
+    assert!(core::ptr::eq(transferred.device(), &other));
+}
+
+#[cfg(feature = "stack")]
+#[test]
+fn test_transfer_cpu_stack_round_trip() {
+    use custos::{Dim2, Stack};
+
+    let device = CPU::new();
+
+    let buf = Buffer::<f32, CPU, Dim2<2, 3>>::from_slice(&device, &[1., 2., 3., 4., 5., 6.]);
+
+    let on_stack: Buffer<f32, Stack, Dim2<2, 3>> = device.transfer(&buf, &Stack);
+    assert_eq!(on_stack.read(), [[1., 2., 3.], [4., 5., 6.]]);
+
+    let back: Buffer<f32, CPU, Dim2<2, 3>> = on_stack.transfer_to(&device);
+    assert_eq!(back.as_slice(), [1., 2., 3., 4., 5., 6.]);
+}
+
+#[cfg(feature = "stack")]
+#[test]
+fn test_transfer_stack_to_stack() {
+    use custos::{Dim1, Stack};
+
+    let buf = Buffer::<_, _, Dim1<3>>::from((&Stack, [3, 2, 1]));
+    let copied = buf.transfer_to(&Stack);
+
+    assert_eq!(copied.read(), [3, 2, 1]);
+}
+
+#[cfg(feature = "opencl")]
+#[test]
+fn test_transfer_cpu_cl_round_trip() -> custos::Result<()> {
+    use custos::OpenCL;
+
+    let device = CPU::new();
+    let cl = OpenCL::new(0)?;
+
+    let buf = Buffer::from((&device, [1, 2, 3, 4]));
+
+    let on_cl = buf.transfer_to(&cl);
+    assert_eq!(on_cl.read_to_vec(), [1, 2, 3, 4]);
+
+    let back = on_cl.transfer_to(&device);
+    assert_eq!(back.as_slice(), [1, 2, 3, 4]);
+    Ok(())
+}
+
+#[cfg(feature = "cuda")]
+#[test]
+fn test_transfer_cpu_cuda_round_trip() -> custos::Result<()> {
+    use custos::CUDA;
+
+    let device = CPU::new();
+    let cuda = CUDA::new(0)?;
+
+    let buf = Buffer::from((&device, [1, 2, 3, 4]));
+
+    let on_cuda = buf.transfer_to(&cuda);
+    assert_eq!(on_cuda.read(), [1, 2, 3, 4]);
+
+    let back = on_cuda.transfer_to(&device);
+    assert_eq!(back.as_slice(), [1, 2, 3, 4]);
+    Ok(())
+}